@@ -22,16 +22,23 @@
 // terms of the Steamworks API by Valve Corporation, the licensors of this
 // Program grant you additional permission to convey the resulting work.
 
-impl super::CommandView {
-    pub fn command_ui<'i, I>(
-        &mut self,
-        _ui: &mut egui::Ui,
-        _db: &luminol_config::command_db::CommandDB,
-        (_index, _command): (usize, &'i mut luminol_data::rpg::EventCommand),
-        _iter: &mut std::iter::Peekable<I>,
-    ) where
-        I: Iterator<Item = (usize, &'i mut luminol_data::rpg::EventCommand)>,
-    {
-        todo!()
+use luminol_data::rpg::EventCommand;
+
+use super::listing::Listing;
+use super::structure::LineKind;
+use super::CommandView;
+
+impl CommandView {
+    /// Shows a line of the command list.
+    pub fn command_ui(
+        ui: &mut egui::Ui,
+        listing: &Listing<'_>,
+        commands: &[EventCommand],
+        kinds: &[LineKind],
+        index: usize,
+        selected: bool,
+    ) -> egui::Response {
+        let job = listing.line(commands, kinds, index).layout_job(ui.style());
+        ui.add(egui::SelectableLabel::new(selected, job))
     }
 }
@@ -0,0 +1,620 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use itertools::Itertools;
+
+use luminol_config::command_db::CommandDB;
+use luminol_data::commands::{CommandDescription, CommandKind, Index, Parameter, ParameterKind};
+use luminol_data::rpg::{EventCommand, MoveCommand, MoveRoute};
+use luminol_data::ParameterType;
+
+use super::structure::{self, LineKind};
+
+/// What a piece of a listing line is, which decides its color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListingToken {
+    /// The `@>` and ` : ` markers
+    Marker,
+    Plain,
+    /// Branches, their structural lines, and the commands that jump around the list
+    Flow,
+    Comment,
+    Switch,
+    Variable,
+    /// The name of a database entry or of a map
+    Reference,
+    Audio,
+    Movement,
+    Error,
+}
+
+impl ListingToken {
+    pub fn color(self, visuals: &egui::Visuals) -> egui::Color32 {
+        let rgb = |dark: [u8; 3], light: [u8; 3]| {
+            let [r, g, b] = if visuals.dark_mode { dark } else { light };
+            egui::Color32::from_rgb(r, g, b)
+        };
+        match self {
+            Self::Marker => visuals.weak_text_color(),
+            Self::Plain => visuals.text_color(),
+            Self::Flow => rgb([110, 160, 250], [0, 60, 200]),
+            Self::Comment => rgb([110, 190, 105], [0, 128, 0]),
+            Self::Switch => rgb([240, 110, 110], [190, 0, 0]),
+            Self::Variable => rgb([235, 140, 200], [160, 0, 110]),
+            Self::Reference => rgb([230, 180, 100], [150, 90, 0]),
+            Self::Audio => rgb([90, 200, 200], [0, 120, 130]),
+            Self::Movement => rgb([180, 140, 240], [100, 40, 170]),
+            Self::Error => egui::Color32::RED,
+        }
+    }
+}
+
+/// A line of an RPG Maker style command listing, like `@>Control Switches: [0001: Door], ON`.
+#[derive(Clone, Debug, Default)]
+pub struct ListingLine {
+    pub indent: usize,
+    pub pieces: Vec<(String, ListingToken)>,
+}
+
+impl ListingLine {
+    fn push(&mut self, text: impl Into<String>, token: ListingToken) {
+        self.pieces.push((text.into(), token));
+    }
+
+    /// The line without colors, indented by two spaces per level.
+    pub fn text(&self) -> String {
+        std::iter::once("  ".repeat(self.indent))
+            .chain(self.pieces.iter().map(|(text, _)| text.clone()))
+            .collect()
+    }
+
+    /// Lays out the line in the monospace font, colored for the given style.
+    pub fn layout_job(&self, style: &egui::Style) -> egui::text::LayoutJob {
+        let font_id = egui::TextStyle::Monospace.resolve(style);
+        let format = |token: ListingToken| {
+            egui::TextFormat::simple(font_id.clone(), token.color(&style.visuals))
+        };
+
+        let mut job = egui::text::LayoutJob::default();
+        job.append(&"  ".repeat(self.indent), 0., format(ListingToken::Marker));
+        for (text, token) in &self.pieces {
+            job.append(text, 0., format(*token));
+        }
+        job
+    }
+}
+
+/// Formats event commands into an RPG Maker style listing, resolving the names of switches,
+/// variables, database entries and maps from the project's data.
+pub struct Listing<'a> {
+    data: &'a luminol_core::Data,
+    db: &'a CommandDB,
+}
+
+impl<'a> Listing<'a> {
+    pub fn new(update_state: &'a luminol_core::UpdateState<'_>) -> Self {
+        Self {
+            data: update_state.data,
+            db: &update_state
+                .project_config
+                .as_ref()
+                .expect("project not loaded")
+                .command_db,
+        }
+    }
+
+    /// Formats every line of a command list.
+    pub fn lines(&self, commands: &[EventCommand]) -> Vec<ListingLine> {
+        let kinds = structure::classify(self.db, commands);
+        (0..commands.len())
+            .map(|index| self.line(commands, &kinds, index))
+            .collect()
+    }
+
+    /// Formats a line of a command list that has already been classified.
+    pub(super) fn line(
+        &self,
+        commands: &[EventCommand],
+        kinds: &[LineKind],
+        index: usize,
+    ) -> ListingLine {
+        let command = &commands[index];
+        let mut line = ListingLine {
+            indent: command.indent,
+            pieces: Vec::new(),
+        };
+
+        if kinds[index] == LineKind::Empty {
+            line.push("@>", ListingToken::Marker);
+            return line;
+        }
+
+        let Some(description) = self.db.get(command.code) else {
+            line.push("@>", ListingToken::Marker);
+            line.push(
+                format!(
+                    "Unknown command {}: {}",
+                    command.code,
+                    raw(&command.parameters)
+                ),
+                ListingToken::Error,
+            );
+            return line;
+        };
+
+        match kinds[index] {
+            LineKind::Empty => {}
+            LineKind::Head => {
+                let token = head_token(description);
+                line.push("@>", ListingToken::Marker);
+                line.push(description.name.clone(), token);
+
+                let mut arguments = Vec::new();
+                match &description.kind {
+                    CommandKind::Multi { .. } => {
+                        let text = first_string(command);
+                        if !text.is_empty() {
+                            arguments.push((text.to_string(), token));
+                        }
+                    }
+                    CommandKind::Single(parameters) | CommandKind::Branch { parameters, .. } => {
+                        // Control Switches and Control Variables edit a range of IDs
+                        let ranges = matches!(command.code, 121 | 122);
+                        self.arguments(
+                            &mut arguments,
+                            parameters,
+                            &command.parameters,
+                            token,
+                            ranges,
+                        );
+                    }
+                }
+                if !arguments.is_empty() {
+                    line.push(": ", ListingToken::Marker);
+                    line.pieces.extend(arguments);
+                }
+            }
+            LineKind::Continuation => {
+                let head = self
+                    .db
+                    .get(commands[structure::block_start(kinds, index)].code);
+                let width = head.map_or(0, |d| d.name.chars().count());
+                let token = head.map_or(ListingToken::Plain, head_token);
+                line.push(format!(" :{:width$}: ", ""), ListingToken::Marker);
+
+                match command.parameters.as_slice() {
+                    [ParameterType::String(text)] => line.push(text.clone(), token),
+                    [ParameterType::MoveCommand(move_command)] => {
+                        line.push("$>", ListingToken::Marker);
+                        line.push(self.move_command(move_command), ListingToken::Movement);
+                    }
+                    values => {
+                        if let CommandKind::Single(parameters) = &description.kind {
+                            self.arguments(&mut line.pieces, parameters, values, token, false);
+                        }
+                    }
+                }
+            }
+            LineKind::Structural => {
+                line.push(" : ", ListingToken::Marker);
+                line.push(description.name.clone(), ListingToken::Flow);
+
+                let mut arguments = Vec::new();
+                match (&description.kind, command.parameters.as_slice()) {
+                    // The choice index of `When` is implied by its position
+                    (_, [ParameterType::Integer(_), ParameterType::String(text)]) => {
+                        arguments.push((format!("[{text}]"), ListingToken::Flow));
+                    }
+                    (CommandKind::Single(parameters), values) => {
+                        self.arguments(
+                            &mut arguments,
+                            parameters,
+                            values,
+                            ListingToken::Flow,
+                            false,
+                        );
+                    }
+                    _ => {}
+                }
+                if !arguments.is_empty() {
+                    line.push(" ", ListingToken::Marker);
+                    line.pieces.extend(arguments);
+                }
+            }
+        }
+
+        line
+    }
+
+    /// Formats the parameters of a command. If `ranges` is true, two switches or variables in a row
+    /// are the bounds of a range of IDs.
+    fn arguments(
+        &self,
+        pieces: &mut Vec<(String, ListingToken)>,
+        parameters: &[Parameter],
+        values: &[ParameterType],
+        token: ListingToken,
+        ranges: bool,
+    ) {
+        let mut parameters = parameters.iter().peekable();
+        while let Some(parameter) = parameters.next() {
+            if let (
+                true,
+                Parameter::Single {
+                    index: first,
+                    kind: kind @ (ParameterKind::Switch | ParameterKind::Variable),
+                    ..
+                },
+                Some(Parameter::Single {
+                    index: last,
+                    kind: last_kind,
+                    ..
+                }),
+            ) = (ranges, parameter, parameters.peek())
+            {
+                let value = |index: &Index| {
+                    values
+                        .get(index.as_usize())
+                        .and_then(ParameterType::as_integer)
+                        .copied()
+                };
+                if let (true, Some(first), Some(last)) =
+                    (kind == last_kind, value(first), value(last))
+                {
+                    parameters.next();
+                    separate(pieces, token);
+                    pieces.push(self.range(kind, first, last));
+                    continue;
+                }
+            }
+
+            self.parameter(pieces, parameter, values, token, ranges);
+        }
+    }
+
+    fn range(&self, kind: &ParameterKind, first: i32, last: i32) -> (String, ListingToken) {
+        let token = match kind {
+            ParameterKind::Switch => ListingToken::Switch,
+            _ => ListingToken::Variable,
+        };
+        if first != last {
+            (format!("[{first:0>4}..{last:0>4}]"), token)
+        } else if token == ListingToken::Switch {
+            (self.switch(first), token)
+        } else {
+            (self.variable(first), token)
+        }
+    }
+
+    fn parameter(
+        &self,
+        pieces: &mut Vec<(String, ListingToken)>,
+        parameter: &Parameter,
+        values: &[ParameterType],
+        token: ListingToken,
+        ranges: bool,
+    ) {
+        match parameter {
+            Parameter::Selection {
+                index, parameters, ..
+            } => {
+                let default = parameters.first().map_or(0, |(value, _)| *value as i32);
+                let value = values
+                    .get(index.as_usize())
+                    .and_then(ParameterType::as_integer)
+                    .copied()
+                    .unwrap_or(default);
+                if let Some((_, option)) = parameters.iter().find(|(v, _)| *v as i32 == value) {
+                    self.parameter(pieces, option, values, token, ranges);
+                }
+            }
+            Parameter::Group { parameters, .. } => {
+                self.arguments(pieces, parameters, values, token, ranges);
+            }
+            Parameter::Single {
+                index, name, kind, ..
+            } => {
+                if let Some(value) = values.get(index.as_usize()) {
+                    if let Some(piece) = self.value(name, kind, value, token) {
+                        separate(pieces, token);
+                        pieces.push(piece);
+                    }
+                }
+            }
+            Parameter::Label(text) => {
+                separate(pieces, token);
+                pieces.push((text.clone(), token));
+            }
+            Parameter::Dummy => {}
+        }
+    }
+
+    /// Formats the value of a parameter. Flags that aren't set are left out.
+    fn value(
+        &self,
+        name: &str,
+        kind: &ParameterKind,
+        value: &ParameterType,
+        token: ListingToken,
+    ) -> Option<(String, ListingToken)> {
+        let piece = match (kind, value) {
+            (ParameterKind::Switch, ParameterType::Integer(id)) => {
+                (self.switch(*id), ListingToken::Switch)
+            }
+            (ParameterKind::Variable, ParameterType::Integer(id)) => {
+                (self.variable(*id), ListingToken::Variable)
+            }
+            (ParameterKind::SelfSwitch, ParameterType::String(self_switch)) => {
+                (self_switch.clone(), ListingToken::Switch)
+            }
+            (ParameterKind::ActorOrParty, ParameterType::Integer(0)) => {
+                ("Entire Party".to_string(), token)
+            }
+            (ParameterKind::Weapon | ParameterKind::Armor, ParameterType::Integer(0)) => {
+                ("(None)".to_string(), token)
+            }
+            (ParameterKind::Map, ParameterType::Integer(id)) => {
+                let name = usize::try_from(*id)
+                    .ok()
+                    .and_then(|id| self.data.map_infos().data.get(&id).map(|i| i.name.clone()));
+                reference(*id, name, true)
+            }
+            (
+                ParameterKind::Actor
+                | ParameterKind::ActorOrParty
+                | ParameterKind::Class
+                | ParameterKind::Skill
+                | ParameterKind::Item
+                | ParameterKind::Weapon
+                | ParameterKind::Armor
+                | ParameterKind::Enemy
+                | ParameterKind::Troop
+                | ParameterKind::State
+                | ParameterKind::Animation
+                | ParameterKind::CommonEvent,
+                ParameterType::Integer(id),
+            ) => reference(*id, self.database_name(kind, *id), false),
+            (ParameterKind::Character, ParameterType::Integer(id)) => (character(*id), token),
+            (ParameterKind::IntBool | ParameterKind::Bool, value) => {
+                return value.truthy().then(|| (name.to_string(), token));
+            }
+            (ParameterKind::Enum { variants }, ParameterType::Integer(value)) => {
+                let text = variants
+                    .iter()
+                    .find(|(_, v)| *v as i32 == *value)
+                    .map_or_else(|| value.to_string(), |(name, _)| name.clone());
+                (text, token)
+            }
+            (ParameterKind::AudioFile, ParameterType::AudioFile(_)) => {
+                (raw(std::slice::from_ref(value)), ListingToken::Audio)
+            }
+            (ParameterKind::MoveRoute, ParameterType::MoveRoute(route)) => {
+                return move_route_flags(route).map(|flags| (flags, ListingToken::Movement));
+            }
+            (ParameterKind::StringArray, ParameterType::Array(array)) => (
+                array.iter().filter_map(ParameterType::as_string).join(", "),
+                token,
+            ),
+            (ParameterKind::String, ParameterType::String(text)) => (text.clone(), token),
+            _ => (raw(std::slice::from_ref(value)), token),
+        };
+        Some(piece)
+    }
+
+    fn switch(&self, id: i32) -> String {
+        let system = self.data.system();
+        let name = usize::try_from(id)
+            .ok()
+            .and_then(|id| id.checked_sub(1))
+            .and_then(|index| system.switches.get(index))
+            .map_or("", String::as_str);
+        format!("[{id:0>4}: {name}]")
+    }
+
+    fn variable(&self, id: i32) -> String {
+        let system = self.data.system();
+        let name = usize::try_from(id)
+            .ok()
+            .and_then(|id| id.checked_sub(1))
+            .and_then(|index| system.variables.get(index))
+            .map_or("", String::as_str);
+        format!("[{id:0>4}: {name}]")
+    }
+
+    fn database_name(&self, kind: &ParameterKind, id: i32) -> Option<String> {
+        let index = usize::try_from(id).ok()?.checked_sub(1)?;
+        macro_rules! name {
+            ($getter:ident) => {
+                self.data.$getter().data.get(index).map(|e| e.name.clone())
+            };
+        }
+        match kind {
+            ParameterKind::Actor | ParameterKind::ActorOrParty => name!(actors),
+            ParameterKind::Class => name!(classes),
+            ParameterKind::Skill => name!(skills),
+            ParameterKind::Item => name!(items),
+            ParameterKind::Weapon => name!(weapons),
+            ParameterKind::Armor => name!(armors),
+            ParameterKind::Enemy => name!(enemies),
+            ParameterKind::Troop => name!(troops),
+            ParameterKind::State => name!(states),
+            ParameterKind::Animation => name!(animations),
+            ParameterKind::CommonEvent => name!(common_events),
+            _ => None,
+        }
+    }
+
+    fn move_command(&self, command: &MoveCommand) -> String {
+        let name = move_command_name(command.code);
+        match (command.code, command.parameters.as_slice()) {
+            (_, []) => name.to_string(),
+            // Switch ON and Switch OFF
+            (27 | 28, [ParameterType::Integer(id)]) => format!("{name}: {}", self.switch(*id)),
+            (_, parameters) => format!("{name}: {}", raw(parameters)),
+        }
+    }
+}
+
+/// The color of a command, decided by what kind of command it is and by its first parameter.
+fn head_token(description: &CommandDescription) -> ListingToken {
+    match &description.kind {
+        CommandKind::Branch { .. } => ListingToken::Flow,
+        CommandKind::Multi {
+            highlight: true, ..
+        } => ListingToken::Plain,
+        CommandKind::Multi { .. } => ListingToken::Comment,
+        CommandKind::Single(parameters) => match parameters.first() {
+            Some(Parameter::Single { kind, .. }) => match kind {
+                ParameterKind::Switch | ParameterKind::SelfSwitch => ListingToken::Switch,
+                ParameterKind::Variable => ListingToken::Variable,
+                ParameterKind::AudioFile => ListingToken::Audio,
+                ParameterKind::MoveRoute => ListingToken::Movement,
+                _ => ListingToken::Plain,
+            },
+            _ => ListingToken::Plain,
+        },
+    }
+}
+
+fn separate(pieces: &mut Vec<(String, ListingToken)>, token: ListingToken) {
+    if !pieces.is_empty() {
+        pieces.push((", ".to_string(), token));
+    }
+}
+
+/// Formats a reference to a database entry or a map, or an error if it doesn't exist.
+fn reference(id: i32, name: Option<String>, show_id: bool) -> (String, ListingToken) {
+    match name {
+        Some(name) if show_id => (format!("[{id:0>3}: {name}]"), ListingToken::Reference),
+        Some(name) => (format!("[{name}]"), ListingToken::Reference),
+        None => (format!("[{id:0>3}: ?]"), ListingToken::Error),
+    }
+}
+
+fn character(id: i32) -> String {
+    match id {
+        -1 => "Player".to_string(),
+        0 => "This Event".to_string(),
+        id => format!("[EV{id:0>3}]"),
+    }
+}
+
+fn move_route_flags(route: &MoveRoute) -> Option<String> {
+    let flags = [
+        route.repeat.then_some("Repeat Action"),
+        route.skippable.then_some("Ignore If Can't Move"),
+    ]
+    .into_iter()
+    .flatten()
+    .join(", ");
+    (!flags.is_empty()).then(|| format!("({flags})"))
+}
+
+fn first_string(command: &EventCommand) -> &str {
+    command
+        .parameters
+        .first()
+        .and_then(ParameterType::as_string)
+        .map_or("", String::as_str)
+}
+
+/// The names RPG Maker gives to the move commands of a move route.
+pub(super) fn move_command_name(code: u16) -> &'static str {
+    match code {
+        0 => "",
+        1 => "Move Down",
+        2 => "Move Left",
+        3 => "Move Right",
+        4 => "Move Up",
+        5 => "Move Lower Left",
+        6 => "Move Lower Right",
+        7 => "Move Upper Left",
+        8 => "Move Upper Right",
+        9 => "Move at Random",
+        10 => "Move toward Player",
+        11 => "Move away from Player",
+        12 => "1 Step Forward",
+        13 => "1 Step Backward",
+        14 => "Jump",
+        15 => "Wait",
+        16 => "Turn Down",
+        17 => "Turn Left",
+        18 => "Turn Right",
+        19 => "Turn Up",
+        20 => "Turn 90° Right",
+        21 => "Turn 90° Left",
+        22 => "Turn 180°",
+        23 => "Turn 90° Right or Left",
+        24 => "Turn at Random",
+        25 => "Turn toward Player",
+        26 => "Turn away from Player",
+        27 => "Switch ON",
+        28 => "Switch OFF",
+        29 => "Change Speed",
+        30 => "Change Frequency",
+        31 => "Move Animation ON",
+        32 => "Move Animation OFF",
+        33 => "Stop Animation ON",
+        34 => "Stop Animation OFF",
+        35 => "Direction Fix ON",
+        36 => "Direction Fix OFF",
+        37 => "Through ON",
+        38 => "Through OFF",
+        39 => "Always on Top ON",
+        40 => "Always on Top OFF",
+        41 => "Graphic",
+        42 => "Change Opacity",
+        43 => "Change Blending",
+        44 => "Play SE",
+        45 => "Script",
+        _ => "Unknown Move Command",
+    }
+}
+
+/// A short description of a list of parameters, for values that don't match their description.
+fn raw(parameters: &[ParameterType]) -> String {
+    parameters
+        .iter()
+        .map(|parameter| match parameter {
+            ParameterType::Integer(value) => value.to_string(),
+            ParameterType::String(text) => format!("{text:?}"),
+            ParameterType::Float(value) => value.to_string(),
+            ParameterType::Bool(value) => value.to_string(),
+            ParameterType::Color(c) => format!("({}, {}, {}, {})", c.red, c.green, c.blue, c.alpha),
+            ParameterType::Tone(t) => format!("({}, {}, {}, {})", t.red, t.green, t.blue, t.gray),
+            ParameterType::AudioFile(a) => format!(
+                "'{}', {}, {}",
+                a.name.as_deref().map_or("", |n| n.as_str()),
+                a.volume,
+                a.pitch
+            ),
+            ParameterType::MoveRoute(route) => {
+                format!("{} move commands", route.list.len().saturating_sub(1))
+            }
+            ParameterType::MoveCommand(command) => move_command_name(command.code).to_string(),
+            ParameterType::Array(array) => format!("[{}]", raw(array)),
+            ParameterType::None => "nil".to_string(),
+        })
+        .join(", ")
+}
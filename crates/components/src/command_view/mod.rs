@@ -25,29 +25,63 @@
 #[macro_use]
 mod macros;
 mod command_ui;
-mod parameter_ui;
+mod listing;
+mod move_route_ui;
+pub(crate) mod parameter_ui;
+pub(crate) mod structure;
 mod ui;
 
-use std::collections::HashMap;
+pub use listing::{Listing, ListingLine, ListingToken};
 
+use luminol_data::rpg::EventCommand;
+
+/// An editor for a list of event commands, laid out like the command lists of RPG Maker.
 pub struct CommandView {
-    _selected_index: usize,
-    _window_state: WindowState,
-    _id: egui::Id,
-    _modals: HashMap<u64, bool>, // todo find a better way to handle modals
+    /// The first line of the first and of the last selected command
+    selection: Option<(usize, usize)>,
+    /// Whether the last click landed on this view, so that it handles keyboard shortcuts
+    active: bool,
+    scroll_to_selection: bool,
+    window_state: WindowState,
+    /// The preview shown while editing Show Text, kept so that its font is remembered
+    message_preview: crate::MessagePreview,
+    id: egui::Id,
 }
 
 enum WindowState {
     None,
+    /// Picking a command to insert before the line at `index`
+    Insert {
+        index: usize,
+        search: String,
+    },
+    /// Editing the parameters of a command. New commands are only inserted once the dialog is
+    /// saved.
+    Edit {
+        index: usize,
+        is_new: bool,
+        command: EventCommand,
+        /// The full text of multiline commands
+        text: String,
+        /// Whether Conditional Branch has an Else branch
+        else_branch: bool,
+    },
+    /// Editing the whole list as a script. The list is only replaced once the script compiles.
+    Script {
+        text: String,
+        error: Option<crate::event_script::ScriptError>,
+    },
 }
 
 impl Default for CommandView {
     fn default() -> Self {
         Self {
-            _selected_index: 0,
-            _window_state: WindowState::None,
-            _id: egui::Id::new("command_view"),
-            _modals: HashMap::new(),
+            selection: None,
+            active: false,
+            scroll_to_selection: false,
+            window_state: WindowState::None,
+            message_preview: Default::default(),
+            id: egui::Id::new("command_view"),
         }
     }
 }
@@ -55,7 +89,7 @@ impl Default for CommandView {
 impl CommandView {
     pub fn new(id: impl std::hash::Hash) -> Self {
         Self {
-            _id: egui::Id::new(id),
+            id: egui::Id::new(id),
             ..Default::default()
         }
     }
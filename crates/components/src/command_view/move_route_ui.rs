@@ -0,0 +1,213 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use luminol_data::commands::ParameterKind;
+use luminol_data::rpg::{MoveCommand, MoveRoute};
+
+use super::listing::move_command_name;
+use super::parameter_ui::{default_value, kind_ui};
+
+/// The last move command code. Code 0 only ends the route and isn't offered.
+const MAX_MOVE_CODE: u16 = 45;
+
+/// Shows an editor for the commands of a move route. The empty command that ends the route is kept
+/// at the end and isn't shown. Returns true if the route was modified.
+pub(crate) fn move_route_ui(
+    ui: &mut egui::Ui,
+    update_state: &luminol_core::UpdateState<'_>,
+    id: egui::Id,
+    route: &mut MoveRoute,
+) -> bool {
+    let mut modified = false;
+    if route.list.last().map_or(true, |c| c.code != 0) {
+        route.list.push(MoveCommand::default());
+        modified = true;
+    }
+
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            modified |= ui.checkbox(&mut route.repeat, "Repeat Action").changed();
+            modified |= ui
+                .checkbox(&mut route.skippable, "Ignore If Can't Move")
+                .changed();
+        });
+
+        let len = route.list.len() - 1;
+        let mut moved = None;
+        let mut removed = None;
+        egui::ScrollArea::vertical()
+            .id_source(id.with("list"))
+            .max_height(240.)
+            .show(ui, |ui| {
+                for (index, command) in route.list[..len].iter_mut().enumerate() {
+                    let id = id.with(index);
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                            moved = Some((index, index - 1));
+                        }
+                        if ui
+                            .add_enabled(index + 1 < len, egui::Button::new("⏷"))
+                            .clicked()
+                        {
+                            moved = Some((index, index + 1));
+                        }
+                        if ui.button("🗙").clicked() {
+                            removed = Some(index);
+                        }
+                        modified |= move_command_ui(ui, update_state, id, command);
+                    });
+                }
+            });
+
+        if let Some((from, to)) = moved {
+            route.list.swap(from, to);
+            modified = true;
+        }
+        if let Some(index) = removed {
+            route.list.remove(index);
+            modified = true;
+        }
+        if ui.button("Add Move Command").clicked() {
+            route.list.insert(route.list.len() - 1, new_move_command(1));
+            modified = true;
+        }
+    });
+
+    modified
+}
+
+/// Shows the code and the parameters of one move command.
+fn move_command_ui(
+    ui: &mut egui::Ui,
+    update_state: &luminol_core::UpdateState<'_>,
+    id: egui::Id,
+    command: &mut MoveCommand,
+) -> bool {
+    let mut modified = false;
+    egui::ComboBox::from_id_source(id.with("code"))
+        .selected_text(move_command_name(command.code))
+        .width(160.)
+        .show_ui(ui, |ui| {
+            for code in 1..=MAX_MOVE_CODE {
+                if ui
+                    .selectable_label(command.code == code, move_command_name(code))
+                    .clicked()
+                    && command.code != code
+                {
+                    *command = new_move_command(code);
+                    modified = true;
+                }
+            }
+        });
+
+    let kinds = parameter_kinds(command.code);
+    if command.parameters.len() < kinds.len() {
+        let missing = &kinds[command.parameters.len()..];
+        command
+            .parameters
+            .extend(missing.iter().map(|(_, kind)| default_value(kind)));
+        modified = true;
+    }
+    for (index, ((name, kind), parameter)) in
+        kinds.iter().zip(command.parameters.iter_mut()).enumerate()
+    {
+        if !name.is_empty() {
+            ui.label(*name);
+        }
+        modified |= kind_ui(ui, update_state, id.with(index), kind, parameter);
+    }
+    modified
+}
+
+fn new_move_command(code: u16) -> MoveCommand {
+    MoveCommand {
+        code,
+        parameters: parameter_kinds(code)
+            .iter()
+            .map(|(_, kind)| default_value(kind))
+            .collect(),
+        guid: rand::random(),
+    }
+}
+
+/// The names and kinds of the parameters of a move command.
+fn parameter_kinds(code: u16) -> Vec<(&'static str, ParameterKind)> {
+    let variants = |variants: &[(&str, i8)]| ParameterKind::Enum {
+        variants: variants
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect(),
+    };
+    match code {
+        // Jump
+        14 => vec![("X", ParameterKind::Int), ("Y", ParameterKind::Int)],
+        // Wait
+        15 => vec![("Frames", ParameterKind::Int)],
+        // Switch ON and Switch OFF
+        27 | 28 => vec![("", ParameterKind::Switch)],
+        // Change Speed
+        29 => vec![(
+            "",
+            variants(&[
+                ("1: Slowest", 1),
+                ("2: Slower", 2),
+                ("3: Slow", 3),
+                ("4: Fast", 4),
+                ("5: Faster", 5),
+                ("6: Fastest", 6),
+            ]),
+        )],
+        // Change Frequency
+        30 => vec![(
+            "",
+            variants(&[
+                ("1: Lowest", 1),
+                ("2: Lower", 2),
+                ("3: Low", 3),
+                ("4: High", 4),
+                ("5: Higher", 5),
+                ("6: Highest", 6),
+            ]),
+        )],
+        // Graphic
+        41 => vec![
+            ("Name", ParameterKind::String),
+            ("Hue", ParameterKind::Int),
+            (
+                "Direction",
+                variants(&[("Down", 2), ("Left", 4), ("Right", 6), ("Up", 8)]),
+            ),
+            ("Pattern", ParameterKind::Int),
+        ],
+        // Change Opacity
+        42 => vec![("", ParameterKind::Int)],
+        // Change Blending
+        43 => vec![("", variants(&[("Normal", 0), ("Add", 1), ("Sub", 2)]))],
+        // Play SE
+        44 => vec![("", ParameterKind::AudioFile)],
+        // Script
+        45 => vec![("", ParameterKind::String)],
+        _ => Vec::new(),
+    }
+}
@@ -22,16 +22,462 @@
 // terms of the Steamworks API by Valve Corporation, the licensors of this
 // Program grant you additional permission to convey the resulting work.
 
+use itertools::Itertools;
+
+use luminol_data::commands::{Parameter, ParameterKind};
+use luminol_data::rpg::{AudioFile, MoveCommand, MoveRoute};
+use luminol_data::{Color, ParameterType, Tone};
+
 use super::CommandView;
 
 impl CommandView {
-    #[allow(clippy::only_used_in_recursion)]
+    /// Shows the widgets for a parameter of a command description, editing the parameters of the
+    /// command. Returns true if the parameters were modified.
     pub fn parameter_ui(
-        &mut self,
-        _ui: &mut egui::Ui,
-        _parameter: &luminol_data::commands::Parameter,
-        _command: &mut luminol_data::rpg::EventCommand,
-    ) {
-        todo!()
+        ui: &mut egui::Ui,
+        update_state: &luminol_core::UpdateState<'_>,
+        parameter: &Parameter,
+        parameters: &mut Vec<ParameterType>,
+    ) -> bool {
+        let mut modified = false;
+
+        match parameter {
+            Parameter::Selection {
+                index,
+                parameters: options,
+                ..
+            } => {
+                let index = index.as_usize();
+                let default = options.first().map_or(0, |(value, _)| *value as i32);
+                let value = *get_or_resize!(parameters, index).into_integer_with(default);
+
+                for (option_value, option) in options {
+                    let selected = value == *option_value as i32;
+                    ui.horizontal(|ui| {
+                        if ui.radio(selected, "").clicked() && !selected {
+                            parameters[index] = (*option_value as i32).into();
+                            apply_defaults(option, parameters, true);
+                            modified = true;
+                        }
+
+                        ui.vertical(|ui| {
+                            if selected {
+                                modified |=
+                                    Self::parameter_ui(ui, update_state, option, parameters);
+                            } else {
+                                // The options share parameter indices, so the options that aren't
+                                // selected are shown with their defaults instead
+                                let mut preview = parameters.clone();
+                                apply_defaults(option, &mut preview, true);
+                                ui.add_enabled_ui(false, |ui| {
+                                    Self::parameter_ui(ui, update_state, option, &mut preview)
+                                });
+                            }
+                        });
+                    });
+                }
+            }
+            Parameter::Group {
+                parameters: group, ..
+            } => {
+                ui.group(|ui| {
+                    for parameter in group {
+                        modified |= Self::parameter_ui(ui, update_state, parameter, parameters);
+                    }
+                });
+            }
+            Parameter::Single {
+                index,
+                description,
+                name,
+                kind,
+                guid,
+            } => {
+                ui.horizontal(|ui| {
+                    if !name.is_empty() {
+                        let response = ui.label(name);
+                        if !description.is_empty() {
+                            response.on_hover_text(description);
+                        }
+                    }
+
+                    let id = ui.make_persistent_id(guid);
+                    let parameter = get_or_resize!(parameters, index.as_usize());
+                    modified |= kind_ui(ui, update_state, id, kind, parameter);
+                });
+            }
+            Parameter::Label(text) => {
+                ui.label(text);
+            }
+            Parameter::Dummy => {}
+        }
+
+        modified
+    }
+}
+
+pub(super) fn kind_ui(
+    ui: &mut egui::Ui,
+    update_state: &luminol_core::UpdateState<'_>,
+    id: egui::Id,
+    kind: &ParameterKind,
+    parameter: &mut ParameterType,
+) -> bool {
+    let data = &update_state.data;
+    let names = |kind: &ParameterKind| -> Vec<String> {
+        macro_rules! names {
+            ($getter:ident) => {
+                data.$getter().data.iter().map(|e| e.name.clone()).collect()
+            };
+        }
+        match kind {
+            ParameterKind::Switch => data.system().switches.clone(),
+            ParameterKind::Variable => data.system().variables.clone(),
+            ParameterKind::Actor | ParameterKind::ActorOrParty => names!(actors),
+            ParameterKind::Class => names!(classes),
+            ParameterKind::Skill => names!(skills),
+            ParameterKind::Item => names!(items),
+            ParameterKind::Weapon => names!(weapons),
+            ParameterKind::Armor => names!(armors),
+            ParameterKind::Enemy => names!(enemies),
+            ParameterKind::Troop => names!(troops),
+            ParameterKind::State => names!(states),
+            ParameterKind::Animation => names!(animations),
+            ParameterKind::CommonEvent => names!(common_events),
+            _ => Vec::new(),
+        }
+    };
+
+    match kind {
+        ParameterKind::Switch
+        | ParameterKind::Variable
+        | ParameterKind::Actor
+        | ParameterKind::Class
+        | ParameterKind::Skill
+        | ParameterKind::Item
+        | ParameterKind::Enemy
+        | ParameterKind::Troop
+        | ParameterKind::State
+        | ParameterKind::Animation
+        | ParameterKind::CommonEvent => {
+            let value = parameter.into_integer_with(1);
+            id_ui(ui, update_state, id, value, &names(kind), false)
+        }
+        // An ID of 0 unequips the weapon or the armor
+        ParameterKind::Weapon | ParameterKind::Armor => {
+            let value = parameter.into_integer_with(1);
+            id_ui(ui, update_state, id, value, &names(kind), true)
+        }
+        ParameterKind::ActorOrParty => {
+            let value = parameter.into_integer();
+            let mut party = *value == 0;
+            let mut modified = false;
+            if ui.checkbox(&mut party, "Entire Party").changed() {
+                *value = if party { 0 } else { 1 };
+                modified = true;
+            }
+            ui.add_enabled_ui(!party, |ui| {
+                modified |= id_ui(ui, update_state, id, value, &names(kind), false);
+            });
+            modified
+        }
+        ParameterKind::Map => {
+            let map_infos = data.map_infos();
+            let ids = map_infos
+                .data
+                .keys()
+                .copied()
+                .sorted_unstable()
+                .collect_vec();
+            let value = parameter.into_integer_with(1);
+            let mut map_id = usize::try_from(*value).unwrap_or_default();
+            let changed = ui
+                .add(crate::OptionalIdComboBox::new(
+                    update_state,
+                    id,
+                    &mut map_id,
+                    ids.iter().copied(),
+                    |id| {
+                        format!(
+                            "{id:0>3}: {}",
+                            map_infos.data.get(&id).map_or("", |i| i.name.as_str())
+                        )
+                    },
+                ))
+                .changed();
+            if changed {
+                *value = map_id as i32;
+            }
+            changed
+        }
+        ParameterKind::Character => {
+            let value = parameter.into_integer();
+            let text = match *value {
+                -1 => "Player".to_string(),
+                0 => "This Event".to_string(),
+                id => format!("Event {id}"),
+            };
+            let mut modified = false;
+            egui::ComboBox::from_id_source(id)
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    modified |= ui.selectable_value(value, -1, "Player").changed();
+                    modified |= ui.selectable_value(value, 0, "This Event").changed();
+                    if ui.selectable_label(*value > 0, "Other Event").clicked() && *value <= 0 {
+                        *value = 1;
+                        modified = true;
+                    }
+                });
+            if *value > 0 {
+                modified |= ui
+                    .add(egui::DragValue::new(value).range(1..=999).prefix("ID "))
+                    .changed();
+            }
+            modified
+        }
+        ParameterKind::SelfSwitch => {
+            let value = parameter.into_string_with("A".to_string());
+            let mut modified = false;
+            egui::ComboBox::from_id_source(id)
+                .selected_text(value.as_str())
+                .show_ui(ui, |ui| {
+                    for self_switch in ["A", "B", "C", "D"] {
+                        if ui
+                            .selectable_label(*value == self_switch, self_switch)
+                            .clicked()
+                            && *value != self_switch
+                        {
+                            *value = self_switch.to_string();
+                            modified = true;
+                        }
+                    }
+                });
+            modified
+        }
+        ParameterKind::String => ui.text_edit_singleline(parameter.into_string()).changed(),
+        ParameterKind::Int => ui
+            .add(egui::DragValue::new(parameter.into_integer()))
+            .changed(),
+        ParameterKind::IntBool => {
+            let value = parameter.into_integer();
+            let mut checked = *value != 0;
+            let changed = ui.checkbox(&mut checked, "").changed();
+            if changed {
+                *value = checked as i32;
+            }
+            changed
+        }
+        ParameterKind::Bool => ui.checkbox(parameter.into_bool(), "").changed(),
+        ParameterKind::Enum { variants } => {
+            let default = variants.first().map_or(0, |(_, value)| *value as i32);
+            let value = parameter.into_integer_with(default);
+            let text = variants
+                .iter()
+                .find(|(_, v)| *v as i32 == *value)
+                .map_or_else(|| value.to_string(), |(name, _)| name.clone());
+            let mut modified = false;
+            egui::ComboBox::from_id_source(id)
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    for (name, v) in variants {
+                        modified |= ui.selectable_value(value, *v as i32, name).changed();
+                    }
+                });
+            modified
+        }
+        ParameterKind::AudioFile => {
+            let audio_file = parameter.into_audiofile();
+            let mut modified = false;
+
+            let mut name = audio_file
+                .name
+                .as_ref()
+                .map_or_else(String::new, |name| name.to_string());
+            if ui
+                .add(egui::TextEdit::singleline(&mut name).desired_width(120.))
+                .changed()
+            {
+                audio_file.name = (!name.is_empty()).then(|| name.into());
+                modified = true;
+            }
+            ui.label("Volume");
+            modified |= ui
+                .add(egui::DragValue::new(&mut audio_file.volume).range(0..=100))
+                .changed();
+            ui.label("Pitch");
+            modified |= ui
+                .add(egui::DragValue::new(&mut audio_file.pitch).range(50..=150))
+                .changed();
+            modified
+        }
+        ParameterKind::Color => {
+            let color = parameter.into_color();
+            let mut modified = false;
+            for (label, value) in [
+                ("R", &mut color.red),
+                ("G", &mut color.green),
+                ("B", &mut color.blue),
+                ("A", &mut color.alpha),
+            ] {
+                ui.label(label);
+                modified |= ui
+                    .add(egui::DragValue::new(value).range(0.0..=255.0))
+                    .changed();
+            }
+            modified
+        }
+        ParameterKind::Tone => {
+            let tone = parameter.into_tone();
+            let mut modified = false;
+            for (label, value, min) in [
+                ("R", &mut tone.red, -255.0),
+                ("G", &mut tone.green, -255.0),
+                ("B", &mut tone.blue, -255.0),
+                ("Gray", &mut tone.gray, 0.0),
+            ] {
+                ui.label(label);
+                modified |= ui
+                    .add(egui::DragValue::new(value).range(min..=255.0))
+                    .changed();
+            }
+            modified
+        }
+        ParameterKind::MoveRoute => {
+            let route = parameter.into_moveroute_with(default_move_route());
+            super::move_route_ui::move_route_ui(ui, update_state, id, route)
+        }
+        ParameterKind::StringArray => {
+            let array = parameter.into_array();
+            let mut modified = false;
+            ui.vertical(|ui| {
+                let mut removed = None;
+                for (i, item) in array.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        modified |= ui.text_edit_singleline(item.into_string()).changed();
+                        if ui.button("-").clicked() {
+                            removed = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = removed {
+                    array.remove(i);
+                    modified = true;
+                }
+                if ui.button("+").clicked() {
+                    array.push(String::new().into());
+                    modified = true;
+                }
+            });
+            modified
+        }
+    }
+}
+
+/// A combo box for a 1-based ID into a list of names. An ID of 0 is shown as "(None)".
+fn id_ui(
+    ui: &mut egui::Ui,
+    update_state: &luminol_core::UpdateState<'_>,
+    id: egui::Id,
+    value: &mut i32,
+    names: &[String],
+    allow_none: bool,
+) -> bool {
+    let mut selected = usize::try_from(*value)
+        .ok()
+        .and_then(|value| value.checked_sub(1));
+    let changed = ui
+        .add(
+            crate::OptionalIdComboBox::new(update_state, id, &mut selected, 0..names.len(), |id| {
+                format!(
+                    "{:0>4}: {}",
+                    id + 1,
+                    names.get(id).map_or("", String::as_str)
+                )
+            })
+            .allow_none(allow_none),
+        )
+        .changed();
+    if changed {
+        *value = selected.map_or(0, |id| id as i32 + 1);
+    }
+    changed
+}
+
+fn default_move_route() -> MoveRoute {
+    MoveRoute {
+        repeat: false,
+        skippable: false,
+        list: vec![MoveCommand::default()],
+    }
+}
+
+/// The value a parameter of this kind has in a new command.
+pub(crate) fn default_value(kind: &ParameterKind) -> ParameterType {
+    match kind {
+        ParameterKind::Switch
+        | ParameterKind::Variable
+        | ParameterKind::Actor
+        | ParameterKind::Class
+        | ParameterKind::Skill
+        | ParameterKind::Item
+        | ParameterKind::Weapon
+        | ParameterKind::Armor
+        | ParameterKind::Enemy
+        | ParameterKind::Troop
+        | ParameterKind::State
+        | ParameterKind::Animation
+        | ParameterKind::CommonEvent
+        | ParameterKind::Map => 1.into(),
+        ParameterKind::Int
+        | ParameterKind::IntBool
+        | ParameterKind::ActorOrParty
+        | ParameterKind::Character => 0.into(),
+        ParameterKind::SelfSwitch => "A".into(),
+        ParameterKind::String => String::new().into(),
+        ParameterKind::Bool => false.into(),
+        ParameterKind::Enum { variants } => variants
+            .first()
+            .map_or(0, |(_, value)| *value as i32)
+            .into(),
+        ParameterKind::AudioFile => AudioFile::default().into(),
+        ParameterKind::Color => Color::default().into(),
+        ParameterKind::Tone => Tone::default().into(),
+        ParameterKind::MoveRoute => default_move_route().into(),
+        ParameterKind::StringArray => ParameterType::Array(vec!["Yes".into(), "No".into()]),
+    }
+}
+
+/// Fills in the parameters described by `parameter` with their default values. If `overwrite` is
+/// false, only parameters that are missing are filled in.
+pub fn apply_defaults(parameter: &Parameter, parameters: &mut Vec<ParameterType>, overwrite: bool) {
+    match parameter {
+        Parameter::Selection {
+            index,
+            parameters: options,
+            ..
+        } => {
+            let value = get_or_resize!(parameters, index.as_usize());
+            if overwrite || value.is_none() {
+                *value = options.first().map_or(0, |(value, _)| *value as i32).into();
+            }
+            let value = value.as_integer().copied();
+            if let Some((_, option)) = options.iter().find(|(v, _)| Some(*v as i32) == value) {
+                apply_defaults(option, parameters, overwrite);
+            }
+        }
+        Parameter::Group {
+            parameters: group, ..
+        } => {
+            for parameter in group {
+                apply_defaults(parameter, parameters, overwrite);
+            }
+        }
+        Parameter::Single { index, kind, .. } => {
+            let value = get_or_resize!(parameters, index.as_usize());
+            if overwrite || value.is_none() {
+                *value = default_value(kind);
+            }
+        }
+        Parameter::Label(_) | Parameter::Dummy => {}
     }
 }
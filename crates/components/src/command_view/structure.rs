@@ -0,0 +1,415 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use itertools::Itertools;
+use std::ops::Range;
+
+use luminol_config::command_db::CommandDB;
+use luminol_data::commands::CommandKind;
+use luminol_data::rpg::EventCommand;
+use luminol_data::ParameterType;
+
+/// Commands that are followed by extra lines that aren't text, like the move commands of Set Move
+/// Route or the other goods of Shop Processing.
+const CONTINUATIONS: &[(u16, u16)] = &[(209, 509), (302, 605)];
+
+/// How a line of a command list behaves in the editor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    /// The first line of a command. Selecting it selects the whole command, including any lines
+    /// and branches that belong to it.
+    Head,
+    /// A line that continues the command above it, like the second line of Show Text.
+    Continuation,
+    /// A line that is part of a branch, like Else or Branch End.
+    Structural,
+    /// The empty command at the end of a list or a branch, where new commands are inserted.
+    Empty,
+}
+
+pub fn line(code: u16, indent: usize, parameters: Vec<ParameterType>) -> EventCommand {
+    EventCommand {
+        code,
+        indent,
+        parameters,
+        guid: rand::random(),
+    }
+}
+
+pub fn empty(indent: usize) -> EventCommand {
+    line(0, indent, vec![])
+}
+
+/// The code of the lines that continue a command with this code, if any.
+pub fn continuation_code(db: &CommandDB, code: u16) -> Option<u16> {
+    if let Some(CommandKind::Multi { code, .. }) = db.get(code).map(|d| &d.kind) {
+        return Some(*code);
+    }
+    CONTINUATIONS
+        .iter()
+        .find(|(head, _)| *head == code)
+        .map(|(_, continuation)| *continuation)
+}
+
+/// The code of the line that ends a branch started by a command with this code, if any.
+pub fn end_code(db: &CommandDB, code: u16) -> Option<u16> {
+    match db.get(code)?.kind {
+        CommandKind::Branch { end_code, .. } => Some(end_code),
+        _ => None,
+    }
+}
+
+/// The Move Command lines that follow a Set Move Route line, one for every command of its route
+/// except the empty one at the end.
+pub fn route_lines(head: &EventCommand) -> Vec<EventCommand> {
+    head.parameters
+        .get(1)
+        .and_then(ParameterType::as_moveroute)
+        .map(|route| {
+            route.list[..route.list.len().saturating_sub(1)]
+                .iter()
+                .map(|c| line(509, head.indent, vec![c.clone().into()]))
+                .collect_vec()
+        })
+        .unwrap_or_default()
+}
+
+/// Works out the kind of every line in a command list.
+pub fn classify(db: &CommandDB, commands: &[EventCommand]) -> Vec<LineKind> {
+    let mut kinds = Vec::with_capacity(commands.len());
+    // The indent and the end code of every branch we're inside of
+    let mut branches: Vec<(usize, u16)> = Vec::new();
+
+    let mut index = 0;
+    while index < commands.len() {
+        let command = &commands[index];
+
+        // Drop branches that were never closed
+        while branches
+            .last()
+            .is_some_and(|&(indent, _)| command.indent < indent)
+        {
+            branches.pop();
+        }
+
+        if let Some(&(indent, end_code)) = branches.last() {
+            if command.indent == indent {
+                kinds.push(LineKind::Structural);
+                if command.code == end_code {
+                    branches.pop();
+                }
+                index += 1;
+                continue;
+            }
+        }
+
+        if command.code == 0 {
+            kinds.push(LineKind::Empty);
+            index += 1;
+            continue;
+        }
+
+        kinds.push(LineKind::Head);
+        if let Some(end_code) = end_code(db, command.code) {
+            if has_end(commands, index, end_code) {
+                branches.push((command.indent, end_code));
+            }
+        }
+        if let Some(continuation) = continuation_code(db, command.code) {
+            while commands
+                .get(index + 1)
+                .is_some_and(|c| c.code == continuation && c.indent == command.indent)
+            {
+                kinds.push(LineKind::Continuation);
+                index += 1;
+            }
+        }
+        index += 1;
+    }
+
+    kinds
+}
+
+/// Checks if the branch started at `index` is closed by a line with the end code.
+fn has_end(commands: &[EventCommand], index: usize, end_code: u16) -> bool {
+    let indent = commands[index].indent;
+    for command in &commands[index + 1..] {
+        if command.indent < indent || (command.indent == indent && command.code == 0) {
+            return false;
+        }
+        if command.indent == indent && command.code == end_code {
+            return true;
+        }
+    }
+    false
+}
+
+/// The index one past the last line of the command starting at `index`.
+pub fn block_end(commands: &[EventCommand], kinds: &[LineKind], index: usize) -> usize {
+    if kinds[index] == LineKind::Empty {
+        return index + 1;
+    }
+
+    let indent = commands[index].indent;
+    let mut end = index + 1;
+    while end < commands.len()
+        && (commands[end].indent > indent
+            || (commands[end].indent == indent
+                && matches!(kinds[end], LineKind::Continuation | LineKind::Structural)))
+    {
+        end += 1;
+    }
+    end
+}
+
+/// The index of the first line of the command that `index` is a part of.
+pub fn block_start(kinds: &[LineKind], mut index: usize) -> usize {
+    while index > 0 && kinds[index] == LineKind::Continuation {
+        index -= 1;
+    }
+    index
+}
+
+/// The start of the command right before the one starting at `start`, if it's at the same indent
+/// and inside the same branch.
+pub fn previous_block(
+    commands: &[EventCommand],
+    kinds: &[LineKind],
+    start: usize,
+) -> Option<usize> {
+    let indent = commands[start].indent;
+    for index in (0..start).rev() {
+        let command = &commands[index];
+        if command.indent < indent || (command.indent == indent && kinds[index] == LineKind::Empty)
+        {
+            return None;
+        }
+        if command.indent == indent && kinds[index] == LineKind::Head {
+            return (block_end(commands, kinds, index) == start).then_some(index);
+        }
+    }
+    None
+}
+
+/// The end of the command right after the one ending at `end`, if it's at the same indent and
+/// inside the same branch.
+pub fn next_block(
+    commands: &[EventCommand],
+    kinds: &[LineKind],
+    end: usize,
+    indent: usize,
+) -> Option<usize> {
+    (kinds.get(end) == Some(&LineKind::Head) && commands[end].indent == indent)
+        .then(|| block_end(commands, kinds, end))
+}
+
+/// Splits the branch started at `index` into its sections.
+///
+/// Each section is the index of the line that starts it (the head for the first one) and the range
+/// of lines inside it. The line that ends the branch is not included.
+pub fn sections(
+    commands: &[EventCommand],
+    kinds: &[LineKind],
+    index: usize,
+) -> Vec<(usize, Range<usize>)> {
+    let indent = commands[index].indent;
+    let end = block_end(commands, kinds, index);
+
+    let mut sections = Vec::new();
+    let mut start = index;
+    for i in index + 1..end {
+        if commands[i].indent == indent && kinds[i] == LineKind::Structural {
+            sections.push((start, start + 1..i));
+            start = i;
+        }
+    }
+    sections
+}
+
+/// Builds every line of a command from its first line.
+///
+/// When replacing the command starting at `old`, the lines inside its branches are kept. `text` is
+/// the full text of multiline commands, and `else_branch` adds an Else branch to Conditional Branch.
+pub fn build_block(
+    db: &CommandDB,
+    commands: &[EventCommand],
+    kinds: &[LineKind],
+    old: Option<usize>,
+    mut head: EventCommand,
+    text: &str,
+    else_branch: bool,
+) -> Vec<EventCommand> {
+    let indent = head.indent;
+    let code = head.code;
+    let mut block = Vec::new();
+
+    if let Some(CommandKind::Multi {
+        code: continuation, ..
+    }) = db.get(code).map(|d| &d.kind)
+    {
+        let mut lines = text.split('\n');
+        head.parameters = vec![lines.next().unwrap_or_default().into()];
+        block.push(head);
+        block.extend(lines.map(|l| line(*continuation, indent, vec![l.into()])));
+        return block;
+    }
+
+    let old_lines = old.map_or(&[][..], |index| {
+        &commands[index..block_end(commands, kinds, index)]
+    });
+
+    match code {
+        209 => {
+            let moves = route_lines(&head);
+            block.push(head);
+            block.extend(moves);
+            return block;
+        }
+        302 => {
+            block.push(head);
+            block.extend(
+                old_lines
+                    .iter()
+                    .skip(1)
+                    .take_while(|c| c.code == 605 && c.indent == indent)
+                    .cloned(),
+            );
+            return block;
+        }
+        _ => {}
+    }
+
+    let Some(end_code) = end_code(db, code) else {
+        block.push(head);
+        return block;
+    };
+
+    let old_sections = old.map_or_else(Vec::new, |index| sections(commands, kinds, index));
+    let children = |code: u16, key: Option<i32>| {
+        old_sections
+            .iter()
+            .find(|(start, _)| {
+                commands[*start].code == code
+                    && (key.is_none()
+                        || commands[*start]
+                            .parameters
+                            .first()
+                            .and_then(ParameterType::as_integer)
+                            .copied()
+                            == key)
+            })
+            .map(|(_, range)| commands[range.clone()].to_vec())
+            .filter(|lines| !lines.is_empty())
+            .unwrap_or_else(|| vec![empty(indent + 1)])
+    };
+
+    let (head_section, markers) = match code {
+        // Show Choices has a branch for every choice, and one for cancelling if that is set to Branch
+        102 => {
+            let mut markers = head
+                .parameters
+                .first()
+                .and_then(ParameterType::as_array)
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .map(|(i, choice)| {
+                    let text = choice.as_string().cloned().unwrap_or_default();
+                    line(402, indent, vec![(i as i32).into(), text.into()])
+                })
+                .collect_vec();
+            if head.parameters.get(1).and_then(ParameterType::as_integer) == Some(&5) {
+                markers.push(line(403, indent, vec![]));
+            }
+            (false, markers)
+        }
+        // Battle Processing only branches if the battle can be escaped or lost
+        301 => {
+            let flag = |i: usize| head.parameters.get(i).is_some_and(ParameterType::truthy);
+            let (escape, lose) = (flag(1), flag(2));
+            if !escape && !lose {
+                block.push(head);
+                return block;
+            }
+            let mut markers = vec![line(601, indent, vec![])];
+            if escape {
+                markers.push(line(602, indent, vec![]));
+            }
+            if lose {
+                markers.push(line(603, indent, vec![]));
+            }
+            (false, markers)
+        }
+        111 => (
+            true,
+            if else_branch {
+                vec![line(411, indent, vec![])]
+            } else {
+                vec![]
+            },
+        ),
+        _ => (
+            true,
+            old_sections
+                .iter()
+                .skip(1)
+                .map(|(start, _)| commands[*start].clone())
+                .collect(),
+        ),
+    };
+
+    let head_children = head_section.then(|| children(code, None));
+    block.push(head);
+    block.extend(head_children.into_iter().flatten());
+    for marker in markers {
+        let key = (marker.code == 402)
+            .then(|| {
+                marker
+                    .parameters
+                    .first()
+                    .and_then(ParameterType::as_integer)
+                    .copied()
+            })
+            .flatten();
+        let marker_children = children(marker.code, key);
+        block.push(marker);
+        block.extend(marker_children);
+    }
+    block.push(line(end_code, indent, vec![]));
+
+    block
+}
+
+/// Copies lines, shifting their indentation so that the first line is at `indent`.
+pub fn reindent(lines: &[EventCommand], indent: usize) -> Vec<EventCommand> {
+    let base = lines.first().map_or(0, |c| c.indent);
+    lines
+        .iter()
+        .map(|c| EventCommand {
+            indent: c.indent.saturating_sub(base) + indent,
+            guid: rand::random(),
+            ..c.clone()
+        })
+        .collect()
+}
@@ -22,14 +22,747 @@
 // terms of the Steamworks API by Valve Corporation, the licensors of this
 // Program grant you additional permission to convey the resulting work.
 
-impl super::CommandView {
-    #[allow(clippy::ptr_arg)]
+use itertools::Itertools;
+
+use luminol_config::command_db::CommandDB;
+use luminol_data::commands::CommandKind;
+use luminol_data::rpg::EventCommand;
+use luminol_data::ParameterType;
+
+use super::listing::Listing;
+use super::parameter_ui::apply_defaults;
+use super::structure::{self, LineKind};
+use super::{CommandView, WindowState};
+
+/// Where copied commands are kept in egui's memory, so they can be pasted into any command list.
+const CLIPBOARD_ID: &str = "luminol_command_view_clipboard";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Insert,
+    Edit,
+    Cut,
+    Copy,
+    Paste,
+    Delete,
+    MoveUp,
+    MoveDown,
+    SelectPrevious,
+    SelectNext,
+    EditScript,
+}
+
+impl CommandView {
+    /// Shows the command list and the dialogs for editing it.
+    ///
+    /// The response is marked as changed if the commands were modified.
     pub fn ui(
         &mut self,
-        _ui: &mut egui::Ui,
-        _db: &luminol_config::command_db::CommandDB,
-        _commands: &mut Vec<luminol_data::rpg::EventCommand>,
+        ui: &mut egui::Ui,
+        update_state: &luminol_core::UpdateState<'_>,
+        commands: &mut Vec<EventCommand>,
+    ) -> egui::Response {
+        let db = &update_state
+            .project_config
+            .as_ref()
+            .expect("project not loaded")
+            .command_db;
+
+        // Every command list ends with an empty command
+        if commands
+            .last()
+            .map_or(true, |c| c.code != 0 || c.indent != 0)
+        {
+            commands.push(structure::empty(0));
+        }
+
+        let mut modified = false;
+        let mut kinds = structure::classify(db, commands);
+        self.validate_selection(commands, &kinds);
+
+        modified |= self.window_ui(ui.ctx(), update_state, db, commands, &kinds);
+        if modified {
+            kinds = structure::classify(db, commands);
+            self.validate_selection(commands, &kinds);
+        }
+
+        let listing = Listing::new(update_state);
+        let mut action = None;
+        let inner_response =
+            ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+                ui.spacing_mut().item_spacing.y = 0.;
+                ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+
+                let selected_range = self.selected_range(commands, &kinds);
+                for index in 0..commands.len() {
+                    let selected = selected_range
+                        .as_ref()
+                        .is_some_and(|range| range.contains(&index));
+                    let response =
+                        Self::command_ui(ui, &listing, commands, &kinds, index, selected);
+
+                    if self.scroll_to_selection
+                        && selected_range
+                            .as_ref()
+                            .is_some_and(|range| range.start == index)
+                    {
+                        response.scroll_to_me(None);
+                    }
+
+                    if response.clicked() {
+                        if ui.input(|i| i.modifiers.shift) {
+                            self.extend_selection(commands, &kinds, index);
+                        } else {
+                            self.select(&kinds, index);
+                        }
+                    }
+                    if response.secondary_clicked()
+                        && !selected_range
+                            .as_ref()
+                            .is_some_and(|range| range.contains(&index))
+                    {
+                        self.select(&kinds, index);
+                    }
+                    if response.double_clicked() {
+                        self.select(&kinds, index);
+                        action = Some(Action::Edit);
+                    }
+
+                    response.context_menu(|ui| {
+                        if let Some(a) = self.context_menu_ui(ui, commands, &kinds) {
+                            action = Some(a);
+                            ui.close_menu();
+                        }
+                    });
+                }
+                self.scroll_to_selection = false;
+            });
+        let mut response = inner_response.response;
+
+        if ui.input(|i| i.pointer.any_pressed()) {
+            self.active = ui.rect_contains_pointer(response.rect);
+        }
+        if self.active
+            && matches!(self.window_state, WindowState::None)
+            && ui.memory(|m| m.focused().is_none())
+        {
+            action = action.or_else(|| Self::keyboard_action(ui));
+        }
+
+        if let Some(action) = action {
+            modified |= self.apply_action(ui.ctx(), db, commands, &kinds, action);
+        }
+
+        if modified {
+            response.mark_changed();
+        }
+        response
+    }
+
+    fn keyboard_action(ui: &mut egui::Ui) -> Option<Action> {
+        ui.input_mut(|i| {
+            let copy = i.events.iter().any(|e| matches!(e, egui::Event::Copy))
+                || i.consume_key(egui::Modifiers::COMMAND, egui::Key::C);
+            let cut = i.events.iter().any(|e| matches!(e, egui::Event::Cut))
+                || i.consume_key(egui::Modifiers::COMMAND, egui::Key::X);
+            let paste = i.events.iter().any(|e| matches!(e, egui::Event::Paste(_)))
+                || i.consume_key(egui::Modifiers::COMMAND, egui::Key::V);
+
+            if copy {
+                Some(Action::Copy)
+            } else if cut {
+                Some(Action::Cut)
+            } else if paste {
+                Some(Action::Paste)
+            } else if i.consume_key(egui::Modifiers::ALT, egui::Key::ArrowUp) {
+                Some(Action::MoveUp)
+            } else if i.consume_key(egui::Modifiers::ALT, egui::Key::ArrowDown) {
+                Some(Action::MoveDown)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                Some(Action::SelectPrevious)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+                Some(Action::SelectNext)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Enter) {
+                Some(Action::Insert)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Space) {
+                Some(Action::Edit)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Delete) {
+                Some(Action::Delete)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn context_menu_ui(
+        &self,
+        ui: &mut egui::Ui,
+        commands: &[EventCommand],
+        kinds: &[LineKind],
+    ) -> Option<Action> {
+        let is_head = self
+            .selection
+            .is_some_and(|(first, _)| kinds[first] == LineKind::Head);
+        let has_clipboard = ui.data(|d| {
+            d.get_temp::<Vec<EventCommand>>(egui::Id::new(CLIPBOARD_ID))
+                .is_some()
+        });
+        let can_move_up = self.selection.is_some_and(|(first, _)| {
+            is_head && structure::previous_block(commands, kinds, first).is_some()
+        });
+        let can_move_down = self.selected_range(commands, kinds).is_some_and(|range| {
+            is_head
+                && structure::next_block(commands, kinds, range.end, commands[range.start].indent)
+                    .is_some()
+        });
+
+        let shortcut = |modifiers, key| {
+            ui.ctx()
+                .format_shortcut(&egui::KeyboardShortcut::new(modifiers, key))
+        };
+        let items = [
+            (
+                "Insert…",
+                Action::Insert,
+                self.selection.is_some(),
+                "Enter".to_string(),
+            ),
+            ("Edit…", Action::Edit, is_head, "Space".to_string()),
+            (
+                "Cut",
+                Action::Cut,
+                is_head,
+                shortcut(egui::Modifiers::COMMAND, egui::Key::X),
+            ),
+            (
+                "Copy",
+                Action::Copy,
+                is_head,
+                shortcut(egui::Modifiers::COMMAND, egui::Key::C),
+            ),
+            (
+                "Paste",
+                Action::Paste,
+                self.selection.is_some() && has_clipboard,
+                shortcut(egui::Modifiers::COMMAND, egui::Key::V),
+            ),
+            ("Delete", Action::Delete, is_head, "Del".to_string()),
+            (
+                "Move Up",
+                Action::MoveUp,
+                can_move_up,
+                shortcut(egui::Modifiers::ALT, egui::Key::ArrowUp),
+            ),
+            (
+                "Move Down",
+                Action::MoveDown,
+                can_move_down,
+                shortcut(egui::Modifiers::ALT, egui::Key::ArrowDown),
+            ),
+            ("Edit as Text…", Action::EditScript, true, String::new()),
+        ];
+
+        let mut action = None;
+        for (label, item_action, enabled, shortcut) in items {
+            if ui
+                .add_enabled(enabled, egui::Button::new(label).shortcut_text(shortcut))
+                .clicked()
+            {
+                action = Some(item_action);
+            }
+        }
+        action
+    }
+
+    /// Applies an action to the selected commands. Returns true if the commands were modified.
+    fn apply_action(
+        &mut self,
+        ctx: &egui::Context,
+        db: &CommandDB,
+        commands: &mut Vec<EventCommand>,
+        kinds: &[LineKind],
+        action: Action,
+    ) -> bool {
+        let Some(range) = self.selected_range(commands, kinds) else {
+            return false;
+        };
+        let Some((first, last)) = self.selection else {
+            return false;
+        };
+        let is_head = kinds[first] == LineKind::Head;
+        let clipboard_id = egui::Id::new(CLIPBOARD_ID);
+
+        match action {
+            Action::EditScript => {
+                self.window_state = WindowState::Script {
+                    text: crate::event_script::decompile(db, commands),
+                    error: None,
+                };
+                false
+            }
+            Action::Insert => {
+                self.window_state = WindowState::Insert {
+                    index: first,
+                    search: String::new(),
+                };
+                false
+            }
+            Action::Edit if is_head => {
+                self.open_edit(db, commands, kinds, first);
+                false
+            }
+            Action::Edit => {
+                self.window_state = WindowState::Insert {
+                    index: first,
+                    search: String::new(),
+                };
+                false
+            }
+            Action::Copy | Action::Cut if is_head => {
+                let lines = structure::reindent(&commands[range.clone()], 0);
+                ctx.data_mut(|d| d.insert_temp(clipboard_id, lines));
+                if action == Action::Cut {
+                    commands.drain(range);
+                    self.selection = Some((first, first));
+                    true
+                } else {
+                    false
+                }
+            }
+            Action::Delete if is_head => {
+                commands.drain(range);
+                self.selection = Some((first, first));
+                true
+            }
+            Action::Paste => {
+                let Some(lines) = ctx.data(|d| d.get_temp::<Vec<EventCommand>>(clipboard_id))
+                else {
+                    return false;
+                };
+                let lines = structure::reindent(&lines, commands[first].indent);
+                commands.splice(first..first, lines);
+                self.selection = Some((first, first));
+                true
+            }
+            Action::MoveUp if is_head => {
+                let Some(previous) = structure::previous_block(commands, kinds, first) else {
+                    return false;
+                };
+                let block = commands.drain(range).collect_vec();
+                commands.splice(previous..previous, block);
+                self.selection = Some((previous, last - (first - previous)));
+                self.scroll_to_selection = true;
+                true
+            }
+            Action::MoveDown if is_head => {
+                let indent = commands[first].indent;
+                let Some(next_end) = structure::next_block(commands, kinds, range.end, indent)
+                else {
+                    return false;
+                };
+                let offset = next_end - range.end;
+                let block = commands.drain(range).collect_vec();
+                commands.splice(first + offset..first + offset, block);
+                self.selection = Some((first + offset, last + offset));
+                self.scroll_to_selection = true;
+                true
+            }
+            Action::SelectPrevious => {
+                let previous = (0..first).rev().find_map(|index| match kinds[index] {
+                    LineKind::Head | LineKind::Empty => Some(index),
+                    LineKind::Continuation => Some(structure::block_start(kinds, index)),
+                    LineKind::Structural => None,
+                });
+                if let Some(index) = previous {
+                    self.select(kinds, index);
+                    self.scroll_to_selection = true;
+                }
+                false
+            }
+            Action::SelectNext => {
+                let next = (first + 1..commands.len())
+                    .find(|&index| matches!(kinds[index], LineKind::Head | LineKind::Empty));
+                if let Some(index) = next {
+                    self.select(kinds, index);
+                    self.scroll_to_selection = true;
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Shows the dialog for the current window state. Returns true if the commands were modified.
+    fn window_ui(
+        &mut self,
+        ctx: &egui::Context,
+        update_state: &luminol_core::UpdateState<'_>,
+        db: &CommandDB,
+        commands: &mut Vec<EventCommand>,
+        kinds: &[LineKind],
+    ) -> bool {
+        let mut modified = false;
+
+        match std::mem::replace(&mut self.window_state, WindowState::None) {
+            WindowState::None => {}
+            WindowState::Insert { index, mut search } => {
+                let mut open = true;
+                let mut chosen = None;
+
+                egui::Window::new("Insert Command")
+                    .id(self.id.with("insert"))
+                    .open(&mut open)
+                    .collapsible(false)
+                    .default_height(400.)
+                    .show(ctx, |ui| {
+                        ui.add(egui::TextEdit::singleline(&mut search).hint_text("Search 🔎"));
+                        ui.separator();
+
+                        let search = search.to_lowercase();
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            ui.with_layout(
+                                egui::Layout::top_down_justified(egui::Align::LEFT),
+                                |ui| {
+                                    let descriptions = db
+                                        .iter()
+                                        .map(|d| d.code)
+                                        .unique()
+                                        .filter_map(|code| db.get(code))
+                                        .filter(|d| {
+                                            !d.hidden && d.name.to_lowercase().contains(&search)
+                                        })
+                                        .sorted_by_key(|d| d.code);
+                                    for description in descriptions {
+                                        let mut response = ui.selectable_label(
+                                            false,
+                                            format!("{}: {}", description.code, description.name),
+                                        );
+                                        if !description.description.is_empty() {
+                                            response = response
+                                                .on_hover_text(description.description.as_str());
+                                        }
+                                        if response.clicked() {
+                                            chosen = Some(description.code);
+                                        }
+                                    }
+                                },
+                            );
+                        });
+                    });
+
+                if let Some(code) = chosen.filter(|_| index < commands.len()) {
+                    modified = self.insert(db, commands, kinds, index, code);
+                } else if open {
+                    self.window_state = WindowState::Insert { index, search };
+                }
+            }
+            WindowState::Edit {
+                index,
+                mut is_new,
+                mut command,
+                mut text,
+                mut else_branch,
+            } => {
+                let Some(description) = db.get(command.code) else {
+                    return false;
+                };
+
+                let mut open = true;
+                let mut save = false;
+                egui::Window::new(description.name.as_str())
+                    .id(self.id.with("edit"))
+                    .collapsible(false)
+                    .show(ctx, |ui| {
+                        if !description.description.is_empty() {
+                            ui.label(egui::RichText::new(description.description.as_str()).weak());
+                            ui.separator();
+                        }
+
+                        match &description.kind {
+                            CommandKind::Multi { .. } => {
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut text)
+                                        .code_editor()
+                                        .desired_width(f32::INFINITY)
+                                        .desired_rows(4),
+                                );
+                                if command.code == 101 {
+                                    ui.separator();
+                                    self.message_preview.ui(ui, update_state, &text);
+                                }
+                            }
+                            CommandKind::Single(parameters)
+                            | CommandKind::Branch { parameters, .. } => {
+                                for parameter in parameters {
+                                    Self::parameter_ui(
+                                        ui,
+                                        update_state,
+                                        parameter,
+                                        &mut command.parameters,
+                                    );
+                                }
+                            }
+                        }
+
+                        // Conditional Branch is the only command where a branch is optional
+                        if command.code == 111 {
+                            ui.checkbox(&mut else_branch, "Else branch").on_hover_text(
+                                "Adds a branch that is run when the condition isn't met",
+                            );
+                        }
+
+                        ui.separator();
+                        crate::close_options_ui(ui, &mut open, &mut save);
+                    });
+
+                if save {
+                    let valid = if is_new {
+                        index < commands.len()
+                    } else {
+                        kinds.get(index) == Some(&LineKind::Head)
+                            && commands[index].code == command.code
+                    };
+                    if valid {
+                        let old = (!is_new).then_some(index);
+                        let end = old.map_or(index, |i| structure::block_end(commands, kinds, i));
+                        let block = structure::build_block(
+                            db,
+                            commands,
+                            kinds,
+                            old,
+                            command.clone(),
+                            &text,
+                            else_branch,
+                        );
+                        commands.splice(index..end, block);
+                        self.selection = Some((index, index));
+                        is_new = false;
+                        modified = true;
+                    }
+                }
+
+                if open {
+                    self.window_state = WindowState::Edit {
+                        index,
+                        is_new,
+                        command,
+                        text,
+                        else_branch,
+                    };
+                }
+            }
+            WindowState::Script {
+                mut text,
+                mut error,
+            } => {
+                let mut open = true;
+                let mut save = false;
+                egui::Window::new("Edit as Text")
+                    .id(self.id.with("script"))
+                    .collapsible(false)
+                    .default_width(500.)
+                    .show(ctx, |ui| {
+                        ui.label(
+                            egui::RichText::new(
+                                "One command per line. Branches put their commands in braces.",
+                            )
+                            .weak(),
+                        );
+                        ui.separator();
+
+                        egui::ScrollArea::vertical()
+                            .max_height(400.)
+                            .show(ui, |ui| {
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut text)
+                                        .code_editor()
+                                        .desired_width(f32::INFINITY)
+                                        .desired_rows(16),
+                                );
+                            });
+                        if let Some(error) = &error {
+                            ui.colored_label(ui.visuals().error_fg_color, error.to_string());
+                        }
+
+                        ui.separator();
+                        crate::close_options_ui(ui, &mut open, &mut save);
+                    });
+
+                if save {
+                    match crate::event_script::compile(db, &text) {
+                        Ok(compiled) => {
+                            *commands = compiled;
+                            self.selection = None;
+                            error = None;
+                            modified = true;
+                        }
+                        // Keep the dialog open so the script can be fixed
+                        Err(e) => {
+                            error = Some(e);
+                            open = true;
+                        }
+                    }
+                }
+
+                if open {
+                    self.window_state = WindowState::Script { text, error };
+                }
+            }
+        }
+
+        modified
+    }
+
+    /// Starts inserting a new command before the line at `index`. Commands without parameters are
+    /// inserted right away. Returns true if the commands were modified.
+    fn insert(
+        &mut self,
+        db: &CommandDB,
+        commands: &mut Vec<EventCommand>,
+        kinds: &[LineKind],
+        index: usize,
+        code: u16,
+    ) -> bool {
+        let Some(description) = db.get(code) else {
+            return false;
+        };
+
+        let mut command = structure::line(code, commands[index].indent, vec![]);
+        let needs_dialog = match &description.kind {
+            CommandKind::Multi { .. } => {
+                command.parameters = vec![String::new().into()];
+                true
+            }
+            CommandKind::Single(parameters) | CommandKind::Branch { parameters, .. } => {
+                for parameter in parameters {
+                    apply_defaults(parameter, &mut command.parameters, true);
+                }
+                !parameters.is_empty()
+            }
+        };
+
+        if needs_dialog {
+            self.window_state = WindowState::Edit {
+                index,
+                is_new: true,
+                command,
+                text: String::new(),
+                else_branch: code == 111,
+            };
+            false
+        } else {
+            let block = structure::build_block(db, commands, kinds, None, command, "", false);
+            commands.splice(index..index, block);
+            self.selection = Some((index, index));
+            true
+        }
+    }
+
+    /// Opens the dialog for editing the command starting at `index`.
+    fn open_edit(
+        &mut self,
+        db: &CommandDB,
+        commands: &[EventCommand],
+        kinds: &[LineKind],
+        index: usize,
     ) {
-        todo!()
+        let Some(description) = db.get(commands[index].code) else {
+            return;
+        };
+
+        let mut command = commands[index].clone();
+        let mut text = String::new();
+        match &description.kind {
+            CommandKind::Multi { .. } => {
+                let end = structure::block_end(commands, kinds, index);
+                text = commands[index..end]
+                    .iter()
+                    .map(|c| {
+                        c.parameters
+                            .first()
+                            .and_then(ParameterType::as_string)
+                            .map_or("", String::as_str)
+                    })
+                    .join("\n");
+            }
+            CommandKind::Single(parameters) | CommandKind::Branch { parameters, .. } => {
+                for parameter in parameters {
+                    apply_defaults(parameter, &mut command.parameters, false);
+                }
+            }
+        }
+        let else_branch = structure::sections(commands, kinds, index)
+            .iter()
+            .any(|(start, _)| commands[*start].code == 411);
+
+        self.window_state = WindowState::Edit {
+            index,
+            is_new: false,
+            command,
+            text,
+            else_branch,
+        };
+    }
+
+    /// The range of lines covered by the selection.
+    fn selected_range(
+        &self,
+        commands: &[EventCommand],
+        kinds: &[LineKind],
+    ) -> Option<std::ops::Range<usize>> {
+        self.selection
+            .map(|(first, last)| first..structure::block_end(commands, kinds, last))
+    }
+
+    /// Clears the selection if the commands changed in a way that made it invalid.
+    fn validate_selection(&mut self, commands: &[EventCommand], kinds: &[LineKind]) {
+        let is_start =
+            |index: usize| matches!(kinds.get(index), Some(LineKind::Head | LineKind::Empty));
+        self.selection = self.selection.filter(|&(first, last)| {
+            first <= last
+                && is_start(first)
+                && is_start(last)
+                && commands[first].indent == commands[last].indent
+                && (first == last || kinds[first] == LineKind::Head)
+        });
+    }
+
+    /// Selects the command that the line at `index` is a part of.
+    fn select(&mut self, kinds: &[LineKind], index: usize) {
+        match kinds[index] {
+            LineKind::Head | LineKind::Empty => self.selection = Some((index, index)),
+            LineKind::Continuation => {
+                let start = structure::block_start(kinds, index);
+                self.selection = Some((start, start));
+            }
+            LineKind::Structural => {}
+        }
+    }
+
+    /// Extends the selection up to the command that the line at `index` is a part of, if both are
+    /// inside the same branch.
+    fn extend_selection(&mut self, commands: &[EventCommand], kinds: &[LineKind], index: usize) {
+        let target = structure::block_start(kinds, index);
+        let Some((first, last)) = self.selection.filter(|&(first, _)| {
+            kinds[first] == LineKind::Head && kinds[target] == LineKind::Head
+        }) else {
+            self.select(kinds, index);
+            return;
+        };
+
+        let indent = commands[first].indent;
+        let (low, high) = (first.min(target), last.max(target));
+        let end = structure::block_end(commands, kinds, high);
+        let same_branch = commands[target].indent == indent
+            && (low..end).all(|i| {
+                commands[i].indent > indent
+                    || (commands[i].indent == indent && kinds[i] != LineKind::Empty)
+            });
+
+        if same_branch {
+            self.selection = Some((low, high));
+        } else {
+            self.select(kinds, index);
+        }
     }
 }
@@ -0,0 +1,396 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+//! Procedural generation of dungeon and cave layouts.
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(strum::Display, strum::EnumIter)]
+pub enum Algorithm {
+    /// Rectangular rooms placed by binary space partitioning, connected by corridors
+    #[default]
+    #[strum(to_string = "BSP Rooms")]
+    BspRooms,
+    /// Caves grown with a cellular automaton
+    #[strum(to_string = "Cellular Caves")]
+    Caves,
+    /// A maze without loops
+    Maze,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorOptions {
+    pub algorithm: Algorithm,
+    pub seed: u64,
+    /// Smallest width and height of a room for BSP rooms
+    pub min_room_size: usize,
+    /// Proportion of the area that starts out solid for caves
+    pub cave_fill: f32,
+    /// Number of smoothing steps for caves
+    pub cave_iterations: usize,
+    /// Width of corridors for BSP rooms and mazes
+    pub corridor_width: usize,
+    /// Height of the wall faces below solid areas, in tiles
+    pub wall_height: usize,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::default(),
+            seed: 0,
+            min_room_size: 4,
+            cave_fill: 0.45,
+            cave_iterations: 5,
+            corridor_width: 1,
+            wall_height: 2,
+        }
+    }
+}
+
+/// The tiles used to paint a generated layout. Autotiles should be given as the first ID of the
+/// autotile; the correct variants are chosen afterwards.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DungeonTiles {
+    pub floor: i16,
+    /// Used for the wall faces below solid areas
+    pub wall: i16,
+    /// Used for the rest of the solid areas
+    pub ceiling: i16,
+}
+
+/// The kind of each tile of a generated layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Floor,
+    Wall,
+    Ceiling,
+}
+
+/// A generated layout, stored in rows from top to bottom.
+pub struct Layout {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Cell>,
+}
+
+impl Layout {
+    /// Generates a layout. The same options and size always generate the same layout.
+    pub fn generate(options: &GeneratorOptions, width: usize, height: usize) -> Self {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(options.seed);
+        let mut grid = Grid {
+            width,
+            height,
+            open: vec![false; width * height],
+        };
+
+        // Leave a border of solid tiles around the layout
+        if width >= 5 && height >= 5 {
+            match options.algorithm {
+                Algorithm::BspRooms => grid.bsp_rooms(&mut rng, options),
+                Algorithm::Caves => grid.caves(&mut rng, options),
+                Algorithm::Maze => grid.maze(&mut rng, options),
+            }
+        }
+
+        let wall_height = options.wall_height;
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                if grid.is_open(x, y) {
+                    return Cell::Floor;
+                }
+                // Solid tiles are walls if there is floor below them close enough, and ceiling
+                // otherwise
+                let floor_below = (1..=wall_height)
+                    .take_while(|&d| y + d < height)
+                    .find(|&d| grid.is_open(x, y + d));
+                if floor_below.is_some() {
+                    Cell::Wall
+                } else {
+                    Cell::Ceiling
+                }
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        self.cells[x + y * self.width]
+    }
+
+    pub fn tile_id(&self, x: usize, y: usize, tiles: &DungeonTiles) -> i16 {
+        match self.get(x, y) {
+            Cell::Floor => tiles.floor,
+            Cell::Wall => tiles.wall,
+            Cell::Ceiling => tiles.ceiling,
+        }
+    }
+}
+
+/// Which tiles are open during generation.
+struct Grid {
+    width: usize,
+    height: usize,
+    open: Vec<bool>,
+}
+
+/// A rectangle of tiles as (x, y, width, height).
+type Rect = (usize, usize, usize, usize);
+
+impl Grid {
+    fn is_open(&self, x: usize, y: usize) -> bool {
+        self.open[x + y * self.width]
+    }
+
+    /// Opens a rectangle, clamped to the inside of the border.
+    fn carve(&mut self, (x, y, width, height): Rect) {
+        for y in y.max(1)..(y + height).min(self.height - 1) {
+            for x in x.max(1)..(x + width).min(self.width - 1) {
+                self.open[x + y * self.width] = true;
+            }
+        }
+    }
+
+    /// Opens an L-shaped corridor between two tiles.
+    fn carve_corridor(
+        &mut self,
+        rng: &mut impl Rng,
+        from: (usize, usize),
+        to: (usize, usize),
+        width: usize,
+    ) {
+        let corner = if rng.gen_bool(0.5) {
+            (to.0, from.1)
+        } else {
+            (from.0, to.1)
+        };
+        for (a, b) in [(from, corner), (corner, to)] {
+            let (min_x, max_x) = (a.0.min(b.0), a.0.max(b.0));
+            let (min_y, max_y) = (a.1.min(b.1), a.1.max(b.1));
+            self.carve((min_x, min_y, max_x - min_x + width, max_y - min_y + width));
+        }
+    }
+
+    fn bsp_rooms(&mut self, rng: &mut impl Rng, options: &GeneratorOptions) {
+        let bounds = (1, 1, self.width - 2, self.height - 2);
+        self.bsp_split(rng, options, bounds);
+    }
+
+    /// Splits an area in two until it is too small, places a room in each of the smallest areas
+    /// and connects the two halves of each split. Returns a tile inside one of the rooms placed in
+    /// the area.
+    fn bsp_split(
+        &mut self,
+        rng: &mut impl Rng,
+        options: &GeneratorOptions,
+        area: Rect,
+    ) -> (usize, usize) {
+        let (x, y, width, height) = area;
+        let min_room_size = options.min_room_size.max(2);
+        // Rooms need space for the wall faces above them and one tile on the other sides
+        let top_padding = options.wall_height.max(1);
+        let min_area_width = min_room_size + 2;
+        let min_area_height = min_room_size + top_padding + 1;
+
+        let can_split_x = width >= min_area_width * 2;
+        let can_split_y = height >= min_area_height * 2;
+        if can_split_x || can_split_y {
+            let split_x = if can_split_x && can_split_y {
+                // Prefer cutting across the longer side to avoid long thin areas
+                width * min_area_height >= height * min_area_width
+            } else {
+                can_split_x
+            };
+
+            let (first, second) = if split_x {
+                let split = rng.gen_range(min_area_width..=width - min_area_width);
+                ((x, y, split, height), (x + split, y, width - split, height))
+            } else {
+                let split = rng.gen_range(min_area_height..=height - min_area_height);
+                ((x, y, width, split), (x, y + split, width, height - split))
+            };
+            let a = self.bsp_split(rng, options, first);
+            let b = self.bsp_split(rng, options, second);
+            self.carve_corridor(rng, a, b, options.corridor_width.max(1));
+            return if rng.gen_bool(0.5) { a } else { b };
+        }
+
+        // Place a room of random size somewhere in the area
+        let max_width = width.saturating_sub(2).max(1);
+        let max_height = height.saturating_sub(top_padding + 1).max(1);
+        let room_width = rng.gen_range(min_room_size.min(max_width)..=max_width);
+        let room_height = rng.gen_range(min_room_size.min(max_height)..=max_height);
+        let room_x = x + 1 + rng.gen_range(0..=max_width - room_width);
+        let room_y = y + top_padding + rng.gen_range(0..=max_height - room_height);
+        self.carve((room_x, room_y, room_width, room_height));
+
+        (
+            rng.gen_range(room_x..room_x + room_width),
+            rng.gen_range(room_y..room_y + room_height),
+        )
+    }
+
+    fn caves(&mut self, rng: &mut impl Rng, options: &GeneratorOptions) {
+        let (width, height) = (self.width, self.height);
+        let is_border = |x: usize, y: usize| x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+
+        for y in 0..height {
+            for x in 0..width {
+                self.open[x + y * width] =
+                    !is_border(x, y) && rng.gen::<f32>() >= options.cave_fill;
+            }
+        }
+
+        for _ in 0..options.cave_iterations {
+            let previous = self.open.clone();
+            for y in 1..height - 1 {
+                for x in 1..width - 1 {
+                    let solid_neighbors = (y - 1..=y + 1)
+                        .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                        .filter(|&(nx, ny)| (nx, ny) != (x, y) && !previous[nx + ny * width])
+                        .count();
+                    // Tiles surrounded by walls become walls and vice versa
+                    if solid_neighbors >= 5 {
+                        self.open[x + y * width] = false;
+                    } else if solid_neighbors <= 3 {
+                        self.open[x + y * width] = true;
+                    }
+                }
+            }
+        }
+
+        self.keep_largest_region();
+    }
+
+    /// Fills every open region except the largest one, so that every floor tile can be reached.
+    fn keep_largest_region(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let mut region_ids = vec![usize::MAX; width * height];
+        let mut region_sizes = Vec::new();
+
+        for start in 0..width * height {
+            if !self.open[start] || region_ids[start] != usize::MAX {
+                continue;
+            }
+            let region_id = region_sizes.len();
+            let mut size = 0;
+            let mut stack = vec![start];
+            region_ids[start] = region_id;
+            while let Some(index) = stack.pop() {
+                size += 1;
+                let (x, y) = (index % width, index / width);
+                let neighbors = [
+                    (x > 0).then(|| index - 1),
+                    (x + 1 < width).then(|| index + 1),
+                    (y > 0).then(|| index - width),
+                    (y + 1 < height).then(|| index + width),
+                ];
+                for neighbor in neighbors.into_iter().flatten() {
+                    if self.open[neighbor] && region_ids[neighbor] == usize::MAX {
+                        region_ids[neighbor] = region_id;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            region_sizes.push(size);
+        }
+
+        let Some(largest) = region_sizes
+            .iter()
+            .enumerate()
+            .max_by_key(|&(_, &size)| size)
+            .map(|(id, _)| id)
+        else {
+            return;
+        };
+        for (open, region_id) in self.open.iter_mut().zip(region_ids) {
+            *open &= region_id == largest;
+        }
+    }
+
+    fn maze(&mut self, rng: &mut impl Rng, options: &GeneratorOptions) {
+        // Every cell of the maze is a square of corridor separated from the cell to its right by
+        // one solid tile, and from the cell below it by enough solid tiles for a wall face
+        let corridor = options.corridor_width.max(1);
+        let wall_below = options.wall_height.max(1);
+        let pitch_x = corridor + 1;
+        let pitch_y = corridor + wall_below;
+        let columns = (self.width - 1) / pitch_x;
+        let rows = (self.height - 1).saturating_sub(wall_below) / pitch_y;
+        if columns == 0 || rows == 0 {
+            return;
+        }
+
+        let cell_origin =
+            |column: usize, row: usize| (1 + column * pitch_x, wall_below + row * pitch_y);
+
+        let mut visited = vec![false; columns * rows];
+        let start = (rng.gen_range(0..columns), rng.gen_range(0..rows));
+        let mut stack = vec![start];
+        visited[start.0 + start.1 * columns] = true;
+        let (x, y) = cell_origin(start.0, start.1);
+        self.carve((x, y, corridor, corridor));
+
+        while let Some(&(column, row)) = stack.last() {
+            let mut neighbors = [
+                (column > 0).then(|| (column - 1, row)),
+                (column + 1 < columns).then(|| (column + 1, row)),
+                (row > 0).then(|| (column, row - 1)),
+                (row + 1 < rows).then(|| (column, row + 1)),
+            ]
+            .into_iter()
+            .flatten()
+            .filter(|&(c, r)| !visited[c + r * columns])
+            .collect::<Vec<_>>();
+
+            neighbors.shuffle(rng);
+            let Some(&(next_column, next_row)) = neighbors.first() else {
+                stack.pop();
+                continue;
+            };
+
+            visited[next_column + next_row * columns] = true;
+            stack.push((next_column, next_row));
+
+            // Open the cell and the wall between it and the current cell
+            let (x, y) = cell_origin(column, row);
+            let (next_x, next_y) = cell_origin(next_column, next_row);
+            self.carve((next_x, next_y, corridor, corridor));
+            self.carve((
+                x.min(next_x),
+                y.min(next_y),
+                x.abs_diff(next_x) + corridor,
+                y.abs_diff(next_y) + corridor,
+            ));
+        }
+    }
+}
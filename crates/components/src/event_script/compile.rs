@@ -0,0 +1,858 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use std::collections::HashMap;
+
+use luminol_config::command_db::CommandDB;
+use luminol_data::commands::{CommandKind, Parameter, ParameterKind};
+use luminol_data::rpg::{AudioFile, EventCommand, MoveCommand, MoveRoute};
+use luminol_data::{Color, ParameterType, Tone};
+
+use super::lexer::{tokenize, Token, TokenKind};
+use super::{resolve, snake_case, ScriptError};
+use crate::command_view::parameter_ui::apply_defaults;
+use crate::command_view::structure::{empty, end_code, line, route_lines};
+
+/// Compiles a script into a command list, ending with the empty command that ends every list.
+pub fn compile(db: &CommandDB, text: &str) -> Result<Vec<EventCommand>, ScriptError> {
+    let mut parser = Parser::new(db, tokenize(text)?);
+    parser.skip_newlines();
+    if parser.peek().kind == TokenKind::Raw {
+        parser.next();
+        parser.raw_list()?;
+    } else {
+        parser.block(0)?;
+        let token = parser.peek();
+        if token.kind != TokenKind::Eof {
+            return Err(token.error(format!("unexpected {}", token.kind)));
+        }
+    }
+    Ok(parser.commands)
+}
+
+/// Compiles a single command without its branches, if the text is exactly one command.
+pub(super) fn compile_command(db: &CommandDB, text: &str, hidden: bool) -> Option<EventCommand> {
+    let mut parser = Parser::new(db, tokenize(text).ok()?);
+    parser.command(0, hidden).ok()?;
+    (parser.peek().kind == TokenKind::Eof).then(|| parser.commands.swap_remove(0))
+}
+
+/// A parameter value as it is written in a script.
+#[derive(Clone, Debug)]
+enum Value {
+    Int(i32),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Nil,
+    /// A bare word, like `ON`, `player` or `>=`
+    Word(String),
+    /// An ID of something, like `switch[12]`
+    Ref(String, i32),
+    Array(Vec<Value>),
+    /// A value with several fields, like `audio("001-Battle01", 100, 100)`
+    Call(String, Vec<Value>),
+}
+
+struct Arg {
+    name: Option<String>,
+    value: Value,
+    token: Token,
+}
+
+/// A parameter of a command's description that a value can be given to, along with the options
+/// that have to be picked for it to be used.
+struct Slot<'d> {
+    /// The index of every selection on the way to this parameter, and the option picked for it
+    path: Vec<(usize, i8, &'d Parameter)>,
+    target: Target<'d>,
+}
+
+enum Target<'d> {
+    Value {
+        index: usize,
+        name: String,
+        kind: &'d ParameterKind,
+    },
+    /// A label in an option without values, which picks the option
+    Label(String),
+}
+
+fn slots<'d>(
+    parameter: &'d Parameter,
+    path: &mut Vec<(usize, i8, &'d Parameter)>,
+    slots: &mut Vec<Slot<'d>>,
+) {
+    match parameter {
+        Parameter::Selection {
+            index,
+            parameters: options,
+            ..
+        } => {
+            for (value, option) in options {
+                path.push((index.as_usize(), *value, option));
+                self::slots(option, path, slots);
+                path.pop();
+            }
+        }
+        Parameter::Group { parameters, .. } => {
+            for parameter in parameters {
+                self::slots(parameter, path, slots);
+            }
+        }
+        Parameter::Single {
+            index, name, kind, ..
+        } => slots.push(Slot {
+            path: path.clone(),
+            target: Target::Value {
+                index: index.as_usize(),
+                name: snake_case(name),
+                kind,
+            },
+        }),
+        Parameter::Label(label) => slots.push(Slot {
+            path: path.clone(),
+            target: Target::Label(snake_case(label)),
+        }),
+        Parameter::Dummy => {}
+    }
+}
+
+/// The kinds of parameters that `name[id]` can be given to.
+fn ref_kinds(name: &str) -> &'static [ParameterKind] {
+    use ParameterKind::*;
+    match name {
+        "switch" => &[Switch],
+        "variable" => &[Variable],
+        "actor" => &[Actor, ActorOrParty],
+        "class" => &[Class],
+        "skill" => &[Skill],
+        "item" => &[Item],
+        "weapon" => &[Weapon],
+        "armor" => &[Armor],
+        "enemy" => &[Enemy],
+        "troop" => &[Troop],
+        "state" => &[State],
+        "animation" => &[Animation],
+        "common_event" => &[CommonEvent],
+        "map" => &[Map],
+        "event" => &[Character],
+        _ => &[],
+    }
+}
+
+fn is_self_switch(text: &str) -> bool {
+    ["A", "B", "C", "D"].contains(&text)
+}
+
+fn variant(variants: &[(String, i8)], text: &str) -> Option<i8> {
+    variants
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(text))
+        .map(|(_, value)| *value)
+}
+
+/// Whether a value written without a name can be given to a parameter.
+fn accepts(target: &Target<'_>, value: &Value) -> bool {
+    use ParameterKind as K;
+
+    let kind = match target {
+        Target::Label(label) => {
+            return matches!(value, Value::Word(text) | Value::String(text) if snake_case(text) == *label)
+        }
+        Target::Value { kind, .. } => *kind,
+    };
+    match value {
+        Value::Ref(name, _) => ref_kinds(name).contains(kind),
+        Value::Word(word) => match kind {
+            K::Enum { variants } => variant(variants, word).is_some(),
+            K::SelfSwitch => is_self_switch(word),
+            K::Character => word == "player" || word == "this",
+            _ => false,
+        },
+        Value::String(string) => match kind {
+            K::Enum { variants } => variant(variants, string).is_some(),
+            K::SelfSwitch => is_self_switch(string),
+            K::String => true,
+            _ => false,
+        },
+        Value::Int(_) => !matches!(
+            kind,
+            K::Enum { .. }
+                | K::String
+                | K::SelfSwitch
+                | K::Bool
+                | K::AudioFile
+                | K::Color
+                | K::Tone
+                | K::MoveRoute
+                | K::StringArray
+        ),
+        Value::Bool(_) => matches!(kind, K::Bool | K::IntBool),
+        Value::Array(_) => *kind == K::StringArray,
+        Value::Call(name, _) => matches!(
+            (name.as_str(), kind),
+            ("audio", K::AudioFile)
+                | ("color", K::Color)
+                | ("tone", K::Tone)
+                | ("route", K::MoveRoute)
+        ),
+        Value::Float(_) | Value::Nil => false,
+    }
+}
+
+/// What a parameter of this kind takes, for error messages.
+fn expected(kind: &ParameterKind) -> String {
+    use ParameterKind as K;
+    match kind {
+        K::Switch => "a switch ID".to_string(),
+        K::Variable => "a variable ID".to_string(),
+        K::SelfSwitch => "a self switch: A, B, C or D".to_string(),
+        K::String => "a string".to_string(),
+        K::IntBool | K::Bool => "true or false".to_string(),
+        K::Character => "player, this or an event ID".to_string(),
+        K::AudioFile => "audio(name, volume, pitch)".to_string(),
+        K::Color => "color(red, green, blue, alpha)".to_string(),
+        K::Tone => "tone(red, green, blue, gray)".to_string(),
+        K::MoveRoute => "route(repeat, skippable, [move(code, ...), ...])".to_string(),
+        K::StringArray => "a list of strings".to_string(),
+        K::Enum { variants } => format!(
+            "one of {}",
+            variants
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => "a number".to_string(),
+    }
+}
+
+/// Converts a value for a parameter of this kind.
+fn convert(kind: &ParameterKind, value: &Value) -> Option<ParameterType> {
+    use ParameterKind as K;
+    Some(match (kind, value) {
+        (K::Enum { variants }, Value::Word(text) | Value::String(text)) => {
+            (variant(variants, text)? as i32).into()
+        }
+        (K::Enum { .. }, Value::Int(value)) => (*value).into(),
+        (K::Enum { .. }, _) => return None,
+        (K::String, Value::String(string)) => string.clone().into(),
+        (K::SelfSwitch, Value::Word(text) | Value::String(text)) if is_self_switch(text) => {
+            text.clone().into()
+        }
+        (K::Bool, Value::Bool(value)) => (*value).into(),
+        (K::IntBool, Value::Bool(value)) => (*value as i32).into(),
+        (K::Character, Value::Word(word)) if word == "player" => (-1).into(),
+        (K::Character, Value::Word(word)) if word == "this" => 0.into(),
+        (K::StringArray, Value::Array(values))
+            if values.iter().all(|v| matches!(v, Value::String(_))) =>
+        {
+            raw(value).ok()?
+        }
+        (K::AudioFile | K::Color | K::Tone | K::MoveRoute, Value::Call(..)) => {
+            let value = raw(value).ok()?;
+            let matches = matches!(
+                (kind, &value),
+                (K::AudioFile, ParameterType::AudioFile(_))
+                    | (K::Color, ParameterType::Color(_))
+                    | (K::Tone, ParameterType::Tone(_))
+                    | (K::MoveRoute, ParameterType::MoveRoute(_))
+            );
+            return matches.then_some(value);
+        }
+        (
+            K::String
+            | K::SelfSwitch
+            | K::Bool
+            | K::StringArray
+            | K::AudioFile
+            | K::Color
+            | K::Tone
+            | K::MoveRoute,
+            _,
+        ) => return None,
+        (_, Value::Int(value) | Value::Ref(_, value)) => (*value).into(),
+        _ => return None,
+    })
+}
+
+/// Converts a value as it is written, without a parameter description.
+fn raw(value: &Value) -> Result<ParameterType, String> {
+    let number = |value: &Value| match value {
+        Value::Int(value) => Ok(*value as f64),
+        Value::Float(value) => Ok(*value),
+        _ => Err("expected a number".to_string()),
+    };
+    let byte = |value: &Value| match value {
+        Value::Int(value) => {
+            u8::try_from(*value).map_err(|_| format!("{value} is not between 0 and 255"))
+        }
+        _ => Err("expected a number".to_string()),
+    };
+
+    Ok(match value {
+        Value::Int(value) | Value::Ref(_, value) => (*value).into(),
+        Value::Float(value) => ParameterType::Float(*value),
+        Value::String(string) => string.clone().into(),
+        Value::Bool(value) => (*value).into(),
+        Value::Nil => ParameterType::None,
+        Value::Word(word) => return Err(format!("unexpected `{word}`")),
+        Value::Array(values) => {
+            ParameterType::Array(values.iter().map(raw).collect::<Result<_, _>>()?)
+        }
+        Value::Call(name, fields) => match (name.as_str(), fields.as_slice()) {
+            ("audio", [name, volume, pitch]) => AudioFile {
+                name: match name {
+                    Value::String(name) => Some(name.into()),
+                    Value::Nil => None,
+                    _ => return Err("expected the name of the audio file".to_string()),
+                },
+                volume: byte(volume)?,
+                pitch: byte(pitch)?,
+            }
+            .into(),
+            ("color", [red, green, blue, alpha]) => Color {
+                red: number(red)?,
+                green: number(green)?,
+                blue: number(blue)?,
+                alpha: number(alpha)?,
+            }
+            .into(),
+            ("tone", [red, green, blue, gray]) => Tone {
+                red: number(red)?,
+                green: number(green)?,
+                blue: number(blue)?,
+                gray: number(gray)?,
+            }
+            .into(),
+            ("route", [Value::Bool(repeat), Value::Bool(skippable), Value::Array(moves)]) => {
+                let mut list = moves
+                    .iter()
+                    .map(|value| match raw(value)? {
+                        ParameterType::MoveCommand(command) => Ok(command),
+                        _ => Err("expected move(code, ...)".to_string()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Every route ends with an empty move command, which is written implicitly
+                if list.last().map_or(true, |command| command.code != 0) {
+                    list.push(MoveCommand {
+                        code: 0,
+                        parameters: vec![],
+                        guid: rand::random(),
+                    });
+                }
+                MoveRoute {
+                    repeat: *repeat,
+                    skippable: *skippable,
+                    list,
+                }
+                .into()
+            }
+            ("move", [Value::Int(code), parameters @ ..]) => MoveCommand {
+                code: u16::try_from(*code).map_err(|_| format!("{code} is not a move code"))?,
+                parameters: parameters.iter().map(raw).collect::<Result<_, _>>()?,
+                guid: rand::random(),
+            }
+            .into(),
+            ("audio" | "color" | "tone" | "route" | "move", _) => {
+                return Err(format!("wrong fields for `{name}(...)`"))
+            }
+            _ => return Err(format!("unknown value `{name}(...)`")),
+        },
+    })
+}
+
+struct Parser<'a> {
+    db: &'a CommandDB,
+    tokens: Vec<Token>,
+    position: usize,
+    commands: Vec<EventCommand>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(db: &'a CommandDB, tokens: Vec<Token>) -> Self {
+        Self {
+            db,
+            tokens,
+            position: 0,
+            commands: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::Eof {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<Token, ScriptError> {
+        let token = self.next();
+        if token.kind == kind {
+            Ok(token)
+        } else {
+            Err(token.error(format!("expected {kind}, found {}", token.kind)))
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek().kind == TokenKind::Newline {
+            self.next();
+        }
+    }
+
+    /// Parses statements until a `}` or the end of the script, and ends the list.
+    fn block(&mut self, indent: usize) -> Result<(), ScriptError> {
+        loop {
+            self.skip_newlines();
+            if matches!(self.peek().kind, TokenKind::CloseBrace | TokenKind::Eof) {
+                break;
+            }
+            self.statement(indent)?;
+        }
+        self.commands.push(empty(indent));
+        Ok(())
+    }
+
+    /// Parses the commands inside braces.
+    fn body(&mut self, indent: usize) -> Result<(), ScriptError> {
+        let open = self.expect(TokenKind::OpenBrace)?;
+        self.block(indent + 1)?;
+        if self.peek().kind == TokenKind::Eof {
+            return Err(open.error("this `{` is never closed"));
+        }
+        self.expect(TokenKind::CloseBrace)?;
+        Ok(())
+    }
+
+    /// Parses a command along with its branches.
+    fn statement(&mut self, indent: usize) -> Result<(), ScriptError> {
+        let code = self.command(indent, false)?;
+
+        let mut has_branches = false;
+        if self.peek().kind == TokenKind::OpenBrace {
+            self.body(indent)?;
+            has_branches = true;
+        }
+
+        if let Some(end_code) = end_code(self.db, code) {
+            let mut ended = false;
+            loop {
+                let position = self.position;
+                self.skip_newlines();
+                if !self.at_marker() {
+                    self.position = position;
+                    break;
+                }
+                has_branches = true;
+                if self.command(indent, true)? == end_code {
+                    ended = true;
+                    break;
+                }
+                if self.peek().kind == TokenKind::OpenBrace {
+                    self.body(indent)?;
+                }
+            }
+            // Commands without branches, like Battle Processing that can't be escaped or lost,
+            // don't have an end either
+            if has_branches && !ended {
+                self.commands.push(line(end_code, indent, vec![]));
+            }
+        }
+
+        let token = self.peek();
+        match token.kind {
+            TokenKind::Newline | TokenKind::Eof | TokenKind::CloseBrace => Ok(()),
+            _ => Err(token.error(format!(
+                "expected the end of the line, found {}",
+                token.kind
+            ))),
+        }
+    }
+
+    /// Whether the next statement is part of a branch, like Else.
+    fn at_marker(&self) -> bool {
+        let code = match &self.peek().kind {
+            TokenKind::Ident(keyword) => resolve(self.db, keyword, true),
+            TokenKind::Code(code) => Some(*code),
+            _ => None,
+        };
+        code.and_then(|code| self.db.get(code))
+            .is_some_and(|description| description.hidden)
+    }
+
+    /// Parses a command and its parameters, and adds its lines.
+    fn command(&mut self, indent: usize, hidden: bool) -> Result<u16, ScriptError> {
+        let token = self.next();
+        let (code, is_raw) = match &token.kind {
+            TokenKind::Code(code) => (*code, true),
+            TokenKind::Ident(keyword) => (
+                resolve(self.db, keyword, hidden)
+                    .ok_or_else(|| token.error(format!("unknown command `{keyword}`")))?,
+                false,
+            ),
+            kind => return Err(token.error(format!("expected a command, found {kind}"))),
+        };
+        let args = self.args()?;
+
+        let description = self.db.get(code);
+        let parameters = match description.map(|d| &d.kind) {
+            _ if is_raw => {
+                let parameters = args
+                    .iter()
+                    .map(|arg| {
+                        if arg.name.is_some() {
+                            return Err(arg
+                                .token
+                                .error("commands written by code only take values"));
+                        }
+                        raw(&arg.value).map_err(|message| arg.token.error(message))
+                    })
+                    .collect::<Result<_, _>>()?;
+                self.commands.push(line(code, indent, parameters));
+                return Ok(code);
+            }
+            Some(CommandKind::Multi {
+                code: continuation, ..
+            }) => {
+                let mut lines = args.iter().map(|arg| match arg {
+                    Arg {
+                        name: None,
+                        value: Value::String(text),
+                        ..
+                    } => Ok(text.clone()),
+                    _ => Err(arg.token.error("expected a line of text")),
+                });
+                let head = lines.next().transpose()?.unwrap_or_default();
+                self.commands.push(line(code, indent, vec![head.into()]));
+                for text in lines {
+                    self.commands
+                        .push(line(*continuation, indent, vec![text?.into()]));
+                }
+                return Ok(code);
+            }
+            Some(CommandKind::Single(parameters) | CommandKind::Branch { parameters, .. }) => {
+                parameters
+            }
+            None => unreachable!("keywords only resolve to known commands"),
+        };
+
+        let parameters = match_args(parameters, &args, &token)?;
+        let head = line(code, indent, parameters);
+        // Set Move Route is followed by a line for every command of the route
+        let moves = if code == 209 {
+            route_lines(&head)
+        } else {
+            vec![]
+        };
+        self.commands.push(head);
+        self.commands.extend(moves);
+        Ok(code)
+    }
+
+    /// Parses the parameters of a command, up to the end of the line or a brace.
+    fn args(&mut self) -> Result<Vec<Arg>, ScriptError> {
+        let mut args = Vec::new();
+        loop {
+            let token = self.peek().clone();
+            match &token.kind {
+                TokenKind::Newline
+                | TokenKind::Eof
+                | TokenKind::OpenBrace
+                | TokenKind::CloseBrace => break,
+                // A comma lets the parameters continue on the next line
+                TokenKind::Comma => {
+                    self.next();
+                    self.skip_newlines();
+                }
+                TokenKind::Ident(name)
+                    if self.tokens.get(self.position + 1).map(|t| &t.kind)
+                        == Some(&TokenKind::Colon) =>
+                {
+                    self.next();
+                    self.next();
+                    args.push(Arg {
+                        name: Some(name.clone()),
+                        value: self.value()?,
+                        token,
+                    });
+                }
+                _ => args.push(Arg {
+                    name: None,
+                    value: self.value()?,
+                    token,
+                }),
+            }
+        }
+        Ok(args)
+    }
+
+    fn value(&mut self) -> Result<Value, ScriptError> {
+        let token = self.next();
+        Ok(match token.kind.clone() {
+            TokenKind::Int(value) => Value::Int(value),
+            TokenKind::Float(value) => Value::Float(value),
+            TokenKind::String(string) => Value::String(string),
+            TokenKind::Op(op) => Value::Word(op),
+            TokenKind::OpenBracket => Value::Array(self.list(TokenKind::CloseBracket)?),
+            TokenKind::Ident(ident) => match (ident.as_str(), &self.peek().kind) {
+                ("true", _) => Value::Bool(true),
+                ("false", _) => Value::Bool(false),
+                ("nil", _) => Value::Nil,
+                (_, TokenKind::OpenBracket) => {
+                    self.next();
+                    let id = self.next();
+                    let TokenKind::Int(id) = id.kind else {
+                        return Err(id.error(format!("expected an ID, found {}", id.kind)));
+                    };
+                    self.expect(TokenKind::CloseBracket)?;
+                    Value::Ref(ident, id)
+                }
+                (_, TokenKind::OpenParen) => {
+                    self.next();
+                    Value::Call(ident, self.list(TokenKind::CloseParen)?)
+                }
+                _ => Value::Word(ident),
+            },
+            kind => return Err(token.error(format!("expected a value, found {kind}"))),
+        })
+    }
+
+    /// Parses values separated by commas, up to a closing bracket.
+    fn list(&mut self, close: TokenKind) -> Result<Vec<Value>, ScriptError> {
+        let mut values = Vec::new();
+        loop {
+            if self.peek().kind == close {
+                self.next();
+                return Ok(values);
+            }
+            values.push(self.value()?);
+            match self.peek().kind {
+                TokenKind::Comma => {
+                    self.next();
+                }
+                ref kind if *kind == close => {}
+                ref kind => {
+                    return Err(self.peek().error(format!("expected {close}, found {kind}")))
+                }
+            }
+        }
+    }
+
+    /// Parses a list written line by line, as `@code indent parameters...`.
+    fn raw_list(&mut self) -> Result<(), ScriptError> {
+        loop {
+            self.skip_newlines();
+            let token = self.next();
+            let code = match &token.kind {
+                TokenKind::Eof => return Ok(()),
+                TokenKind::Code(code) => *code,
+                kind => return Err(token.error(format!("expected `@code`, found {kind}"))),
+            };
+            let indent = self.next();
+            let TokenKind::Int(indent @ 0..) = indent.kind else {
+                return Err(indent.error(format!("expected an indent, found {}", indent.kind)));
+            };
+            let parameters = self
+                .args()?
+                .iter()
+                .map(|arg| match arg.name {
+                    Some(_) => Err(arg.token.error("commands written by code only take values")),
+                    None => raw(&arg.value).map_err(|message| arg.token.error(message)),
+                })
+                .collect::<Result<_, _>>()?;
+            self.commands.push(line(code, indent as usize, parameters));
+
+            let token = self.peek();
+            if !matches!(token.kind, TokenKind::Newline | TokenKind::Eof) {
+                return Err(token.error(format!(
+                    "expected the end of the line, found {}",
+                    token.kind
+                )));
+            }
+        }
+    }
+}
+
+/// Gives the parameters of a command to the parameters of its description.
+fn match_args(
+    parameters: &[Parameter],
+    args: &[Arg],
+    command: &Token,
+) -> Result<Vec<ParameterType>, ScriptError> {
+    let mut all_slots = Vec::new();
+    for parameter in parameters {
+        slots(parameter, &mut Vec::new(), &mut all_slots);
+    }
+    let mut values = Vec::new();
+    for parameter in parameters {
+        apply_defaults(parameter, &mut values, true);
+    }
+
+    let mut picked: HashMap<usize, i8> = HashMap::new();
+    let mut filled = vec![false; all_slots.len()];
+    for arg in args {
+        let compatible = |slot: &Slot<'_>| {
+            slot.path
+                .iter()
+                .all(|(index, value, _)| picked.get(index).map_or(true, |v| v == value))
+        };
+        let found = match &arg.name {
+            Some(name) => {
+                let named = all_slots.iter().enumerate().filter(
+                    |(_, slot)| matches!(&slot.target, Target::Value { name: n, .. } if n == name),
+                );
+                if named.clone().next().is_none() {
+                    return Err(arg
+                        .token
+                        .error(format!("{} has no parameter `{name}`", command.kind)));
+                }
+                let mut compatible_slots = named.filter(|(_, slot)| compatible(slot));
+                compatible_slots
+                    .clone()
+                    .find(|(i, _)| !filled[*i])
+                    .or_else(|| compatible_slots.next())
+            }
+            None => all_slots.iter().enumerate().find(|(i, slot)| {
+                !filled[*i] && compatible(slot) && accepts(&slot.target, &arg.value)
+            }),
+        };
+        let Some((i, slot)) = found else {
+            return Err(arg.token.error(match &arg.name {
+                Some(name) => {
+                    format!("`{name}` can't be used together with the parameters before it")
+                }
+                None => format!(
+                    "{} has no parameter left that takes this value",
+                    command.kind
+                ),
+            }));
+        };
+
+        let value = match &slot.target {
+            Target::Value { kind, name, .. } => {
+                Some(convert(kind, &arg.value).ok_or_else(|| {
+                    arg.token
+                        .error(format!("expected {} for `{name}`", expected(kind)))
+                })?)
+            }
+            Target::Label(_) => None,
+        };
+        for &(index, option_value, option) in &slot.path {
+            if picked.insert(index, option_value).is_none() {
+                *crate::get_or_resize!(values, index) = (option_value as i32).into();
+                apply_defaults(option, &mut values, true);
+            }
+        }
+        if let (Target::Value { index, .. }, Some(value)) = (&slot.target, value) {
+            *crate::get_or_resize!(values, *index) = value;
+        }
+        filled[i] = true;
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(commands: &[EventCommand]) -> Vec<(u16, usize, Vec<ParameterType>)> {
+        commands
+            .iter()
+            .map(|c| (c.code, c.indent, c.parameters.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn branches_and_named_parameters() {
+        let db = CommandDB::new(luminol_config::RMVer::XP);
+        let commands = compile(
+            &db,
+            r#"
+text "Hello!", "How are you?"
+if switch[12] {
+  transfer map:3 x:10 y:5
+} else {
+  wait frames:20
+}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            lines(&commands),
+            vec![
+                (101, 0, vec!["Hello!".into()]),
+                (401, 0, vec!["How are you?".into()]),
+                (111, 0, vec![0.into(), 12.into(), 0.into()]),
+                (
+                    201,
+                    1,
+                    vec![0.into(), 3.into(), 10.into(), 5.into(), 0.into(), 0.into()]
+                ),
+                (0, 1, vec![]),
+                (411, 0, vec![]),
+                (106, 1, vec![20.into()]),
+                (0, 1, vec![]),
+                (412, 0, vec![]),
+                (0, 0, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_have_locations() {
+        let db = CommandDB::new(luminol_config::RMVer::XP);
+        for (script, line, column) in [
+            ("frobnicate 3", 1, 1),
+            ("transfer map:3 x:10 y:", 1, 23),
+            ("text \"a\"\nwait bogus:3", 2, 6),
+            ("wait frames:1\nif switch[1] {\n  wait frames:20", 2, 14),
+            ("text \"unterminated", 1, 6),
+        ] {
+            let error = compile(&db, script).unwrap_err();
+            assert_eq!(
+                (error.line, error.column),
+                (line, column),
+                "{error} in\n{script}"
+            );
+        }
+    }
+
+    #[test]
+    fn text_codes_are_kept() {
+        let db = CommandDB::new(luminol_config::RMVer::XP);
+        for text in [r"\n[1]", r"\N[1]", r"\v[2]", r"Hi \n[1]"] {
+            let commands = compile(&db, &format!("text \"{text}\"")).unwrap();
+            assert_eq!(commands[0].code, 101);
+            assert_eq!(commands[0].parameters, vec![ParameterType::from(text)]);
+        }
+    }
+}
@@ -0,0 +1,587 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use luminol_config::command_db::CommandDB;
+use luminol_data::commands::{CommandKind, Parameter, ParameterKind};
+use luminol_data::rpg::{EventCommand, MoveCommand};
+use luminol_data::ParameterType;
+
+use super::compile::{compile, compile_command};
+use super::lexer::{is_ident, is_operator};
+use super::{keyword, snake_case};
+use crate::command_view::parameter_ui::default_value;
+use crate::command_view::structure::{end_code, route_lines};
+
+/// Writes a command list as a script. Compiling the script gives back the same list.
+pub fn decompile(db: &CommandDB, commands: &[EventCommand]) -> String {
+    let mut writer = Writer {
+        db,
+        text: String::new(),
+    };
+    if writer.block(commands, 0).is_some()
+        && compile(db, &writer.text).is_ok_and(|compiled| same_lines(&compiled, commands))
+    {
+        return writer.text;
+    }
+
+    // Lists with missing or misplaced lines are written line by line instead
+    let mut text = "@raw\n".to_string();
+    for command in commands {
+        text.push_str(&format!("@{} {}", command.code, command.indent));
+        if !command.parameters.is_empty() {
+            text.push(' ');
+            text.push_str(&list(&command.parameters));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Compares command lists, ignoring the IDs that only exist for the editor.
+fn same_lines(a: &[EventCommand], b: &[EventCommand]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.code == b.code
+                && a.indent == b.indent
+                && same_parameters(&a.parameters, &b.parameters)
+        })
+}
+
+fn same_parameters(a: &[ParameterType], b: &[ParameterType]) -> bool {
+    let same_moves = |a: &[MoveCommand], b: &[MoveCommand]| {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| a.code == b.code && same_parameters(&a.parameters, &b.parameters))
+    };
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| match (a, b) {
+            (ParameterType::MoveRoute(a), ParameterType::MoveRoute(b)) => {
+                a.repeat == b.repeat && a.skippable == b.skippable && same_moves(&a.list, &b.list)
+            }
+            (ParameterType::MoveCommand(a), ParameterType::MoveCommand(b)) => {
+                same_moves(std::slice::from_ref(a), std::slice::from_ref(b))
+            }
+            (ParameterType::Array(a), ParameterType::Array(b)) => same_parameters(a, b),
+            _ => a == b,
+        })
+}
+
+fn string(text: &str) -> String {
+    let mut string = String::with_capacity(text.len() + 2);
+    string.push('"');
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => string.push_str("\\\""),
+            // Other backslashes are kept as they are when reading strings
+            '\\' if matches!(chars.peek(), None | Some('"' | '\\')) => string.push_str("\\\\"),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// Writes a number without a fractional part if it doesn't have one.
+fn number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e9 {
+        format!("{}", value as i64)
+    } else {
+        float(value)
+    }
+}
+
+/// Writes a float in a form the lexer reads back as the same float: digits with a decimal point
+/// and no exponent.
+fn float(value: f64) -> String {
+    // `Display` never uses an exponent and writes the shortest digits that read back exactly
+    let text = value.to_string();
+    if text.contains('.') {
+        text
+    } else {
+        format!("{text}.0")
+    }
+}
+
+fn word(text: &str) -> String {
+    if is_ident(text) || is_operator(text) {
+        text.to_string()
+    } else {
+        string(text)
+    }
+}
+
+fn list(values: &[ParameterType]) -> String {
+    values.iter().map(value).collect::<Vec<_>>().join(", ")
+}
+
+fn move_command(command: &MoveCommand) -> String {
+    let mut text = format!("move({}", command.code);
+    for parameter in &command.parameters {
+        text.push_str(", ");
+        text.push_str(&value(parameter));
+    }
+    text.push(')');
+    text
+}
+
+fn value(value: &ParameterType) -> String {
+    match value {
+        ParameterType::Integer(value) => value.to_string(),
+        ParameterType::Float(value) => float(*value),
+        ParameterType::String(text) => string(text),
+        ParameterType::Bool(value) => value.to_string(),
+        ParameterType::None => "nil".to_string(),
+        ParameterType::Array(values) => format!("[{}]", list(values)),
+        ParameterType::Color(color) => format!(
+            "color({}, {}, {}, {})",
+            number(color.red),
+            number(color.green),
+            number(color.blue),
+            number(color.alpha)
+        ),
+        ParameterType::Tone(tone) => format!(
+            "tone({}, {}, {}, {})",
+            number(tone.red),
+            number(tone.green),
+            number(tone.blue),
+            number(tone.gray)
+        ),
+        ParameterType::AudioFile(audio) => format!(
+            "audio({}, {}, {})",
+            audio
+                .name
+                .as_ref()
+                .map_or_else(|| "nil".to_string(), |name| string(name.as_str())),
+            audio.volume,
+            audio.pitch
+        ),
+        ParameterType::MoveRoute(route) => {
+            // The empty move command at the end is added back when compiling
+            let moves = match route.list.split_last() {
+                Some((last, moves)) if last.code == 0 && last.parameters.is_empty() => moves,
+                _ => &route.list[..],
+            };
+            format!(
+                "route({}, {}, [{}])",
+                route.repeat,
+                route.skippable,
+                moves
+                    .iter()
+                    .map(move_command)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        ParameterType::MoveCommand(command) => move_command(command),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Style {
+    /// Switches, variables, options and text are written on their own, everything else by name.
+    /// Parameters that have their default value are left out.
+    Short,
+    /// Like `Short`, but with every parameter
+    Full,
+    /// Every parameter by name
+    Named,
+}
+
+fn has_single(parameter: &Parameter) -> bool {
+    match parameter {
+        Parameter::Single { .. } => true,
+        Parameter::Group { parameters, .. } => parameters.iter().any(has_single),
+        Parameter::Selection { parameters, .. } => parameters.iter().any(|(_, p)| has_single(p)),
+        Parameter::Label(_) | Parameter::Dummy => false,
+    }
+}
+
+fn first_label(parameter: &Parameter) -> Option<&str> {
+    match parameter {
+        Parameter::Label(label) => Some(label),
+        Parameter::Group { parameters, .. } => parameters.iter().find_map(first_label),
+        _ => None,
+    }
+}
+
+/// Writes the parameters of a command, following the options picked by its selections.
+fn args(
+    parameter: &Parameter,
+    values: &[ParameterType],
+    style: Style,
+    args: &mut Vec<String>,
+) -> Option<()> {
+    match parameter {
+        Parameter::Selection {
+            index,
+            parameters: options,
+            ..
+        } => {
+            let picked = *values.get(index.as_usize())?.as_integer()?;
+            let (_, option) = options.iter().find(|(v, _)| *v as i32 == picked)?;
+            self::args(option, values, style, args)?;
+            // Options without parameters are picked by their label
+            let is_default = options.first().is_some_and(|(v, _)| *v as i32 == picked);
+            let implied = style == Style::Short && is_default;
+            if !has_single(option) && !implied {
+                args.push(snake_case(first_label(option)?));
+            }
+        }
+        Parameter::Group { parameters, .. } => {
+            for parameter in parameters {
+                self::args(parameter, values, style, args)?;
+            }
+        }
+        Parameter::Single {
+            index, name, kind, ..
+        } => {
+            let value = values.get(index.as_usize())?;
+            let named = || format!("{}:{}", snake_case(name), self::value(value));
+            if style == Style::Named {
+                args.push(named());
+                return Some(());
+            }
+
+            let after_operator = args.last().is_some_and(|arg| is_operator(arg));
+            let is_default = *value == default_value(kind);
+            let arg = match (kind, value) {
+                (ParameterKind::Switch, ParameterType::Integer(id)) => format!("switch[{id}]"),
+                (ParameterKind::Variable, ParameterType::Integer(id)) => format!("variable[{id}]"),
+                (
+                    ParameterKind::String | ParameterKind::StringArray | ParameterKind::SelfSwitch,
+                    _,
+                ) => self::value(value),
+                (ParameterKind::Character, ParameterType::Integer(-1)) => "player".to_string(),
+                (ParameterKind::Character, ParameterType::Integer(0)) => "this".to_string(),
+                (ParameterKind::Character, ParameterType::Integer(id)) => format!("event[{id}]"),
+                (ParameterKind::Enum { variants }, ParameterType::Integer(v)) => {
+                    match variants.iter().find(|(_, value)| *value as i32 == *v) {
+                        Some(_) if is_default && style == Style::Short => return Some(()),
+                        Some((name, _)) => word(name),
+                        None => named(),
+                    }
+                }
+                // Comparisons read better without a name, like `variable[1] >= 10`
+                (ParameterKind::Int, _) if after_operator => self::value(value),
+                // IDs are always written, since a command without them is hard to read
+                (
+                    ParameterKind::Actor
+                    | ParameterKind::Class
+                    | ParameterKind::Skill
+                    | ParameterKind::Item
+                    | ParameterKind::Weapon
+                    | ParameterKind::Armor
+                    | ParameterKind::Enemy
+                    | ParameterKind::Troop
+                    | ParameterKind::State
+                    | ParameterKind::Animation
+                    | ParameterKind::CommonEvent
+                    | ParameterKind::Map,
+                    _,
+                ) => named(),
+                _ if is_default && style == Style::Short => return Some(()),
+                _ => named(),
+            };
+            args.push(arg);
+        }
+        Parameter::Label(_) | Parameter::Dummy => {}
+    }
+    Some(())
+}
+
+struct Writer<'a> {
+    db: &'a CommandDB,
+    text: String,
+}
+
+impl Writer<'_> {
+    fn indent(&mut self, indent: usize) {
+        self.text.push_str(&"  ".repeat(indent));
+    }
+
+    /// Writes the commands of a list or a branch, which has to end with an empty command.
+    fn block(&mut self, commands: &[EventCommand], indent: usize) -> Option<()> {
+        let (last, commands) = commands.split_last()?;
+        if last.code != 0 || last.indent != indent || !last.parameters.is_empty() {
+            return None;
+        }
+        let mut index = 0;
+        while index < commands.len() {
+            index = self.statement(commands, index, indent)?;
+        }
+        Some(())
+    }
+
+    /// Writes a block of commands in braces, if there are any lines inside the command at
+    /// `index`. Returns the index after them.
+    fn body(&mut self, commands: &[EventCommand], index: usize, indent: usize) -> Option<usize> {
+        let end = commands[index..]
+            .iter()
+            .position(|c| c.indent <= indent)
+            .map_or(commands.len(), |i| index + i);
+        if end > index {
+            self.text.push_str(" {\n");
+            self.block(&commands[index..end], indent + 1)?;
+            self.indent(indent);
+            self.text.push('}');
+        }
+        Some(end)
+    }
+
+    /// Writes the command at `index` along with its branches, and returns the index after it.
+    fn statement(
+        &mut self,
+        commands: &[EventCommand],
+        index: usize,
+        indent: usize,
+    ) -> Option<usize> {
+        let head = &commands[index];
+        if head.indent != indent {
+            return None;
+        }
+        self.indent(indent);
+        let mut next = index + 1;
+
+        let keyword = keyword(self.db, head.code, false);
+        match (self.db.get(head.code).map(|d| &d.kind), &keyword) {
+            (
+                Some(CommandKind::Multi {
+                    code: continuation, ..
+                }),
+                Some(keyword),
+            ) if matches!(head.parameters[..], [ParameterType::String(_)]) => {
+                let lines = commands[index + 1..]
+                    .iter()
+                    .take_while(|c| {
+                        c.code == *continuation
+                            && c.indent == indent
+                            && matches!(c.parameters[..], [ParameterType::String(_)])
+                    })
+                    .count();
+                // Extra lines are lined up under the first one
+                let separator = format!(
+                    ",\n{}{}",
+                    "  ".repeat(indent),
+                    " ".repeat(keyword.len() + 1)
+                );
+                let text = commands[index..=index + lines]
+                    .iter()
+                    .map(|c| value(&c.parameters[0]))
+                    .collect::<Vec<_>>()
+                    .join(&separator);
+                self.text.push_str(&format!("{keyword} {text}"));
+                next += lines;
+            }
+            // Set Move Route is followed by a line for every command of its route, which are left
+            // out if they match the route
+            _ if head.code == 209 => {
+                let moves = route_lines(head);
+                let text = if commands[next..].len() >= moves.len()
+                    && same_lines(&commands[next..next + moves.len()], &moves)
+                {
+                    self.command(head, false)
+                } else {
+                    raw_command(head)
+                };
+                // Commands written by code don't add the lines
+                if !text.starts_with('@') {
+                    next += moves.len();
+                }
+                self.text.push_str(&text);
+            }
+            _ => {
+                let text = self.command(head, false);
+                self.text.push_str(&text);
+            }
+        }
+
+        let body = next;
+        next = self.body(commands, next, indent)?;
+        let mut has_branches = next > body;
+
+        if let Some(end_code) = end_code(self.db, head.code) {
+            let mut ended = false;
+            while let Some(marker) = commands
+                .get(next)
+                .filter(|c| c.indent == indent && self.db.get(c.code).is_some_and(|d| d.hidden))
+            {
+                next += 1;
+                if marker.code == end_code && marker.parameters.is_empty() {
+                    ended = true;
+                    break;
+                }
+                if self.text.ends_with('}') {
+                    self.text.push(' ');
+                } else {
+                    self.text.push('\n');
+                    self.indent(indent);
+                }
+                let text = self.command(marker, true);
+                self.text.push_str(&text);
+                has_branches = true;
+                if marker.code == end_code {
+                    ended = true;
+                    break;
+                }
+                next = self.body(commands, next, indent)?;
+            }
+            if ended != has_branches {
+                return None;
+            }
+        }
+
+        self.text.push('\n');
+        Some(next)
+    }
+
+    /// Writes a command without its branches, in the shortest way that compiles back to it.
+    fn command(&self, command: &EventCommand, hidden: bool) -> String {
+        let parameters = match self.db.get(command.code).map(|d| &d.kind) {
+            Some(CommandKind::Single(parameters) | CommandKind::Branch { parameters, .. }) => {
+                Some(parameters)
+            }
+            _ => None,
+        };
+        if let (Some(keyword), Some(parameters)) =
+            (keyword(self.db, command.code, hidden), parameters)
+        {
+            for style in [Style::Short, Style::Full, Style::Named] {
+                let mut written = vec![keyword.clone()];
+                if parameters
+                    .iter()
+                    .try_for_each(|p| args(p, &command.parameters, style, &mut written))
+                    .is_none()
+                {
+                    continue;
+                }
+                let text = written.join(" ");
+                if compile_command(self.db, &text, hidden).is_some_and(|compiled| {
+                    compiled.code == command.code
+                        && same_parameters(&compiled.parameters, &command.parameters)
+                }) {
+                    return text;
+                }
+            }
+        }
+
+        raw_command(command)
+    }
+}
+
+/// Writes a command by its code, with its parameters in order.
+fn raw_command(command: &EventCommand) -> String {
+    let mut text = format!("@{}", command.code);
+    if !command.parameters.is_empty() {
+        text.push(' ');
+        text.push_str(&list(&command.parameters));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_view::parameter_ui::apply_defaults;
+    use crate::command_view::structure;
+    use luminol_data::commands::CommandDescription;
+
+    /// Builds every line of a command with its default parameters, like inserting it in the
+    /// command view does. Lines that are only part of other commands are built on their own.
+    fn command_lines(db: &CommandDB, description: &CommandDescription) -> Vec<EventCommand> {
+        let mut head = structure::line(description.code, 0, vec![]);
+        if let CommandKind::Single(parameters) | CommandKind::Branch { parameters, .. } =
+            &description.kind
+        {
+            for parameter in parameters {
+                apply_defaults(parameter, &mut head.parameters, true);
+            }
+        }
+        let mut list = if description.hidden {
+            vec![head]
+        } else {
+            structure::build_block(
+                db,
+                &[],
+                &[],
+                None,
+                head,
+                "First line\nSecond line",
+                description.code == 111,
+            )
+        };
+        list.push(structure::empty(0));
+        list
+    }
+
+    fn round_trip(db: &CommandDB, list: &[EventCommand]) -> String {
+        let text = decompile(db, list);
+        let compiled = compile(db, &text).unwrap_or_else(|e| panic!("{e} in\n{text}"));
+        assert!(
+            same_lines(&compiled, list),
+            "{list:#?} changed when compiling\n{text}"
+        );
+        text
+    }
+
+    #[test]
+    fn every_command_round_trips() {
+        let db = CommandDB::new(luminol_config::RMVer::XP);
+        for description in db.iter() {
+            let text = round_trip(&db, &command_lines(&db, description));
+            assert!(
+                description.hidden || !text.starts_with("@raw"),
+                "{} was written line by line:\n{text}",
+                description.name
+            );
+        }
+    }
+
+    #[test]
+    fn strings_round_trip() {
+        for text in [
+            "Line\nbreak",
+            "\\n[1] is an actor's name",
+            "\\v[1] and \\c[2]",
+            "\"quoted\"",
+            "trailing backslash \\",
+            "\\\\n",
+        ] {
+            let tokens = super::super::lexer::tokenize(&string(text)).unwrap();
+            assert_eq!(
+                tokens[0].kind,
+                super::super::lexer::TokenKind::String(text.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn floats_round_trip() {
+        for value in [0.5, -2.25, 1e20, -1e20, 1e-7, 123456789.125, 3.0] {
+            let tokens = super::super::lexer::tokenize(&float(value)).unwrap();
+            assert_eq!(tokens[0].kind, super::super::lexer::TokenKind::Float(value));
+        }
+    }
+}
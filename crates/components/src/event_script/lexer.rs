@@ -0,0 +1,270 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use super::ScriptError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Ident(String),
+    /// `@101`, a command written by its code
+    Code(u16),
+    /// `@raw`, which starts a list written line by line
+    Raw,
+    Int(i32),
+    Float(f64),
+    String(String),
+    /// A run of operator characters, like `>=`
+    Op(String),
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    Colon,
+    Comma,
+    /// The end of a statement: a line break or a `;`
+    Newline,
+    Eof,
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Token {
+    pub fn error(&self, message: impl Into<String>) -> ScriptError {
+        ScriptError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ident(ident) => write!(f, "`{ident}`"),
+            Self::Code(code) => write!(f, "`@{code}`"),
+            Self::Raw => f.write_str("`@raw`"),
+            Self::Int(value) => write!(f, "`{value}`"),
+            Self::Float(value) => write!(f, "`{value:?}`"),
+            Self::String(_) => f.write_str("a string"),
+            Self::Op(op) => write!(f, "`{op}`"),
+            Self::OpenBrace => f.write_str("`{`"),
+            Self::CloseBrace => f.write_str("`}`"),
+            Self::OpenBracket => f.write_str("`[`"),
+            Self::CloseBracket => f.write_str("`]`"),
+            Self::OpenParen => f.write_str("`(`"),
+            Self::CloseParen => f.write_str("`)`"),
+            Self::Colon => f.write_str("`:`"),
+            Self::Comma => f.write_str("`,`"),
+            Self::Newline => f.write_str("the end of the line"),
+            Self::Eof => f.write_str("the end of the script"),
+        }
+    }
+}
+
+const OPERATOR_CHARS: &str = "=!<>+-*/%&|";
+
+pub fn is_operator(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| OPERATOR_CHARS.contains(c))
+}
+
+pub fn is_ident(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Splits a script into tokens. Line breaks inside brackets and parentheses don't end statements.
+pub fn tokenize(text: &str) -> Result<Vec<Token>, ScriptError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+    let mut depth = 0usize;
+
+    macro_rules! bump {
+        () => {{
+            let c = chars.next();
+            if c == Some('\n') {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            c
+        }};
+    }
+
+    while let Some(&c) = chars.peek() {
+        let (start_line, start_column) = (line, column);
+        let error = |message: String| ScriptError {
+            line: start_line,
+            column: start_column,
+            message,
+        };
+
+        let kind = match c {
+            '\n' | ';' => {
+                bump!();
+                if depth > 0 {
+                    continue;
+                }
+                TokenKind::Newline
+            }
+            c if c.is_whitespace() => {
+                bump!();
+                continue;
+            }
+            '#' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    bump!();
+                }
+                continue;
+            }
+            '"' => {
+                bump!();
+                let mut string = String::new();
+                loop {
+                    match bump!() {
+                        None => return Err(error("unterminated string".to_string())),
+                        Some('"') => break,
+                        // Only quotes and backslashes are escaped, so that text codes like \v[1]
+                        // and \n[1] can be written as they are. Line breaks are written as they are
+                        // too, so strings can span several lines.
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\')) => {
+                            string.extend(bump!());
+                        }
+                        Some(c) => string.push(c),
+                    }
+                }
+                TokenKind::String(string)
+            }
+            '@' => {
+                bump!();
+                let mut word = String::new();
+                while chars.peek().is_some_and(|c| c.is_alphanumeric()) {
+                    word.extend(bump!());
+                }
+                if word == "raw" {
+                    TokenKind::Raw
+                } else {
+                    TokenKind::Code(
+                        word.parse()
+                            .map_err(|_| error(format!("`@{word}` is not a command code")))?,
+                    )
+                }
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    ahead.next().is_some_and(|c| c.is_ascii_digit())
+                }) =>
+            {
+                let mut number = String::new();
+                number.extend(bump!());
+                while chars
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    number.extend(bump!());
+                }
+                if number.contains('.') {
+                    TokenKind::Float(
+                        number
+                            .parse()
+                            .map_err(|_| error(format!("`{number}` is not a number")))?,
+                    )
+                } else {
+                    TokenKind::Int(
+                        number
+                            .parse()
+                            .map_err(|_| error(format!("`{number}` is too large")))?,
+                    )
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while chars
+                    .peek()
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+                {
+                    ident.extend(bump!());
+                }
+                TokenKind::Ident(ident)
+            }
+            c if OPERATOR_CHARS.contains(c) => {
+                let mut op = String::new();
+                while chars.peek().is_some_and(|c| OPERATOR_CHARS.contains(*c)) {
+                    op.extend(bump!());
+                }
+                TokenKind::Op(op)
+            }
+            _ => {
+                bump!();
+                match c {
+                    '{' => TokenKind::OpenBrace,
+                    '}' => TokenKind::CloseBrace,
+                    '[' | '(' => {
+                        depth += 1;
+                        if c == '[' {
+                            TokenKind::OpenBracket
+                        } else {
+                            TokenKind::OpenParen
+                        }
+                    }
+                    ']' | ')' => {
+                        depth = depth.saturating_sub(1);
+                        if c == ']' {
+                            TokenKind::CloseBracket
+                        } else {
+                            TokenKind::CloseParen
+                        }
+                    }
+                    ':' => TokenKind::Colon,
+                    ',' => TokenKind::Comma,
+                    _ => return Err(error(format!("unexpected character `{c}`"))),
+                }
+            }
+        };
+        tokens.push(Token {
+            kind,
+            line: start_line,
+            column: start_column,
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        line,
+        column,
+    });
+    Ok(tokens)
+}
@@ -0,0 +1,142 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+//! A plain text language for event commands, so that cutscenes can be drafted in a text editor.
+//!
+//! ```text
+//! text "Hello!", "How are you?"
+//! if switch[12] {
+//!   transfer map:3 x:10 y:5
+//! } else {
+//!   wait frames:20
+//! }
+//! ```
+//!
+//! Every statement is a command: a keyword followed by its parameters. The keyword is either a
+//! short alias (`text`, `if`, `transfer`, ...) or the name of the command in snake_case
+//! (`change_gold`), and `@code` writes any command by its code. Parameters are matched to the
+//! command's description: `name:value` sets a parameter by name, `switch[n]` and `variable[n]` fill
+//! switch and variable parameters, and words like `ON` or `>=` pick the option of the same name.
+//! Anything else fills the next parameter that takes that kind of value.
+//!
+//! Branches put their commands in braces, followed by parts like `else` or `when`, and the lines
+//! that end lists and branches are added automatically. Extra strings after multiline commands like
+//! Show Text become their following lines.
+//!
+//! Lists that can't be written this way, like lists with missing lines, are written line by line
+//! after `@raw`, with the code and the indent of every line.
+
+mod compile;
+mod decompile;
+mod lexer;
+
+pub use compile::compile;
+pub use decompile::decompile;
+
+use luminol_config::command_db::CommandDB;
+
+/// An error in a script, at a 1-based line and column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Short keywords for common commands. They're only used if the command database has the code.
+const ALIASES: &[(&str, u16)] = &[
+    ("text", 101),
+    ("choices", 102),
+    ("when", 402),
+    ("cancel", 403),
+    ("wait", 106),
+    ("comment", 108),
+    ("if", 111),
+    ("else", 411),
+    ("loop", 112),
+    ("break", 113),
+    ("exit", 115),
+    ("call", 117),
+    ("label", 118),
+    ("jump", 119),
+    ("transfer", 201),
+    ("move", 209),
+    ("battle", 301),
+    ("win", 601),
+    ("escape", 602),
+    ("lose", 603),
+    ("shop", 302),
+    ("goods", 605),
+    ("script", 355),
+];
+
+/// Turns a command name like "Wait for Move's Completion" into `wait_for_moves_completion`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for c in name.chars().filter(|&c| c != '\'') {
+        if c.is_alphanumeric() {
+            snake.extend(c.to_lowercase());
+        } else if !snake.is_empty() && !snake.ends_with('_') {
+            snake.push('_');
+        }
+    }
+    snake.trim_end_matches('_').to_string()
+}
+
+/// The code a keyword stands for. Commands hidden from the insert menu, like Else, are preferred
+/// when `hidden` is set and avoided otherwise.
+fn resolve(db: &CommandDB, keyword: &str, hidden: bool) -> Option<u16> {
+    if let Some(&(_, code)) = ALIASES.iter().find(|(alias, _)| *alias == keyword) {
+        if db.get(code).is_some() {
+            return Some(code);
+        }
+    }
+    let mut candidates = db
+        .iter()
+        .filter(|description| snake_case(&description.name) == keyword)
+        .map(|description| description.code);
+    let first = candidates.next()?;
+    std::iter::once(first)
+        .chain(candidates)
+        .find(|&code| db.get(code).is_some_and(|d| d.hidden == hidden))
+        .or(Some(first))
+}
+
+/// The keyword that stands for a code, if there is one.
+fn keyword(db: &CommandDB, code: u16, hidden: bool) -> Option<String> {
+    let keyword = ALIASES
+        .iter()
+        .find(|&&(_, c)| c == code)
+        .map(|(alias, _)| alias.to_string())
+        .or_else(|| db.get(code).map(|d| snake_case(&d.name)))?;
+    (resolve(db, &keyword, hidden) == Some(code)).then_some(keyword)
+}
@@ -0,0 +1,1293 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+//! A headless interpreter for the standard RMXP event commands, for testing and debugging event
+//! logic without running the game.
+//!
+//! Commands run against a [`GameState`] the same way the RGSS interpreter runs them: branches are
+//! skipped by indent and the result of the last condition at each indent decides whether Else and
+//! When lines are entered. Nothing is rendered. Commands that wait for the player, like Show
+//! Choices, stop the interpreter until they are answered, and commands that depend on things that
+//! aren't simulated, like actor stats, are noted in the trace.
+
+use std::collections::{HashMap, HashSet};
+
+use luminol_data::rpg::{CommonEvent, EventCommand, SelfSwitch};
+use luminol_data::ParameterType;
+use rand::{Rng, SeedableRng};
+
+/// Most common events can call each other before the call is treated as endless recursion
+const MAX_CALL_DEPTH: usize = 100;
+const MAX_GOLD: i32 = 9_999_999;
+const MAX_ITEMS: i32 = 99;
+const MAX_VARIABLE: i32 = 99_999_999;
+const MAX_PARTY_SIZE: usize = 4;
+
+/// The simulated game state. IDs are the ones used by event commands, so switch 1 is the first
+/// switch in the database.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameState {
+    /// IDs of the switches that are on
+    pub switches: HashSet<usize>,
+    /// Values of the variables that aren't 0
+    pub variables: HashMap<usize, i32>,
+    /// (map ID, event ID, self switch) of the self switches that are on
+    pub self_switches: HashSet<(usize, usize, SelfSwitch)>,
+    /// Actor IDs of the party members, in order
+    pub party: Vec<usize>,
+    pub gold: i32,
+    /// How many of each item the party has
+    pub items: HashMap<usize, i32>,
+    pub weapons: HashMap<usize, i32>,
+    pub armors: HashMap<usize, i32>,
+    /// Seconds left on the timer, if it is running
+    pub timer: Option<i32>,
+}
+
+impl GameState {
+    pub fn switch(&self, id: usize) -> bool {
+        self.switches.contains(&id)
+    }
+
+    pub fn set_switch(&mut self, id: usize, value: bool) {
+        if value {
+            self.switches.insert(id);
+        } else {
+            self.switches.remove(&id);
+        }
+    }
+
+    pub fn variable(&self, id: usize) -> i32 {
+        self.variables.get(&id).copied().unwrap_or_default()
+    }
+
+    pub fn set_variable(&mut self, id: usize, value: i32) {
+        let value = value.clamp(-MAX_VARIABLE, MAX_VARIABLE);
+        if value == 0 {
+            self.variables.remove(&id);
+        } else {
+            self.variables.insert(id, value);
+        }
+    }
+}
+
+/// Where the event being run is. Self switches are set for this event, and Control Variables can
+/// read the map ID.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Context {
+    pub map_id: usize,
+    /// The ID of the event on the map, or 0 for common events
+    pub event_id: usize,
+    /// The seed of the random numbers used by Control Variables
+    pub seed: u64,
+}
+
+/// Which command list a line belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// The list the interpreter was started with
+    Event,
+    CommonEvent(usize),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Event => f.write_str("Event"),
+            Self::CommonEvent(id) => write!(f, "Common Event {id:0>4}"),
+        }
+    }
+}
+
+/// What the interpreter is waiting for the player to do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// Show Choices. Choices can be cancelled unless cancelling is disallowed.
+    Choice {
+        choices: Vec<String>,
+        can_cancel: bool,
+    },
+    /// Input Number, with the number of digits
+    Number { digits: usize },
+    /// Button Input Processing
+    Button,
+    /// Battle Processing
+    Battle { can_escape: bool, can_lose: bool },
+}
+
+/// The player's answer to an [`Input`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Answer {
+    /// The index of the choice picked
+    Choice(usize),
+    Cancel,
+    Number(i32),
+    /// The code of the button pressed, like 11 for A or 0 for none
+    Button(i32),
+    Battle(BattleResult),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display, strum::EnumIter)]
+pub enum BattleResult {
+    Win,
+    Escape,
+    Lose,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// There are commands left to run
+    Running,
+    /// Stopped at a breakpoint, before running its line
+    Breakpoint,
+    /// Waiting for an answer
+    Waiting(Input),
+    /// Stopped after running the maximum number of commands, which usually means an endless loop
+    StepLimit,
+    Finished,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceKind {
+    /// A command that changed the game state or moved the interpreter
+    Command,
+    /// A branch that was entered
+    Taken,
+    /// A branch that was skipped
+    Skipped,
+    /// Something that would be shown to the player, like text
+    Output,
+    /// A command that can't be simulated, or a condition that was assumed to be false
+    NotSimulated,
+}
+
+/// A line that ran, and what it did.
+#[derive(Clone, Debug)]
+pub struct TraceEntry {
+    pub source: Source,
+    pub index: usize,
+    /// How many common event calls deep the line is
+    pub depth: usize,
+    pub kind: TraceKind,
+    pub message: String,
+}
+
+struct Frame {
+    source: Source,
+    list: Vec<EventCommand>,
+    /// The next line to run
+    index: usize,
+    /// The result of the last branch at each indent: 1 or 0 for Conditional Branch, the choice for
+    /// Show Choices and the battle result for Battle Processing
+    branches: HashMap<usize, i32>,
+}
+
+/// Runs a command list one line at a time.
+pub struct Interpreter {
+    context: Context,
+    frames: Vec<Frame>,
+    /// The command waiting for an answer, and the indent it's at
+    waiting: Option<(Input, usize, EventCommand)>,
+    pub breakpoints: HashSet<(Source, usize)>,
+    trace: Vec<TraceEntry>,
+    steps: usize,
+    rng: rand::rngs::StdRng,
+}
+
+fn int(command: &EventCommand, index: usize) -> i32 {
+    command
+        .parameters
+        .get(index)
+        .and_then(ParameterType::as_integer)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn id(command: &EventCommand, index: usize) -> usize {
+    int(command, index).max(0) as usize
+}
+
+fn string(command: &EventCommand, index: usize) -> String {
+    command
+        .parameters
+        .get(index)
+        .and_then(ParameterType::as_string)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn truthy(command: &EventCommand, index: usize) -> bool {
+    command
+        .parameters
+        .get(index)
+        .is_some_and(ParameterType::truthy)
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+fn compare(value: i32, comparison: i32, other: i32) -> bool {
+    match comparison {
+        0 => value == other,
+        1 => value >= other,
+        2 => value <= other,
+        3 => value > other,
+        4 => value < other,
+        _ => value != other,
+    }
+}
+
+/// Divides like Ruby does, rounding towards negative infinity instead of towards zero.
+fn floor_div(value: i64, divisor: i64) -> i64 {
+    let quotient = value / divisor;
+    if value % divisor != 0 && (value < 0) != (divisor < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+impl Interpreter {
+    pub fn new(list: Vec<EventCommand>, context: Context) -> Self {
+        Self {
+            context,
+            frames: vec![Frame {
+                source: Source::Event,
+                list,
+                index: 0,
+                branches: HashMap::new(),
+            }],
+            waiting: None,
+            breakpoints: HashSet::new(),
+            trace: Vec::new(),
+            steps: 0,
+            rng: rand::rngs::StdRng::seed_from_u64(context.seed),
+        }
+    }
+
+    pub fn context(&self) -> Context {
+        self.context
+    }
+
+    pub fn trace(&self) -> &[TraceEntry] {
+        &self.trace
+    }
+
+    /// The number of lines run so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn waiting(&self) -> Option<&Input> {
+        self.waiting.as_ref().map(|(input, ..)| input)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The list that is running, with the index of the next line to run.
+    pub fn position(&self) -> Option<(Source, &[EventCommand], usize)> {
+        self.frames
+            .last()
+            .map(|frame| (frame.source, frame.list.as_slice(), frame.index))
+    }
+
+    /// The lists that called the running list, outermost first, with the index of the line after
+    /// each call.
+    pub fn call_stack(&self) -> impl Iterator<Item = (Source, usize)> + '_ {
+        self.frames.iter().map(|frame| (frame.source, frame.index))
+    }
+
+    fn status(&self) -> Status {
+        match &self.waiting {
+            Some((input, ..)) => Status::Waiting(input.clone()),
+            None if self.frames.is_empty() => Status::Finished,
+            None => Status::Running,
+        }
+    }
+
+    fn log(&mut self, index: usize, kind: TraceKind, message: impl Into<String>) {
+        let Some(frame) = self.frames.last() else {
+            return;
+        };
+        self.trace.push(TraceEntry {
+            source: frame.source,
+            index,
+            depth: self.frames.len() - 1,
+            kind,
+            message: message.into(),
+        });
+    }
+
+    /// Drops the lists that have run to the end.
+    fn pop_finished(&mut self) {
+        while self
+            .frames
+            .last()
+            .is_some_and(|frame| frame.index >= frame.list.len())
+        {
+            self.frames.pop();
+        }
+    }
+
+    /// Runs lines until the interpreter finishes, waits for an answer, reaches a breakpoint or has
+    /// run `max_steps` lines. The line it is stopped at is run even if it has a breakpoint, so that
+    /// running again continues past the breakpoint.
+    pub fn run(
+        &mut self,
+        state: &mut GameState,
+        common_events: &[CommonEvent],
+        max_steps: usize,
+    ) -> Status {
+        for _ in 0..max_steps {
+            let status = self.step(state, common_events);
+            if status != Status::Running {
+                return status;
+            }
+            if self
+                .position()
+                .is_some_and(|(source, _, index)| self.breakpoints.contains(&(source, index)))
+            {
+                return Status::Breakpoint;
+            }
+        }
+        Status::StepLimit
+    }
+
+    /// Answers the command that is waiting, and continues after it.
+    pub fn answer(&mut self, state: &mut GameState, answer: Answer) -> Status {
+        let Some((input, indent, command)) = self.waiting.take() else {
+            return self.status();
+        };
+        let index = self.frames.last().map_or(0, |frame| frame.index - 1);
+
+        let branch = match (&input, answer) {
+            (Input::Choice { choices, .. }, Answer::Choice(choice)) if choice < choices.len() => {
+                self.log(
+                    index,
+                    TraceKind::Command,
+                    format!("Picked choice {}: {}", choice + 1, choices[choice]),
+                );
+                Some(choice as i32)
+            }
+            (
+                Input::Choice {
+                    can_cancel: true, ..
+                },
+                Answer::Cancel,
+            ) => {
+                self.log(index, TraceKind::Command, "Cancelled the choices");
+                // Cancelling either picks a choice or takes the When Cancel branch
+                Some(match int(&command, 1) {
+                    5 => 4,
+                    cancel => cancel - 1,
+                })
+            }
+            (Input::Number { .. }, Answer::Number(number)) => {
+                let variable = id(&command, 0);
+                state.set_variable(variable, number);
+                self.log(
+                    index,
+                    TraceKind::Command,
+                    format!("Entered {number} into variable {variable:0>4}"),
+                );
+                None
+            }
+            (Input::Button, Answer::Button(button)) => {
+                let variable = id(&command, 0);
+                state.set_variable(variable, button);
+                self.log(
+                    index,
+                    TraceKind::Command,
+                    format!("Pressed button {button}, stored in variable {variable:0>4}"),
+                );
+                None
+            }
+            (
+                Input::Battle {
+                    can_escape,
+                    can_lose,
+                },
+                Answer::Battle(result),
+            ) if result != BattleResult::Escape || *can_escape => {
+                self.log(
+                    index,
+                    TraceKind::Command,
+                    format!("The battle ended: {result}"),
+                );
+                if result == BattleResult::Lose && !can_lose {
+                    self.log(index, TraceKind::Output, "Game Over");
+                    self.frames.clear();
+                    return self.status();
+                }
+                Some(result as i32)
+            }
+            // The answer doesn't fit, so keep waiting
+            _ => {
+                self.waiting = Some((input, indent, command));
+                return self.status();
+            }
+        };
+
+        if let (Some(branch), Some(frame)) = (branch, self.frames.last_mut()) {
+            frame.branches.insert(indent, branch);
+        }
+        self.pop_finished();
+        self.status()
+    }
+
+    /// Runs the next line.
+    pub fn step(&mut self, state: &mut GameState, common_events: &[CommonEvent]) -> Status {
+        self.pop_finished();
+        if self.waiting.is_some() {
+            return self.status();
+        }
+        let Some(frame) = self.frames.last_mut() else {
+            return Status::Finished;
+        };
+        let index = frame.index;
+        let command = frame.list[index].clone();
+        frame.index += 1;
+        self.steps += 1;
+
+        self.execute(state, common_events, index, &command);
+
+        self.pop_finished();
+        self.status()
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("no list is running")
+    }
+
+    /// Skips the lines inside the branch started by the line that just ran.
+    fn skip_branch(&mut self, indent: usize) {
+        let frame = self.frame();
+        while frame
+            .list
+            .get(frame.index)
+            .is_some_and(|command| command.indent > indent)
+        {
+            frame.index += 1;
+        }
+    }
+
+    /// Enters the branch of a line like When or If Win if the last branch result at its indent
+    /// matches, and skips it otherwise.
+    fn branch_section(&mut self, index: usize, indent: usize, value: i32, name: &str) {
+        if self.frame().branches.get(&indent) == Some(&value) {
+            self.frame().branches.remove(&indent);
+            self.log(index, TraceKind::Taken, format!("Entered {name}"));
+        } else {
+            self.log(index, TraceKind::Skipped, format!("Skipped {name}"));
+            self.skip_branch(indent);
+        }
+    }
+
+    fn execute(
+        &mut self,
+        state: &mut GameState,
+        common_events: &[CommonEvent],
+        index: usize,
+        command: &EventCommand,
+    ) {
+        let indent = command.indent;
+        match command.code {
+            // Empty lines, and lines that are part of the command before them
+            0 | 401 | 408 | 509 | 605 | 655 | 412 | 404 | 604 | 112 | 118 => {}
+
+            101 => {
+                let frame = self.frame();
+                let mut lines = vec![string(command, 0)];
+                while let Some(line) = frame
+                    .list
+                    .get(frame.index)
+                    .filter(|c| c.code == 401 && c.indent == indent)
+                {
+                    lines.push(string(line, 0));
+                    frame.index += 1;
+                }
+                self.log(index, TraceKind::Output, lines.join("\n"));
+            }
+            102 => {
+                let choices = command
+                    .parameters
+                    .first()
+                    .and_then(ParameterType::as_array)
+                    .map(|choices| {
+                        choices
+                            .iter()
+                            .map(|c| c.as_string().cloned().unwrap_or_default())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let input = Input::Choice {
+                    choices,
+                    can_cancel: int(command, 1) != 0,
+                };
+                self.waiting = Some((input, indent, command.clone()));
+            }
+            402 => {
+                let name = format!("When [{}]", string(command, 1));
+                self.branch_section(index, indent, int(command, 0), &name);
+            }
+            403 => self.branch_section(index, indent, 4, "When Cancel"),
+            103 => {
+                let input = Input::Number {
+                    digits: id(command, 1),
+                };
+                self.waiting = Some((input, indent, command.clone()));
+            }
+            105 => self.waiting = Some((Input::Button, indent, command.clone())),
+            106 => self.log(
+                index,
+                TraceKind::Command,
+                format!("Waited {} frames", int(command, 0)),
+            ),
+
+            111 => {
+                let (result, note) = self.condition(state, command);
+                self.frame().branches.insert(indent, result as i32);
+                let kind = match (result, note.is_some()) {
+                    (_, true) => TraceKind::NotSimulated,
+                    (true, false) => TraceKind::Taken,
+                    (false, false) => TraceKind::Skipped,
+                };
+                let message = match note {
+                    Some(note) => format!("{note}, so the condition was assumed to be false"),
+                    None if result => "The condition was met".to_string(),
+                    None => "The condition wasn't met".to_string(),
+                };
+                self.log(index, kind, message);
+                if !result {
+                    self.skip_branch(indent);
+                }
+            }
+            411 => {
+                if self.frame().branches.get(&indent) == Some(&0) {
+                    self.frame().branches.remove(&indent);
+                    self.log(index, TraceKind::Taken, "Entered Else");
+                } else {
+                    self.log(index, TraceKind::Skipped, "Skipped Else");
+                    self.skip_branch(indent);
+                }
+            }
+
+            413 => {
+                // Go back to the line after the start of the loop
+                let frame = self.frame();
+                if let Some(start) = frame.list[..index].iter().rposition(|c| c.indent == indent) {
+                    frame.index = start + 1;
+                }
+                self.log(index, TraceKind::Command, "Repeated the loop");
+            }
+            113 => {
+                let frame = self.frame();
+                if let Some(end) = frame.list[index + 1..]
+                    .iter()
+                    .position(|c| c.code == 413 && c.indent < indent)
+                {
+                    frame.index = index + 1 + end + 1;
+                }
+                self.log(index, TraceKind::Command, "Broke out of the loop");
+            }
+            115 => {
+                let frame = self.frame();
+                frame.index = frame.list.len();
+                self.log(index, TraceKind::Command, "Exited event processing");
+            }
+            117 => {
+                let id = id(command, 0);
+                let event = id.checked_sub(1).and_then(|i| common_events.get(i));
+                match event {
+                    Some(_) if self.frames.len() > MAX_CALL_DEPTH => self.log(
+                        index,
+                        TraceKind::NotSimulated,
+                        format!("Too many nested calls, so common event {id:0>4} wasn't called"),
+                    ),
+                    Some(event) => {
+                        self.log(
+                            index,
+                            TraceKind::Command,
+                            format!("Called common event {id:0>4}: {}", event.name),
+                        );
+                        self.frames.push(Frame {
+                            source: Source::CommonEvent(id),
+                            list: event.list.clone(),
+                            index: 0,
+                            branches: HashMap::new(),
+                        });
+                    }
+                    None => self.log(
+                        index,
+                        TraceKind::NotSimulated,
+                        format!("Common event {id:0>4} doesn't exist"),
+                    ),
+                }
+            }
+            119 => {
+                let label = string(command, 0);
+                let frame = self.frame();
+                let target = frame
+                    .list
+                    .iter()
+                    .position(|c| c.code == 118 && string(c, 0) == label);
+                if let Some(target) = target {
+                    frame.index = target + 1;
+                    self.log(
+                        index,
+                        TraceKind::Command,
+                        format!("Jumped to label {label}"),
+                    );
+                } else {
+                    self.log(
+                        index,
+                        TraceKind::NotSimulated,
+                        format!("There is no label {label}"),
+                    );
+                }
+            }
+
+            121 => {
+                let value = int(command, 2) == 0;
+                for id in id(command, 0)..=id(command, 1) {
+                    state.set_switch(id, value);
+                }
+                self.log(
+                    index,
+                    TraceKind::Command,
+                    format!(
+                        "Set switches {:0>4}..{:0>4} to {}",
+                        id(command, 0),
+                        id(command, 1),
+                        on_off(value)
+                    ),
+                );
+            }
+            122 => self.control_variables(state, index, command),
+            123 => {
+                let self_switch = match string(command, 0).as_str() {
+                    "B" => SelfSwitch::B,
+                    "C" => SelfSwitch::C,
+                    "D" => SelfSwitch::D,
+                    _ => SelfSwitch::A,
+                };
+                let value = int(command, 1) == 0;
+                if self.context.event_id == 0 {
+                    self.log(
+                        index,
+                        TraceKind::NotSimulated,
+                        "Self switches can only be set by map events",
+                    );
+                    return;
+                }
+                let key = (self.context.map_id, self.context.event_id, self_switch);
+                if value {
+                    state.self_switches.insert(key);
+                } else {
+                    state.self_switches.remove(&key);
+                }
+                self.log(
+                    index,
+                    TraceKind::Command,
+                    format!("Set self switch {self_switch} to {}", on_off(value)),
+                );
+            }
+            124 => {
+                if int(command, 0) == 0 {
+                    state.timer = Some(int(command, 1));
+                    self.log(
+                        index,
+                        TraceKind::Command,
+                        format!("Started the timer at {} seconds", int(command, 1)),
+                    );
+                } else {
+                    state.timer = None;
+                    self.log(index, TraceKind::Command, "Stopped the timer");
+                }
+            }
+            125 => {
+                let value = Self::operand(state, command, 0);
+                state.gold = (state.gold + value).clamp(0, MAX_GOLD);
+                self.log(
+                    index,
+                    TraceKind::Command,
+                    format!("Gold changed by {value} to {}", state.gold),
+                );
+            }
+            126..=128 => {
+                let value = Self::operand(state, command, 1);
+                let (counts, name) = match command.code {
+                    126 => (&mut state.items, "item"),
+                    127 => (&mut state.weapons, "weapon"),
+                    _ => (&mut state.armors, "armor"),
+                };
+                let id = id(command, 0);
+                let count = counts.entry(id).or_default();
+                *count = (*count + value).clamp(0, MAX_ITEMS);
+                let count = *count;
+                if count == 0 {
+                    counts.remove(&id);
+                }
+                self.log(
+                    index,
+                    TraceKind::Command,
+                    format!("The party now has {count} of {name} {id:0>3}"),
+                );
+            }
+            129 => {
+                let actor = id(command, 0);
+                // Actors already in the party and actors not in it are left as they are
+                if int(command, 1) == 0 {
+                    if state.party.len() < MAX_PARTY_SIZE && !state.party.contains(&actor) {
+                        state.party.push(actor);
+                        self.log(
+                            index,
+                            TraceKind::Command,
+                            format!("Actor {actor:0>3} joined the party"),
+                        );
+                    }
+                } else if state.party.contains(&actor) {
+                    state.party.retain(|&a| a != actor);
+                    self.log(
+                        index,
+                        TraceKind::Command,
+                        format!("Actor {actor:0>3} left the party"),
+                    );
+                }
+            }
+
+            201 => {
+                let message = if int(command, 0) == 0 {
+                    format!(
+                        "Transferred the player to map {:0>3} ({}, {})",
+                        int(command, 1),
+                        int(command, 2),
+                        int(command, 3)
+                    )
+                } else {
+                    format!(
+                        "Transferred the player to map {:0>3} ({}, {})",
+                        state.variable(id(command, 1)),
+                        state.variable(id(command, 2)),
+                        state.variable(id(command, 3))
+                    )
+                };
+                self.log(index, TraceKind::Output, message);
+            }
+            301 => {
+                let input = Input::Battle {
+                    can_escape: truthy(command, 1),
+                    can_lose: truthy(command, 2),
+                };
+                self.waiting = Some((input, indent, command.clone()));
+            }
+            601 => self.branch_section(index, indent, 0, "If Win"),
+            602 => self.branch_section(index, indent, 1, "If Escape"),
+            603 => self.branch_section(index, indent, 2, "If Lose"),
+            353 | 354 => {
+                let message = if command.code == 353 {
+                    "Game Over"
+                } else {
+                    "Returned to the title screen"
+                };
+                self.log(index, TraceKind::Output, message);
+                self.frames.clear();
+            }
+            355 => {
+                let frame = self.frame();
+                let mut script = vec![string(command, 0)];
+                while let Some(line) = frame
+                    .list
+                    .get(frame.index)
+                    .filter(|c| c.code == 655 && c.indent == indent)
+                {
+                    script.push(string(line, 0));
+                    frame.index += 1;
+                }
+                self.log(
+                    index,
+                    TraceKind::NotSimulated,
+                    format!("Scripts aren't run: {}", script.join("\n")),
+                );
+            }
+            code => self.log(
+                index,
+                TraceKind::NotSimulated,
+                format!("Command {code} has no effect in the simulation"),
+            ),
+        }
+    }
+
+    /// The value of the operation at `start` used by commands like Change Gold: the operation
+    /// (increase or decrease), whether the operand is a constant or a variable, and the operand.
+    fn operand(state: &GameState, command: &EventCommand, start: usize) -> i32 {
+        let value = match int(command, start + 1) {
+            0 => int(command, start + 2),
+            _ => state.variable(id(command, start + 2)),
+        };
+        if int(command, start) == 1 {
+            -value
+        } else {
+            value
+        }
+    }
+
+    /// Checks the condition of a Conditional Branch. Conditions that can't be checked are false,
+    /// along with the reason why.
+    fn condition(&self, state: &GameState, command: &EventCommand) -> (bool, Option<String>) {
+        let value = |index| int(command, index);
+        let count =
+            |counts: &HashMap<usize, i32>| counts.get(&id(command, 1)).copied().unwrap_or(0);
+        let at_least = |amount: i32, limit: i32, comparison: i32| {
+            if comparison == 0 {
+                amount >= limit
+            } else {
+                amount <= limit
+            }
+        };
+
+        let result = match value(0) {
+            0 => state.switch(id(command, 1)) == (value(2) == 0),
+            1 => {
+                let other = match value(2) {
+                    0 => value(3),
+                    _ => state.variable(id(command, 3)),
+                };
+                compare(state.variable(id(command, 1)), value(4), other)
+            }
+            2 if self.context.event_id == 0 => {
+                return (
+                    false,
+                    Some("Self switches only exist for map events".to_string()),
+                )
+            }
+            2 => {
+                let self_switch = match string(command, 1).as_str() {
+                    "B" => SelfSwitch::B,
+                    "C" => SelfSwitch::C,
+                    "D" => SelfSwitch::D,
+                    _ => SelfSwitch::A,
+                };
+                let key = (self.context.map_id, self.context.event_id, self_switch);
+                state.self_switches.contains(&key) == (value(2) == 0)
+            }
+            3 => state
+                .timer
+                .is_some_and(|seconds| at_least(seconds, value(1), value(2))),
+            4 if value(2) == 0 => state.party.contains(&id(command, 1)),
+            4 => {
+                return (
+                    false,
+                    Some("Actor names, skills, equipment and states aren't simulated".to_string()),
+                )
+            }
+            5 => return (false, Some("Enemies aren't simulated".to_string())),
+            6 => {
+                return (
+                    false,
+                    Some("Character directions aren't simulated".to_string()),
+                )
+            }
+            7 => at_least(state.gold, value(1), value(2)),
+            8 => count(&state.items) > 0,
+            9 => count(&state.weapons) > 0,
+            10 => count(&state.armors) > 0,
+            11 => return (false, Some("Buttons aren't simulated".to_string())),
+            12 => return (false, Some("Scripts aren't run".to_string())),
+            kind => return (false, Some(format!("Unknown condition {kind}"))),
+        };
+        (result, None)
+    }
+
+    fn control_variables(&mut self, state: &mut GameState, index: usize, command: &EventCommand) {
+        let (first, last) = (id(command, 0), id(command, 1));
+        let mut note = None;
+        let value = match int(command, 3) {
+            0 => int(command, 4),
+            1 => state.variable(id(command, 4)),
+            2 => {
+                let (min, max) = (int(command, 4), int(command, 5));
+                self.rng.gen_range(min.min(max)..=min.max(max))
+            }
+            3 => state.items.get(&id(command, 4)).copied().unwrap_or(0),
+            7 => match int(command, 4) {
+                0 => self.context.map_id as i32,
+                1 => state.party.len() as i32,
+                2 => state.gold,
+                5 => state.timer.unwrap_or(0),
+                _ => {
+                    note = Some("Steps, play time and save count aren't simulated");
+                    0
+                }
+            },
+            4 => {
+                note = Some("Actor stats aren't simulated");
+                0
+            }
+            5 => {
+                note = Some("Enemies aren't simulated");
+                0
+            }
+            _ => {
+                note = Some("Character positions aren't simulated");
+                0
+            }
+        };
+
+        let operation = int(command, 2);
+        for id in first..=last {
+            let old = state.variable(id) as i64;
+            let value = value as i64;
+            let new = match operation {
+                0 => value,
+                1 => old + value,
+                2 => old - value,
+                3 => old * value,
+                // The remainder has the sign of the divisor, like in Ruby
+                4 if value != 0 => floor_div(old, value),
+                5 if value != 0 => old - floor_div(old, value) * value,
+                _ => old,
+            };
+            state.set_variable(id, new.clamp(i32::MIN as i64, i32::MAX as i64) as i32);
+        }
+
+        let operator = ["=", "+=", "-=", "*=", "/=", "%="]
+            .get(operation as usize)
+            .copied()
+            .unwrap_or("?");
+        let message = format!("Variables {first:0>4}..{last:0>4} {operator} {value}");
+        match note {
+            Some(note) => self.log(
+                index,
+                TraceKind::NotSimulated,
+                format!("{message} ({note}, so 0 was used)"),
+            ),
+            None => self.log(index, TraceKind::Command, message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(code: u16, indent: usize, parameters: Vec<ParameterType>) -> EventCommand {
+        EventCommand {
+            code,
+            indent,
+            parameters,
+            guid: 0,
+        }
+    }
+
+    /// Control Variables setting variable `id` to `value`.
+    fn set_variable(indent: usize, id: i32, value: i32) -> EventCommand {
+        line(
+            122,
+            indent,
+            vec![id.into(), id.into(), 0.into(), 0.into(), value.into()],
+        )
+    }
+
+    /// Control Variables adding `value` to variable `id`.
+    fn add_variable(indent: usize, id: i32, value: i32) -> EventCommand {
+        line(
+            122,
+            indent,
+            vec![id.into(), id.into(), 1.into(), 0.into(), value.into()],
+        )
+    }
+
+    /// Conditional Branch on variable `id` compared to a constant.
+    fn if_variable(indent: usize, id: i32, comparison: i32, value: i32) -> EventCommand {
+        line(
+            111,
+            indent,
+            vec![
+                1.into(),
+                id.into(),
+                0.into(),
+                value.into(),
+                comparison.into(),
+            ],
+        )
+    }
+
+    fn run(
+        list: Vec<EventCommand>,
+        state: &mut GameState,
+        common_events: &[CommonEvent],
+    ) -> (Interpreter, Status) {
+        let mut interpreter = Interpreter::new(list, Context::default());
+        let status = interpreter.run(state, common_events, 10_000);
+        (interpreter, status)
+    }
+
+    #[test]
+    fn conditional_branches_use_the_result_at_their_indent() {
+        let list = vec![
+            line(111, 0, vec![0.into(), 1.into(), 0.into()]),
+            line(111, 1, vec![0.into(), 2.into(), 0.into()]),
+            set_variable(2, 1, 1),
+            line(0, 2, vec![]),
+            line(411, 1, vec![]),
+            set_variable(2, 1, 2),
+            line(0, 2, vec![]),
+            line(412, 1, vec![]),
+            line(0, 1, vec![]),
+            line(411, 0, vec![]),
+            set_variable(1, 1, 3),
+            line(0, 1, vec![]),
+            line(412, 0, vec![]),
+            line(0, 0, vec![]),
+        ];
+
+        for (switches, expected) in [(vec![1, 2], 1), (vec![1], 2), (vec![2], 3), (vec![], 3)] {
+            let mut state = GameState::default();
+            for switch in &switches {
+                state.set_switch(*switch, true);
+            }
+            let (_, status) = run(list.clone(), &mut state, &[]);
+            assert_eq!(status, Status::Finished);
+            assert_eq!(state.variable(1), expected, "switches {switches:?}");
+        }
+    }
+
+    #[test]
+    fn loops_repeat_until_break() {
+        let list = vec![
+            line(112, 0, vec![]),
+            add_variable(1, 1, 1),
+            if_variable(1, 1, 1, 3),
+            line(113, 2, vec![]),
+            line(0, 2, vec![]),
+            line(412, 1, vec![]),
+            line(0, 1, vec![]),
+            line(413, 0, vec![]),
+            set_variable(0, 2, 7),
+            line(0, 0, vec![]),
+        ];
+
+        let mut state = GameState::default();
+        let (_, status) = run(list, &mut state, &[]);
+        assert_eq!(status, Status::Finished);
+        assert_eq!(state.variable(1), 3);
+        assert_eq!(state.variable(2), 7);
+    }
+
+    #[test]
+    fn division_rounds_like_ruby() {
+        let operate = |id: i32, operation: i32, value: i32| {
+            line(
+                122,
+                0,
+                vec![
+                    id.into(),
+                    id.into(),
+                    operation.into(),
+                    0.into(),
+                    value.into(),
+                ],
+            )
+        };
+        let list = vec![
+            set_variable(0, 1, -7),
+            operate(1, 4, 2),
+            set_variable(0, 2, -7),
+            operate(2, 5, 2),
+            set_variable(0, 3, 7),
+            operate(3, 4, -2),
+            set_variable(0, 4, 7),
+            operate(4, 5, -2),
+            set_variable(0, 5, -6),
+            operate(5, 4, 2),
+            line(0, 0, vec![]),
+        ];
+
+        let mut state = GameState::default();
+        let (_, status) = run(list, &mut state, &[]);
+        assert_eq!(status, Status::Finished);
+        assert_eq!(state.variable(1), -4);
+        assert_eq!(state.variable(2), 1);
+        assert_eq!(state.variable(3), -4);
+        assert_eq!(state.variable(4), -1);
+        assert_eq!(state.variable(5), -3);
+    }
+
+    fn choices(cancel: i32) -> Vec<EventCommand> {
+        let mut list = vec![
+            line(
+                102,
+                0,
+                vec![
+                    ParameterType::Array(vec!["Yes".into(), "No".into()]),
+                    cancel.into(),
+                ],
+            ),
+            line(402, 0, vec![0.into(), "Yes".into()]),
+            set_variable(1, 1, 1),
+            line(0, 1, vec![]),
+            line(402, 0, vec![1.into(), "No".into()]),
+            set_variable(1, 1, 2),
+            line(0, 1, vec![]),
+        ];
+        if cancel == 5 {
+            list.extend([
+                line(403, 0, vec![]),
+                set_variable(1, 1, 3),
+                line(0, 1, vec![]),
+            ]);
+        }
+        list.extend([line(404, 0, vec![]), line(0, 0, vec![])]);
+        list
+    }
+
+    #[test]
+    fn choices_enter_the_picked_branch() {
+        let cases = [
+            (2, Answer::Choice(0), 1),
+            (2, Answer::Choice(1), 2),
+            // Cancelling picks the second choice
+            (2, Answer::Cancel, 2),
+            // Cancelling takes the When Cancel branch
+            (5, Answer::Cancel, 3),
+        ];
+        for (cancel, answer, expected) in cases {
+            let mut state = GameState::default();
+            let (mut interpreter, status) = run(choices(cancel), &mut state, &[]);
+            assert_eq!(
+                status,
+                Status::Waiting(Input::Choice {
+                    choices: vec!["Yes".to_string(), "No".to_string()],
+                    can_cancel: true,
+                })
+            );
+
+            interpreter.answer(&mut state, answer);
+            let status = interpreter.run(&mut state, &[], 10_000);
+            assert_eq!(status, Status::Finished);
+            assert_eq!(
+                state.variable(1),
+                expected,
+                "{answer:?} with cancel {cancel}"
+            );
+        }
+    }
+
+    #[test]
+    fn jumps_go_to_their_label() {
+        let list = vec![
+            line(118, 0, vec!["top".into()]),
+            add_variable(0, 1, 1),
+            if_variable(0, 1, 4, 3),
+            line(119, 1, vec!["top".into()]),
+            line(0, 1, vec![]),
+            line(412, 0, vec![]),
+            line(119, 0, vec!["nowhere".into()]),
+            line(0, 0, vec![]),
+        ];
+
+        let mut state = GameState::default();
+        let (interpreter, status) = run(list, &mut state, &[]);
+        assert_eq!(status, Status::Finished);
+        assert_eq!(state.variable(1), 3);
+        let missing = interpreter.trace().last().unwrap();
+        assert_eq!((missing.index, missing.kind), (6, TraceKind::NotSimulated));
+    }
+
+    #[test]
+    fn common_events_run_and_return() {
+        let common_event = |id: usize, list: Vec<EventCommand>| CommonEvent {
+            id,
+            name: format!("Common Event {id}"),
+            list,
+            ..Default::default()
+        };
+        let common_events = [
+            common_event(1, vec![add_variable(0, 1, 5), line(0, 0, vec![])]),
+            common_event(
+                2,
+                vec![
+                    line(117, 0, vec![1.into()]),
+                    line(117, 0, vec![1.into()]),
+                    line(0, 0, vec![]),
+                ],
+            ),
+            // Calls itself forever
+            common_event(3, vec![line(117, 0, vec![3.into()]), line(0, 0, vec![])]),
+        ];
+        let list = vec![
+            line(117, 0, vec![2.into()]),
+            line(
+                122,
+                0,
+                vec![2.into(), 2.into(), 0.into(), 1.into(), 1.into()],
+            ),
+            line(117, 0, vec![3.into()]),
+            line(0, 0, vec![]),
+        ];
+
+        let mut state = GameState::default();
+        let (interpreter, status) = run(list, &mut state, &common_events);
+        assert_eq!(status, Status::Finished);
+        assert_eq!(state.variable(1), 10);
+        assert_eq!(state.variable(2), 10);
+        assert!(interpreter
+            .trace()
+            .iter()
+            .any(|entry| entry.depth == 2 && entry.source == Source::CommonEvent(1)));
+        assert!(interpreter
+            .trace()
+            .iter()
+            .any(|entry| entry.kind == TraceKind::NotSimulated
+                && entry.source == Source::CommonEvent(3)));
+    }
+
+    #[test]
+    fn party_changes_are_only_logged_when_the_party_changes() {
+        let list = vec![
+            line(129, 0, vec![1.into(), 0.into()]),
+            line(129, 0, vec![1.into(), 0.into()]),
+            line(129, 0, vec![2.into(), 1.into()]),
+            line(0, 0, vec![]),
+        ];
+
+        let mut state = GameState::default();
+        let (interpreter, _) = run(list, &mut state, &[]);
+        assert_eq!(state.party, vec![1]);
+        let indices = interpreter
+            .trace()
+            .iter()
+            .map(|entry| entry.index)
+            .collect::<Vec<_>>();
+        assert_eq!(indices, vec![0]);
+    }
+}
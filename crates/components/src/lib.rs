@@ -30,15 +30,28 @@ pub mod syntax_highlighting;
 
 /// The tilemap.
 mod map_view;
-pub use map_view::{MapView, SelectedLayer};
+pub use map_view::{render_map_image, MapView, SelectedLayer, TileOverlay};
 mod tilepicker;
 pub use tilepicker::{SelectedTile, Tilepicker};
+mod tileset_flag_view;
+pub use tileset_flag_view::{TilesetFlag, TilesetFlagView};
+mod minimap;
+pub use minimap::Minimap;
+mod move_route_path;
+pub use move_route_path::{MoveRoutePath, RouteCollision, RouteEnd, RouteStep, StepKind};
+pub mod dungeon;
+pub mod event_script;
+pub mod interpreter;
+pub mod tiled;
 
 mod sound_tab;
 pub use sound_tab::SoundTab;
 
 mod command_view;
-pub use command_view::CommandView;
+pub use command_view::{CommandView, Listing, ListingLine, ListingToken};
+
+mod message_preview;
+pub use message_preview::MessagePreview;
 
 mod filesystem_view;
 pub use filesystem_view::FileSystemView;
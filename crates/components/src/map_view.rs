@@ -15,8 +15,10 @@
 // You should have received a copy of the GNU General Public License
 // along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
 
+use crate::move_route_path::{MoveRoutePath, RouteCollision, RouteEnd};
 use color_eyre::eyre::{ContextCompat, WrapErr};
 use itertools::Itertools;
+use luminol_data::ParameterType;
 use luminol_graphics::{Drawable, Renderable};
 use std::collections::HashMap;
 use std::io::Write;
@@ -26,6 +28,14 @@ pub struct MapView {
     pub visible_display: bool,
     /// Toggle move route preview
     pub move_preview: bool,
+    /// The paths of the move routes of the selected event, with the ID of the character following
+    /// each path (`None` for the player)
+    move_route_paths: Vec<(Option<usize>, MoveRoutePath)>,
+    /// The time the move route animation started at and the paths being animated
+    route_animation: Option<(f64, Vec<(Option<usize>, MoveRoutePath)>)>,
+    /// The tile passages used for tracing move routes, with the ID of the tileset they were
+    /// calculated with
+    route_passages: Option<(usize, luminol_data::Table2)>,
 
     pub pan: egui::Vec2,
     pub inter_tile_pan: egui::Vec2,
@@ -34,6 +44,9 @@ pub struct MapView {
     /// and the second sprite is for the hover preview.
     preview_events: HashMap<usize, PreviewEvent>,
     last_events: HashMap<usize, PreviewEvent>,
+    /// The index of the page each event sprite was created from
+    event_pages: HashMap<usize, usize>,
+    map_id: usize,
     pub map: luminol_graphics::Map,
 
     pub selected_layer: SelectedLayer,
@@ -53,9 +66,22 @@ pub struct MapView {
 
     /// Whether to display the tile IDs on the map
     pub display_tile_ids: bool,
+    /// Which tileset flags to display on top of the map
+    pub tile_overlay: TileOverlay,
+    /// Whether to display the map's annotations
+    pub annotations_enabled: bool,
+    /// The area of the map selected with shift-drag, in tile coordinates
+    pub selected_area: Option<egui::Rect>,
 
     pub scale: f32,
     previous_scale: f32,
+    /// The size of a tile on screen as of the last frame
+    tile_size: f32,
+
+    /// The area of the map visible on screen as of the last frame, in tile coordinates
+    pub visible_tiles: egui::Rect,
+    /// The area of the screen covered by the map as of the last frame
+    map_rect: egui::Rect,
 
     /// Used to store the bounding boxes of event graphics in order to render them on top of the
     /// fog and collision layers
@@ -76,6 +102,17 @@ pub enum SelectedLayer {
     Tiles(usize),
 }
 
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Default)]
+#[derive(strum::Display, strum::EnumIter)]
+pub enum TileOverlay {
+    #[default]
+    None,
+    Passability,
+    Priority,
+    #[strum(to_string = "Terrain Tags")]
+    TerrainTag,
+}
+
 impl MapView {
     pub fn new(
         update_state: &luminol_core::UpdateState<'_>,
@@ -126,12 +163,17 @@ impl MapView {
         Ok(Self {
             visible_display: false,
             move_preview: false,
+            move_route_paths: Vec::new(),
+            route_animation: None,
+            route_passages: None,
 
             pan,
             inter_tile_pan,
 
             preview_events: HashMap::new(),
             last_events: HashMap::new(),
+            event_pages: HashMap::new(),
+            map_id,
             map,
 
             selected_layer: SelectedLayer::default(),
@@ -146,9 +188,16 @@ impl MapView {
             selected_event_is_hovered: false,
 
             display_tile_ids: false,
+            tile_overlay: TileOverlay::default(),
+            annotations_enabled: true,
+            selected_area: None,
 
             scale,
             previous_scale: scale,
+            tile_size: 32. * scale / 100.,
+
+            visible_tiles: egui::Rect::NOTHING,
+            map_rect: egui::Rect::NOTHING,
 
             event_rects: Vec::new(),
 
@@ -156,6 +205,361 @@ impl MapView {
         })
     }
 
+    /// Pans the view so that the given position, in tile coordinates, is at the center.
+    pub fn center_on(&mut self, map: &luminol_data::rpg::Map, tile: egui::Pos2) {
+        let half_size = egui::vec2(map.width as f32 / 2., map.height as f32 / 2.);
+        self.pan = (half_size - tile.to_vec2()) * self.tile_size;
+        self.inter_tile_pan = egui::Vec2::ZERO;
+    }
+
+    /// Traces the move routes of the selected event: the custom move route of the page being
+    /// displayed and the Set Move Route commands in that page. `player_start` is the position of
+    /// the player if this map is the starting map. Events block movement depending on their active
+    /// page in `page_simulation`, or their first page if the game state isn't being simulated.
+    pub fn update_move_route_paths(
+        &mut self,
+        map: &luminol_data::rpg::Map,
+        tileset: &luminol_data::rpg::Tileset,
+        player_start: Option<(i32, i32)>,
+        page_simulation: Option<&luminol_core::PageSimulation>,
+    ) {
+        self.move_route_paths.clear();
+        let Some(event) = self
+            .selected_event_id
+            .filter(|_| self.move_preview)
+            .and_then(|id| map.events.get(id))
+        else {
+            return;
+        };
+        let page_index = self.event_pages.get(&event.id).copied().unwrap_or_default();
+        let Some(page) = event.pages.get(page_index) else {
+            return;
+        };
+
+        if self
+            .route_passages
+            .as_ref()
+            .map_or(true, |(tileset_id, _)| *tileset_id != map.tileset_id)
+        {
+            self.route_passages =
+                Some((map.tileset_id, RouteCollision::tile_passages(map, tileset)));
+        }
+        let Some((_, passages)) = &self.route_passages else {
+            return;
+        };
+        let map_id = self.map_id;
+        let active_page = |event: &luminol_data::rpg::Event| match page_simulation {
+            Some(simulation) => simulation.active_page(map_id, event),
+            None => Some(0),
+        };
+
+        if page.move_type == luminol_data::rpg::MoveType::Custom {
+            let collision = RouteCollision::new(map, passages, Some(event.id), active_page);
+            self.move_route_paths.push((
+                Some(event.id),
+                MoveRoutePath::trace(
+                    &page.move_route,
+                    &collision,
+                    (event.x, event.y),
+                    page.graphic.direction,
+                    page.move_speed,
+                    page.through,
+                ),
+            ));
+        }
+
+        for command in page.list.iter().filter(|command| command.code == 209) {
+            let [ParameterType::Integer(character_id), ParameterType::MoveRoute(route), ..] =
+                command.parameters.as_slice()
+            else {
+                continue;
+            };
+            let path = match *character_id {
+                -1 => {
+                    let Some(position) = player_start else {
+                        continue;
+                    };
+                    let collision = RouteCollision::new(map, passages, None, active_page);
+                    (
+                        None,
+                        MoveRoutePath::trace(
+                            route,
+                            &collision,
+                            position,
+                            2,
+                            luminol_data::rpg::MoveSpeed::Fast,
+                            false,
+                        ),
+                    )
+                }
+                id => {
+                    let id = if id == 0 { event.id } else { id as usize };
+                    let Some(character) = map.events.get(id) else {
+                        continue;
+                    };
+                    let Some(character_page) =
+                        active_page(character).and_then(|index| character.pages.get(index))
+                    else {
+                        continue;
+                    };
+                    let collision = RouteCollision::new(map, passages, Some(id), active_page);
+                    (
+                        Some(id),
+                        MoveRoutePath::trace(
+                            route,
+                            &collision,
+                            (character.x, character.y),
+                            character_page.graphic.direction,
+                            character_page.move_speed,
+                            character_page.through,
+                        ),
+                    )
+                }
+            };
+            self.move_route_paths.push(path);
+        }
+    }
+
+    /// Discards the tile passages used for tracing move routes so that they're recalculated the
+    /// next time they're needed. This has to be called whenever the tiles of the map or the flags of
+    /// its tileset change.
+    pub fn invalidate_route_passages(&mut self) {
+        self.route_passages = None;
+    }
+
+    /// Starts animating the characters along the move route paths of the selected event.
+    pub fn play_move_routes(&mut self, time: f64) {
+        self.route_animation =
+            (!self.move_route_paths.is_empty()).then(|| (time, self.move_route_paths.clone()));
+    }
+
+    pub fn stop_move_routes(&mut self) {
+        self.route_animation = None;
+    }
+
+    pub fn is_playing_move_routes(&self) -> bool {
+        self.route_animation.is_some()
+    }
+
+    /// Returns the position of a character along the move route animation, or `None` if the
+    /// character isn't being animated.
+    fn animated_position(&self, character_id: Option<usize>, time: f64) -> Option<egui::Pos2> {
+        let (start, paths) = self.route_animation.as_ref()?;
+        let elapsed = (time - start) as f32;
+        paths
+            .iter()
+            .filter(|(id, _)| *id == character_id)
+            .find_map(|(_, path)| {
+                let duration = path.duration();
+                if path.end == RouteEnd::Repeats && duration > 0. {
+                    path.position_at(elapsed % duration)
+                } else {
+                    path.position_at(elapsed)
+                }
+            })
+    }
+
+    /// Recreates the tilemap if the atlas of the map's tileset was reloaded since the tilemap was
+    /// created, keeping the layer and display toggles. Returns true if the tilemap was recreated.
+    pub fn reload_if_atlas_changed(
+        &mut self,
+        update_state: &luminol_core::UpdateState<'_>,
+        map: &luminol_data::rpg::Map,
+        tileset: &luminol_data::rpg::Tileset,
+        passages: &luminol_data::Table2,
+    ) -> color_eyre::Result<bool> {
+        let is_current = update_state
+            .graphics
+            .atlas_loader
+            .get_atlas(tileset.id)
+            .map_or(true, |atlas| atlas.ptr_eq(&self.map.atlas));
+        if is_current {
+            return Ok(false);
+        }
+
+        let mut new_map = luminol_graphics::Map::new(
+            &update_state.graphics,
+            update_state.filesystem,
+            map,
+            tileset,
+            passages,
+        )?;
+        new_map.fog_enabled = self.map.fog_enabled;
+        new_map.pano_enabled = self.map.pano_enabled;
+        new_map.coll_enabled = self.map.coll_enabled;
+        new_map.grid_enabled = self.map.grid_enabled;
+        new_map.event_enabled = self.map.event_enabled;
+        let (old_tiles, new_tiles) = (&self.map.tiles, &mut new_map.tiles);
+        new_tiles
+            .enabled_layers
+            .clone_from(&old_tiles.enabled_layers);
+        new_tiles.locked_layers.clone_from(&old_tiles.locked_layers);
+        new_tiles.dimmed_layers.clone_from(&old_tiles.dimmed_layers);
+        new_tiles.selected_layer = old_tiles.selected_layer;
+        new_tiles.auto_opacity = old_tiles.auto_opacity;
+        self.map = new_map;
+        Ok(true)
+    }
+
+    /// Converts a position on the screen to tile coordinates, without rounding to a whole tile.
+    pub fn tile_at(&self, pos: egui::Pos2) -> egui::Pos2 {
+        ((pos - self.map_rect.min) / self.tile_size).to_pos2()
+    }
+
+    /// Returns the ID of the tile whose tileset flags apply at a position on the map: the tile on
+    /// the selected layer if a tile layer is selected, otherwise the topmost tile that isn't blank.
+    pub fn flag_tile_id(&self, map: &luminol_data::rpg::Map, x: usize, y: usize) -> Option<usize> {
+        match self.selected_layer {
+            SelectedLayer::Tiles(layer) => Some(map.data[(x, y, layer)].max(0) as usize),
+            SelectedLayer::Events => (0..map.data.zsize())
+                .rev()
+                .map(|z| map.data[(x, y, z)].max(0) as usize)
+                .find(|&tile_id| tile_id >= 48),
+        }
+        .filter(|&tile_id| tile_id >= 48)
+    }
+
+    /// Draws the priorities or terrain tags of the visible tiles. Passability is drawn by the
+    /// collision layer of the tilemap instead. Must be called after `ui`.
+    pub fn tile_overlay_ui(
+        &self,
+        ui: &egui::Ui,
+        map: &luminol_data::rpg::Map,
+        tileset: &luminol_data::rpg::Tileset,
+    ) {
+        let table = match self.tile_overlay {
+            TileOverlay::Priority => &tileset.priorities,
+            TileOverlay::TerrainTag => &tileset.terrain_tags,
+            TileOverlay::None | TileOverlay::Passability => return,
+        };
+
+        let min_x = self.visible_tiles.min.x.floor().max(0.) as usize;
+        let min_y = self.visible_tiles.min.y.floor().max(0.) as usize;
+        let max_x = (self.visible_tiles.max.x.ceil().max(0.) as usize).min(map.data.xsize());
+        let max_y = (self.visible_tiles.max.y.ceil().max(0.) as usize).min(map.data.ysize());
+
+        let painter = ui.painter();
+        for (y, x) in (min_y..max_y).cartesian_product(min_x..max_x) {
+            let value = match self.tile_overlay {
+                // Like the game, use the topmost non-zero terrain tag
+                TileOverlay::TerrainTag if self.selected_layer == SelectedLayer::Events => {
+                    (0..map.data.zsize())
+                        .rev()
+                        .map(|z| map.data[(x, y, z)].max(0) as usize)
+                        .filter(|&tile_id| tile_id >= 48 && tile_id < table.len())
+                        .map(|tile_id| table[tile_id])
+                        .find(|&tag| tag > 0)
+                        .unwrap_or_default()
+                }
+                _ => self
+                    .flag_tile_id(map, x, y)
+                    .filter(|&tile_id| tile_id < table.len())
+                    .map_or(0, |tile_id| table[tile_id]),
+            };
+            if value == 0 {
+                continue;
+            }
+
+            let tile_rect = egui::Rect::from_min_size(
+                self.map_rect.min + egui::vec2(x as f32, y as f32) * self.tile_size,
+                egui::Vec2::splat(self.tile_size),
+            );
+            painter.rect_filled(
+                tile_rect.shrink(self.tile_size / 4.),
+                2.,
+                egui::Color32::from_black_alpha(160),
+            );
+            painter.text(
+                tile_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                value.to_string(),
+                egui::FontId::monospace(self.tile_size / 2.5),
+                egui::ecolor::Hsva::new(value as f32 / 8., 0.6, 1., 1.).into(),
+            );
+        }
+    }
+
+    /// Draws annotations on top of the map, with a yellow outline around the selected one. Must be
+    /// called after `ui`.
+    pub fn annotations_ui<'a>(
+        &self,
+        ui: &egui::Ui,
+        annotations: impl IntoIterator<Item = &'a luminol_data::Annotation>,
+        selected: Option<usize>,
+    ) {
+        if !self.annotations_enabled {
+            return;
+        }
+
+        let painter = ui.painter();
+        let tile_rect = |x: i32, y: i32| {
+            egui::Rect::from_min_size(
+                self.map_rect.min + egui::vec2(x as f32, y as f32) * self.tile_size,
+                egui::Vec2::splat(self.tile_size),
+            )
+        };
+        let font_id = egui::FontId::proportional((self.tile_size * 0.4).max(10.));
+        let selected_stroke = egui::Stroke::new(2., egui::Color32::YELLOW);
+
+        for (index, annotation) in annotations.into_iter().enumerate() {
+            let [r, g, b] = annotation.color;
+            let color = egui::Color32::from_rgb(r, g, b);
+            let stroke = egui::Stroke::new((self.tile_size / 16.).max(2.), color);
+            let is_selected = selected == Some(index);
+
+            // The rectangle containing the shape, used to place the text and the selection outline
+            let (shape_rect, text_pos) = match annotation.shape {
+                luminol_data::AnnotationShape::Note { x, y } => {
+                    let rect = tile_rect(x, y);
+                    painter.rect_filled(rect.shrink(self.tile_size / 8.), 2., color);
+                    (rect, rect.right_top())
+                }
+                luminol_data::AnnotationShape::Rectangle {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    let rect = tile_rect(x, y).union(tile_rect(x + width - 1, y + height - 1));
+                    painter.rect_filled(rect, 2., color.gamma_multiply(0.2));
+                    painter.rect_stroke(rect, 2., stroke);
+                    (rect, rect.left_top())
+                }
+                luminol_data::AnnotationShape::Arrow { from, to } => {
+                    let from = tile_rect(from.0, from.1);
+                    let to = tile_rect(to.0, to.1);
+                    painter.arrow(from.center(), to.center() - from.center(), stroke);
+                    (
+                        egui::Rect::from_two_pos(from.center(), to.center()),
+                        from.center().lerp(to.center(), 0.5),
+                    )
+                }
+            };
+
+            if is_selected {
+                painter.rect_stroke(shape_rect.expand(2.), 2., selected_stroke);
+            }
+
+            if annotation.text.is_empty() {
+                continue;
+            }
+            let galley = painter.layout(
+                annotation.text.clone(),
+                font_id.clone(),
+                egui::Color32::BLACK,
+                self.tile_size * 6.,
+            );
+            let text_rect = egui::Rect::from_min_size(text_pos, galley.size()).expand(4.);
+            painter.rect(
+                text_rect,
+                3.,
+                egui::Color32::from_rgb(255, 245, 170),
+                if is_selected { selected_stroke } else { stroke },
+            );
+            painter.galley(text_pos, galley, egui::Color32::BLACK);
+        }
+    }
+
     // FIXME lots of arguments
     #[allow(clippy::too_many_arguments)]
     pub fn ui(
@@ -297,11 +701,18 @@ impl MapView {
         let width2 = map.width as f32 / 2.;
         let height2 = map.height as f32 / 2.;
 
+        self.tile_size = tile_size;
+        self.visible_tiles = egui::Rect::from_min_max(
+            ((canvas_rect.min - canvas_pos) / tile_size + egui::vec2(width2, height2)).to_pos2(),
+            ((canvas_rect.max - canvas_pos) / tile_size + egui::vec2(width2, height2)).to_pos2(),
+        );
+
         let map_size2 = egui::Vec2::new(width2 * tile_size, height2 * tile_size);
         let map_rect = egui::Rect {
             min: canvas_pos - map_size2,
             max: canvas_pos + map_size2,
         };
+        self.map_rect = map_rect;
 
         self.map.tiles.selected_layer = match self.selected_layer {
             SelectedLayer::Events => None,
@@ -321,8 +732,21 @@ impl MapView {
             glam::Vec2::splat(scale),
         );
 
+        let time = ui.input(|i| i.time);
         self.map
-            .update_animation(&update_state.graphics.render_state, ui.input(|i| i.time));
+            .update_animation(&update_state.graphics.render_state, time);
+
+        // Stop the move route animation once every character has reached the end of its path
+        if let Some((start, paths)) = &self.route_animation {
+            let elapsed = (time - start) as f32;
+            if paths.iter().all(|(_, path)| {
+                path.end != RouteEnd::Repeats && path.position_at(elapsed).is_none()
+            }) {
+                self.route_animation = None;
+            } else {
+                ui.ctx().request_repaint();
+            }
+        }
         ui.ctx()
             .request_repaint_after(std::time::Duration::from_secs_f32(16. / 60.));
 
@@ -345,7 +769,9 @@ impl MapView {
         );
         let pattern_rect = egui::Rect::from_min_size(
             map_rect.min + (self.cursor_pos.to_vec2() * tile_size),
-            if tilepicker.brush_random || (!force_show_pattern_rect && drawing_shape_pos.is_some())
+            if tilepicker.brush_random
+                || matches!(update_state.toolbar.pencil, luminol_core::Pencil::Scatter)
+                || (!force_show_pattern_rect && drawing_shape_pos.is_some())
             {
                 egui::Vec2::splat(tile_size)
             } else {
@@ -366,21 +792,58 @@ impl MapView {
         }
         self.selected_event_is_hovered = false;
 
+        // Remove the sprites of events that no longer exist, e.g. because their creation was undone
+        let stale_event_ids = self
+            .map
+            .events
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|&id| !map.events.contains(id))
+            .collect_vec();
+        for id in stale_event_ids {
+            self.map.events.remove(id);
+            self.event_pages.remove(&id);
+        }
+        if self
+            .selected_event_id
+            .is_some_and(|id| !map.events.contains(id))
+        {
+            self.selected_event_id = None;
+        }
+
         if self.map.event_enabled {
             let mut selected_event = None;
             let mut selected_event_rect = None;
 
             for (_, event) in map.events.iter() {
-                if event.extra_data.graphic_modified.get() {
+                // When simulating the game state, display events using their active page and
+                // grey out events without one
+                let (page_index, is_inactive) = match &update_state.toolbar.page_simulation {
+                    Some(simulation) => simulation
+                        .active_page(self.map_id, event)
+                        .map_or((0, true), |page_index| (page_index, false)),
+                    None => (0, false),
+                };
+
+                if event.extra_data.graphic_modified.get()
+                    // Sprites that haven't been rebuilt yet were created from the first page
+                    || self.event_pages.get(&event.id).copied().unwrap_or_default() != page_index
+                {
                     event.extra_data.graphic_modified.set(false);
-                    let sprite = luminol_graphics::Event::new_map(
-                        &update_state.graphics,
-                        update_state.filesystem,
-                        &self.map.viewport,
-                        event,
-                        &self.map.atlas,
-                    )
-                    .unwrap(); // FIXME handle
+                    self.event_pages.insert(event.id, page_index);
+                    self.last_events.remove(&event.id);
+                    let sprite = match event.pages.get(page_index) {
+                        Some(page) => luminol_graphics::Event::new_map_page(
+                            &update_state.graphics,
+                            update_state.filesystem,
+                            &self.map.viewport,
+                            event,
+                            page,
+                            &self.map.atlas,
+                        )
+                        .unwrap(), // FIXME handle
+                        None => None,
+                    };
                     if let Some(sprite) = sprite {
                         self.map.events.insert(event.id, sprite);
                     } else {
@@ -388,6 +851,7 @@ impl MapView {
                     }
                 }
 
+                let animated_position = self.animated_position(Some(event.id), time);
                 let sprite = self.map.events.get_mut(event.id);
                 let has_sprite = sprite.is_some();
                 let event_size = sprite
@@ -399,7 +863,18 @@ impl MapView {
                 // update relevant properties
                 if let Some(sprite) = sprite {
                     // FIXME only update if necessary
-                    sprite.set_position(&update_state.graphics.render_state, event.x, event.y);
+                    match animated_position {
+                        Some(position) => sprite.set_tile_position(
+                            &update_state.graphics.render_state,
+                            position.x,
+                            position.y,
+                        ),
+                        None => sprite.set_position(
+                            &update_state.graphics.render_state,
+                            event.x,
+                            event.y,
+                        ),
+                    }
                     sprite.sprite.graphic.set_opacity_multiplier(
                         &update_state.graphics.render_state,
                         if self.darken_unselected_layers
@@ -408,7 +883,7 @@ impl MapView {
                             0.5
                         } else {
                             1.
-                        },
+                        } * if is_inactive { 0.3 } else { 1. },
                     );
                 }
 
@@ -460,6 +935,13 @@ impl MapView {
                     if ui.rect_contains_pointer(box_rect) {
                         response = response.on_hover_ui_at_pointer(|ui| {
                             ui.label(format!("Event {:0>3}: {:?}", event.id, event.name));
+                            if update_state.toolbar.page_simulation.is_some() {
+                                ui.label(if is_inactive {
+                                    "No active page".to_string()
+                                } else {
+                                    format!("Active page: {}", page_index + 1)
+                                });
+                            }
 
                             let (response, _painter) = ui.allocate_painter(
                                 event_size * ui.ctx().pixels_per_point(),
@@ -473,7 +955,7 @@ impl MapView {
                                             &update_state.graphics,
                                             glam::vec2(event_size.x, event_size.y),
                                         );
-                                        let graphic = &event.pages[0].graphic; // FIXME handle missing first page (should never happen though...)
+                                        let graphic = &event.pages[page_index].graphic;
                                         let sprite = luminol_graphics::Event::new_standalone(
                                             &update_state.graphics,
                                             update_state.filesystem,
@@ -607,6 +1089,32 @@ impl MapView {
             }
         }
 
+        // Draw the paths of the selected event's move routes
+        if self.move_preview {
+            for (index, (character_id, path)) in self.move_route_paths.iter().enumerate() {
+                let color: egui::Color32 =
+                    egui::ecolor::Hsva::new(0.5 + index as f32 * 0.17, 0.8, 1., 1.).into();
+                path.paint(
+                    ui.painter(),
+                    |tile| map_rect.min + tile.to_vec2() * tile_size,
+                    tile_size,
+                    color,
+                );
+
+                // The player doesn't have a sprite on the map, so mark its position instead
+                if character_id.is_none() {
+                    if let Some(position) = self.animated_position(None, time) {
+                        ui.painter().circle_filled(
+                            map_rect.min
+                                + (position.to_vec2() + egui::Vec2::splat(0.5)) * tile_size,
+                            tile_size / 3.,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+
         // FIXME: If we want to be fast, we should be rendering all the tile ids to a texture once and then just rendering that texture here
         if self.display_tile_ids {
             if let SelectedLayer::Tiles(layer) = self.selected_layer {
@@ -650,6 +1158,17 @@ impl MapView {
             );
         }
 
+        if let Some(area) = self.selected_area {
+            ui.painter().rect_stroke(
+                egui::Rect::from_min_max(
+                    map_rect.min + area.min.to_vec2() * tile_size,
+                    map_rect.min + area.max.to_vec2() * tile_size,
+                ),
+                0.,
+                egui::Stroke::new(2., egui::Color32::LIGHT_BLUE),
+            );
+        }
+
         // Draw the origin tile for the rectangle and circle brushes
         if drawing_shape {
             if let Some(drawing_shape_pos) = drawing_shape_pos {
@@ -666,12 +1185,27 @@ impl MapView {
         }
 
         // Display cursor.
-        if matches!(self.selected_layer, SelectedLayer::Tiles(_)) {
-            ui.painter().rect_stroke(
-                pattern_rect,
-                5.,
-                egui::Stroke::new(1., egui::Color32::WHITE),
-            );
+        if let SelectedLayer::Tiles(layer) = self.selected_layer {
+            // Draw the brush outline in red if the selected layer can't be drawn on
+            let pattern_color = if self.map.tiles.is_layer_editable(layer) {
+                egui::Color32::WHITE
+            } else {
+                if !panning_map_view {
+                    response = response.on_hover_cursor(egui::CursorIcon::NotAllowed);
+                }
+                egui::Color32::RED
+            };
+            ui.painter()
+                .rect_stroke(pattern_rect, 5., egui::Stroke::new(1., pattern_color));
+
+            // Display the area covered by the scatter brush
+            if matches!(update_state.toolbar.pencil, luminol_core::Pencil::Scatter) {
+                ui.painter().circle_stroke(
+                    cursor_rect.center(),
+                    (update_state.toolbar.brush_scatter_radius as f32 + 0.5) * tile_size,
+                    egui::Stroke::new(1., pattern_color),
+                );
+            }
         }
         ui.painter().rect_stroke(
             cursor_rect,
@@ -700,201 +1234,32 @@ impl MapView {
     ) -> impl std::future::Future<Output = color_eyre::Result<()>> {
         let c = "While screenshotting the map";
 
-        let max_texture_dimension_2d = graphics_state
-            .render_state
-            .device
-            .limits()
-            .max_texture_dimension_2d
-            / wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
-            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
-        let max_buffer_size = graphics_state.render_state.device.limits().max_buffer_size as u32
-            / wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
-            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
-
-        let screenshot_width = map.width as u32 * 32;
-        let screenshot_height = map.height as u32 * 32;
-
-        let max_texture_width = screenshot_width
-            .min(max_texture_dimension_2d)
-            .min(max_buffer_size);
-        let max_texture_height = screenshot_height
-            .min(max_texture_dimension_2d)
-            .min(max_buffer_size / (max_texture_width * 4));
-
-        let mut command_encoder = graphics_state
-            .render_state
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-
-        let buffers = (0..screenshot_height)
-            .step_by(max_texture_height as usize)
-            .cartesian_product((0..screenshot_width).step_by(max_texture_width as usize))
-            .map(|(y_offset, x_offset)| {
-                let width = max_texture_width.min(screenshot_width - x_offset);
-                let height = max_texture_height.min(screenshot_height - y_offset);
-                let width_padded = width.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT / 4);
-
-                let texture =
-                    graphics_state
-                        .render_state
-                        .device
-                        .create_texture(&wgpu::TextureDescriptor {
-                            label: Some("map editor screenshot texture"),
-                            size: wgpu::Extent3d {
-                                width,
-                                height,
-                                depth_or_array_layers: 1,
-                            },
-                            mip_level_count: 1,
-                            sample_count: 1,
-                            dimension: wgpu::TextureDimension::D2,
-                            format: graphics_state.render_state.target_format,
-                            usage: wgpu::TextureUsages::COPY_SRC
-                                | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                            view_formats: &[],
-                        });
-                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-                let buffer =
-                    graphics_state
-                        .render_state
-                        .device
-                        .create_buffer(&wgpu::BufferDescriptor {
-                            label: Some("map editor screenshot buffer"),
-                            size: width_padded as u64 * height as u64 * 4,
-                            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-                            mapped_at_creation: false,
-                        });
+        self.map.tiles.selected_layer = match self.selected_layer {
+            SelectedLayer::Events => None,
+            SelectedLayer::Tiles(selected_layer) if self.darken_unselected_layers => {
+                Some(selected_layer)
+            }
+            SelectedLayer::Tiles(_) => None,
+        };
 
-                self.map.viewport.set(
+        for (_, event) in map.events.iter() {
+            if let Some(sprite) = self.map.events.get_mut(event.id) {
+                sprite.sprite.graphic.set_opacity_multiplier(
                     &graphics_state.render_state,
-                    glam::vec2(width as f32, height as f32),
-                    glam::vec2(x_offset as f32, y_offset as f32),
-                    glam::Vec2::ONE,
-                );
-
-                self.map.tiles.selected_layer = match self.selected_layer {
-                    SelectedLayer::Events => None,
-                    SelectedLayer::Tiles(selected_layer) if self.darken_unselected_layers => {
-                        Some(selected_layer)
-                    }
-                    SelectedLayer::Tiles(_) => None,
-                };
-
-                for (_, event) in map.events.iter() {
-                    if let Some(sprite) = self.map.events.get_mut(event.id) {
-                        sprite.sprite.graphic.set_opacity_multiplier(
-                            &graphics_state.render_state,
-                            if self.darken_unselected_layers
-                                && !matches!(self.selected_layer, SelectedLayer::Events)
-                            {
-                                0.5
-                            } else {
-                                1.
-                            },
-                        );
-                    }
-                }
-
-                // we probably don't need to prepare the map every time, but it's not that expensive
-                let prepared = self.map.prepare(graphics_state);
-
-                let mut render_pass =
-                    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: Some("map editor screenshot render pass"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
-                            ops: wgpu::Operations::default(),
-                        })],
-                        depth_stencil_attachment: None,
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                    });
-
-                prepared.draw(&mut render_pass);
-
-                drop(render_pass);
-
-                command_encoder.copy_texture_to_buffer(
-                    wgpu::ImageCopyTexture {
-                        texture: &texture,
-                        mip_level: 0,
-                        origin: wgpu::Origin3d::ZERO,
-                        aspect: wgpu::TextureAspect::All,
-                    },
-                    wgpu::ImageCopyBuffer {
-                        buffer: &buffer,
-                        layout: wgpu::ImageDataLayout {
-                            offset: 0,
-                            bytes_per_row: Some(width_padded * 4),
-                            rows_per_image: Some(height),
-                        },
-                    },
-                    wgpu::Extent3d {
-                        width,
-                        height,
-                        depth_or_array_layers: 1,
+                    if self.darken_unselected_layers
+                        && !matches!(self.selected_layer, SelectedLayer::Events)
+                    {
+                        0.5
+                    } else {
+                        1.
                     },
                 );
-
-                buffer
-            })
-            .collect_vec();
-
-        graphics_state
-            .render_state
-            .queue
-            .submit(std::iter::once(command_encoder.finish()));
-
-        let graphics_state = graphics_state.clone();
-        let mut vec = vec![0; screenshot_width as usize * screenshot_height as usize * 4];
-        async move {
-            for ((y_offset, x_offset), buffer) in (0..screenshot_height)
-                .step_by(max_texture_height as usize)
-                .cartesian_product((0..screenshot_width).step_by(max_texture_width as usize))
-                .zip(buffers)
-            {
-                let width = max_texture_width.min(screenshot_width - x_offset);
-                let width_padded = width.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT / 4);
-
-                let (tx, rx) = oneshot::channel();
-                buffer
-                    .slice(..)
-                    .map_async(wgpu::MapMode::Read, move |result| {
-                        let _ = tx.send(result);
-                    });
-                if !graphics_state
-                    .render_state
-                    .device
-                    .poll(wgpu::Maintain::Wait)
-                    .is_queue_empty()
-                {
-                    return Err(color_eyre::eyre::eyre!("wgpu::Device::poll timed out").wrap_err(c));
-                }
-                rx.await.unwrap().wrap_err(c)?;
-
-                for (i, row) in buffer
-                    .slice(..)
-                    .get_mapped_range()
-                    .chunks_exact(width_padded as usize * 4)
-                    .enumerate()
-                {
-                    let offset = ((y_offset as usize + i) * screenshot_width as usize
-                        + x_offset as usize)
-                        * 4;
-                    vec[offset..offset + width as usize * 4]
-                        .copy_from_slice(&row[..width as usize * 4]);
-                }
-            }
-
-            if graphics_state.render_state.target_format == wgpu::TextureFormat::Bgra8Unorm {
-                for (b, _g, r, _a) in vec.iter_mut().tuples() {
-                    std::mem::swap(b, r);
-                }
             }
+        }
 
-            let screenshot =
-                image::RgbaImage::from_raw(screenshot_width, screenshot_height, vec).wrap_err(c)?;
+        let screenshot = render_map_image(graphics_state, &mut self.map, map);
+        async move {
+            let screenshot = screenshot.await.wrap_err(c)?;
             let mut file = luminol_filesystem::host::File::new().wrap_err(c)?;
             screenshot
                 .write_to(
@@ -909,3 +1274,186 @@ impl MapView {
         }
     }
 }
+
+/// Renders a whole map into an image. Maps larger than the GPU's texture size limit are rendered
+/// in several parts that are stitched together.
+/// This function returns a future that you need to `.await` to get the image, but the future
+/// doesn't borrow anything so you don't need to worry about lifetime-related issues.
+pub fn render_map_image(
+    graphics_state: &std::sync::Arc<luminol_graphics::GraphicsState>,
+    map_renderer: &mut luminol_graphics::Map,
+    map: &luminol_data::rpg::Map,
+) -> impl std::future::Future<Output = color_eyre::Result<image::RgbaImage>> {
+    let c = "While rendering the map";
+
+    let max_texture_dimension_2d = graphics_state
+        .render_state
+        .device
+        .limits()
+        .max_texture_dimension_2d
+        / wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let max_buffer_size = graphics_state.render_state.device.limits().max_buffer_size as u32
+        / wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let screenshot_width = map.width as u32 * 32;
+    let screenshot_height = map.height as u32 * 32;
+
+    let max_texture_width = screenshot_width
+        .min(max_texture_dimension_2d)
+        .min(max_buffer_size);
+    let max_texture_height = screenshot_height
+        .min(max_texture_dimension_2d)
+        .min(max_buffer_size / (max_texture_width * 4));
+
+    let mut command_encoder = graphics_state
+        .render_state
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+
+    let buffers = (0..screenshot_height)
+        .step_by(max_texture_height as usize)
+        .cartesian_product((0..screenshot_width).step_by(max_texture_width as usize))
+        .map(|(y_offset, x_offset)| {
+            let width = max_texture_width.min(screenshot_width - x_offset);
+            let height = max_texture_height.min(screenshot_height - y_offset);
+            let width_padded = width.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT / 4);
+
+            let texture =
+                graphics_state
+                    .render_state
+                    .device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        label: Some("map editor screenshot texture"),
+                        size: wgpu::Extent3d {
+                            width,
+                            height,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: graphics_state.render_state.target_format,
+                        usage: wgpu::TextureUsages::COPY_SRC
+                            | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                        view_formats: &[],
+                    });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let buffer =
+                graphics_state
+                    .render_state
+                    .device
+                    .create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("map editor screenshot buffer"),
+                        size: width_padded as u64 * height as u64 * 4,
+                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                        mapped_at_creation: false,
+                    });
+
+            map_renderer.viewport.set(
+                &graphics_state.render_state,
+                glam::vec2(width as f32, height as f32),
+                glam::vec2(x_offset as f32, y_offset as f32),
+                glam::Vec2::ONE,
+            );
+
+            // we probably don't need to prepare the map every time, but it's not that expensive
+            let prepared = map_renderer.prepare(graphics_state);
+
+            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("map editor screenshot render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            prepared.draw(&mut render_pass);
+
+            drop(render_pass);
+
+            command_encoder.copy_texture_to_buffer(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(width_padded * 4),
+                        rows_per_image: Some(height),
+                    },
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            buffer
+        })
+        .collect_vec();
+
+    graphics_state
+        .render_state
+        .queue
+        .submit(std::iter::once(command_encoder.finish()));
+
+    let graphics_state = graphics_state.clone();
+    let mut vec = vec![0; screenshot_width as usize * screenshot_height as usize * 4];
+    async move {
+        for ((y_offset, x_offset), buffer) in (0..screenshot_height)
+            .step_by(max_texture_height as usize)
+            .cartesian_product((0..screenshot_width).step_by(max_texture_width as usize))
+            .zip(buffers)
+        {
+            let width = max_texture_width.min(screenshot_width - x_offset);
+            let width_padded = width.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT / 4);
+
+            let (tx, rx) = oneshot::channel();
+            buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let _ = tx.send(result);
+                });
+            if !graphics_state
+                .render_state
+                .device
+                .poll(wgpu::Maintain::Wait)
+                .is_queue_empty()
+            {
+                return Err(color_eyre::eyre::eyre!("wgpu::Device::poll timed out").wrap_err(c));
+            }
+            rx.await.unwrap().wrap_err(c)?;
+
+            for (i, row) in buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks_exact(width_padded as usize * 4)
+                .enumerate()
+            {
+                let offset =
+                    ((y_offset as usize + i) * screenshot_width as usize + x_offset as usize) * 4;
+                vec[offset..offset + width as usize * 4]
+                    .copy_from_slice(&row[..width as usize * 4]);
+            }
+        }
+
+        if graphics_state.render_state.target_format == wgpu::TextureFormat::Bgra8Unorm {
+            for (b, _g, r, _a) in vec.iter_mut().tuples() {
+                std::mem::swap(b, r);
+            }
+        }
+
+        image::RgbaImage::from_raw(screenshot_width, screenshot_height, vec).wrap_err(c)
+    }
+}
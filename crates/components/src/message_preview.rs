@@ -0,0 +1,500 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use std::sync::Arc;
+
+use luminol_filesystem::FileSystem;
+
+/// The size of the message window of the default scripts.
+const WINDOW_SIZE: egui::Vec2 = egui::vec2(480., 160.);
+/// The padding between the window frame and its contents.
+const PADDING: f32 = 16.;
+/// Lines are drawn this far from the left of the contents.
+const TEXT_X: f32 = 4.;
+const LINE_HEIGHT: f32 = 32.;
+/// How many lines fit in the message window.
+const MAX_LINES: usize = 4;
+/// The opacity of the message window's background.
+const BACK_OPACITY: u8 = 160;
+/// The size of `Font.default_size`.
+const DEFAULT_FONT_SIZE: f32 = 22.;
+/// The value of `Font.default_name` if the scripts don't change it.
+const DEFAULT_FONT_NAME: &str = "Arial";
+
+/// The colors `\c[n]` can switch to, from `Window_Base#text_color`.
+const TEXT_COLORS: [egui::Color32; 8] = [
+    egui::Color32::from_rgb(255, 255, 255),
+    egui::Color32::from_rgb(128, 128, 255),
+    egui::Color32::from_rgb(255, 128, 128),
+    egui::Color32::from_rgb(128, 255, 128),
+    egui::Color32::from_rgb(128, 255, 255),
+    egui::Color32::from_rgb(255, 128, 255),
+    egui::Color32::from_rgb(255, 255, 128),
+    egui::Color32::from_rgb(192, 192, 192),
+];
+
+/// A preview of the message box a Show Text command displays in game.
+/// The text is drawn over the project's windowskin the way the default `Window_Message` draws it,
+/// with its control codes applied, and lines too wide for the window are pointed out.
+pub struct MessagePreview {
+    font_family: egui::FontFamily,
+    font_size: f32,
+    /// The families of the fonts in the project's Fonts folder, once they have been loaded
+    project_fonts: Option<Vec<egui::FontFamily>>,
+    /// How many digits `\v[n]` is assumed to have when the page simulation is off, so that the
+    /// widest value a variable is expected to hold can be checked
+    variable_digits: usize,
+    /// The windowskin that was last loaded, or the error loading it
+    windowskin: Option<(
+        camino::Utf8PathBuf,
+        Result<Arc<luminol_graphics::Texture>, String>,
+    )>,
+}
+
+impl Default for MessagePreview {
+    fn default() -> Self {
+        Self {
+            font_family: egui::FontFamily::Proportional,
+            font_size: DEFAULT_FONT_SIZE,
+            project_fonts: None,
+            variable_digits: 4,
+            windowskin: None,
+        }
+    }
+}
+
+/// A message with its control codes applied, split into colored pieces for each line.
+#[derive(Debug, Default)]
+struct Message {
+    lines: Vec<Vec<(String, egui::Color32)>>,
+    /// Whether `\g` shows the gold window
+    shows_gold: bool,
+}
+
+impl MessagePreview {
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        update_state: &luminol_core::UpdateState<'_>,
+        text: &str,
+    ) {
+        self.load_project_fonts(ui.ctx(), update_state);
+        // Fonts that were just added can only be used once egui has loaded them next frame
+        let available = ui.fonts(|f| f.families());
+        if !available.contains(&self.font_family) {
+            ui.ctx().request_repaint();
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Font");
+            egui::ComboBox::from_id_source(ui.id().with("message_preview_font"))
+                .selected_text(self.font_family.to_string())
+                .show_ui(ui, |ui| {
+                    let project_fonts = self.project_fonts.clone().unwrap_or_default();
+                    for family in &project_fonts {
+                        let text = family.to_string();
+                        ui.selectable_value(&mut self.font_family, family.clone(), text);
+                    }
+                    if !project_fonts.is_empty() {
+                        ui.separator();
+                    }
+                    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+                        let text = family.to_string();
+                        ui.selectable_value(&mut self.font_family, family, text);
+                    }
+                });
+            ui.add(
+                egui::DragValue::new(&mut self.font_size)
+                    .range(8.0..=48.0)
+                    .suffix(" px"),
+            );
+
+            if update_state.toolbar.page_simulation.is_none() {
+                ui.separator();
+                ui.label("Variable digits");
+                ui.add(egui::DragValue::new(&mut self.variable_digits).range(1..=9))
+                    .on_hover_text(
+                        "How many digits \\v[n] is displayed with. Turn on the page simulation to \
+                         use the simulated values instead.",
+                    );
+            }
+        });
+
+        let windowskin = self.windowskin(update_state);
+        let message = self.message(update_state, text);
+
+        let (rect, _) = ui.allocate_exact_size(WINDOW_SIZE, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        paint_window(&painter, rect, windowskin.as_deref().ok());
+
+        let contents = rect.shrink(PADDING);
+        let family = if available.contains(&self.font_family) {
+            self.font_family.clone()
+        } else {
+            egui::FontFamily::Proportional
+        };
+        let font_id = egui::FontId::new(self.font_size, family);
+        let mut warnings = Vec::new();
+        for (index, pieces) in message.lines.iter().enumerate() {
+            let mut job = egui::text::LayoutJob::default();
+            for (text, color) in pieces {
+                job.append(text, 0., egui::TextFormat::simple(font_id.clone(), *color));
+            }
+            let galley = ui.fonts(|f| f.layout_job(job));
+
+            let overflow = TEXT_X + galley.size().x - contents.width();
+            if overflow > 0. {
+                warnings.push(format!(
+                    "Line {} is {} px too wide for the window",
+                    index + 1,
+                    overflow.ceil()
+                ));
+            }
+            if index >= MAX_LINES {
+                continue;
+            }
+
+            // Center the text vertically in its line like `Bitmap#draw_text` does
+            let y = LINE_HEIGHT * index as f32 + (LINE_HEIGHT - galley.size().y) / 2.;
+            let pos = contents.min + egui::vec2(TEXT_X, y);
+            painter
+                .with_clip_rect(contents)
+                .galley(pos, galley, TEXT_COLORS[0]);
+            if overflow > 0. {
+                painter.text(
+                    egui::pos2(contents.right(), pos.y + LINE_HEIGHT / 2.),
+                    egui::Align2::LEFT_CENTER,
+                    "▶",
+                    egui::FontId::proportional(12.),
+                    ui.visuals().error_fg_color,
+                );
+            }
+        }
+        if message.lines.len() > MAX_LINES {
+            warnings.push(format!(
+                "Only the first {MAX_LINES} of {} lines fit in the window",
+                message.lines.len()
+            ));
+        }
+
+        if let Err(error) = &windowskin {
+            ui.label(
+                egui::RichText::new(format!("The windowskin couldn't be loaded: {error}")).weak(),
+            );
+        }
+        if message.shows_gold {
+            ui.label(egui::RichText::new("\\g shows the gold window").weak());
+        }
+        for warning in warnings {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {warning}"));
+        }
+    }
+
+    /// Adds the fonts in the project's Fonts folder to egui, each as a family named after its file,
+    /// and selects the font the game draws text with if it is one of them.
+    fn load_project_fonts(
+        &mut self,
+        ctx: &egui::Context,
+        update_state: &luminol_core::UpdateState<'_>,
+    ) {
+        if self.project_fonts.is_some() {
+            return;
+        }
+
+        let directory = camino::Utf8Path::new("Fonts");
+        let entries = update_state
+            .filesystem
+            .read_dir(directory)
+            .unwrap_or_default();
+        let mut definitions = ctx.fonts(|f| f.lock().fonts.definitions().clone());
+        // Glyphs the project's fonts don't have are drawn with the editor's fonts
+        let fallback = definitions
+            .families
+            .get(&egui::FontFamily::Proportional)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut families = Vec::new();
+        let mut added = false;
+        for entry in entries {
+            let path = directory.join(entry.path.file_name().unwrap_or_default());
+            let is_font = path.extension().is_some_and(|extension| {
+                ["ttf", "otf", "ttc"].contains(&extension.to_lowercase().as_str())
+            });
+            let Some(name) = path.file_stem().filter(|_| is_font) else {
+                continue;
+            };
+            if !definitions.font_data.contains_key(name) {
+                let Ok(bytes) = update_state.filesystem.read(&path) else {
+                    continue;
+                };
+                definitions
+                    .font_data
+                    .insert(name.to_string(), egui::FontData::from_owned(bytes));
+                let fonts = std::iter::once(name.to_string())
+                    .chain(fallback.iter().cloned())
+                    .collect();
+                definitions
+                    .families
+                    .insert(egui::FontFamily::Name(name.into()), fonts);
+                added = true;
+            }
+            families.push(egui::FontFamily::Name(name.into()));
+        }
+        if added {
+            ctx.set_fonts(definitions);
+        }
+
+        let game_font = font_key(&game_font_name(update_state));
+        if let Some(family) = families
+            .iter()
+            .find(|family| font_key(&family.to_string()) == game_font)
+        {
+            self.font_family = family.clone();
+        }
+        self.project_fonts = Some(families);
+    }
+
+    /// Loads the windowskin set in the system data, if it changed since it was last loaded.
+    fn windowskin(
+        &mut self,
+        update_state: &luminol_core::UpdateState<'_>,
+    ) -> Result<Arc<luminol_graphics::Texture>, String> {
+        let Some(name) = update_state.data.system().windowskin_name.clone() else {
+            return Err("no windowskin is set in the system data".to_string());
+        };
+
+        match &self.windowskin {
+            Some((loaded, result)) if *loaded == name => result.clone(),
+            _ => {
+                let result = update_state
+                    .graphics
+                    .texture_loader
+                    .load_now_dir(update_state.filesystem, "Graphics/Windowskins", &name)
+                    .map_err(|e| e.to_string());
+                self.windowskin = Some((name, result.clone()));
+                result
+            }
+        }
+    }
+
+    /// Applies the control codes of a message, in the same order as `Window_Message#refresh`.
+    fn message(&self, update_state: &luminol_core::UpdateState<'_>, text: &str) -> Message {
+        // Escaped backslashes are hidden from the other control codes
+        let text = text.replace("\\\\", "\0");
+
+        let text = replace_code(&text, 'v', |id| {
+            match &update_state.toolbar.page_simulation {
+                // The simulation uses the 0-based indices of the system data
+                Some(simulation) => id
+                    .checked_sub(1)
+                    .and_then(|index| simulation.variables.get(&index))
+                    .copied()
+                    .unwrap_or_default()
+                    .to_string(),
+                None => "0".repeat(self.variable_digits),
+            }
+        });
+
+        let actors = update_state.data.actors();
+        let text = replace_code(&text, 'n', |id| {
+            id.checked_sub(1)
+                .and_then(|index| actors.data.get(index))
+                .map(|actor| actor.name.clone())
+                .unwrap_or_default()
+        });
+        drop(actors);
+
+        let mut message = Message::default();
+        for line in text.split('\n') {
+            let mut pieces: Vec<(String, egui::Color32)> = Vec::new();
+            let mut color = TEXT_COLORS[0];
+            let mut rest = line;
+            while let Some(c) = rest.chars().next() {
+                if c == '\\' {
+                    if let Some((id, len)) = code_argument(rest, 'c') {
+                        // Colors past the last one are ignored
+                        color = TEXT_COLORS.get(id).copied().unwrap_or(color);
+                        rest = &rest[len..];
+                        continue;
+                    }
+                    if rest[1..].starts_with(['g', 'G']) {
+                        message.shows_gold = true;
+                        rest = &rest[2..];
+                        continue;
+                    }
+                }
+
+                let c = if c == '\0' { '\\' } else { c };
+                match pieces.last_mut() {
+                    Some((text, last_color)) if *last_color == color => text.push(c),
+                    _ => pieces.push((c.to_string(), color)),
+                }
+                rest = &rest[c.len_utf8()..];
+            }
+            message.lines.push(pieces);
+        }
+        message
+    }
+}
+
+/// Parses a control code like `\c[2]` at the start of `text`, ignoring the case of the letter.
+/// Returns its argument and its length.
+fn code_argument(text: &str, letter: char) -> Option<(usize, usize)> {
+    let rest = text.strip_prefix('\\')?;
+    let rest = rest
+        .strip_prefix(letter)
+        .or_else(|| rest.strip_prefix(letter.to_ascii_uppercase()))?;
+    let rest = rest.strip_prefix('[')?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 || !rest[digits..].starts_with(']') {
+        return None;
+    }
+    let id = rest[..digits].parse().unwrap_or(usize::MAX);
+    Some((id, digits + 4))
+}
+
+/// Replaces every control code like `\v[n]` with the text for its argument.
+fn replace_code(text: &str, letter: char, mut replacement: impl FnMut(usize) -> String) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('\\') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match code_argument(rest, letter) {
+            Some((id, len)) => {
+                result.push_str(&replacement(id));
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Paints a window the way RGSS draws one with a windowskin: the background stretched under a
+/// frame with 16 pixel corners. Without a windowskin a plain dark window is painted instead.
+fn paint_window(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    windowskin: Option<&luminol_graphics::Texture>,
+) {
+    let Some(windowskin) = windowskin else {
+        painter.rect(
+            rect,
+            4.,
+            egui::Color32::from_rgba_unmultiplied(16, 24, 64, BACK_OPACITY),
+            egui::Stroke::new(2., egui::Color32::WHITE),
+        );
+        return;
+    };
+
+    let size = windowskin.size_vec2();
+    let uv = |x: f32, y: f32, w: f32, h: f32| {
+        egui::Rect::from_min_size(
+            egui::pos2(x / size.x, y / size.y),
+            egui::vec2(w / size.x, h / size.y),
+        )
+    };
+    let image = |dest: egui::Rect, uv: egui::Rect, tint: egui::Color32| {
+        painter.image(windowskin.texture_id, dest, uv, tint);
+    };
+
+    image(
+        rect.shrink(2.),
+        uv(0., 0., 128., 128.),
+        egui::Color32::from_white_alpha(BACK_OPACITY),
+    );
+
+    let white = egui::Color32::WHITE;
+    let corner = egui::vec2(16., 16.);
+    let (left, right, top, bottom) = (rect.left(), rect.right(), rect.top(), rect.bottom());
+    let inner = rect.shrink(16.);
+    // Corners
+    for (dest, x, y) in [
+        (egui::pos2(left, top), 128., 0.),
+        (egui::pos2(right - 16., top), 176., 0.),
+        (egui::pos2(left, bottom - 16.), 128., 48.),
+        (egui::pos2(right - 16., bottom - 16.), 176., 48.),
+    ] {
+        image(
+            egui::Rect::from_min_size(dest, corner),
+            uv(x, y, 16., 16.),
+            white,
+        );
+    }
+    // Edges
+    image(
+        egui::Rect::from_x_y_ranges(inner.x_range(), top..=top + 16.),
+        uv(144., 0., 32., 16.),
+        white,
+    );
+    image(
+        egui::Rect::from_x_y_ranges(inner.x_range(), bottom - 16.0..=bottom),
+        uv(144., 48., 32., 16.),
+        white,
+    );
+    image(
+        egui::Rect::from_x_y_ranges(left..=left + 16., inner.y_range()),
+        uv(128., 16., 16., 32.),
+        white,
+    );
+    image(
+        egui::Rect::from_x_y_ranges(right - 16.0..=right, inner.y_range()),
+        uv(176., 16., 16., 32.),
+        white,
+    );
+}
+
+/// The font the game draws text with: the last `Font.default_name` the scripts set, or the default
+/// of RGSS.
+fn game_font_name(update_state: &luminol_core::UpdateState<'_>) -> String {
+    update_state
+        .data
+        .scripts()
+        .data
+        .iter()
+        .flat_map(|script| script.script_text.lines())
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let value = line.split_once("Font.default_name")?.1.trim_start();
+            let value = value.strip_prefix('=').filter(|v| !v.starts_with('='))?;
+            // An array of names starts with the preferred one
+            value.split('"').nth(1).map(str::to_string)
+        })
+        .next_back()
+        .unwrap_or_else(|| DEFAULT_FONT_NAME.to_string())
+}
+
+/// Font names and font file names compared without case, spaces, dashes or underscores.
+fn font_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,267 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use luminol_data::rpg;
+use std::sync::Arc;
+
+/// A downscaled overview of a map with one pixel per tile, used to navigate a `MapView`.
+pub struct Minimap {
+    atlas: luminol_graphics::Atlas,
+    /// The average color of every tile ID, premultiplied by alpha, once they have been read from
+    /// the atlas
+    tile_colors: Option<Arc<[egui::Color32]>>,
+    /// The tiles of the map while waiting for the tile colors
+    pending_tiles: Option<luminol_data::Table3>,
+    image: egui::ColorImage,
+    texture: Option<egui::TextureHandle>,
+    /// The (min x, min y, max x, max y) bounds of the pixels that have changed since the texture
+    /// was last updated
+    dirty_bounds: Option<[usize; 4]>,
+    /// Offset between the pointer and the center of the viewport rectangle while it's dragged
+    drag_offset: Option<egui::Vec2>,
+}
+
+impl Minimap {
+    /// Creates a minimap of a map that uses the tileset of the given atlas. Atlases are shared
+    /// between maps that use the same tileset, and so are the tile colors read from them.
+    pub fn new(atlas: luminol_graphics::Atlas, map: &rpg::Map) -> Self {
+        let mut minimap = Self {
+            atlas,
+            tile_colors: None,
+            pending_tiles: Some(map.data.clone()),
+            image: egui::ColorImage::new(
+                [map.data.xsize(), map.data.ysize()],
+                egui::Color32::TRANSPARENT,
+            ),
+            texture: None,
+            dirty_bounds: None,
+            drag_offset: None,
+        };
+        minimap.update_tile_colors();
+        minimap
+    }
+
+    /// Fills in the minimap once the tile colors have been read from the atlas. Returns `false` if
+    /// they're still being read.
+    fn update_tile_colors(&mut self) -> bool {
+        if self.tile_colors.is_some() {
+            return true;
+        }
+        let Some(tile_colors) = self.atlas.tile_colors() else {
+            return false;
+        };
+        self.tile_colors = Some(tile_colors);
+
+        if let Some(tiles) = self.pending_tiles.take() {
+            for y in 0..tiles.ysize() {
+                for x in 0..tiles.xsize() {
+                    self.update_pixel(&tiles, x, y);
+                }
+            }
+        }
+        true
+    }
+
+    /// Updates the minimap after the tile at the given position has changed.
+    pub fn set_tile(&mut self, map: &rpg::Map, position: (usize, usize, usize)) {
+        match &mut self.pending_tiles {
+            Some(tiles) => tiles[position] = map.data[position],
+            None => self.update_pixel(&map.data, position.0, position.1),
+        }
+    }
+
+    fn update_pixel(&mut self, tiles: &luminol_data::Table3, x: usize, y: usize) {
+        let Some(tile_colors) = &self.tile_colors else {
+            return;
+        };
+
+        // Blend the layers from bottom to top
+        let color = (0..tiles.zsize())
+            .map(|z| {
+                let tile_id = tiles[(x, y, z)];
+                tile_colors
+                    .get(tile_id.max(0) as usize)
+                    .copied()
+                    .unwrap_or(egui::Color32::TRANSPARENT)
+            })
+            .fold(egui::Color32::TRANSPARENT, |below, above| {
+                let below_factor = 255 - above.a() as u16;
+                let blend = |above: u8, below: u8| {
+                    above.saturating_add((below as u16 * below_factor / 255) as u8)
+                };
+                egui::Color32::from_rgba_premultiplied(
+                    blend(above.r(), below.r()),
+                    blend(above.g(), below.g()),
+                    blend(above.b(), below.b()),
+                    blend(above.a(), below.a()),
+                )
+            });
+
+        let width = self.image.size[0];
+        if self.image.pixels[x + y * width] == color {
+            return;
+        }
+        self.image.pixels[x + y * width] = color;
+
+        self.dirty_bounds = Some(match self.dirty_bounds {
+            Some([min_x, min_y, max_x, max_y]) => {
+                [min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)]
+            }
+            None => [x, y, x, y],
+        });
+    }
+
+    /// The size of the minimap in pixels, which is the same as the size of the map in tiles.
+    pub fn size(&self) -> [usize; 2] {
+        self.image.size
+    }
+
+    /// Returns the texture of the minimap, uploading any changes to the GPU first.
+    pub fn texture_id(&mut self, ctx: &egui::Context) -> egui::TextureId {
+        self.update_texture(ctx);
+        self.texture
+            .as_ref()
+            .expect("minimap texture not loaded")
+            .id()
+    }
+
+    /// Uploads the changed part of the minimap to the GPU.
+    fn update_texture(&mut self, ctx: &egui::Context) {
+        if !self.update_tile_colors() {
+            // Check again next frame, reading the atlas back only takes a moment
+            ctx.request_repaint();
+        }
+
+        let Some(texture) = &mut self.texture else {
+            self.texture = Some(ctx.load_texture(
+                "luminol_minimap",
+                self.image.clone(),
+                egui::TextureOptions::NEAREST,
+            ));
+            self.dirty_bounds = None;
+            return;
+        };
+
+        let Some([min_x, min_y, max_x, max_y]) = self.dirty_bounds.take() else {
+            return;
+        };
+        let width = self.image.size[0];
+        let pixels = (min_y..=max_y)
+            .flat_map(|y| self.image.pixels[min_x + y * width..=max_x + y * width].iter())
+            .copied()
+            .collect();
+        texture.set_partial(
+            [min_x, min_y],
+            egui::ColorImage {
+                size: [max_x - min_x + 1, max_y - min_y + 1],
+                pixels,
+            },
+            egui::TextureOptions::NEAREST,
+        );
+    }
+
+    /// Displays the minimap with markers for the events and the player's starting position, if it
+    /// is on this map. Clicking or dragging on the minimap moves the map view.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        map_view: &mut crate::MapView,
+        map: &rpg::Map,
+        start_position: Option<egui::Pos2>,
+    ) -> egui::Response {
+        self.update_texture(ui.ctx());
+
+        let map_size = egui::vec2(map.data.xsize() as f32, map.data.ysize() as f32);
+        let scale = (ui.available_width() / map_size.x)
+            .min(ui.available_height() / map_size.y)
+            .max(f32::EPSILON);
+        let (response, painter) =
+            ui.allocate_painter(map_size * scale, egui::Sense::click_and_drag());
+        let rect = response.rect;
+
+        let to_screen = |tile: egui::Pos2| rect.min + tile.to_vec2() * scale;
+        let to_tile = |pos: egui::Pos2| ((pos - rect.min) / scale).to_pos2();
+
+        painter.rect_filled(rect, 0., ui.visuals().extreme_bg_color);
+        if let Some(texture) = &self.texture {
+            painter.image(
+                texture.id(),
+                rect,
+                egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.)),
+                egui::Color32::WHITE,
+            );
+        }
+
+        let marker_radius = (scale / 2.).max(1.5);
+        for (_, event) in map.events.iter() {
+            painter.circle_filled(
+                to_screen(egui::pos2(event.x as f32 + 0.5, event.y as f32 + 0.5)),
+                marker_radius,
+                egui::Color32::YELLOW,
+            );
+        }
+        if let Some(start_position) = start_position {
+            painter.circle(
+                to_screen(start_position + egui::vec2(0.5, 0.5)),
+                marker_radius + 1.,
+                egui::Color32::GREEN,
+                egui::Stroke::new(1., egui::Color32::BLACK),
+            );
+        }
+
+        let viewport_rect = egui::Rect::from_min_max(
+            to_screen(map_view.visible_tiles.min),
+            to_screen(map_view.visible_tiles.max),
+        );
+        painter.rect_stroke(
+            viewport_rect.intersect(rect),
+            0.,
+            egui::Stroke::new(1.5, egui::Color32::WHITE),
+        );
+
+        // Dragging the viewport rectangle moves it, clicking or dragging anywhere else centers
+        // the map view on that position
+        if response.drag_started() {
+            self.drag_offset = response.interact_pointer_pos().map(|pos| {
+                if viewport_rect.contains(pos) {
+                    viewport_rect.center() - pos
+                } else {
+                    egui::Vec2::ZERO
+                }
+            });
+        }
+        if response.clicked() || response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let offset = self.drag_offset.unwrap_or_default();
+                map_view.center_on(map, to_tile(pos + offset));
+                ui.ctx().request_repaint();
+            }
+        }
+        if !response.dragged() {
+            self.drag_offset = None;
+        }
+
+        response.on_hover_cursor(egui::CursorIcon::Crosshair)
+    }
+}
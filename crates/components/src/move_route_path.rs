@@ -0,0 +1,462 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use luminol_data::rpg;
+use luminol_data::ParameterType;
+
+/// Stop tracing routes that are longer than this many steps
+const MAX_STEPS: usize = 1000;
+/// The number of frames per second of the game
+const FRAME_RATE: f32 = 40.;
+
+/// The path a character takes when following a move route, as far as it can be predicted without
+/// running the game.
+#[derive(Debug, Clone)]
+pub struct MoveRoutePath {
+    pub steps: Vec<RouteStep>,
+    pub end: RouteEnd,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RouteStep {
+    pub from: (i32, i32),
+    pub to: (i32, i32),
+    pub kind: StepKind,
+    /// How long the step takes in game, in seconds
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    Move,
+    Jump,
+    /// A move that was blocked by a tile or an event. `to` is the tile the character tried to
+    /// move to.
+    Blocked,
+    Wait,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteEnd {
+    /// The route ends normally
+    Finished,
+    /// The route starts over after the last step
+    Repeats,
+    /// The character gets stuck on a blocked move forever because the route can't be skipped
+    Stuck,
+    /// The rest of the route depends on random numbers or the player's position
+    Unpredictable,
+}
+
+/// Converts a direction to an (x, y) offset.
+fn direction_offset(direction: i32) -> (i32, i32) {
+    match direction {
+        2 => (0, 1),
+        4 => (-1, 0),
+        6 => (1, 0),
+        8 => (0, -1),
+        _ => (0, 0),
+    }
+}
+
+/// The collision of a map for a character that follows a move route.
+pub struct RouteCollision<'a> {
+    /// The passage values of the tiles of the map, not including events
+    passages: &'a luminol_data::Table2,
+    /// Positions of the events that block movement
+    events: Vec<(i32, i32)>,
+    map: &'a rpg::Map,
+}
+
+impl<'a> RouteCollision<'a> {
+    /// Calculates the passage values of the tiles of a map, not including events. This is slow, so
+    /// the result should be kept until the tiles of the map or the tileset change.
+    pub fn tile_passages(map: &rpg::Map, tileset: &rpg::Tileset) -> luminol_data::Table2 {
+        let mut passages = luminol_data::Table2::new(map.data.xsize(), map.data.ysize());
+        luminol_graphics::Collision::calculate_passages(
+            &tileset.passages,
+            &tileset.priorities,
+            &map.data,
+            None,
+            (0..map.data.zsize()).rev(),
+            |x, y, passage| passages[(x, y)] = passage,
+        );
+        passages
+    }
+
+    /// Calculates the collision for a character moving on a map, given the passages returned by
+    /// `tile_passages`. `character_id` is the ID of the event following the route, whose own
+    /// position doesn't block movement. `active_page` returns the index of the active page of an
+    /// event, if it has one.
+    pub fn new(
+        map: &'a rpg::Map,
+        passages: &'a luminol_data::Table2,
+        character_id: Option<usize>,
+        active_page: impl Fn(&rpg::Event) -> Option<usize>,
+    ) -> Self {
+        // Like in `Game_Character#passable?`, only events with a graphic that aren't set to
+        // Through block movement
+        let events = map
+            .events
+            .iter()
+            .filter(|(id, event)| {
+                Some(*id) != character_id
+                    && active_page(event)
+                        .and_then(|index| event.pages.get(index))
+                        .is_some_and(|page| {
+                            !page.through
+                                && (page.graphic.character_name.is_some()
+                                    || page.graphic.tile_id.is_some())
+                        })
+            })
+            .map(|(_, event)| (event.x, event.y))
+            .collect();
+        Self {
+            passages,
+            events,
+            map,
+        }
+    }
+
+    fn is_valid(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.map.width as i32 && y < self.map.height as i32
+    }
+
+    fn passage(&self, x: i32, y: i32) -> i16 {
+        self.passages[(x as usize, y as usize)]
+    }
+
+    /// Whether a character can move from a tile in a direction, like `Game_Character#passable?`.
+    /// A direction of 0 checks if the tile itself can be stood on.
+    fn is_passable(&self, x: i32, y: i32, direction: i32, through: bool) -> bool {
+        let (dx, dy) = direction_offset(direction);
+        let (new_x, new_y) = (x + dx, y + dy);
+        if !self.is_valid(new_x, new_y) {
+            return false;
+        }
+        if through {
+            return true;
+        }
+
+        if direction == 0 {
+            if self.passage(x, y) & 0x0F == 0x0F {
+                return false;
+            }
+        } else {
+            let bit = |direction: i32| 1 << (direction / 2 - 1);
+            if self.passage(x, y) & bit(direction) != 0
+                || self.passage(new_x, new_y) & bit(10 - direction) != 0
+            {
+                return false;
+            }
+        }
+
+        !self.events.contains(&(new_x, new_y))
+    }
+}
+
+impl MoveRoutePath {
+    /// Traces the path of a character that starts at `position` facing `direction` and follows a
+    /// move route.
+    pub fn trace(
+        route: &rpg::MoveRoute,
+        collision: &RouteCollision<'_>,
+        position: (i32, i32),
+        direction: i32,
+        move_speed: rpg::MoveSpeed,
+        through: bool,
+    ) -> Self {
+        let (mut x, mut y) = position;
+        let mut direction = direction;
+        let mut move_speed = u8::from(move_speed) as i32;
+        let mut through = through;
+        let mut steps = Vec::new();
+
+        let integer = |command: &rpg::MoveCommand, index: usize| match command.parameters.get(index)
+        {
+            Some(ParameterType::Integer(value)) => *value,
+            _ => 0,
+        };
+
+        for command in route.list.iter() {
+            if steps.len() >= MAX_STEPS {
+                break;
+            }
+
+            let move_duration = (128 >> move_speed.clamp(1, 6)) as f32 / FRAME_RATE;
+
+            // (x offset, y offset, horizontal direction, vertical direction) of a move, where
+            // diagonal moves have both directions
+            let movement = match command.code {
+                1 => Some((2, 0)),
+                2 => Some((4, 0)),
+                3 => Some((6, 0)),
+                4 => Some((8, 0)),
+                5 => Some((4, 2)),
+                6 => Some((6, 2)),
+                7 => Some((4, 8)),
+                8 => Some((6, 8)),
+                12 => Some((direction, 0)),
+                13 => Some((10 - direction, 0)),
+                _ => None,
+            };
+
+            if let Some((first, second)) = movement {
+                let (dx, dy) = direction_offset(first);
+                let (dx2, dy2) = direction_offset(second);
+                let to = (x + dx + dx2, y + dy + dy2);
+
+                let passable = if second == 0 {
+                    collision.is_passable(x, y, first, through)
+                } else {
+                    // Diagonal moves are allowed if either of the two ways of getting there
+                    // using straight moves is possible
+                    (collision.is_passable(x, y, second, through)
+                        && collision.is_passable(x + dx2, y + dy2, first, through))
+                        || (collision.is_passable(x, y, first, through)
+                            && collision.is_passable(x + dx, y + dy, second, through))
+                };
+
+                // Moving backward doesn't change the direction the character faces, and diagonal
+                // moves only turn characters that face away from where they're going
+                if second == 0 {
+                    if command.code != 13 {
+                        direction = first;
+                    }
+                } else if direction == 10 - first {
+                    direction = first;
+                } else if direction == 10 - second {
+                    direction = second;
+                }
+
+                if passable {
+                    steps.push(RouteStep {
+                        from: (x, y),
+                        to,
+                        kind: StepKind::Move,
+                        duration: move_duration,
+                    });
+                    (x, y) = to;
+                } else {
+                    steps.push(RouteStep {
+                        from: (x, y),
+                        to,
+                        kind: StepKind::Blocked,
+                        duration: 0.,
+                    });
+                    if !route.skippable {
+                        return Self {
+                            steps,
+                            end: RouteEnd::Stuck,
+                        };
+                    }
+                }
+                continue;
+            }
+
+            match command.code {
+                0 => break,
+                // Random moves and moves relative to the player
+                9..=11 => {
+                    return Self {
+                        steps,
+                        end: RouteEnd::Unpredictable,
+                    }
+                }
+                14 => {
+                    let (x_plus, y_plus) = (integer(command, 0), integer(command, 1));
+                    let to = (x + x_plus, y + y_plus);
+                    let passable = (x_plus == 0 && y_plus == 0)
+                        || (collision.is_valid(to.0, to.1)
+                            && collision.is_passable(to.0, to.1, 0, through));
+                    let distance = ((x_plus * x_plus + y_plus * y_plus) as f32).sqrt().round();
+                    let peak = 10. + distance - move_speed as f32;
+                    steps.push(RouteStep {
+                        from: (x, y),
+                        to,
+                        kind: if passable {
+                            StepKind::Jump
+                        } else {
+                            StepKind::Blocked
+                        },
+                        duration: if passable { peak * 2. / FRAME_RATE } else { 0. },
+                    });
+                    if passable {
+                        (x, y) = to;
+                    }
+                }
+                15 => steps.push(RouteStep {
+                    from: (x, y),
+                    to: (x, y),
+                    kind: StepKind::Wait,
+                    duration: integer(command, 0) as f32 * 2. / FRAME_RATE,
+                }),
+                16 => direction = 2,
+                17 => direction = 4,
+                18 => direction = 6,
+                19 => direction = 8,
+                20 => {
+                    direction = match direction {
+                        2 => 4,
+                        4 => 8,
+                        6 => 2,
+                        _ => 6,
+                    }
+                }
+                21 => {
+                    direction = match direction {
+                        2 => 6,
+                        4 => 2,
+                        6 => 8,
+                        _ => 4,
+                    }
+                }
+                22 => direction = 10 - direction,
+                29 => move_speed = integer(command, 0),
+                37 => through = true,
+                38 => through = false,
+                _ => {}
+            }
+        }
+
+        Self {
+            steps,
+            end: if route.repeat {
+                RouteEnd::Repeats
+            } else {
+                RouteEnd::Finished
+            },
+        }
+    }
+
+    /// The time it takes to follow the whole path once, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+
+    /// Returns the position of the character along the path at the given time in seconds since it
+    /// started following the path, or `None` if it has reached the end.
+    pub fn position_at(&self, mut time: f32) -> Option<egui::Pos2> {
+        for step in self.steps.iter() {
+            if time < step.duration {
+                let from = egui::pos2(step.from.0 as f32, step.from.1 as f32);
+                let to = egui::pos2(step.to.0 as f32, step.to.1 as f32);
+                let t = time / step.duration;
+                let mut position = from.lerp(to, t);
+                if step.kind == StepKind::Jump {
+                    // Jumps follow a parabola that peaks half a tile above the straight line
+                    position.y -= 2. * t * (1. - t);
+                }
+                return Some(position);
+            }
+            time -= step.duration;
+        }
+        None
+    }
+
+    /// Draws the path as arrows, given a function that converts tile coordinates to screen
+    /// coordinates and the size of a tile on the screen.
+    pub fn paint(
+        &self,
+        painter: &egui::Painter,
+        to_screen: impl Fn(egui::Pos2) -> egui::Pos2,
+        tile_size: f32,
+        color: egui::Color32,
+    ) {
+        let center = |(x, y): (i32, i32)| to_screen(egui::pos2(x as f32 + 0.5, y as f32 + 0.5));
+        let stroke = egui::Stroke::new((tile_size / 12.).max(1.5), color);
+        let blocked_stroke = egui::Stroke::new(stroke.width, egui::Color32::RED);
+
+        for step in self.steps.iter() {
+            let from = center(step.from);
+            let to = center(step.to);
+            match step.kind {
+                StepKind::Move => {
+                    painter.arrow(from, (to - from) * 0.8, stroke);
+                }
+                StepKind::Jump => {
+                    let control = from + (to - from) / 2. - egui::vec2(0., tile_size);
+                    painter.add(egui::epaint::QuadraticBezierShape::from_points_stroke(
+                        [from, control, to],
+                        false,
+                        egui::Color32::TRANSPARENT,
+                        stroke,
+                    ));
+                }
+                StepKind::Blocked => {
+                    let end = from + (to - from) * 0.4;
+                    painter.line_segment([from, end], blocked_stroke);
+                    let cross = tile_size / 8.;
+                    painter.line_segment(
+                        [
+                            end - egui::vec2(cross, cross),
+                            end + egui::vec2(cross, cross),
+                        ],
+                        blocked_stroke,
+                    );
+                    painter.line_segment(
+                        [
+                            end - egui::vec2(cross, -cross),
+                            end + egui::vec2(cross, -cross),
+                        ],
+                        blocked_stroke,
+                    );
+                }
+                StepKind::Wait => {
+                    painter.circle_stroke(from, tile_size / 6., stroke);
+                }
+            }
+        }
+
+        let end = self.steps.last().map(|step| {
+            center(if step.kind == StepKind::Blocked {
+                step.from
+            } else {
+                step.to
+            })
+        });
+        let (Some(end), Some(symbol)) = (
+            end,
+            match self.end {
+                RouteEnd::Finished => None,
+                RouteEnd::Repeats => Some("↻"),
+                RouteEnd::Stuck => Some("✖"),
+                RouteEnd::Unpredictable => Some("?"),
+            },
+        ) else {
+            return;
+        };
+        painter.text(
+            end + egui::vec2(tile_size / 4., -tile_size / 4.),
+            egui::Align2::CENTER_CENTER,
+            symbol,
+            egui::FontId::proportional(tile_size / 2.),
+            if self.end == RouteEnd::Stuck {
+                egui::Color32::RED
+            } else {
+                color
+            },
+        );
+    }
+}
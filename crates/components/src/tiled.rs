@@ -0,0 +1,436 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+//! Conversion between RMXP maps and the TMX/TSX formats of the Tiled map editor.
+//!
+//! Tiled tile IDs are RMXP tile IDs plus one: the exported tileset image has one row of 8 tiles
+//! for every 8 RMXP tile IDs, with the 48 variants of each autotile expanded into their own tiles
+//! and the tileset below them. The first 48 IDs are empty and are exported as empty tiles.
+
+use color_eyre::eyre::{bail, WrapErr};
+use itertools::Itertools;
+use luminol_data::rpg;
+use luminol_graphics::primitives::tiles::autotile_ids::AUTOTILES;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event as XmlEvent};
+
+const TILE_SIZE: u32 = 32;
+const COLUMNS: u32 = 8;
+/// The first tile ID of the tileset, after the empty tiles and the autotiles
+const TILESET_START: u32 = 384;
+/// The ID of the first tileset in exported maps
+const FIRST_GID: u32 = 1;
+/// Tiled stores whether a tile is flipped or rotated in the highest bits of its ID
+const GID_FLAGS_MASK: u32 = 0xF000_0000;
+/// The name of the object layer that events are exported to
+const EVENT_LAYER_NAME: &str = "Events";
+
+/// Renders the tileset image used by the TSX: the first frame of every autotile expanded into its
+/// 48 variants, followed by the tileset.
+pub fn tileset_image(
+    filesystem: &impl luminol_filesystem::FileSystem,
+    tileset: &rpg::Tileset,
+) -> image::RgbaImage {
+    let load_image = |directory: &str, name: &camino::Utf8Path| {
+        filesystem
+            .read(camino::Utf8Path::new(directory).join(name))
+            .ok()
+            .and_then(|file| image::load_from_memory(&file).ok())
+            .map(|image| image.into_rgba8())
+    };
+    let tileset_image = tileset
+        .tileset_name
+        .as_ref()
+        .and_then(|tileset_name| load_image("Graphics/Tilesets", tileset_name));
+    let tileset_rows = tileset_image
+        .as_ref()
+        .map_or(0, |image| image.height() / TILE_SIZE);
+
+    let mut image = image::RgbaImage::new(
+        COLUMNS * TILE_SIZE,
+        (TILESET_START / COLUMNS + tileset_rows) * TILE_SIZE,
+    );
+
+    for (index, autotile_name) in tileset.autotile_names.iter().enumerate().take(7) {
+        if autotile_name.is_empty() {
+            continue;
+        }
+        let Some(autotile) = load_image("Graphics/Autotiles", camino::Utf8Path::new(autotile_name))
+        else {
+            continue;
+        };
+
+        let first_id = 48 * (index as u32 + 1);
+        for (variant, sub_tiles) in AUTOTILES.into_iter().enumerate() {
+            let id = first_id + variant as u32;
+            let tile_x = id % COLUMNS * TILE_SIZE;
+            let tile_y = id / COLUMNS * TILE_SIZE;
+            // Every variant is made of four 16x16 corners taken from the autotile image
+            for (corner, sub_tile) in sub_tiles.into_iter().enumerate() {
+                let source_x = sub_tile % 6 * 16;
+                let source_y = sub_tile / 6 * 16;
+                let dest_x = tile_x + corner as u32 % 2 * 16;
+                let dest_y = tile_y + corner as u32 / 2 * 16;
+                for y in 0..16 {
+                    for x in 0..16 {
+                        if source_x + x < autotile.width() && source_y + y < autotile.height() {
+                            image.put_pixel(
+                                dest_x + x,
+                                dest_y + y,
+                                *autotile.get_pixel(source_x + x, source_y + y),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(tileset_image) = tileset_image {
+        image::imageops::replace(
+            &mut image,
+            &image::imageops::crop_imm(
+                &tileset_image,
+                0,
+                0,
+                COLUMNS * TILE_SIZE,
+                tileset_rows * TILE_SIZE,
+            )
+            .to_image(),
+            0,
+            (TILESET_START * TILE_SIZE / COLUMNS) as i64,
+        );
+    }
+
+    image
+}
+
+/// Creates an XML element with the given attributes.
+fn element<'a>(name: &'a str, attributes: &'a [(&'a str, String)]) -> BytesStart<'a> {
+    BytesStart::new(name)
+        .with_attributes(attributes.iter().map(|(key, value)| (*key, value.as_str())))
+}
+
+fn new_writer() -> color_eyre::Result<quick_xml::Writer<Vec<u8>>> {
+    let mut writer = quick_xml::Writer::new_with_indent(Vec::new(), b' ', 1);
+    writer.write_event(XmlEvent::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    Ok(writer)
+}
+
+fn finish_writer(writer: quick_xml::Writer<Vec<u8>>) -> color_eyre::Result<String> {
+    Ok(String::from_utf8(writer.into_inner())?)
+}
+
+/// Writes a TSX tileset referring to an image created by `tileset_image`.
+pub fn write_tsx(
+    tileset: &rpg::Tileset,
+    image_source: &str,
+    image: &image::RgbaImage,
+) -> color_eyre::Result<String> {
+    let tile_count = image.height() / TILE_SIZE * COLUMNS;
+    let mut writer = new_writer()?;
+    writer.write_event(XmlEvent::Start(element(
+        "tileset",
+        &[
+            ("version", "1.10".to_string()),
+            ("name", tileset.name.clone()),
+            ("tilewidth", TILE_SIZE.to_string()),
+            ("tileheight", TILE_SIZE.to_string()),
+            ("tilecount", tile_count.to_string()),
+            ("columns", COLUMNS.to_string()),
+        ],
+    )))?;
+    writer.write_event(XmlEvent::Empty(element(
+        "image",
+        &[
+            ("source", image_source.to_string()),
+            ("width", image.width().to_string()),
+            ("height", image.height().to_string()),
+        ],
+    )))?;
+    writer.write_event(XmlEvent::End(BytesEnd::new("tileset")))?;
+    finish_writer(writer)
+}
+
+/// Writes a map as a TMX map with one tile layer per map layer and an object layer containing the
+/// events, using the TSX tileset at `tsx_source`.
+pub fn write_tmx(map: &rpg::Map, tsx_source: &str) -> color_eyre::Result<String> {
+    let (width, height) = (map.data.xsize(), map.data.ysize());
+    let next_object_id = map.events.iter().map(|(id, _)| id).max().unwrap_or(0) + 1;
+
+    let mut writer = new_writer()?;
+    writer.write_event(XmlEvent::Start(element(
+        "map",
+        &[
+            ("version", "1.10".to_string()),
+            ("orientation", "orthogonal".to_string()),
+            ("renderorder", "right-down".to_string()),
+            ("width", width.to_string()),
+            ("height", height.to_string()),
+            ("tilewidth", TILE_SIZE.to_string()),
+            ("tileheight", TILE_SIZE.to_string()),
+            ("infinite", "0".to_string()),
+            ("nextlayerid", (map.data.zsize() + 2).to_string()),
+            ("nextobjectid", next_object_id.to_string()),
+        ],
+    )))?;
+    writer.write_event(XmlEvent::Empty(element(
+        "tileset",
+        &[
+            ("firstgid", FIRST_GID.to_string()),
+            ("source", tsx_source.to_string()),
+        ],
+    )))?;
+
+    for layer in 0..map.data.zsize() {
+        writer.write_event(XmlEvent::Start(element(
+            "layer",
+            &[
+                ("id", (layer + 1).to_string()),
+                ("name", format!("Layer {}", layer + 1)),
+                ("width", width.to_string()),
+                ("height", height.to_string()),
+            ],
+        )))?;
+        writer.write_event(XmlEvent::Start(element(
+            "data",
+            &[("encoding", "csv".to_string())],
+        )))?;
+        let csv = (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| match map.data[(x, y, layer)] {
+                        id if id < 48 => 0,
+                        id => id as u32 + FIRST_GID,
+                    })
+                    .join(",")
+            })
+            .join(",\n");
+        writer.write_event(XmlEvent::Text(BytesText::new(&format!("\n{csv}\n"))))?;
+        writer.write_event(XmlEvent::End(BytesEnd::new("data")))?;
+        writer.write_event(XmlEvent::End(BytesEnd::new("layer")))?;
+    }
+
+    writer.write_event(XmlEvent::Start(element(
+        "objectgroup",
+        &[
+            ("id", (map.data.zsize() + 1).to_string()),
+            ("name", EVENT_LAYER_NAME.to_string()),
+        ],
+    )))?;
+    for (id, event) in map.events.iter() {
+        writer.write_event(XmlEvent::Start(element(
+            "object",
+            &[
+                ("id", id.to_string()),
+                ("name", event.name.clone()),
+                ("x", (event.x * TILE_SIZE as i32).to_string()),
+                ("y", (event.y * TILE_SIZE as i32).to_string()),
+                ("width", TILE_SIZE.to_string()),
+                ("height", TILE_SIZE.to_string()),
+            ],
+        )))?;
+        writer.write_event(XmlEvent::Start(BytesStart::new("properties")))?;
+        writer.write_event(XmlEvent::Empty(element(
+            "property",
+            &[
+                ("name", "event_id".to_string()),
+                ("type", "int".to_string()),
+                ("value", id.to_string()),
+            ],
+        )))?;
+        writer.write_event(XmlEvent::End(BytesEnd::new("properties")))?;
+        writer.write_event(XmlEvent::End(BytesEnd::new("object")))?;
+    }
+    writer.write_event(XmlEvent::End(BytesEnd::new("objectgroup")))?;
+    writer.write_event(XmlEvent::End(BytesEnd::new("map")))?;
+    finish_writer(writer)
+}
+
+/// The parts of a TMX map that can be imported into an RMXP map.
+#[derive(Debug, Default)]
+pub struct TiledMap {
+    pub width: usize,
+    pub height: usize,
+    /// The tiles of every tile layer as RMXP tile IDs, in rows from top to bottom
+    pub layers: Vec<Vec<i16>>,
+    /// The objects of the event layer
+    pub objects: Vec<TiledObject>,
+    /// Whether the map has the object layer that events are exported to. If it does, events
+    /// whose objects were removed from it in Tiled should be deleted.
+    pub has_event_layer: bool,
+    /// The number of tiles that weren't from the exported tileset and were left empty
+    pub invalid_tiles: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct TiledObject {
+    /// The ID of the event this object was exported from, if any
+    pub event_id: Option<usize>,
+    pub name: String,
+    /// The tile the center of the object is on
+    pub x: i32,
+    pub y: i32,
+}
+
+fn attribute(element: &BytesStart<'_>, name: &str) -> color_eyre::Result<Option<String>> {
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        if attribute.key.as_ref() == name.as_bytes() {
+            return Ok(Some(attribute.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_attribute<T: std::str::FromStr>(
+    element: &BytesStart<'_>,
+    name: &str,
+) -> color_eyre::Result<Option<T>> {
+    attribute(element, name)?
+        .map(|value| {
+            value.trim().parse().map_err(|_| {
+                color_eyre::eyre::eyre!("Invalid value {value:?} for the attribute {name:?}")
+            })
+        })
+        .transpose()
+}
+
+impl TiledMap {
+    /// Reads a TMX map that uses a single tileset exported by `write_tsx`. Only tile layers with
+    /// CSV encoding are supported, which is the default in Tiled.
+    pub fn parse(tmx: &str) -> color_eyre::Result<Self> {
+        let mut reader = quick_xml::Reader::from_str(tmx);
+        reader.trim_text(true);
+
+        let mut tiled_map = Self::default();
+        let mut first_gid = FIRST_GID;
+        let mut is_in_data = false;
+        let mut is_in_event_layer = false;
+        let mut object = None;
+
+        loop {
+            let event = reader.read_event().wrap_err("While parsing the TMX file")?;
+            let is_empty = matches!(event, XmlEvent::Empty(_));
+            match event {
+                XmlEvent::Start(element) | XmlEvent::Empty(element) => {
+                    match element.name().as_ref() {
+                        b"map" => {
+                            if attribute(&element, "orientation")?
+                                .is_some_and(|o| o != "orthogonal")
+                            {
+                                bail!("Only orthogonal maps can be imported");
+                            }
+                            if attribute(&element, "infinite")?.is_some_and(|i| i == "1") {
+                                bail!("Infinite maps can't be imported");
+                            }
+                            tiled_map.width = parse_attribute(&element, "width")?.unwrap_or(0);
+                            tiled_map.height = parse_attribute(&element, "height")?.unwrap_or(0);
+                        }
+                        b"tileset" => {
+                            first_gid = parse_attribute(&element, "firstgid")?.unwrap_or(1);
+                        }
+                        b"data" => {
+                            if attribute(&element, "encoding")?.as_deref() != Some("csv")
+                                || attribute(&element, "compression")?.is_some()
+                            {
+                                bail!("Only tile layers stored as CSV can be imported. Change the tile layer format to CSV in the map properties in Tiled.");
+                            }
+                            tiled_map.layers.push(Vec::new());
+                            is_in_data = !is_empty;
+                        }
+                        b"objectgroup" => {
+                            if attribute(&element, "name")?.as_deref() == Some(EVENT_LAYER_NAME) {
+                                tiled_map.has_event_layer = true;
+                                is_in_event_layer = !is_empty;
+                            }
+                        }
+                        // Objects on other layers, like collision or notes, aren't events
+                        b"object" if is_in_event_layer => {
+                            let x: f32 = parse_attribute(&element, "x")?.unwrap_or(0.);
+                            let y: f32 = parse_attribute(&element, "y")?.unwrap_or(0.);
+                            let width: f32 = parse_attribute(&element, "width")?.unwrap_or(0.);
+                            let height: f32 = parse_attribute(&element, "height")?.unwrap_or(0.);
+                            let new_object = TiledObject {
+                                event_id: None,
+                                name: attribute(&element, "name")?.unwrap_or_default(),
+                                x: ((x + width / 2.) / TILE_SIZE as f32).floor() as i32,
+                                y: ((y + height / 2.) / TILE_SIZE as f32).floor() as i32,
+                            };
+                            if is_empty {
+                                tiled_map.objects.push(new_object);
+                            } else {
+                                object = Some(new_object);
+                            }
+                        }
+                        b"property" => {
+                            if let Some(object) = &mut object {
+                                if attribute(&element, "name")?.as_deref() == Some("event_id") {
+                                    object.event_id = parse_attribute(&element, "value")?;
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                XmlEvent::Text(text) if is_in_data => {
+                    let text = text.unescape().wrap_err("While parsing the TMX file")?;
+                    let layer = tiled_map.layers.last_mut().expect("no tile layer");
+                    for gid in text.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                        let gid = gid
+                            .parse::<u32>()
+                            .wrap_err_with(|| format!("Invalid tile {gid:?} in the TMX file"))?
+                            & !GID_FLAGS_MASK;
+                        let id = match gid {
+                            0 => 0,
+                            gid if gid >= first_gid + 48 && gid - first_gid <= i16::MAX as u32 => {
+                                (gid - first_gid) as i16
+                            }
+                            _ => {
+                                tiled_map.invalid_tiles += 1;
+                                0
+                            }
+                        };
+                        layer.push(id);
+                    }
+                }
+                XmlEvent::End(element) => match element.name().as_ref() {
+                    b"data" => is_in_data = false,
+                    b"objectgroup" => is_in_event_layer = false,
+                    b"object" => tiled_map.objects.extend(object.take()),
+                    _ => {}
+                },
+                XmlEvent::Eof => break,
+                _ => {}
+            }
+        }
+
+        if tiled_map.width == 0 || tiled_map.height == 0 {
+            bail!("The TMX file doesn't contain a map");
+        }
+        for layer in tiled_map.layers.iter_mut() {
+            layer.resize(tiled_map.width * tiled_map.height, 0);
+        }
+        Ok(tiled_map)
+    }
+}
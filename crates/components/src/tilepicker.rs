@@ -31,6 +31,11 @@ pub struct Tilepicker {
     pub brush_random: bool,
     /// Seed for the PRNG used for the brush when brush tile ID randomization is enabled.
     brush_seed: [u8; 16],
+    /// User-controlled seed from the toolbar that is mixed into `brush_seed`.
+    brush_user_seed: u64,
+    /// Relative weights of tiles when picking a random tile out of the selected tiles. Tiles that
+    /// aren't in here have a weight of 1.
+    pub brush_weights: std::collections::HashMap<SelectedTile, u8>,
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
@@ -104,10 +109,76 @@ impl Tilepicker {
 
             drag_origin: None,
             brush_seed,
+            brush_user_seed: 0,
             brush_random: false,
+            brush_weights: Default::default(),
         }
     }
 
+    /// Returns the tile at the given position in the tilepicker.
+    fn tile_at(x: i16, y: i16) -> SelectedTile {
+        match y {
+            ..=0 => SelectedTile::Autotile(x),
+            _ => SelectedTile::Tile(x + (y - 1) * 8 + 384),
+        }
+    }
+
+    /// Returns the relative weight of a tile for the random brush.
+    pub fn weight(&self, tile: SelectedTile) -> u8 {
+        self.brush_weights.get(&tile).copied().unwrap_or(1)
+    }
+
+    /// Iterates over the tiles currently selected in the tilepicker in row-major order.
+    pub fn selected_tiles(&self) -> impl Iterator<Item = SelectedTile> {
+        let (left, right) = (self.selected_tiles_left, self.selected_tiles_right);
+        (self.selected_tiles_top..=self.selected_tiles_bottom)
+            .flat_map(move |y| (left..=right).map(move |x| Self::tile_at(x, y)))
+    }
+
+    /// Picks a pseudorandom tile out of the selected tiles, taking the tile weights into account.
+    /// The same position always results in the same tile for a given seed.
+    pub fn get_random_tile(
+        &self,
+        absolute_x: i16,
+        absolute_y: i16,
+        absolute_z: i16,
+    ) -> SelectedTile {
+        let mut preimage = [0u8; 48];
+        preimage[0..16].copy_from_slice(&self.brush_seed);
+        preimage[16..24].copy_from_slice(&(absolute_x as u64).to_le_bytes());
+        preimage[24..32].copy_from_slice(&(absolute_y as u64).to_le_bytes());
+        preimage[32..40].copy_from_slice(&(absolute_z as u64).to_le_bytes());
+        preimage[40..48].copy_from_slice(&self.brush_user_seed.to_le_bytes());
+        let image = murmur3::murmur3_32(&mut std::io::Cursor::new(preimage), 5381).unwrap();
+
+        let total_weight: u32 = self
+            .selected_tiles()
+            .map(|tile| self.weight(tile) as u32)
+            .sum();
+
+        // If every tile has a weight of 0, fall back to picking them uniformly
+        if total_weight == 0 {
+            let width = self.selected_tiles_right - self.selected_tiles_left + 1;
+            let height = self.selected_tiles_bottom - self.selected_tiles_top + 1;
+            let x = (image & 0xffff) as i16;
+            let y = (image >> 16) as i16;
+            return Self::tile_at(
+                self.selected_tiles_left + x.rem_euclid(width),
+                self.selected_tiles_top + y.rem_euclid(height),
+            );
+        }
+
+        let mut roll = image % total_weight;
+        for tile in self.selected_tiles() {
+            let weight = self.weight(tile) as u32;
+            if roll < weight {
+                return tile;
+            }
+            roll -= weight;
+        }
+        unreachable!()
+    }
+
     pub fn get_tile_from_offset(
         &self,
         absolute_x: i16,
@@ -119,32 +190,33 @@ impl Tilepicker {
         let width = self.selected_tiles_right - self.selected_tiles_left + 1;
         let height = self.selected_tiles_bottom - self.selected_tiles_top + 1;
 
-        let (x, y) = if self.brush_random {
-            let mut preimage = [0u8; 40];
-            preimage[0..16].copy_from_slice(&self.brush_seed);
-            preimage[16..24].copy_from_slice(&(absolute_x as u64).to_le_bytes());
-            preimage[24..32].copy_from_slice(&(absolute_y as u64).to_le_bytes());
-            preimage[32..40].copy_from_slice(&(absolute_z as u64).to_le_bytes());
-            let image = murmur3::murmur3_32(&mut std::io::Cursor::new(preimage), 5381).unwrap();
-            let x = (image & 0xffff) as i16;
-            let y = (image >> 16) as i16;
-            (
-                self.selected_tiles_left
-                    + (self.selected_tiles_left + x.rem_euclid(width)).rem_euclid(width),
-                self.selected_tiles_top
-                    + (self.selected_tiles_top + y.rem_euclid(height)).rem_euclid(height),
-            )
-        } else {
-            (
-                self.selected_tiles_left + relative_x.rem_euclid(width),
-                self.selected_tiles_top + relative_y.rem_euclid(height),
-            )
-        };
-
-        match y {
-            ..=0 => SelectedTile::Autotile(x),
-            _ => SelectedTile::Tile(x + (y - 1) * 8 + 384),
+        if self.brush_random {
+            return self.get_random_tile(absolute_x, absolute_y, absolute_z);
         }
+
+        Self::tile_at(
+            self.selected_tiles_left + relative_x.rem_euclid(width),
+            self.selected_tiles_top + relative_y.rem_euclid(height),
+        )
+    }
+
+    /// Displays sliders for editing the random brush weights of the selected tiles.
+    pub fn weights_ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("tilepicker_brush_weights")
+            .striped(true)
+            .show(ui, |ui| {
+                for tile in self.selected_tiles().collect::<Vec<_>>() {
+                    ui.label(match tile {
+                        SelectedTile::Autotile(autotile) => format!("Autotile {autotile}"),
+                        SelectedTile::Tile(id) => format!("Tile {id}"),
+                    });
+                    let mut weight = self.weight(tile);
+                    if ui.add(egui::Slider::new(&mut weight, 0..=20)).changed() {
+                        self.brush_weights.insert(tile, weight);
+                    }
+                    ui.end_row();
+                }
+            });
     }
 
     pub fn ui(
@@ -154,6 +226,7 @@ impl Tilepicker {
         scroll_rect: egui::Rect,
     ) -> egui::Response {
         self.brush_random = update_state.toolbar.brush_random != ui.input(|i| i.modifiers.alt);
+        self.brush_user_seed = update_state.toolbar.brush_seed;
 
         let (canvas_rect, response) = ui.allocate_exact_size(
             egui::vec2(256., self.view.atlas.tileset_height() as f32 + 32.),
@@ -0,0 +1,303 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+
+use luminol_graphics::Renderable;
+
+/// The tileset flag edited by clicking on a `TilesetFlagView`.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Default)]
+#[derive(strum::Display, strum::EnumIter)]
+pub enum TilesetFlag {
+    #[default]
+    Passage,
+    #[strum(to_string = "Passage (4 dir)")]
+    DirectionalPassage,
+    Priority,
+    #[strum(to_string = "Bush Flag")]
+    Bush,
+    #[strum(to_string = "Counter Flag")]
+    Counter,
+    #[strum(to_string = "Terrain Tag")]
+    TerrainTag,
+}
+
+/// Displays a tileset with one of its flags drawn over every tile, and edits the flag of the
+/// clicked tile. Left clicking increments priorities and terrain tags and right clicking
+/// decrements them.
+pub struct TilesetFlagView {
+    pub view: luminol_graphics::Tilepicker,
+    pub flag: TilesetFlag,
+}
+
+impl TilesetFlagView {
+    pub fn new(
+        update_state: &luminol_core::UpdateState<'_>,
+        tileset: &luminol_data::rpg::Tileset,
+    ) -> Self {
+        let view = luminol_graphics::Tilepicker::new(
+            &update_state.graphics,
+            tileset,
+            update_state.filesystem,
+            false,
+        );
+        Self {
+            view,
+            flag: TilesetFlag::default(),
+        }
+    }
+
+    /// Returns the number of tile IDs of a tileset with the tileset graphic used by this view,
+    /// including the IDs reserved for autotiles.
+    pub fn tile_count(&self) -> usize {
+        384 + self.view.atlas.tileset_height() as usize / 32 * 8
+    }
+
+    /// Returns the first tile ID shown at a position of the view and how many tile IDs share its
+    /// flags, or `None` for the empty tile.
+    fn tile_ids_at(x: usize, y: usize) -> Option<(usize, usize)> {
+        match (x, y) {
+            (0, 0) => None,
+            (x, 0) => Some((x * 48, 48)),
+            (x, y) => Some((384 + (y - 1) * 8 + x, 1)),
+        }
+    }
+
+    pub fn ui(
+        &mut self,
+        update_state: &luminol_core::UpdateState<'_>,
+        ui: &mut egui::Ui,
+        scroll_rect: egui::Rect,
+        tileset: &mut luminol_data::rpg::Tileset,
+    ) -> egui::Response {
+        let (canvas_rect, mut response) = ui.allocate_exact_size(
+            egui::vec2(256., self.view.atlas.tileset_height() as f32 + 32.),
+            egui::Sense::click(),
+        );
+
+        let absolute_scroll_rect = ui
+            .ctx()
+            .screen_rect()
+            .intersect(scroll_rect.translate(canvas_rect.min.to_vec2()));
+        let scroll_rect = absolute_scroll_rect.translate(-canvas_rect.min.to_vec2());
+
+        self.view.grid.display.set_pixels_per_point(
+            &update_state.graphics.render_state,
+            ui.ctx().pixels_per_point(),
+        );
+
+        self.view.set_position(
+            &update_state.graphics.render_state,
+            glam::vec2(0.0, -scroll_rect.top()),
+        );
+        self.view.viewport.set(
+            &update_state.graphics.render_state,
+            glam::vec2(scroll_rect.width(), scroll_rect.height()),
+            glam::Vec2::ZERO,
+            glam::Vec2::ONE,
+        );
+        self.view
+            .update_animation(&update_state.graphics.render_state, ui.input(|i| i.time));
+
+        let painter = luminol_graphics::Painter::new(self.view.prepare(&update_state.graphics));
+        ui.painter()
+            .add(luminol_egui_wgpu::Callback::new_paint_callback(
+                absolute_scroll_rect,
+                painter,
+            ));
+
+        // Draw the flags of the visible tiles
+        let rows = self.view.atlas.tileset_height() as usize / 32 + 1;
+        let first_row = (scroll_rect.top().max(0.) / 32.) as usize;
+        let last_row = ((scroll_rect.bottom() / 32.).ceil() as usize).min(rows);
+        let painter = ui.painter_at(absolute_scroll_rect);
+        for y in first_row..last_row {
+            for x in 0..8 {
+                let Some((tile_id, _)) = Self::tile_ids_at(x, y) else {
+                    continue;
+                };
+                let rect = egui::Rect::from_min_size(
+                    canvas_rect.min + egui::vec2(x as f32 * 32., y as f32 * 32.),
+                    egui::Vec2::splat(32.),
+                );
+                self.paint_flag(&painter, rect, tileset, tile_id);
+            }
+        }
+
+        let clicked = response.clicked() || response.secondary_clicked();
+        if let Some(pos) = response.interact_pointer_pos().filter(|_| clicked) {
+            let pos = ((pos - canvas_rect.min) / 32.).to_pos2();
+            let (x, y) = (pos.x.floor(), pos.y.floor());
+            if (0. ..8.).contains(&x) && y >= 0. {
+                if let Some(tile_ids) = Self::tile_ids_at(x as usize, y as usize) {
+                    let offset = pos - egui::pos2(x, y);
+                    if self.edit_flag(tileset, tile_ids, offset, response.secondary_clicked()) {
+                        response.mark_changed();
+                    }
+                }
+            }
+        }
+
+        response
+    }
+
+    fn paint_flag(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        tileset: &luminol_data::rpg::Tileset,
+        tile_id: usize,
+    ) {
+        let passage = tileset
+            .passages
+            .as_slice()
+            .get(tile_id)
+            .copied()
+            .unwrap_or(0);
+        let center = rect.center();
+        let stroke = egui::Stroke::new(2., egui::Color32::WHITE);
+        let shadow = egui::Stroke::new(4., egui::Color32::from_black_alpha(160));
+        let radius = rect.width() * 0.25;
+
+        match self.flag {
+            TilesetFlag::Passage => {
+                if passage & 0x0F == 0 {
+                    painter.circle_stroke(center, radius, shadow);
+                    painter.circle_stroke(center, radius, stroke);
+                } else {
+                    let d = egui::Vec2::splat(radius * 0.8);
+                    let e = egui::vec2(d.x, -d.y);
+                    for stroke in [shadow, stroke] {
+                        painter.line_segment([center - d, center + d], stroke);
+                        painter.line_segment([center - e, center + e], stroke);
+                    }
+                }
+            }
+            TilesetFlag::DirectionalPassage => {
+                // Draw an arrow towards every direction that can be passed and a dot for every
+                // direction that is blocked
+                for (bit, direction) in [
+                    (0x01, egui::vec2(0., 1.)),
+                    (0x02, egui::vec2(-1., 0.)),
+                    (0x04, egui::vec2(1., 0.)),
+                    (0x08, egui::vec2(0., -1.)),
+                ] {
+                    let tip = center + direction * rect.width() * 0.42;
+                    if passage & bit == 0 {
+                        let base = center + direction * rect.width() * 0.22;
+                        let side = direction.rot90() * rect.width() * 0.12;
+                        painter.add(egui::Shape::convex_polygon(
+                            vec![tip, base + side, base - side],
+                            egui::Color32::WHITE,
+                            egui::Stroke::new(1., egui::Color32::from_black_alpha(160)),
+                        ));
+                    } else {
+                        painter.circle_filled(tip - direction * 4., 2.5, egui::Color32::WHITE);
+                    }
+                }
+            }
+            TilesetFlag::Bush | TilesetFlag::Counter => {
+                let bit = if self.flag == TilesetFlag::Bush {
+                    0x40
+                } else {
+                    0x80
+                };
+                if passage & bit != 0 {
+                    painter.circle_filled(center, radius * 0.8, egui::Color32::WHITE);
+                    painter.circle_stroke(center, radius * 0.8, shadow);
+                } else {
+                    painter.circle_filled(center, 2.5, egui::Color32::WHITE);
+                }
+            }
+            TilesetFlag::Priority | TilesetFlag::TerrainTag => {
+                let table = if self.flag == TilesetFlag::Priority {
+                    &tileset.priorities
+                } else {
+                    &tileset.terrain_tags
+                };
+                let value = table.as_slice().get(tile_id).copied().unwrap_or(0);
+                let font = egui::FontId::proportional(18.);
+                painter.text(
+                    center + egui::vec2(1., 1.),
+                    egui::Align2::CENTER_CENTER,
+                    value,
+                    font.clone(),
+                    egui::Color32::BLACK,
+                );
+                painter.text(
+                    center,
+                    egui::Align2::CENTER_CENTER,
+                    value,
+                    font,
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+    }
+
+    /// Edits the flag of the given tile IDs. The offset is the position that was clicked relative
+    /// to the tile, from (0, 0) to (1, 1). Returns true if the tileset was modified.
+    fn edit_flag(
+        &self,
+        tileset: &mut luminol_data::rpg::Tileset,
+        (first_tile_id, tile_count): (usize, usize),
+        offset: egui::Vec2,
+        decrement: bool,
+    ) -> bool {
+        let table = match self.flag {
+            TilesetFlag::Priority => &mut tileset.priorities,
+            TilesetFlag::TerrainTag => &mut tileset.terrain_tags,
+            _ => &mut tileset.passages,
+        };
+        let range = first_tile_id..first_tile_id + tile_count;
+        if range.end > table.len() {
+            return false;
+        }
+
+        let old_value = table[first_tile_id];
+        let new_value = match self.flag {
+            TilesetFlag::Passage => {
+                if old_value & 0x0F == 0 {
+                    old_value | 0x0F
+                } else {
+                    old_value & !0x0F
+                }
+            }
+            TilesetFlag::DirectionalPassage => {
+                // Toggle the direction of the edge closest to the click
+                let offset = offset - egui::Vec2::splat(0.5);
+                let direction = if offset.x.abs() > offset.y.abs() {
+                    if offset.x < 0. {
+                        0x02
+                    } else {
+                        0x04
+                    }
+                } else if offset.y < 0. {
+                    0x08
+                } else {
+                    0x01
+                };
+                old_value ^ direction
+            }
+            TilesetFlag::Bush => old_value ^ 0x40,
+            TilesetFlag::Counter => old_value ^ 0x80,
+            TilesetFlag::Priority => (old_value + if decrement { -1 } else { 1 }).rem_euclid(6),
+            TilesetFlag::TerrainTag => (old_value + if decrement { -1 } else { 1 }).rem_euclid(8),
+        };
+
+        table.as_mut_slice()[range].fill(new_value);
+        true
+    }
+}
@@ -51,9 +51,81 @@ static ACE_DEFAULT: Lazy<Vec<CommandDescription>> = Lazy::new(|| {
 pub struct CommandDB {
     /// Default commands
     default: Vec<CommandDescription>,
-    /// User defined commands
-    // FIXME: visible to user?
+    /// User defined commands, edited in the command maker and only used by this project
     pub user: Vec<CommandDescription>,
+    /// Command packs installed in this project
+    #[serde(default)]
+    pub packs: Vec<InstalledPack>,
+}
+
+/// A named set of custom commands that is shared between projects as a RON file.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct CommandPack {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Raised by the author of the pack whenever its commands change
+    #[serde(default)]
+    pub version: u32,
+    pub commands: Vec<CommandDescription>,
+}
+
+/// A copy of a command pack in a project.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct InstalledPack {
+    pub pack: CommandPack,
+    /// Disabled packs stay installed but their commands aren't used
+    pub enabled: bool,
+}
+
+/// Where a command of a command database comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandSource {
+    Default,
+    User,
+    Pack(String),
+}
+
+impl std::fmt::Display for CommandSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "the default commands"),
+            Self::User => write!(f, "the project's commands"),
+            Self::Pack(name) => write!(f, "the pack '{name}'"),
+        }
+    }
+}
+
+/// A command of a pack whose code is already used by another command.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub code: u16,
+    /// The name of the command of the pack
+    pub name: String,
+    /// The name of the command that already uses the code
+    pub other_name: String,
+    pub other_source: CommandSource,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}) conflicts with {} from {}",
+            self.name, self.code, self.other_name, self.other_source
+        )
+    }
+}
+
+impl CommandPack {
+    pub fn from_ron(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        let pretty_config = ron::ser::PrettyConfig::new().struct_names(true);
+        ron::ser::to_string_pretty(self, pretty_config)
+    }
 }
 
 impl CommandDB {
@@ -66,29 +138,116 @@ impl CommandDB {
             }
             .clone(),
             user: vec![],
+            packs: vec![],
         }
     }
 
+    /// Finds the command with a code. User commands take priority over the commands of packs,
+    /// which take priority over the default commands.
     pub fn get(&self, code: u16) -> Option<&CommandDescription> {
         self.user
             .iter()
+            .chain(self.pack_commands())
+            .chain(self.default.iter())
             .find(|c| c.code == code)
-            .or_else(|| self.default.iter().find(|c| c.code == code))
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &CommandDescription> {
-        self.default.iter().chain(self.user.iter())
+        self.default
+            .iter()
+            .chain(self.pack_commands())
+            .chain(self.user.iter())
     }
 
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut CommandDescription> {
-        self.default.iter_mut().chain(self.user.iter_mut())
+        let packs = self
+            .packs
+            .iter_mut()
+            .filter(|p| p.enabled)
+            .flat_map(|p| p.pack.commands.iter_mut());
+        self.default
+            .iter_mut()
+            .chain(packs)
+            .chain(self.user.iter_mut())
     }
 
     pub fn len(&self) -> usize {
-        self.default.len() + self.user.len()
+        self.iter().count()
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// The commands of the enabled packs.
+    fn pack_commands(&self) -> impl Iterator<Item = &CommandDescription> {
+        self.packs
+            .iter()
+            .filter(|p| p.enabled)
+            .flat_map(|p| p.pack.commands.iter())
+    }
+
+    pub fn installed_pack(&self, name: &str) -> Option<&InstalledPack> {
+        self.packs.iter().find(|p| p.pack.name == name)
+    }
+
+    /// Finds the commands of a pack whose codes are already used by the default commands, the
+    /// project's commands, another enabled pack, or an earlier command of the same pack.
+    pub fn conflicts(&self, pack: &CommandPack) -> Vec<Conflict> {
+        let others = self
+            .default
+            .iter()
+            .map(|c| (c, CommandSource::Default))
+            .chain(self.user.iter().map(|c| (c, CommandSource::User)))
+            .chain(
+                self.packs
+                    .iter()
+                    .filter(|p| p.enabled && p.pack.name != pack.name)
+                    .flat_map(|p| {
+                        p.pack
+                            .commands
+                            .iter()
+                            .map(|c| (c, CommandSource::Pack(p.pack.name.clone())))
+                    }),
+            );
+
+        let mut conflicts = Vec::new();
+        for (index, command) in pack.commands.iter().enumerate() {
+            let earlier = pack.commands[..index]
+                .iter()
+                .map(|c| (c, CommandSource::Pack(pack.name.clone())));
+            if let Some((other, source)) = others
+                .clone()
+                .chain(earlier)
+                .find(|(c, _)| c.code == command.code)
+            {
+                conflicts.push(Conflict {
+                    code: command.code,
+                    name: command.name.clone(),
+                    other_name: other.name.clone(),
+                    other_source: source,
+                });
+            }
+        }
+        conflicts
+    }
+
+    /// Installs a pack, or replaces the installed pack with the same name, and returns its
+    /// conflicts. Packs that conflict with other commands are disabled so that they can't override
+    /// them. Otherwise new packs are enabled, while updated packs stay enabled or disabled.
+    pub fn install(&mut self, pack: CommandPack) -> Vec<Conflict> {
+        let conflicts = self.conflicts(&pack);
+        if let Some(installed) = self.packs.iter_mut().find(|p| p.pack.name == pack.name) {
+            installed.pack = pack;
+            installed.enabled &= conflicts.is_empty();
+        } else {
+            let enabled = conflicts.is_empty();
+            self.packs.push(InstalledPack { pack, enabled });
+        }
+        conflicts
+    }
+
+    pub fn uninstall(&mut self, name: &str) {
+        self.packs.retain(|p| p.pack.name != name);
+    }
 }
@@ -24,6 +24,7 @@
 
 #[cfg(not(target_arch = "wasm32"))]
 use crate::terminal;
+use crate::command_db::CommandPack;
 use crate::CodeTheme;
 use std::collections::VecDeque;
 
@@ -44,6 +45,9 @@ pub struct Config {
     pub theme: CodeTheme,
     #[cfg(not(target_arch = "wasm32"))]
     pub rtp_paths: indexmap::IndexMap<String, String>,
+
+    /// Command packs that can be installed in any project.
+    pub command_packs: Vec<CommandPack>,
 }
 
 impl Default for Config {
@@ -61,6 +65,7 @@ impl Config {
             rtp_paths: indexmap::IndexMap::new(),
             #[cfg(not(target_arch = "wasm32"))]
             terminal: terminal::Config::default(),
+            command_packs: Vec::new(),
         }
     }
 }
@@ -57,6 +57,9 @@ pub enum Data {
         weapons: RefCell<rpg::Weapons>,
 
         maps: RefCell<HashMap<usize, rpg::Map>>,
+
+        event_templates: RefCell<luminol_data::EventTemplates>,
+        annotations: RefCell<luminol_data::Annotations>,
     },
 }
 
@@ -91,6 +94,37 @@ macro_rules! save {
     }};
 }
 
+/// Loads editor-only data that is stored in the `.luminol` folder of the project. Unlike the game
+/// data, a missing or broken file doesn't prevent the project from loading.
+macro_rules! load_luminol {
+    ($fs:ident, $toasts:ident, $type:ty, $path:literal) => {{
+        let mut value = <$type>::default();
+        if $fs.exists($path).unwrap_or_default() {
+            match $fs
+                .read_to_string($path)
+                .wrap_err(concat!("While reading ", $path))
+                .and_then(|s| ron::from_str(&s).wrap_err(concat!("While parsing ", $path)))
+            {
+                Ok(data) => value.data = data,
+                Err(e) => error!(*$toasts, e),
+            }
+        }
+        RefCell::new(value)
+    }};
+}
+
+macro_rules! save_luminol {
+    ($fs:ident, $field:ident, $path:literal, $pretty_config:expr) => {{
+        let borrowed = $field.get_mut();
+        if borrowed.modified {
+            let data = ron::ser::to_string_pretty(&borrowed.data, $pretty_config)
+                .wrap_err(concat!("While serializing ", $path))?;
+            $fs.write($path, data)
+                .wrap_err(concat!("While writing ", $path))?;
+        }
+    }};
+}
+
 impl Data {
     /// Load all data required when opening a project.
     /// Does not load config. That is expected to have been loaded beforehand.
@@ -153,6 +187,19 @@ impl Data {
 
         let maps = RefCell::new(std::collections::HashMap::with_capacity(32));
 
+        let event_templates = load_luminol!(
+            filesystem,
+            toasts,
+            luminol_data::EventTemplates,
+            ".luminol/event_templates"
+        );
+        let annotations = load_luminol!(
+            filesystem,
+            toasts,
+            luminol_data::Annotations,
+            ".luminol/annotations"
+        );
+
         *self = Self::Loaded {
             actors: load!(filesystem, Actors, handler),
             animations: load!(filesystem, Animations, handler),
@@ -170,6 +217,8 @@ impl Data {
             system,
             scripts,
             maps,
+            event_templates,
+            annotations,
         };
 
         Ok(())
@@ -203,6 +252,9 @@ impl Data {
         maps.insert(1, rpg::Map::default());
         let maps = RefCell::new(maps);
 
+        let event_templates = RefCell::new(luminol_data::EventTemplates::default());
+        let annotations = RefCell::new(luminol_data::Annotations::default());
+
         Self::Loaded {
             actors: from_defaults!(Actors, Actor),
             animations: from_defaults!(Animations, Animation),
@@ -220,6 +272,8 @@ impl Data {
             system,
             scripts,
             maps,
+            event_templates,
+            annotations,
         }
     }
 
@@ -253,6 +307,8 @@ impl Data {
             weapons,
             system,
             maps,
+            event_templates,
+            annotations,
         } = self
         else {
             panic!("project not loaded")
@@ -332,6 +388,19 @@ impl Data {
             .write(".luminol/commands", command_db)
             .wrap_err("While writing .luminol/config")?;
 
+        save_luminol!(
+            filesystem,
+            event_templates,
+            ".luminol/event_templates",
+            pretty_config.clone()
+        );
+        save_luminol!(
+            filesystem,
+            annotations,
+            ".luminol/annotations",
+            pretty_config
+        );
+
         // even though Ini uses fmt::write internally, it provides no easy way to write to a string.
         // so we need to open a file instead
         let mut ini_file = filesystem
@@ -361,6 +430,8 @@ impl Data {
         weapons.borrow_mut().modified = false;
         map_infos.borrow_mut().modified = false;
         scripts.borrow_mut().modified = false;
+        event_templates.borrow_mut().modified = false;
+        annotations.borrow_mut().modified = false;
         for (_, map) in maps.borrow_mut().iter_mut() {
             map.modified = false;
         }
@@ -400,6 +471,8 @@ impl Data {
         rpg::Tilesets, tilesets,
         rpg::Troops, troops,
         rpg::Weapons, weapons,
+        luminol_data::EventTemplates, event_templates,
+        luminol_data::Annotations, annotations,
     }
 
     /// Load a map.
@@ -425,6 +498,43 @@ impl Data {
         })
     }
 
+    /// Calls `f` with a map without adding it to the cache. If the map is already loaded, the
+    /// loaded version is used so that unsaved changes are visible.
+    #[allow(clippy::panic)]
+    pub fn with_map<R>(
+        &self,
+        id: usize,
+        filesystem: &impl luminol_filesystem::FileSystem,
+        config: &luminol_config::project::Config,
+        f: impl FnOnce(&rpg::Map) -> R,
+    ) -> color_eyre::Result<R> {
+        let Self::Loaded { maps, .. } = self else {
+            panic!("project not loaded")
+        };
+        if let Some(map) = maps.borrow().get(&id) {
+            return Ok(f(map));
+        }
+        let handler = data_formats::Handler::new(config.project.data_format);
+        let map = handler.read_data(filesystem, format!("Map{id:0>3}"))?;
+        Ok(f(&map))
+    }
+
+    /// Adds a new map to the project. It is written to disk the next time the project is saved.
+    #[allow(clippy::panic)]
+    pub fn add_map(&self, id: usize, info: rpg::MapInfo, mut map: rpg::Map) {
+        let Self::Loaded {
+            maps, map_infos, ..
+        } = self
+        else {
+            panic!("project not loaded")
+        };
+        map.modified = true;
+        maps.borrow_mut().insert(id, map);
+        let mut map_infos = map_infos.borrow_mut();
+        map_infos.data.insert(id, info);
+        map_infos.modified = true;
+    }
+
     pub fn get_map(&self, id: usize) -> RefMut<'_, rpg::Map> {
         let maps_ref = match self {
             Self::Loaded { maps, .. } => maps.borrow_mut(),
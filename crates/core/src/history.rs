@@ -0,0 +1,169 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use std::collections::VecDeque;
+
+/// Maximum number of steps kept in the undo history.
+pub const HISTORY_SIZE: usize = 100;
+
+/// A single undoable change to the project data.
+///
+/// Steps only touch the data cache. Tabs and windows are expected to notice the change on their
+/// own, the same way they would if another tab or window had made it.
+pub trait Step {
+    /// A short description of this step, displayed in the history panel.
+    fn description(&self) -> String;
+
+    /// Reverts this step. Afterwards, calling this function again must re-apply the step.
+    fn revert(&mut self, data: &crate::Data);
+}
+
+struct Entry {
+    scope: egui::Id,
+    step: Box<dyn Step>,
+    /// Whether or not this step is currently applied to the data cache.
+    applied: bool,
+}
+
+/// The undo history shared by every tab and window.
+///
+/// Every step belongs to a scope (for example, a map or an animation). Keyboard shortcuts usually
+/// only undo and redo the steps in the scope of the focused tab or window, while the history panel
+/// can jump to any point in the combined history.
+///
+/// Steps are kept in a single list in the order they were applied in. Undoing a step in one scope
+/// leaves the steps of other scopes where they are, so within a scope the undone steps are always
+/// the last ones, but in the combined history applied and undone steps can be interleaved.
+#[derive(Default)]
+pub struct History {
+    entries: VecDeque<Entry>,
+    revision: usize,
+}
+
+impl History {
+    /// Adds a step that has already been applied to the history.
+    ///
+    /// This clears the steps in the same scope that could have been redone.
+    pub fn push(&mut self, scope: egui::Id, step: impl Step + 'static) {
+        self.entries
+            .retain(|entry| entry.applied || entry.scope != scope);
+        while self.entries.len() >= HISTORY_SIZE {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            scope,
+            step: Box::new(step),
+            applied: true,
+        });
+    }
+
+    fn toggle(&mut self, index: usize, data: &crate::Data) {
+        let entry = &mut self.entries[index];
+        entry.step.revert(data);
+        entry.applied = !entry.applied;
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    /// Undoes the most recent step in the given scope, or the most recent step overall if no scope
+    /// is given. Returns whether or not a step was undone.
+    pub fn undo(&mut self, scope: Option<egui::Id>, data: &crate::Data) -> bool {
+        let Some(index) = self
+            .entries
+            .iter()
+            .rposition(|entry| entry.applied && scope.map_or(true, |scope| entry.scope == scope))
+        else {
+            return false;
+        };
+        self.toggle(index, data);
+        true
+    }
+
+    /// Redoes the most recently undone step in the given scope, or the earliest undone step overall
+    /// if no scope is given. Returns whether or not a step was redone.
+    pub fn redo(&mut self, scope: Option<egui::Id>, data: &crate::Data) -> bool {
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| !entry.applied && scope.map_or(true, |scope| entry.scope == scope))
+        else {
+            return false;
+        };
+        self.toggle(index, data);
+        true
+    }
+
+    /// Undoes every step after the first `position` steps and redoes every step before it. Returns
+    /// whether or not any step was undone or redone.
+    pub fn jump_to(&mut self, position: usize, data: &crate::Data) -> bool {
+        let mut changed = false;
+        // Undo from the end and redo from the start so that the steps of each scope are reverted
+        // in the same order they would be with the keyboard shortcuts
+        for index in (position..self.entries.len()).rev() {
+            if self.entries[index].applied {
+                self.toggle(index, data);
+                changed = true;
+            }
+        }
+        for index in 0..position.min(self.entries.len()) {
+            if !self.entries[index].applied {
+                self.toggle(index, data);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// A counter that changes every time a step is undone or redone. Tabs and windows that cache
+    /// anything derived from the data cache can compare this against its previous value to know
+    /// when they need to refresh.
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// The number of steps up to and including the last applied step.
+    pub fn position(&self) -> usize {
+        self.entries
+            .iter()
+            .rposition(|entry| entry.applied)
+            .map_or(0, |index| index + 1)
+    }
+
+    /// Iterates over the descriptions of every step in the history, in the order they were applied
+    /// in, along with whether or not each step is currently applied.
+    pub fn descriptions(&self) -> impl Iterator<Item = (String, bool)> + '_ {
+        self.entries
+            .iter()
+            .map(|entry| (entry.step.description(), entry.applied))
+    }
+
+    /// Removes every step in the given scope from the history.
+    pub fn remove_scope(&mut self, scope: egui::Id) {
+        self.entries.retain(|entry| entry.scope != scope);
+    }
+
+    /// Removes every step from the history.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
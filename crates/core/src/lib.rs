@@ -37,6 +37,10 @@ pub use modal::Modal;
 mod data_cache;
 pub use data_cache::Data;
 
+/// The undo history shared by every tab and window.
+pub mod history;
+pub use history::History;
+
 /// Toasts to be displayed for errors, information, etc.
 mod toasts;
 pub use toasts::Toasts;
@@ -83,6 +87,7 @@ pub struct UpdateState<'res> {
     pub global_config: &'res mut luminol_config::global::Config,
 
     pub toolbar: &'res mut ToolbarState,
+    pub history: &'res mut History,
 
     pub modified: ModifiedState,
     pub modified_during_prev_frame: &'res mut bool,
@@ -163,6 +168,42 @@ pub struct ToolbarState {
     pub brush_density: f32,
     /// Whether or not brush tile ID randomization is active.
     pub brush_random: bool,
+    /// Radius of the scatter brush in tiles.
+    pub brush_scatter_radius: u8,
+    /// Between 0 and 1 inclusive; how much the brush density drops off towards the edge of the
+    /// scatter brush
+    pub brush_falloff: f32,
+    /// Seed mixed into the brush PRNGs. Changing it rerolls the random tiles placed by the brush.
+    pub brush_seed: u64,
+    /// If set, map views display each event using the page that would be active in this state.
+    pub page_simulation: Option<PageSimulation>,
+}
+
+/// The switches, variables and self switches used to preview which event pages are active.
+#[derive(Default, Debug, Clone)]
+pub struct PageSimulation {
+    /// IDs of the switches that are on
+    pub switches: std::collections::HashSet<usize>,
+    /// Values of the variables that aren't 0
+    pub variables: std::collections::HashMap<usize, i32>,
+    /// (map ID, event ID, self switch) of the self switches that are on
+    pub self_switches: std::collections::HashSet<(usize, usize, luminol_data::rpg::SelfSwitch)>,
+}
+
+impl PageSimulation {
+    /// Returns the index of the page of an event that is active in this state.
+    pub fn active_page(&self, map_id: usize, event: &luminol_data::rpg::Event) -> Option<usize> {
+        event.active_page(|condition| {
+            condition.is_met(
+                |id| self.switches.contains(&id),
+                |id| self.variables.get(&id).copied().unwrap_or_default(),
+                |self_switch| {
+                    self.self_switches
+                        .contains(&(map_id, event.id, self_switch))
+                },
+            )
+        })
+    }
 }
 
 #[derive(Default, strum::EnumIter, strum::Display, PartialEq, Eq, Clone, Copy)]
@@ -173,6 +214,7 @@ pub enum Pencil {
     Circle,
     Rectangle,
     Fill,
+    Scatter,
 }
 
 impl Default for ToolbarState {
@@ -181,10 +223,21 @@ impl Default for ToolbarState {
             pencil: Default::default(),
             brush_density: 1.,
             brush_random: false,
+            brush_scatter_radius: 2,
+            brush_falloff: 0.5,
+            brush_seed: 0,
+            page_simulation: None,
         }
     }
 }
 
+impl ToolbarState {
+    /// Picks a new random brush seed.
+    pub fn reroll_brush_seed(&mut self) {
+        self.brush_seed = rand::random();
+    }
+}
+
 impl<'res> UpdateState<'res> {
     pub(crate) fn reborrow_with_edit_window<'this>(
         &'this mut self,
@@ -203,6 +256,7 @@ impl<'res> UpdateState<'res> {
             project_config: self.project_config,
             global_config: self.global_config,
             toolbar: self.toolbar,
+            history: self.history,
             modified: self.modified.clone(),
             modified_during_prev_frame: self.modified_during_prev_frame,
             project_manager: self.project_manager,
@@ -227,6 +281,7 @@ impl<'res> UpdateState<'res> {
             project_config: self.project_config,
             global_config: self.global_config,
             toolbar: self.toolbar,
+            history: self.history,
             modified: self.modified.clone(),
             modified_during_prev_frame: self.modified_during_prev_frame,
             project_manager: self.project_manager,
@@ -234,6 +289,28 @@ impl<'res> UpdateState<'res> {
         }
     }
 
+    /// Adds a step that has already been applied to the undo history.
+    pub fn push_history(&mut self, scope: egui::Id, step: impl history::Step + 'static) {
+        self.modified.set(true);
+        self.history.push(scope, step);
+    }
+
+    /// Undoes the most recent step in the given scope, or the most recent step overall if no scope
+    /// is given.
+    pub fn undo(&mut self, scope: Option<egui::Id>) {
+        if self.history.undo(scope, self.data) {
+            self.modified.set(true);
+        }
+    }
+
+    /// Redoes the most recently undone step in the given scope, or the earliest undone step overall
+    /// if no scope is given.
+    pub fn redo(&mut self, scope: Option<egui::Id>) {
+        if self.history.redo(scope, self.data) {
+            self.modified.set(true);
+        }
+    }
+
     pub fn manage_projects(&mut self, show_modal: bool) {
         let mut should_close = false;
         let mut should_save = false;
@@ -430,6 +507,7 @@ impl<'res> UpdateState<'res> {
         self.graphics.texture_loader.clear();
         self.filesystem.unload_project();
         *self.project_config = None;
+        self.history.clear();
         self.data.unload();
         self.modified.set(false);
     }
@@ -35,6 +35,8 @@ pub struct EditTabs {
     clean_fn: Option<CleanFn>,
     added: Vec<Box<dyn Tab>>,
     removed: std::collections::HashSet<egui::Id>,
+    focused: Option<egui::Id>,
+    open: std::collections::HashSet<egui::Id>,
 }
 
 type CleanFn = Box<dyn Fn(&Box<dyn Tab>) -> bool>;
@@ -77,6 +79,33 @@ impl Tabs {
         if let Some(f) = edit_tabs.clean_fn.take() {
             self.clean_tabs(f);
         }
+        if let Some(id) = edit_tabs.focused.take() {
+            self.focus_tab(id);
+        }
+    }
+
+    /// Creates an `EditTabs` that knows which tabs are currently open in this tab viewer.
+    pub fn edit_tabs(&self) -> EditTabs {
+        EditTabs {
+            open: self
+                .dock_state
+                .iter_all_tabs()
+                .map(|(_, t)| t.id())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Makes the tab with the given ID the active and focused tab, if it's open.
+    pub fn focus_tab(&mut self, id: egui::Id) {
+        if let Some((surface_index, node_index, tab_index)) =
+            self.dock_state.find_tab_from(|t| t.id() == id)
+        {
+            self.dock_state
+                .set_active_tab((surface_index, node_index, tab_index));
+            self.dock_state
+                .set_focused_node_and_surface((surface_index, node_index));
+        }
     }
 
     pub fn ui_without_edit(
@@ -220,6 +249,16 @@ impl EditTabs {
     pub fn remove_tab_by_id(&mut self, id: egui::Id) -> bool {
         self.removed.insert(id)
     }
+
+    /// Focuses the tab with the given ID once edits are processed, if it's open by then.
+    pub fn focus_tab_by_id(&mut self, id: egui::Id) {
+        self.focused = Some(id);
+    }
+
+    /// Returns whether a tab with the given ID was open when this `EditTabs` was created.
+    pub fn is_open(&self, id: egui::Id) -> bool {
+        self.open.contains(&id)
+    }
 }
 
 impl<'a, 'res> egui_dock::TabViewer for TabViewer<'a, 'res> {
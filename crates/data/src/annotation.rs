@@ -0,0 +1,102 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+/// A note that designers place on a map in the editor. Annotations are stored in the project's
+/// `.luminol` folder and never end up in the game data.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Annotation {
+    pub shape: AnnotationShape,
+    pub text: String,
+    /// The sRGB color of the annotation
+    pub color: [u8; 3],
+}
+
+/// Where an annotation is on the map, in tile coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum AnnotationShape {
+    /// A sticky note attached to a tile
+    Note { x: i32, y: i32 },
+    /// A rectangle covering the tiles from (`x`, `y`) to (`x + width - 1`, `y + height - 1`)
+    Rectangle {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    /// An arrow from the center of one tile to the center of another
+    Arrow { from: (i32, i32), to: (i32, i32) },
+}
+
+impl AnnotationShape {
+    /// The tile the annotation is attached to, e.g. to center the view on it.
+    pub fn anchor(&self) -> (i32, i32) {
+        match *self {
+            Self::Note { x, y } => (x, y),
+            Self::Rectangle {
+                x,
+                y,
+                width,
+                height,
+            } => (x + width / 2, y + height / 2),
+            Self::Arrow { from, .. } => from,
+        }
+    }
+
+    /// Whether a point in tile coordinates is on the annotation.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        match *self {
+            Self::Note {
+                x: note_x,
+                y: note_y,
+            } => x.floor() as i32 == note_x && y.floor() as i32 == note_y,
+            Self::Rectangle {
+                x: rect_x,
+                y: rect_y,
+                width,
+                height,
+            } => {
+                x >= rect_x as f32
+                    && y >= rect_y as f32
+                    && x < (rect_x + width) as f32
+                    && y < (rect_y + height) as f32
+            }
+            Self::Arrow { from, to } => {
+                // Distance from the point to the arrow's line segment
+                let (x1, y1) = (from.0 as f32 + 0.5, from.1 as f32 + 0.5);
+                let (x2, y2) = (to.0 as f32 + 0.5, to.1 as f32 + 0.5);
+                let (dx, dy) = (x2 - x1, y2 - y1);
+                let length_squared = dx * dx + dy * dy;
+                let t = if length_squared == 0. {
+                    0.
+                } else {
+                    (((x - x1) * dx + (y - y1) * dy) / length_squared).clamp(0., 1.)
+                };
+                let (closest_x, closest_y) = (x1 + t * dx, y1 + t * dy);
+                (x - closest_x).powi(2) + (y - closest_y).powi(2) < 0.25
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Annotations {
+    /// The annotations of each map, by map ID
+    pub data: BTreeMap<usize, Vec<Annotation>>,
+    pub modified: bool,
+}
@@ -224,6 +224,50 @@ pub enum ParameterKind {
     Int,
     /// Parameter is a bool stored as an integer
     IntBool,
+    /// Parameter is a bool
+    Bool,
+
+    /// Parameter is the ID of an actor
+    Actor,
+    /// Parameter is the ID of an actor, or 0 for the entire party
+    ActorOrParty,
+    /// Parameter is the ID of a class
+    Class,
+    /// Parameter is the ID of a skill
+    Skill,
+    /// Parameter is the ID of an item
+    Item,
+    /// Parameter is the ID of a weapon
+    Weapon,
+    /// Parameter is the ID of an armor
+    Armor,
+    /// Parameter is the ID of an enemy
+    Enemy,
+    /// Parameter is the ID of a troop
+    Troop,
+    /// Parameter is the ID of a state
+    State,
+    /// Parameter is the ID of an animation
+    Animation,
+    /// Parameter is the ID of a common event
+    CommonEvent,
+    /// Parameter is the ID of a map
+    Map,
+    /// Parameter is a character on the current map
+    ///
+    /// -1 is the player, 0 is the event running the command, anything else is the ID of an event
+    Character,
+
+    /// Parameter is an audio file
+    AudioFile,
+    /// Parameter is a color
+    Color,
+    /// Parameter is a tone
+    Tone,
+    /// Parameter is a move route
+    MoveRoute,
+    /// Parameter is an array of strings
+    StringArray,
 
     /// Parameter is a choice between a set of enums
     ///
@@ -0,0 +1,116 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::rpg;
+
+/// An event saved in the project so that copies of it can be placed on any map.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct EventTemplate {
+    pub name: String,
+    /// The event this template places. Its ID and position are replaced when it is placed.
+    pub event: rpg::Event,
+    /// The (map ID, event ID) pairs of the events placed from this template that are updated
+    /// when the template is updated.
+    #[serde(default)]
+    pub instances: Vec<(usize, usize)>,
+}
+
+impl EventTemplate {
+    /// Creates a template from an event.
+    pub fn new(name: String, event: &rpg::Event) -> Self {
+        let mut event = event.clone();
+        event.extra_data = Default::default();
+        Self {
+            name,
+            event,
+            instances: Vec::new(),
+        }
+    }
+
+    /// Creates a copy of the template's event with the given ID and position.
+    pub fn instantiate(&self, id: usize, x: i32, y: i32) -> rpg::Event {
+        let mut event = self.event.clone();
+        event.id = id;
+        event.x = x;
+        event.y = y;
+        self.retarget_self_references(&mut event.pages, id);
+        event
+    }
+
+    /// Replaces everything in `event` except its ID and position with the template's event.
+    pub fn apply_to(&self, event: &mut rpg::Event) {
+        event.name.clone_from(&self.event.name);
+        event.pages.clone_from(&self.event.pages);
+        self.retarget_self_references(&mut event.pages, event.id);
+        event.extra_data.graphic_modified.set(true);
+    }
+
+    /// Makes the commands that refer to the template's event by its ID, like a Set Move Route on
+    /// the event itself, refer to the event with the given ID instead.
+    fn retarget_self_references(&self, pages: &mut [rpg::EventPage], id: usize) {
+        for command in pages.iter_mut().flat_map(|page| page.list.iter_mut()) {
+            command.for_each_event_id_mut(|target| {
+                if *target as usize == self.event.id {
+                    *target = id as i32;
+                }
+            });
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EventTemplates {
+    pub data: Vec<EventTemplate>,
+    pub modified: bool,
+}
+
+impl EventTemplates {
+    /// Changes the IDs of the linked events on a map according to `mapping`, which maps old event
+    /// IDs to new event IDs.
+    pub fn remap_instances(
+        &mut self,
+        map_id: usize,
+        mapping: &std::collections::HashMap<usize, usize>,
+    ) {
+        for (instance_map_id, event_id) in self
+            .data
+            .iter_mut()
+            .flat_map(|template| template.instances.iter_mut())
+        {
+            if *instance_map_id == map_id {
+                if let Some(&new_id) = mapping.get(event_id) {
+                    *event_id = new_id;
+                    self.modified = true;
+                }
+            }
+        }
+    }
+
+    /// Unlinks an event from every template. This must be called when an event is deleted so that
+    /// a new event that reuses its ID isn't updated from the template.
+    pub fn remove_instance(&mut self, map_id: usize, event_id: usize) {
+        for template in self.data.iter_mut() {
+            let len = template.instances.len();
+            template
+                .instances
+                .retain(|&instance| instance != (map_id, event_id));
+            if template.instances.len() != len {
+                self.modified = true;
+            }
+        }
+    }
+}
@@ -8,12 +8,18 @@ mod shared;
 
 mod option_vec;
 
+mod annotation;
+
+mod event_template;
+
 mod rgss_structs;
 
 pub mod helpers;
 
 pub mod commands;
 
+pub use annotation::{Annotation, AnnotationShape, Annotations};
+pub use event_template::{EventTemplate, EventTemplates};
 pub use helpers::*;
 pub use option_vec::OptionVec;
 pub use rgss_structs::{Color, Table1, Table2, Table3, Tone};
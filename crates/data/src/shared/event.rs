@@ -56,6 +56,13 @@ impl Event {
             extra_data: EventExtraData::default(),
         }
     }
+
+    /// Returns the index of the page that would be active in game, given a function that checks
+    /// whether a page's conditions are met. Like in RGSS, the last page whose conditions are met is
+    /// the active one.
+    pub fn active_page(&self, mut is_met: impl FnMut(&EventCondition) -> bool) -> Option<usize> {
+        self.pages.iter().rposition(|page| is_met(&page.condition))
+    }
 }
 
 #[derive(Default, Debug, serde::Deserialize, serde::Serialize, Clone)]
@@ -232,6 +239,22 @@ pub struct EventCondition {
     pub self_switch_ch: SelfSwitch,
 }
 
+impl EventCondition {
+    /// Checks whether these conditions are met, given functions that return the state of a switch
+    /// or self switch and the value of a variable.
+    pub fn is_met(
+        &self,
+        switch: impl Fn(usize) -> bool,
+        variable: impl Fn(usize) -> i32,
+        self_switch: impl Fn(SelfSwitch) -> bool,
+    ) -> bool {
+        (!self.switch1_valid || switch(self.switch1_id))
+            && (!self.switch2_valid || switch(self.switch2_id))
+            && (!self.variable_valid || variable(self.variable_id) >= self.variable_value)
+            && (!self.self_switch_valid || self_switch(self.self_switch_ch))
+    }
+}
+
 impl Default for EventCondition {
     fn default() -> Self {
         Self {
@@ -250,7 +273,7 @@ impl Default for EventCondition {
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[derive(alox_48::Deserialize, alox_48::Serialize)]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[derive(strum::Display, strum::EnumIter)]
 #[serde(from = "String", into = "String")]
 #[marshal(from = "String", into = "String")]
@@ -299,3 +322,86 @@ pub struct EventCommand {
     #[serde(skip)]
     pub guid: u16,
 }
+
+impl EventCommand {
+    /// If this is a Transfer Player command with a fixed destination, returns the destination map
+    /// ID and coordinates. Transfers to a destination stored in variables return `None`.
+    pub fn transfer_destination(&self) -> Option<(usize, i32, i32)> {
+        if self.code != 201 {
+            return None;
+        }
+        match self.parameters.as_slice() {
+            [ParameterType::Integer(0), ParameterType::Integer(map_id), ParameterType::Integer(x), ParameterType::Integer(y), ..] => {
+                Some((usize::try_from(*map_id).ok()?, *x, *y))
+            }
+            _ => None,
+        }
+    }
+
+    /// If this is a Call Common Event command, returns the ID of the common event it calls.
+    pub fn called_common_event(&self) -> Option<usize> {
+        if self.code != 117 {
+            return None;
+        }
+        match self.parameters.as_slice() {
+            [ParameterType::Integer(id), ..] => usize::try_from(*id).ok(),
+            _ => None,
+        }
+    }
+
+    /// Calls `f` with every parameter of this command that refers to an event on the current map by
+    /// its ID, such as the character of a Set Move Route command. Parameters that refer to the
+    /// player (-1) or to the event running the command (0) are skipped.
+    pub fn for_each_event_id_mut(&mut self, mut f: impl FnMut(&mut i32)) {
+        let is = |parameters: &[ParameterType], index: usize, value: i32| matches!(parameters.get(index), Some(ParameterType::Integer(v)) if *v == value);
+
+        let indices: &[usize] = match self.code {
+            // Conditional Branch on a character's direction
+            111 if is(&self.parameters, 0, 6) => &[1],
+            // Control Variables with a character as the operand
+            122 if is(&self.parameters, 3, 6) => &[4],
+            // Set Event Location, exchanging with another event
+            202 if is(&self.parameters, 1, 2) => &[0, 2],
+            // Set Event Location, Show Animation, Set Move Route
+            202 | 207 | 209 => &[0],
+            _ => &[],
+        };
+
+        for &index in indices {
+            if let Some(ParameterType::Integer(id)) = self.parameters.get_mut(index) {
+                if *id > 0 {
+                    f(id);
+                }
+            }
+        }
+    }
+
+    /// Calls `f` with every parameter of this command that refers to a member of the current
+    /// troop by its index, such as the enemy of a Change Enemy HP command. Parameters that refer to
+    /// the entire troop (-1) or to the last or a random target (-2 and -1) are skipped.
+    pub fn for_each_enemy_index_mut(&mut self, mut f: impl FnMut(&mut i32)) {
+        let is = |parameters: &[ParameterType], index: usize, value: i32| matches!(parameters.get(index), Some(ParameterType::Integer(v)) if *v == value);
+
+        let indices: &[usize] = match self.code {
+            // Conditional Branch on an enemy
+            111 if is(&self.parameters, 0, 5) => &[1],
+            // Control Variables with an enemy's stat as the operand
+            122 if is(&self.parameters, 3, 5) => &[4],
+            // Show Battle Animation and Deal Damage on an enemy, Force Action of an enemy
+            337..=339 if is(&self.parameters, 0, 0) => &[1],
+            // Force Action of an actor, targeting an enemy
+            339 if is(&self.parameters, 0, 1) => &[4],
+            // Change Enemy HP, SP and State, Enemy Recover All, Appearance and Transform
+            331..=336 => &[0],
+            _ => &[],
+        };
+
+        for &index in indices {
+            if let Some(ParameterType::Integer(member)) = self.parameters.get_mut(index) {
+                if *member >= 0 {
+                    f(member);
+                }
+            }
+        }
+    }
+}
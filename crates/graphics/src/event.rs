@@ -36,7 +36,18 @@ impl Event {
         let Some(page) = event.pages.first() else {
             color_eyre::eyre::bail!("event does not have first page");
         };
+        Self::new_map_page(graphics_state, filesystem, viewport, event, page, atlas)
+    }
 
+    /// Like `new_map`, but uses the graphic of the given page instead of the first page.
+    pub fn new_map_page(
+        graphics_state: &GraphicsState,
+        filesystem: &impl luminol_filesystem::FileSystem,
+        viewport: &Viewport,
+        event: &luminol_data::rpg::Event,
+        page: &luminol_data::rpg::EventPage,
+        atlas: &Atlas,
+    ) -> color_eyre::Result<Option<Self>> {
         let mut is_placeholder = false;
         let texture = if let Some(ref filename) = page.graphic.character_name {
             let texture = graphics_state
@@ -193,8 +204,18 @@ impl Event {
     }
 
     pub fn set_position(&mut self, render_state: &luminol_egui_wgpu::RenderState, x: i32, y: i32) {
-        let x = x as f32 * 32. + (32. - self.sprite_size.x) / 2.;
-        let y = y as f32 * 32. + (32. - self.sprite_size.y);
+        self.set_tile_position(render_state, x as f32, y as f32);
+    }
+
+    /// Like `set_position`, but allows the event to be placed in between tiles.
+    pub fn set_tile_position(
+        &mut self,
+        render_state: &luminol_egui_wgpu::RenderState,
+        x: f32,
+        y: f32,
+    ) {
+        let x = x * 32. + (32. - self.sprite_size.x) / 2.;
+        let y = y * 32. + (32. - self.sprite_size.y);
         self.sprite
             .transform
             .set_position(render_state, glam::vec2(x, y));
@@ -80,6 +80,57 @@ impl Map {
             passages,
         );
 
+        let (panorama, fog) = Self::planes(graphics_state, filesystem, map, tileset, &viewport);
+
+        let events = map
+            .events
+            .iter()
+            .map(|(id, event)| {
+                Event::new_map(graphics_state, filesystem, &viewport, event, &atlas)
+                    .map(|opt_e| opt_e.map(|e| (id, e)))
+            })
+            .flatten_ok()
+            .try_collect()?;
+
+        Ok(Self {
+            tiles,
+            panorama,
+            fog,
+            collision,
+            grid,
+            events,
+            viewport,
+            atlas,
+
+            ani_time: None,
+
+            fog_enabled: true,
+            pano_enabled: true,
+            coll_enabled: false,
+            grid_enabled: true,
+            event_enabled: true,
+        })
+    }
+
+    /// Recreates the panorama and fog planes from the tileset, keeping the tiles and events.
+    pub fn reload_planes(
+        &mut self,
+        graphics_state: &GraphicsState,
+        filesystem: &impl luminol_filesystem::FileSystem,
+        map: &luminol_data::rpg::Map,
+        tileset: &luminol_data::rpg::Tileset,
+    ) {
+        (self.panorama, self.fog) =
+            Self::planes(graphics_state, filesystem, map, tileset, &self.viewport);
+    }
+
+    fn planes(
+        graphics_state: &GraphicsState,
+        filesystem: &impl luminol_filesystem::FileSystem,
+        map: &luminol_data::rpg::Map,
+        tileset: &luminol_data::rpg::Tileset,
+        viewport: &Viewport,
+    ) -> (Option<Plane>, Option<Plane>) {
         let panorama = if let Some(ref panorama_name) = tileset.panorama_name {
             let texture = graphics_state
                 .texture_loader
@@ -93,7 +144,7 @@ impl Map {
 
             Some(Plane::new(
                 graphics_state,
-                &viewport,
+                viewport,
                 &texture,
                 tileset.panorama_hue,
                 100,
@@ -118,7 +169,7 @@ impl Map {
 
             Some(Plane::new(
                 graphics_state,
-                &viewport,
+                viewport,
                 &texture,
                 tileset.fog_hue,
                 tileset.fog_zoom,
@@ -131,34 +182,7 @@ impl Map {
             None
         };
 
-        let events = map
-            .events
-            .iter()
-            .map(|(id, event)| {
-                Event::new_map(graphics_state, filesystem, &viewport, event, &atlas)
-                    .map(|opt_e| opt_e.map(|e| (id, e)))
-            })
-            .flatten_ok()
-            .try_collect()?;
-
-        Ok(Self {
-            tiles,
-            panorama,
-            fog,
-            collision,
-            grid,
-            events,
-            viewport,
-            atlas,
-
-            ani_time: None,
-
-            fog_enabled: true,
-            pano_enabled: true,
-            coll_enabled: false,
-            grid_enabled: true,
-            event_enabled: true,
-        })
+        (panorama, fog)
     }
 
     pub fn set_tile(
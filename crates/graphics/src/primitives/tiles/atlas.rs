@@ -52,6 +52,20 @@ pub struct Atlas {
     tileset_height: u32,
     pub(super) autotile_width: u32,
     pub(super) autotile_frames: [u32; AUTOTILE_AMOUNT as usize],
+    tile_colors: Arc<parking_lot::Mutex<TileColors>>,
+}
+
+/// The average color of every tile in the atlas, which has to be read back from the GPU.
+enum TileColors {
+    Pending {
+        /// Contains one row of 8 tiles after another, starting from the first autotile
+        buffer: wgpu::Buffer,
+        rows: u32,
+        /// Set to whether or not mapping the buffer succeeded once it's done
+        mapped: Arc<std::sync::OnceLock<bool>>,
+        device: Arc<wgpu::Device>,
+    },
+    Ready(Arc<[egui::Color32]>),
 }
 
 impl Atlas {
@@ -282,11 +296,114 @@ impl Atlas {
             .texture_loader
             .register_texture(format!("tileset_atlases/{}", tileset.id), atlas_texture);
 
-        Atlas {
+        let atlas = Atlas {
             atlas_texture,
             autotile_width,
             tileset_height,
             autotile_frames,
+            tile_colors: Arc::new(parking_lot::Mutex::new(TileColors::Ready(Arc::new([])))),
+        };
+        *atlas.tile_colors.lock() = atlas.read_tile_colors(graphics_state);
+        atlas
+    }
+
+    /// Starts copying the first frame of every tile in the atlas into a buffer that the tile colors
+    /// are calculated from once it can be read.
+    fn read_tile_colors(&self, graphics_state: &GraphicsState) -> TileColors {
+        let render_state = &graphics_state.render_state;
+        let rows = TOTAL_AUTOTILE_ROWS + self.tileset_height / TILE_SIZE;
+        // 1024 bytes, which is a multiple of `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`
+        let bytes_per_row = TILESET_WIDTH * 4;
+        let row_size = (bytes_per_row * TILE_SIZE) as wgpu::BufferAddress;
+
+        let buffer = render_state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tileset atlas tile colors"),
+            size: row_size * rows as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder =
+            render_state
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("tileset atlas tile color readback"),
+                });
+        for row in 0..rows {
+            // The 8 tiles of a row are always next to each other in the atlas
+            let tile_id = AUTOTILE_ID_AMOUNT + row * TILESET_COLUMNS;
+            let origin = self.calc_quad(tile_id as i16).tex_coords.min;
+            encoder.copy_texture_to_buffer(
+                wgpu::ImageCopyTexture {
+                    texture: &self.atlas_texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: origin.x as u32,
+                        y: origin.y as u32,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: row_size * row as wgpu::BufferAddress,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                wgpu::Extent3d {
+                    width: TILESET_WIDTH,
+                    height: TILE_SIZE,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        render_state.queue.submit(std::iter::once(encoder.finish()));
+
+        let mapped = Arc::new(std::sync::OnceLock::new());
+        let mapped_clone = mapped.clone();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = mapped_clone.set(result.is_ok());
+            });
+
+        TileColors::Pending {
+            buffer,
+            rows,
+            mapped,
+            device: render_state.device.clone(),
+        }
+    }
+
+    /// Returns the average color of the first frame of every tile ID, premultiplied by alpha, or
+    /// `None` if the atlas hasn't been read back from the GPU yet.
+    pub fn tile_colors(&self) -> Option<Arc<[egui::Color32]>> {
+        let mut tile_colors = self.tile_colors.lock();
+        if let TileColors::Pending {
+            buffer,
+            rows,
+            mapped,
+            device,
+        } = &*tile_colors
+        {
+            device.poll(wgpu::Maintain::Poll);
+            let colors = match *mapped.get()? {
+                true => {
+                    let colors = average_tile_colors(&buffer.slice(..).get_mapped_range(), *rows);
+                    buffer.unmap();
+                    colors
+                }
+                // There's nothing to show if the atlas couldn't be read, but the minimap and the
+                // like shouldn't wait forever
+                false => vec![egui::Color32::TRANSPARENT; AUTOTILE_ID_AMOUNT as usize],
+            };
+            *tile_colors = TileColors::Ready(colors.into());
+        }
+        match &*tile_colors {
+            TileColors::Ready(colors) => Some(colors.clone()),
+            TileColors::Pending { .. } => None,
         }
     }
 
@@ -355,6 +472,46 @@ impl Atlas {
     pub fn tileset_height(&self) -> u32 {
         self.tileset_height
     }
+
+    /// Returns true if both atlases are clones of the same atlas, so that an atlas that was
+    /// reloaded through the atlas loader can be told apart from the one it replaced
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.atlas_texture, &other.atlas_texture)
+    }
+}
+
+/// Calculates the average color of every tile from rows of 8 tiles read from the atlas. The first
+/// 48 tile IDs are empty and aren't part of `bytes`.
+fn average_tile_colors(bytes: &[u8], rows: u32) -> Vec<egui::Color32> {
+    let bytes_per_row = (TILESET_WIDTH * 4) as usize;
+    let tile_size = TILE_SIZE as usize;
+
+    let mut colors = vec![egui::Color32::TRANSPARENT; AUTOTILE_ID_AMOUNT as usize];
+    for row in 0..rows as usize {
+        let row_bytes = &bytes[row * bytes_per_row * tile_size..][..bytes_per_row * tile_size];
+        for column in 0..TILESET_COLUMNS as usize {
+            let mut sums = [0u64; 4];
+            for y in 0..tile_size {
+                let start = y * bytes_per_row + column * tile_size * 4;
+                for pixel in row_bytes[start..start + tile_size * 4].chunks_exact(4) {
+                    let alpha = pixel[3] as u64;
+                    sums[0] += pixel[0] as u64 * alpha / 255;
+                    sums[1] += pixel[1] as u64 * alpha / 255;
+                    sums[2] += pixel[2] as u64 * alpha / 255;
+                    sums[3] += alpha;
+                }
+            }
+            let count = (tile_size * tile_size) as u64;
+            colors.push(egui::Color32::from_rgba_premultiplied(
+                (sums[0] / count) as u8,
+                (sums[1] / count) as u8,
+                (sums[2] / count) as u8,
+                (sums[3] / count) as u8,
+            ));
+        }
+    }
+    colors
 }
 
 fn write_texture_region<P>(
@@ -29,7 +29,7 @@ use display::Display;
 use instance::Instances;
 
 mod atlas;
-mod autotile_ids;
+pub mod autotile_ids;
 pub(crate) mod autotiles;
 pub(crate) mod display;
 mod instance;
@@ -40,6 +40,11 @@ pub struct Tiles {
     pub display: Display,
     pub transform: Transform,
     pub enabled_layers: Vec<bool>,
+    /// Layers that can't be drawn on. This doesn't affect rendering; it's stored here alongside
+    /// the other per-layer toggles so that every editor displaying these tiles can share it.
+    pub locked_layers: Vec<bool>,
+    /// Layers that are dimmed when a different layer is selected.
+    pub dimmed_layers: Vec<bool>,
     pub selected_layer: Option<usize>,
     pub auto_opacity: bool,
 
@@ -85,6 +90,8 @@ impl Tiles {
             display,
             transform,
             enabled_layers: vec![true; tiles.zsize()],
+            locked_layers: vec![false; tiles.zsize()],
+            dimmed_layers: vec![true; tiles.zsize()],
             selected_layer: None,
             auto_opacity: true,
 
@@ -93,6 +100,11 @@ impl Tiles {
         }
     }
 
+    /// Returns true if the given layer is visible and not locked.
+    pub fn is_layer_editable(&self, layer: usize) -> bool {
+        self.enabled_layers[layer] && !self.locked_layers[layer]
+    }
+
     pub fn set_tile(
         &self,
         render_state: &luminol_egui_wgpu::RenderState,
@@ -122,7 +134,9 @@ impl Renderable for Tiles {
 
         if self.auto_opacity {
             for layer in 0..self.enabled_layers.len() {
-                let opacity = if self.selected_layer.is_some_and(|s| s != layer) {
+                let opacity = if self.dimmed_layers[layer]
+                    && self.selected_layer.is_some_and(|s| s != layer)
+                {
                     0.5
                 } else {
                     1.0
@@ -0,0 +1,324 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use strum::IntoEnumIterator;
+
+/// What clicking and dragging on the map places when annotating.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(strum::Display, strum::EnumIter)]
+pub(super) enum AnnotationTool {
+    Note,
+    Rectangle,
+    Arrow,
+}
+
+/// Makes the tab of the given map select one of its annotations and center the view on it the
+/// next time the tab is shown. The tab needs to be opened separately if it isn't already open.
+pub fn focus_annotation(ctx: &egui::Context, map_id: usize, index: usize) {
+    ctx.data_mut(|d| d.insert_temp(focus_annotation_id(map_id), index));
+}
+
+pub(super) fn focus_annotation_id(map_id: usize) -> egui::Id {
+    super::history_scope(map_id).with("focus_annotation")
+}
+
+impl super::Tab {
+    /// Displays the contents of the annotation menu in the toolbar.
+    pub(super) fn annotation_menu_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        update_state: &luminol_core::UpdateState<'_>,
+    ) {
+        ui.radio_value(&mut self.annotation_tool, None, "Edit map")
+            .on_hover_text("Clicking on the map edits tiles and events as usual");
+        for tool in AnnotationTool::iter() {
+            if ui
+                .radio_value(
+                    &mut self.annotation_tool,
+                    Some(tool),
+                    format!("Place {tool}"),
+                )
+                .clicked()
+            {
+                self.view.annotations_enabled = true;
+            }
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Color");
+            ui.color_edit_button_srgb(&mut self.annotation_color);
+        });
+
+        let annotations = update_state.data.annotations();
+        ui.label(format!(
+            "{} annotations on this map",
+            annotations.data.get(&self.id).map_or(0, |a| a.len())
+        ));
+    }
+
+    /// Handles placing and selecting annotations with the mouse. Returns the annotation being
+    /// dragged out, if any, so that it can be drawn before it is placed.
+    pub(super) fn annotation_input(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        update_state: &luminol_core::UpdateState<'_>,
+        map: &luminol_data::rpg::Map,
+        is_focused: bool,
+    ) -> Option<luminol_data::Annotation> {
+        let Some(tool) = self.annotation_tool else {
+            self.selected_annotation = None;
+            self.annotation_drag_start = None;
+            return None;
+        };
+        if ui.input(|i| i.modifiers.command) {
+            // Control-dragging pans the map
+            return None;
+        }
+
+        let mut annotations = update_state.data.annotations();
+        let hover_tile = response
+            .hover_pos()
+            .or(response.interact_pointer_pos())
+            .map(|pos| self.view.tile_at(pos));
+        let clamp_tile = |tile: egui::Pos2| {
+            (
+                (tile.x.floor() as i32).clamp(0, map.width as i32 - 1),
+                (tile.y.floor() as i32).clamp(0, map.height as i32 - 1),
+            )
+        };
+
+        let snapshot = |annotations: &luminol_data::Annotations, description| {
+            super::history::AnnotationsEntry {
+                map_id: self.id,
+                description,
+                annotations: annotations.data.get(&self.id).cloned().unwrap_or_default(),
+            }
+        };
+
+        if response.clicked() || response.secondary_clicked() {
+            let hovered_index = hover_tile.and_then(|tile| {
+                annotations.data.get(&self.id).and_then(|map_annotations| {
+                    map_annotations
+                        .iter()
+                        .rposition(|a| a.shape.contains(tile.x, tile.y))
+                })
+            });
+            self.selected_annotation = match (hovered_index, hover_tile) {
+                (Some(index), _) => Some(index),
+                (None, Some(tile)) if tool == AnnotationTool::Note && response.clicked() => {
+                    let (x, y) = clamp_tile(tile);
+                    self.annotation_history
+                        .push(snapshot(&annotations, "add annotation"));
+                    let map_annotations = annotations.data.entry(self.id).or_default();
+                    map_annotations.push(luminol_data::Annotation {
+                        shape: luminol_data::AnnotationShape::Note { x, y },
+                        text: String::new(),
+                        color: self.annotation_color,
+                    });
+                    annotations.modified = true;
+                    update_state.modified.set(true);
+                    Some(map_annotations.len() - 1)
+                }
+                _ => None,
+            };
+        }
+
+        if tool != AnnotationTool::Note && response.drag_started_by(egui::PointerButton::Primary) {
+            self.annotation_drag_start = hover_tile.map(|tile| {
+                let (x, y) = clamp_tile(tile);
+                egui::pos2(x as f32, y as f32)
+            });
+        }
+
+        let mut preview = None;
+        if let (Some(start), Some(tile)) = (self.annotation_drag_start, hover_tile) {
+            let start = (start.x as i32, start.y as i32);
+            let end = clamp_tile(tile);
+            let shape = match tool {
+                AnnotationTool::Rectangle => Some(luminol_data::AnnotationShape::Rectangle {
+                    x: start.0.min(end.0),
+                    y: start.1.min(end.1),
+                    width: (end.0 - start.0).abs() + 1,
+                    height: (end.1 - start.1).abs() + 1,
+                }),
+                AnnotationTool::Arrow if start != end => {
+                    Some(luminol_data::AnnotationShape::Arrow {
+                        from: start,
+                        to: end,
+                    })
+                }
+                _ => None,
+            };
+            let annotation = shape.map(|shape| luminol_data::Annotation {
+                shape,
+                text: String::new(),
+                color: self.annotation_color,
+            });
+
+            if response.dragged_by(egui::PointerButton::Primary) {
+                preview = annotation;
+            } else {
+                self.annotation_drag_start = None;
+                if let Some(annotation) = annotation {
+                    self.annotation_history
+                        .push(snapshot(&annotations, "add annotation"));
+                    let map_annotations = annotations.data.entry(self.id).or_default();
+                    map_annotations.push(annotation);
+                    annotations.modified = true;
+                    update_state.modified.set(true);
+                    self.selected_annotation = Some(map_annotations.len() - 1);
+                }
+            }
+        } else if !response.dragged() {
+            self.annotation_drag_start = None;
+        }
+
+        // Press delete or backspace to delete the selected annotation
+        if let Some(index) = self.selected_annotation {
+            if is_focused
+                && ui.memory(|m| m.focused().is_none())
+                && ui.input(|i| {
+                    i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace)
+                })
+            {
+                if annotations
+                    .data
+                    .get(&self.id)
+                    .is_some_and(|map_annotations| index < map_annotations.len())
+                {
+                    self.annotation_history
+                        .push(snapshot(&annotations, "delete annotation"));
+                    if let Some(map_annotations) = annotations.data.get_mut(&self.id) {
+                        map_annotations.remove(index);
+                    }
+                    annotations.modified = true;
+                    update_state.modified.set(true);
+                }
+                self.selected_annotation = None;
+            }
+        }
+
+        preview
+    }
+
+    /// Displays a window for editing the text and color of the selected annotation, and adds the
+    /// annotation changes made this frame to the history.
+    pub(super) fn show_annotation_window(
+        &mut self,
+        ctx: &egui::Context,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let scope = super::history_scope(self.id);
+        for entry in self.annotation_history.drain(..) {
+            update_state.history.push(scope, entry);
+        }
+
+        let mut annotations = update_state.data.annotations();
+        let annotations = &mut *annotations;
+        let Some(index) = self.selected_annotation.filter(|&index| {
+            annotations
+                .data
+                .get(&self.id)
+                .is_some_and(|map_annotations| index < map_annotations.len())
+        }) else {
+            self.selected_annotation = None;
+            if let Some(entry) = self.annotation_edit.take() {
+                update_state.history.push(scope, entry);
+            }
+            return;
+        };
+        let before = annotations.data[&self.id].clone();
+        let annotation = &mut annotations
+            .data
+            .get_mut(&self.id)
+            .expect("annotation exists")[index];
+
+        let mut open = true;
+        let mut should_delete = false;
+        let mut is_editing = false;
+        egui::Window::new("Annotation")
+            .id(egui::Id::new("luminol_annotation_editor").with(self.id))
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(240.)
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut annotation.text)
+                        .hint_text("TODO, note or reminder")
+                        .desired_width(f32::INFINITY),
+                );
+                let mut changed = response.changed();
+                is_editing = response.has_focus();
+
+                ui.horizontal(|ui| {
+                    ui.label("Color");
+                    changed |= ui.color_edit_button_srgb(&mut annotation.color).changed();
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        should_delete = ui.button("Delete").clicked();
+                    });
+                });
+                is_editing |= ui.memory(|m| m.any_popup_open());
+
+                if changed {
+                    annotations.modified = true;
+                    update_state.modified.set(true);
+                    if self.annotation_edit.is_none() {
+                        self.annotation_edit = Some(super::history::AnnotationsEntry {
+                            map_id: self.id,
+                            description: "edit annotation",
+                            annotations: before.clone(),
+                        });
+                    }
+                }
+            });
+
+        // Text and color changes become one step once the user stops editing the annotation
+        if !is_editing || !open || should_delete {
+            if let Some(entry) = self.annotation_edit.take() {
+                update_state.history.push(scope, entry);
+            }
+        }
+        if should_delete {
+            update_state.history.push(
+                scope,
+                super::history::AnnotationsEntry {
+                    map_id: self.id,
+                    description: "delete annotation",
+                    annotations: annotations.data[&self.id].clone(),
+                },
+            );
+            if let Some(map_annotations) = annotations.data.get_mut(&self.id) {
+                map_annotations.remove(index);
+            }
+            annotations.modified = true;
+            update_state.modified.set(true);
+        }
+        if !open || should_delete {
+            self.selected_annotation = None;
+        }
+    }
+}
@@ -80,6 +80,46 @@ impl super::Tab {
                 }
             }
 
+            luminol_core::Pencil::Scatter => {
+                if self.drawing_shape_pos.is_none() {
+                    self.drawing_shape_pos = Some(map_pos);
+                }
+
+                // Paint every tile within the brush radius, always picking a random tile out of
+                // the selected ones. The density drops off linearly towards the edge of the brush
+                // depending on the brush falloff.
+                let radius = self.brush_scatter_radius as isize;
+                for (y, x) in (-radius..=radius).cartesian_product(-radius..=radius) {
+                    let distance = ((x * x + y * y) as f32).sqrt();
+                    if distance > radius as f32 + 0.5 {
+                        continue;
+                    }
+
+                    // Skip out-of-bounds tiles
+                    let (Some(absolute_x), Some(absolute_y)) =
+                        (map_x.checked_add_signed(x), map_y.checked_add_signed(y))
+                    else {
+                        continue;
+                    };
+                    if absolute_x >= map.data.xsize() || absolute_y >= map.data.ysize() {
+                        continue;
+                    }
+
+                    let density = self.brush_density
+                        * (1. - self.brush_falloff * distance / (radius as f32 + 1.));
+                    self.set_tile_with_density(
+                        map,
+                        self.tilepicker.get_random_tile(
+                            absolute_x as i16,
+                            absolute_y as i16,
+                            tile_layer as i16,
+                        ),
+                        (absolute_x, absolute_y, tile_layer),
+                        density,
+                    );
+                }
+            }
+
             luminol_core::Pencil::Fill => {
                 let drawing_shape_pos = if let Some(drawing_shape_pos) = self.drawing_shape_pos {
                     drawing_shape_pos
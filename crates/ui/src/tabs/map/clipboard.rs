@@ -0,0 +1,163 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use itertools::Itertools;
+use luminol_data::rpg;
+use std::collections::HashMap;
+
+/// An event copied from a map, in the form it is stored in the clipboard.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct ClipboardEvent {
+    /// ID of the map the event was copied from
+    map_id: usize,
+    /// Tileset of the map the event was copied from
+    tileset_id: usize,
+    event: rpg::Event,
+    /// Names of the other events on the original map that the event refers to, used to find the
+    /// matching events when it's pasted into another map
+    #[serde(default)]
+    referenced_names: HashMap<usize, String>,
+}
+
+impl super::Tab {
+    /// Copies an event from this map into the clipboard.
+    pub(super) fn copy_event(
+        &self,
+        ctx: &egui::Context,
+        toasts: &mut luminol_core::Toasts,
+        map: &rpg::Map,
+        event_id: usize,
+    ) {
+        let Some(event) = map.events.get(event_id) else {
+            return;
+        };
+
+        let mut event = event.clone();
+        let mut referenced_names = HashMap::new();
+        for command in event.pages.iter_mut().flat_map(|page| page.list.iter_mut()) {
+            command.for_each_event_id_mut(|id| {
+                if let Some(referenced) = map.events.get(*id as usize) {
+                    referenced_names.insert(*id as usize, referenced.name.clone());
+                }
+            });
+        }
+
+        let clipboard_event = ClipboardEvent {
+            map_id: self.id,
+            tileset_id: map.tileset_id,
+            event,
+            referenced_names,
+        };
+        let pretty_config = ron::ser::PrettyConfig::new().struct_names(true);
+        match ron::ser::to_string_pretty(&clipboard_event, pretty_config) {
+            Ok(text) => ctx.copy_text(text),
+            Err(e) => luminol_core::error!(
+                *toasts,
+                color_eyre::eyre::eyre!(e).wrap_err("While copying an event")
+            ),
+        }
+    }
+
+    /// Pastes an event from the clipboard at the cursor position, giving it a new ID. Returns the
+    /// ID of the pasted event, or `None` if the clipboard doesn't contain an event or there is no
+    /// room for it. Problems the user should know about are added to `warnings`.
+    pub(super) fn paste_event(
+        &mut self,
+        update_state: &luminol_core::UpdateState<'_>,
+        map: &mut rpg::Map,
+        text: &str,
+        warnings: &mut Vec<String>,
+    ) -> Option<usize> {
+        // Other text in the clipboard is not an error, there's just nothing to paste
+        let clipboard_event = ron::from_str::<ClipboardEvent>(text).ok()?;
+        let old_id = clipboard_event.event.id;
+
+        let template = luminol_data::EventTemplate::new(String::new(), &clipboard_event.event);
+        let Some(new_id) = self.add_event(update_state, map, Some(&template)) else {
+            warnings.push(
+                "Can't paste the event here because the tile is occupied or there are no event IDs \
+                left"
+                    .to_string(),
+            );
+            return None;
+        };
+
+        // The event may refer to itself by ID, which needs to follow it to its new ID. When it's
+        // pasted into another map, references to other events are moved to the event with the
+        // same name on this map, or to the pasted event itself if there isn't exactly one.
+        let mut same_name_ids = HashMap::new();
+        if clipboard_event.map_id != self.id {
+            for (&old_id, name) in &clipboard_event.referenced_names {
+                if let Ok((id, _)) = map
+                    .events
+                    .iter()
+                    .filter(|(id, event)| *id != new_id && &event.name == name)
+                    .exactly_one()
+                {
+                    same_name_ids.insert(old_id, id);
+                }
+            }
+        }
+
+        let mut cleared_ids = Vec::new();
+        let event = map.events.get_mut(new_id).unwrap();
+        for command in event.pages.iter_mut().flat_map(|page| page.list.iter_mut()) {
+            command.for_each_event_id_mut(|id| {
+                if *id as usize == old_id {
+                    *id = new_id as i32;
+                } else if clipboard_event.map_id != self.id {
+                    if let Some(&same_name_id) = same_name_ids.get(&(*id as usize)) {
+                        *id = same_name_id as i32;
+                    } else {
+                        cleared_ids.push(*id);
+                        *id = 0;
+                    }
+                }
+            });
+        }
+
+        if !cleared_ids.is_empty() {
+            warnings.push(format!(
+                "Event {new_id} referred to events {} of map {}, which have no matching event on \
+                this map. These commands now refer to the pasted event itself.",
+                cleared_ids.iter().sorted().dedup().join(", "),
+                clipboard_event.map_id
+            ));
+        }
+
+        if clipboard_event.tileset_id != map.tileset_id
+            && event
+                .pages
+                .iter()
+                .any(|page| page.graphic.tile_id.is_some_and(|tile_id| tile_id != 0))
+        {
+            warnings.push(format!(
+                "Event {new_id} uses tile graphics from tileset {}, but this map uses tileset {}",
+                clipboard_event.tileset_id, map.tileset_id
+            ));
+        }
+
+        Some(new_id)
+    }
+}
@@ -21,3 +21,286 @@
 // it with Steamworks API by Valve Corporation, containing parts covered by
 // terms of the Steamworks API by Valve Corporation, the licensors of this
 // Program grant you additional permission to convey the resulting work.
+
+use luminol_data::rpg;
+
+/// The map and event IDs of the event editors that have to be closed because undoing or redoing
+/// changed which event has their event's ID. The map tabs close them before showing their editors.
+static STALE_EVENT_EDITORS: std::sync::Mutex<Vec<(usize, usize)>> =
+    std::sync::Mutex::new(Vec::new());
+
+/// Takes the IDs of the events on the given map whose editors have to be closed.
+pub fn take_stale_event_editors(map_id: usize) -> Vec<usize> {
+    let mut stale = STALE_EVENT_EDITORS.lock().unwrap();
+    let (closed, kept): (Vec<_>, Vec<_>) = stale.drain(..).partition(|&(id, _)| id == map_id);
+    *stale = kept;
+    closed.into_iter().map(|(_, event_id)| event_id).collect()
+}
+
+/// The scope of the history entries of the map with the given ID. This is also the ID of the map's
+/// tab.
+pub fn history_scope(map_id: usize) -> egui::Id {
+    egui::Id::new("luminol_map").with(map_id)
+}
+
+/// The scope of the history entries that edit the flags of the tileset with the given ID. Tilesets
+/// are shared by every map that uses them, so these entries aren't part of any map's scope.
+pub fn tileset_history_scope(tileset_id: usize) -> egui::Id {
+    egui::Id::new("luminol_tileset").with(tileset_id)
+}
+
+pub enum HistoryEntry {
+    /// Contains the (x, y, tile_id) delta for a changed map layer.
+    Tiles {
+        map_id: usize,
+        layer: usize,
+        delta: Vec<(usize, usize, i16)>,
+    },
+    /// Contains the (x, y, tile_id) deltas for every layer of a map changed in one step, e.g. by a
+    /// generator.
+    TileLayers {
+        map_id: usize,
+        description: &'static str,
+        deltas: Vec<Vec<(usize, usize, i16)>>,
+    },
+    /// Contains the original map coordinates of a moved event and the ID of the event.
+    EventMoved {
+        map_id: usize,
+        id: usize,
+        x: i32,
+        y: i32,
+    },
+    /// Contains the ID of a created event.
+    EventCreated { map_id: usize, id: usize },
+    /// Contains a deleted event.
+    EventDeleted {
+        map_id: usize,
+        event: Box<rpg::Event>,
+    },
+    /// Contains the state of an event before it was edited in the event editor.
+    EventEdited {
+        map_id: usize,
+        event: Box<rpg::Event>,
+    },
+    /// Contains every event of a map before their IDs were changed, and the mapping from the old
+    /// IDs to the new IDs. Since steps are always undone in reverse order within a map, the other
+    /// entries can keep referring to events by ID.
+    EventIdsChanged {
+        map_id: usize,
+        events: Box<luminol_data::OptionVec<rpg::Event>>,
+        mapping: std::collections::HashMap<usize, usize>,
+    },
+    /// Contains every event of a map before several of them were created, moved or deleted in one
+    /// step, e.g. by an import.
+    EventsChanged {
+        map_id: usize,
+        description: &'static str,
+        events: Box<luminol_data::OptionVec<rpg::Event>>,
+    },
+}
+
+/// Contains the annotations of a map before some of them were added, edited or deleted.
+pub struct AnnotationsEntry {
+    pub map_id: usize,
+    pub description: &'static str,
+    pub annotations: Vec<luminol_data::Annotation>,
+}
+
+/// Contains the values of one kind of tileset flag for a range of tile IDs before they were edited
+/// from a map.
+pub struct TilesetFlagsEntry {
+    pub tileset_id: usize,
+    pub flags: luminol_components::TileOverlay,
+    pub tile_id: usize,
+    pub values: Vec<i16>,
+}
+
+impl HistoryEntry {
+    fn map_id(&self) -> usize {
+        match self {
+            Self::Tiles { map_id, .. }
+            | Self::TileLayers { map_id, .. }
+            | Self::EventMoved { map_id, .. }
+            | Self::EventCreated { map_id, .. }
+            | Self::EventDeleted { map_id, .. }
+            | Self::EventEdited { map_id, .. }
+            | Self::EventIdsChanged { map_id, .. }
+            | Self::EventsChanged { map_id, .. } => *map_id,
+        }
+    }
+}
+
+impl luminol_core::history::Step for HistoryEntry {
+    fn description(&self) -> String {
+        match self {
+            Self::Tiles { map_id, layer, .. } => {
+                format!("Map {map_id}: draw on layer {}", layer + 1)
+            }
+            Self::TileLayers {
+                map_id,
+                description,
+                ..
+            } => format!("Map {map_id}: {description}"),
+            Self::EventMoved { map_id, id, .. } => format!("Map {map_id}: move event {id}"),
+            Self::EventCreated { map_id, id } => format!("Map {map_id}: create event {id}"),
+            Self::EventDeleted { map_id, event } => {
+                format!("Map {map_id}: delete event {}", event.id)
+            }
+            Self::EventEdited { map_id, event } => {
+                format!("Map {map_id}: edit event {}", event.id)
+            }
+            Self::EventIdsChanged { map_id, .. } => format!("Map {map_id}: change event IDs"),
+            Self::EventsChanged {
+                map_id,
+                description,
+                ..
+            } => format!("Map {map_id}: {description}"),
+        }
+    }
+
+    fn revert(&mut self, data: &luminol_core::Data) {
+        let mut map = data.get_map(self.map_id());
+        map.modified = true;
+
+        match self {
+            Self::Tiles { layer, delta, .. } => {
+                // The tilemap is updated by the map tab when it notices the tiles have changed
+                for (x, y, tile_id) in delta.iter_mut() {
+                    std::mem::swap(&mut map.data[(*x, *y, *layer)], tile_id);
+                }
+            }
+
+            Self::TileLayers { deltas, .. } => {
+                for (layer, delta) in deltas.iter_mut().enumerate() {
+                    for (x, y, tile_id) in delta.iter_mut() {
+                        std::mem::swap(&mut map.data[(*x, *y, layer)], tile_id);
+                    }
+                }
+            }
+
+            Self::EventMoved { id, x, y, .. } => {
+                if let Some(event) = map.events.get_mut(*id) {
+                    std::mem::swap(&mut event.x, x);
+                    std::mem::swap(&mut event.y, y);
+                }
+            }
+
+            Self::EventCreated { map_id, id } => {
+                if let Ok(event) = map.events.try_remove(*id) {
+                    data.event_templates().remove_instance(*map_id, *id);
+                    *self = Self::EventDeleted {
+                        map_id: *map_id,
+                        event: Box::new(event),
+                    };
+                }
+            }
+
+            Self::EventDeleted { map_id, event } => {
+                let map_id = *map_id;
+                let id = event.id;
+                event.extra_data.graphic_modified.set(true);
+                let Self::EventDeleted { event, .. } =
+                    std::mem::replace(self, Self::EventCreated { map_id, id })
+                else {
+                    unreachable!()
+                };
+                map.events.insert(id, *event);
+            }
+
+            Self::EventEdited { event, .. } => {
+                if let Some(map_event) = map.events.get_mut(event.id) {
+                    std::mem::swap(map_event, event.as_mut());
+                    map_event.extra_data.graphic_modified.set(true);
+                }
+            }
+
+            Self::EventIdsChanged {
+                map_id,
+                events,
+                mapping,
+            } => {
+                std::mem::swap(&mut map.events, events.as_mut());
+                for (_, event) in map.events.iter() {
+                    event.extra_data.graphic_modified.set(true);
+                }
+
+                // Template links have to follow the events back to their old IDs
+                *mapping = mapping.iter().map(|(&old, &new)| (new, old)).collect();
+                data.event_templates().remap_instances(*map_id, mapping);
+
+                // Open editors would save their copy of an event over whichever event has its ID now
+                STALE_EVENT_EDITORS.lock().unwrap().extend(
+                    mapping
+                        .iter()
+                        .flat_map(|(&old, &new)| [old, new])
+                        .map(|id| (*map_id, id)),
+                );
+            }
+
+            Self::EventsChanged { events, .. } => {
+                std::mem::swap(&mut map.events, events.as_mut());
+                for (_, event) in map.events.iter() {
+                    event.extra_data.graphic_modified.set(true);
+                }
+            }
+        }
+    }
+}
+
+impl luminol_core::history::Step for AnnotationsEntry {
+    fn description(&self) -> String {
+        format!("Map {}: {}", self.map_id, self.description)
+    }
+
+    fn revert(&mut self, data: &luminol_core::Data) {
+        // Annotations are stored separately from the map, so the map itself isn't modified
+        let mut annotations = data.annotations();
+        annotations.modified = true;
+        std::mem::swap(
+            annotations.data.entry(self.map_id).or_default(),
+            &mut self.annotations,
+        );
+    }
+}
+
+impl luminol_core::history::Step for TilesetFlagsEntry {
+    fn description(&self) -> String {
+        format!(
+            "Tileset {}: edit {} of tile {}",
+            self.tileset_id,
+            self.flags.to_string().to_lowercase(),
+            self.tile_id
+        )
+    }
+
+    fn revert(&mut self, data: &luminol_core::Data) {
+        // Map tabs update their collision layer when they notice the passages have changed
+        let mut tilesets = data.tilesets();
+        tilesets.modified = true;
+        if let Some(tileset) = tilesets.data.get_mut(self.tileset_id) {
+            if let Some(table) = tileset_flags_mut(tileset, self.flags) {
+                for (current, value) in table
+                    .as_mut_slice()
+                    .iter_mut()
+                    .skip(self.tile_id)
+                    .zip(self.values.iter_mut())
+                {
+                    std::mem::swap(current, value);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the table of a tileset that stores the given kind of flag.
+pub(crate) fn tileset_flags_mut(
+    tileset: &mut rpg::Tileset,
+    flags: luminol_components::TileOverlay,
+) -> Option<&mut luminol_data::Table1> {
+    match flags {
+        luminol_components::TileOverlay::Passability => Some(&mut tileset.passages),
+        luminol_components::TileOverlay::Priority => Some(&mut tileset.priorities),
+        luminol_components::TileOverlay::TerrainTag => Some(&mut tileset.terrain_tags),
+        luminol_components::TileOverlay::None => None,
+    }
+}
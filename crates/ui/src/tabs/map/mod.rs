@@ -24,9 +24,7 @@
 
 #![allow(unused_imports)]
 use egui::Pos2;
-use std::{cell::RefMut, collections::HashMap, collections::VecDeque};
-
-const HISTORY_SIZE: usize = 50;
+use std::{cell::RefMut, collections::HashMap};
 
 struct EventDragInfo {
     /// ID of the event being dragged
@@ -43,25 +41,65 @@ struct EventDragInfo {
 use crate::windows::event_edit;
 
 use itertools::Itertools;
+use strum::IntoEnumIterator;
 
+mod annotations;
 mod brush;
+mod clipboard;
 mod history;
+mod templates;
 mod util;
 
+pub use annotations::focus_annotation;
+pub use history::history_scope;
+pub(crate) use history::HistoryEntry;
+pub(crate) use util::{next_event_id, recompute_autotile, stale_autotiles};
+
 pub struct Tab {
     /// ID of the map that is being edited.
     pub id: usize,
     /// The tilemap.
     pub view: luminol_components::MapView,
     pub tilepicker: luminol_components::Tilepicker,
+    minimap: luminol_components::Minimap,
+    show_minimap: bool,
+    /// Whether clicking on the map edits the tileset flags shown by the tile overlay instead of
+    /// drawing
+    edit_tileset_flags: bool,
+
+    /// What clicking on the map places when annotating, or `None` to edit the map as usual
+    annotation_tool: Option<annotations::AnnotationTool>,
+    /// Color of the next annotation placed
+    annotation_color: [u8; 3],
+    /// The tile a rectangle or arrow annotation is being dragged out from
+    annotation_drag_start: Option<egui::Pos2>,
+    /// Index of the selected annotation in this map's annotations
+    selected_annotation: Option<usize>,
+    /// Annotations added or deleted on the map, which are added to the history once the map is
+    /// no longer borrowed
+    annotation_history: Vec<history::AnnotationsEntry>,
+    /// The annotations of this map before the edit that is in progress in the annotation window
+    annotation_edit: Option<history::AnnotationsEntry>,
 
     drawing_shape: bool,
     event_windows: luminol_core::Windows,
     force_close: bool,
 
     event_drag_info: Option<EventDragInfo>,
+    /// The ID to change the selected event's ID to in the event ID menu
+    new_event_id: usize,
+
+    /// Index of the event template that is placed when double-clicking on the map, or `None` to
+    /// place blank events
+    selected_template: Option<usize>,
+    /// Name for the next template saved from the selected event
+    new_template_name: String,
+    /// The placed events waiting for the user to confirm they should be updated from a template
+    template_update: Option<templates::TemplateUpdate>,
 
-    layer_cache: Vec<i16>,
+    /// The tiles of every layer as of the last time they were written to the tilemap, so that we
+    /// can find the tiles that were changed by the brush, by undoing or by other tabs and windows
+    tile_cache: Vec<i16>,
 
     /// This cache is used by the depth-first search when using the fill brush
     dfs_cache: Vec<bool>,
@@ -71,11 +109,11 @@ pub struct Tab {
     /// When drawing with any brush,
     /// this is set to the position of the original tile we began drawing on
     drawing_shape_pos: Option<egui::Pos2>,
+    /// The tile a shift-drag area selection started on
+    area_selection_start: Option<egui::Pos2>,
+    /// The revision of the tileset's panorama and fog that the map view was last updated to
+    planes_revision: u64,
 
-    /// Undo history
-    history: VecDeque<HistoryEntry>,
-    /// When operations are undone, they are put here so that they can be redone
-    redo_history: Vec<HistoryEntry>,
     /// When starting to draw tiles, this is set to the state of the layer before
     /// any tiles are drawn in order to compute the deltas for the history
     tilemap_undo_cache: Vec<i16>,
@@ -91,30 +129,18 @@ pub struct Tab {
     brush_density: f32,
     /// Seed for the PRNG used for the brush when brush density is less than 1
     brush_seed: [u8; 16],
+    /// User-controlled seed from the toolbar that is mixed into `brush_seed`
+    brush_user_seed: u64,
+    /// Radius of the scatter brush in tiles
+    brush_scatter_radius: u8,
+    /// Between 0 and 1 inclusive; how much the brush density drops off towards the edge of the
+    /// scatter brush
+    brush_falloff: f32,
 
     /// Asynchronous task used to save the map as an image file
     save_as_image_promise: Option<poll_promise::Promise<color_eyre::Result<()>>>,
 }
 
-// TODO: If we add support for changing event IDs, these need to be added as history entries
-// in order to not corrupt the EventMoved and EventCreated entries.
-enum HistoryEntry {
-    /// Contains the (x, y, tile_id) delta for a changed map layer.
-    Tiles {
-        layer: usize,
-        delta: Vec<(usize, usize, i16)>,
-    },
-    /// Contains the original map coordinates of a moved event and the ID of the event.
-    EventMoved { id: usize, x: i32, y: i32 },
-    /// Contains the ID of a created event.
-    EventCreated(usize),
-    /// Contains a deleted event and its corresponding graphic.
-    EventDeleted {
-        event: luminol_data::rpg::Event,
-        sprite: Option<luminol_graphics::Event>,
-    },
-}
-
 impl Tab {
     /// Create a new map editor.
     pub fn new(
@@ -144,6 +170,8 @@ impl Tab {
             |x, y, passage| passages[(x, y)] = passage,
         );
 
+        let minimap = luminol_components::Minimap::new(tilepicker.view.atlas.clone(), &map);
+
         let mut brush_seed = [0u8; 16];
         brush_seed[0..8].copy_from_slice(
             &update_state
@@ -161,21 +189,36 @@ impl Tab {
 
             view,
             tilepicker,
+            minimap,
+            show_minimap: true,
+            edit_tileset_flags: false,
+
+            annotation_tool: None,
+            annotation_color: [255, 200, 0],
+            annotation_drag_start: None,
+            selected_annotation: None,
+            annotation_history: Vec::new(),
+            annotation_edit: None,
 
             drawing_shape: false,
             event_windows: luminol_core::Windows::default(),
             force_close: false,
 
             event_drag_info: None,
+            new_event_id: 1,
+
+            selected_template: None,
+            new_template_name: String::new(),
+            template_update: None,
 
-            layer_cache: vec![0; map.data.xsize() * map.data.ysize()],
+            tile_cache: map.data.as_slice().to_vec(),
 
             dfs_cache: vec![false; map.data.xsize() * map.data.ysize()],
             brush_layer_cache: vec![0; map.data.xsize() * map.data.ysize()],
             drawing_shape_pos: None,
+            area_selection_start: None,
+            planes_revision: 0,
 
-            history: VecDeque::with_capacity(HISTORY_SIZE),
-            redo_history: Vec::with_capacity(HISTORY_SIZE),
             tilemap_undo_cache: vec![0; map.data.xsize() * map.data.ysize()],
             tilemap_undo_cache_layer: 0,
 
@@ -183,12 +226,45 @@ impl Tab {
 
             brush_density: 1.,
             brush_seed,
+            brush_user_seed: 0,
+            brush_scatter_radius: 0,
+            brush_falloff: 0.,
 
             save_as_image_promise: None,
         })
     }
 }
 
+/// Makes the tab of the given map select the given event and center the view on it the next time
+/// the tab is shown. The tab needs to be opened separately if it isn't already open.
+pub fn focus_event(ctx: &egui::Context, map_id: usize, event_id: usize) {
+    ctx.data_mut(|d| d.insert_temp(focus_event_id(map_id), event_id));
+}
+
+fn focus_event_id(map_id: usize) -> egui::Id {
+    history_scope(map_id).with("focus_event")
+}
+
+/// Returns the area selected with shift-drag in the tab of the given map as (x, y, width, height),
+/// if the tab is open and has a selection.
+pub fn selected_area(ctx: &egui::Context, map_id: usize) -> Option<(usize, usize, usize, usize)> {
+    ctx.data(|d| d.get_temp(selected_area_id(map_id)))
+}
+
+fn selected_area_id(map_id: usize) -> egui::Id {
+    history_scope(map_id).with("selected_area")
+}
+
+/// Makes the tabs of the maps using the given tileset recreate their panorama and fog the next
+/// time they are shown.
+pub fn reload_tileset_planes(ctx: &egui::Context, tileset_id: usize) {
+    ctx.data_mut(|d| *d.get_temp_mut_or_default::<u64>(planes_revision_id(tileset_id)) += 1);
+}
+
+fn planes_revision_id(tileset_id: usize) -> egui::Id {
+    history::tileset_history_scope(tileset_id).with("planes_revision")
+}
+
 impl luminol_core::Tab for Tab {
     fn name(&self, update_state: &luminol_core::UpdateState<'_>) -> String {
         let map_infos = update_state.data.map_infos();
@@ -205,7 +281,7 @@ impl luminol_core::Tab for Tab {
     }
 
     fn id(&self) -> egui::Id {
-        egui::Id::new("luminol_map").with(self.id)
+        history_scope(self.id)
     }
 
     fn force_close(&mut self) -> bool {
@@ -219,6 +295,90 @@ impl luminol_core::Tab for Tab {
         is_focused: bool,
     ) {
         self.brush_density = update_state.toolbar.brush_density;
+        self.brush_user_seed = update_state.toolbar.brush_seed;
+        self.brush_scatter_radius = update_state.toolbar.brush_scatter_radius;
+        self.brush_falloff = update_state.toolbar.brush_falloff;
+
+        // Pick up changes made to the tileset in the tileset editor
+        {
+            let map = update_state.data.get_map(self.id);
+            let tilesets = update_state.data.tilesets();
+            if let Some(tileset) = tilesets.data.get(map.tileset_id) {
+                match self
+                    .view
+                    .reload_if_atlas_changed(update_state, &map, tileset, &self.passages)
+                {
+                    Ok(true) => {
+                        self.tilepicker.view = luminol_graphics::Tilepicker::new(
+                            &update_state.graphics,
+                            tileset,
+                            update_state.filesystem,
+                            false,
+                        );
+                        let bottom = self.tilepicker.view.atlas.tileset_height() as i16 / 32;
+                        let tilepicker = &mut self.tilepicker;
+                        tilepicker.selected_tiles_top = tilepicker.selected_tiles_top.min(bottom);
+                        tilepicker.selected_tiles_bottom =
+                            tilepicker.selected_tiles_bottom.min(bottom);
+                        self.minimap = luminol_components::Minimap::new(
+                            self.tilepicker.view.atlas.clone(),
+                            &map,
+                        );
+                    }
+                    Ok(false) => {}
+                    Err(e) => luminol_core::error!(
+                        update_state.toasts,
+                        e.wrap_err(format!("Error reloading the tileset of map {}", self.id))
+                    ),
+                }
+
+                let planes_revision = ui.data(|d| {
+                    d.get_temp::<u64>(planes_revision_id(tileset.id))
+                        .unwrap_or_default()
+                });
+                if planes_revision != self.planes_revision {
+                    self.planes_revision = planes_revision;
+                    self.view.map.reload_planes(
+                        &update_state.graphics,
+                        update_state.filesystem,
+                        &map,
+                        tileset,
+                    );
+                }
+            }
+        }
+
+        if let Some(event_id) = ui.data_mut(|d| d.remove_temp::<usize>(focus_event_id(self.id))) {
+            let map = update_state.data.get_map(self.id);
+            if let Some(event) = map.events.get(event_id) {
+                let position = egui::pos2(event.x as f32, event.y as f32);
+                self.view.selected_layer = luminol_components::SelectedLayer::Events;
+                self.view.selected_event_id = Some(event_id);
+                self.view.cursor_pos = position;
+                self.view.center_on(&map, position + egui::vec2(0.5, 0.5));
+            }
+        }
+
+        if let Some(index) =
+            ui.data_mut(|d| d.remove_temp::<usize>(annotations::focus_annotation_id(self.id)))
+        {
+            let map = update_state.data.get_map(self.id);
+            let project_annotations = update_state.data.annotations();
+            if let Some(annotation) = project_annotations
+                .data
+                .get(&self.id)
+                .and_then(|map_annotations| map_annotations.get(index))
+            {
+                let (x, y) = annotation.shape.anchor();
+                self.view.annotations_enabled = true;
+                if self.annotation_tool.is_none() {
+                    self.annotation_tool = Some(annotations::AnnotationTool::Note);
+                }
+                self.selected_annotation = Some(index);
+                self.view
+                    .center_on(&map, egui::pos2(x as f32 + 0.5, y as f32 + 0.5));
+            }
+        }
 
         // Display the toolbar.
         // FIXME: find a proper place for this toolbar! it looks very out of place right now.
@@ -244,13 +404,16 @@ impl luminol_core::Tab for Tab {
                             match self.view.selected_layer {
                                 luminol_components::SelectedLayer::Events => "Events ⏷".to_string(),
                                 luminol_components::SelectedLayer::Tiles(layer) => {
-                                    format!("Layer {} ⏷", layer + 1)
+                                    if self.view.map.tiles.locked_layers[layer] {
+                                        format!("Layer {} 🔒 ⏷", layer + 1)
+                                    } else {
+                                        format!("Layer {} ⏷", layer + 1)
+                                    }
                                 }
                             },
                             |ui| {
                                 ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
 
-                                // TODO: Add layer enable button
                                 // Display all layers.
                                 egui::Grid::new(self.id().with("layer_select"))
                                     .striped(true)
@@ -259,12 +422,12 @@ impl luminol_core::Tab for Tab {
                                         ui.checkbox(&mut self.view.map.pano_enabled, "👁");
                                         ui.end_row();
 
-                                        for (index, layer) in self
-                                            .view
-                                            .map
-                                            .tiles
+                                        let tiles = &mut self.view.map.tiles;
+                                        for (index, ((enabled, locked), dimmed)) in tiles
                                             .enabled_layers
                                             .iter_mut()
+                                            .zip(tiles.locked_layers.iter_mut())
+                                            .zip(tiles.dimmed_layers.iter_mut())
                                             .enumerate()
                                         {
                                             ui.columns(1, |columns| {
@@ -274,7 +437,17 @@ impl luminol_core::Tab for Tab {
                                                     format!("Layer {}", index + 1),
                                                 );
                                             });
-                                            ui.checkbox(layer, "👁");
+                                            ui.checkbox(enabled, "👁")
+                                                .on_hover_text("Show this layer");
+                                            ui.checkbox(locked, "🔒")
+                                                .on_hover_text("Prevent drawing on this layer");
+                                            ui.add_enabled(
+                                                self.view.darken_unselected_layers,
+                                                egui::Checkbox::new(dimmed, "🌓"),
+                                            )
+                                            .on_hover_text(
+                                                "Darken this layer when another layer is selected",
+                                            );
                                             ui.end_row();
                                         }
 
@@ -300,6 +473,10 @@ impl luminol_core::Tab for Tab {
                                         ui.label(egui::RichText::new("Grid").underline());
                                         ui.checkbox(&mut self.view.map.grid_enabled, "👁");
                                         ui.end_row();
+
+                                        ui.label(egui::RichText::new("Annotations").underline());
+                                        ui.checkbox(&mut self.view.annotations_enabled, "👁");
+                                        ui.end_row();
                                     });
                             },
                         );
@@ -324,6 +501,137 @@ impl luminol_core::Tab for Tab {
                                 .on_disabled_hover_text(
                                     "Display the tile IDs of the currently selected layer",
                                 );
+                            ui.checkbox(&mut self.show_minimap, "Display minimap")
+                                .on_hover_text("Display an overview of the map for navigation");
+                        });
+
+                        if self.view.move_preview {
+                            if self.view.is_playing_move_routes() {
+                                if ui.button("⏹ Stop").clicked() {
+                                    self.view.stop_move_routes();
+                                }
+                            } else if ui
+                                .button("▶ Play")
+                                .on_hover_text(
+                                    "Animate the selected event's move routes on the map",
+                                )
+                                .clicked()
+                            {
+                                self.view.play_move_routes(ui.input(|i| i.time));
+                            }
+                        }
+
+                        ui.separator();
+
+                        ui.menu_button("Overlay ⏷", |ui| {
+                            ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+
+                            for overlay in luminol_components::TileOverlay::iter() {
+                                if ui
+                                    .radio_value(
+                                        &mut self.view.tile_overlay,
+                                        overlay,
+                                        overlay.to_string(),
+                                    )
+                                    .clicked()
+                                    && overlay == luminol_components::TileOverlay::Passability
+                                {
+                                    // Passability is shown by the collision layer
+                                    self.view.map.coll_enabled = true;
+                                }
+                            }
+
+                            ui.separator();
+
+                            ui.add_enabled(
+                                self.view.tile_overlay != luminol_components::TileOverlay::None,
+                                egui::Checkbox::new(
+                                    &mut self.edit_tileset_flags,
+                                    "Edit tileset flags",
+                                ),
+                            )
+                            .on_hover_text(
+                                "Click on a tile to change its flags in the tileset instead of \
+                                 drawing. For passability, click near an edge to toggle that \
+                                 direction or near the center to toggle every direction. For \
+                                 priorities and terrain tags, left-click to increase and \
+                                 right-click to decrease.",
+                            );
+                        });
+
+                        ui.separator();
+
+                        ui.menu_button(
+                            if self.annotation_tool.is_some() {
+                                "Annotating ⏷"
+                            } else {
+                                "Annotations ⏷"
+                            },
+                            |ui| {
+                                ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+                                self.annotation_menu_ui(ui, update_state);
+                            },
+                        );
+
+                        ui.separator();
+
+                        ui.menu_button("Event IDs ⏷", |ui| {
+                            ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+
+                            let mut map = update_state.data.get_map(self.id);
+
+                            if let Some(selected_event_id) = self
+                                .view
+                                .selected_event_id
+                                .filter(|&id| map.events.contains(id))
+                            {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("Change ID of event {selected_event_id} to"));
+                                    ui.add(
+                                        egui::DragValue::new(&mut self.new_event_id)
+                                            .range(1..=9999),
+                                    );
+                                    if ui
+                                        .add_enabled(
+                                            !map.events.contains(self.new_event_id),
+                                            egui::Button::new("Apply"),
+                                        )
+                                        .on_disabled_hover_text(format!(
+                                            "ID {} is already in use",
+                                            self.new_event_id
+                                        ))
+                                        .clicked()
+                                    {
+                                        self.change_event_id(
+                                            update_state.history,
+                                            &update_state.modified,
+                                            &mut update_state.data.event_templates(),
+                                            &mut map,
+                                            selected_event_id,
+                                            self.new_event_id,
+                                        );
+                                    }
+                                });
+                            } else {
+                                ui.weak("Select an event to change its ID");
+                            }
+
+                            if ui
+                                .button("Compact IDs")
+                                .on_hover_text(
+                                    "Renumber the events on this map so that their IDs are \
+                                    consecutive, starting from 1",
+                                )
+                                .clicked()
+                            {
+                                self.compact_event_ids(
+                                    update_state.history,
+                                    &update_state.modified,
+                                    &mut update_state.data.event_templates(),
+                                    &mut map,
+                                );
+                                ui.close_menu();
+                            }
                         });
 
                         ui.separator();
@@ -356,6 +664,33 @@ impl luminol_core::Tab for Tab {
             .default_width(tilepicker_default_width)
             .max_width(tilepicker_default_width)
             .show_inside(ui, |ui| {
+                if self.tilepicker.brush_random
+                    || matches!(update_state.toolbar.pencil, luminol_core::Pencil::Scatter)
+                {
+                    egui::TopBottomPanel::bottom(format!("map_{}_brush_weights", self.id))
+                        .show_inside(ui, |ui| {
+                            ui.collapsing("Random tile weights", |ui| {
+                                egui::ScrollArea::vertical()
+                                    .max_height(200.)
+                                    .show(ui, |ui| self.tilepicker.weights_ui(ui));
+                            });
+                        });
+                }
+
+                if matches!(
+                    self.view.selected_layer,
+                    luminol_components::SelectedLayer::Events
+                ) {
+                    egui::TopBottomPanel::bottom(format!("map_{}_event_templates", self.id))
+                        .show_inside(ui, |ui| {
+                            ui.collapsing("Event templates", |ui| {
+                                egui::ScrollArea::vertical()
+                                    .max_height(300.)
+                                    .show(ui, |ui| self.event_templates_ui(ui, update_state));
+                            });
+                        });
+                }
+
                 egui::ScrollArea::both()
                     .id_source(
                         update_state
@@ -373,19 +708,43 @@ impl luminol_core::Tab for Tab {
                     });
             });
 
+        if self.show_minimap {
+            egui::SidePanel::right(format!("map_{}_minimap", self.id))
+                .default_width(192.)
+                .show_inside(ui, |ui| {
+                    let map = update_state.data.get_map(self.id);
+                    let system = update_state.data.system();
+                    let start_position = (system.start_map_id == self.id)
+                        .then(|| egui::pos2(system.start_x as f32, system.start_y as f32));
+                    self.minimap.ui(ui, &mut self.view, &map, start_position);
+                });
+        }
+
+        // Undo and redo are handled after the map is no longer borrowed, since reverting a history
+        // entry needs to borrow the map again
+        let mut is_undo_requested = false;
+        let mut is_redo_requested = false;
+        // While editing tileset flags, undo and redo apply to the tileset instead of the map
+        let mut undo_scope = self.id();
+
         egui::CentralPanel::default().show_inside(ui, |ui| {
             egui::Frame::canvas(ui.style()).show(ui, |ui| {
                 // Get the map.
                 let mut map = update_state.data.get_map(self.id);
-                let tilesets = update_state.data.tilesets();
-                let tileset = &tilesets.data[map.tileset_id];
-
-                // Save the state of the selected layer into the cache
-                if let luminol_components::SelectedLayer::Tiles(tile_layer) =
-                    self.view.selected_layer
-                {
-                    self.layer_cache
-                        .copy_from_slice(map.data.layer_as_slice(tile_layer));
+                let mut tilesets = update_state.data.tilesets();
+
+                if self.view.move_preview {
+                    let system = update_state.data.system();
+                    let player_start = (system.start_map_id == self.id)
+                        .then_some((system.start_x, system.start_y));
+                    self.view.update_move_route_paths(
+                        &map,
+                        &tilesets.data[map.tileset_id],
+                        player_start,
+                        update_state.toolbar.page_simulation.as_ref(),
+                    );
+                } else {
+                    self.view.stop_move_routes();
                 }
 
                 let response = self.view.ui(
@@ -400,6 +759,61 @@ impl luminol_core::Tab for Tab {
                     is_focused,
                 );
 
+                let is_editing_tileset_flags = self.edit_tileset_flags
+                    && self.view.tile_overlay != luminol_components::TileOverlay::None;
+                if is_editing_tileset_flags {
+                    undo_scope = history::tileset_history_scope(map.tileset_id);
+                }
+                if is_editing_tileset_flags
+                    && self.annotation_tool.is_none()
+                    && (response.clicked() || response.secondary_clicked())
+                    && ui.input(|i| !i.modifiers.command)
+                {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        let tile = self.view.tile_at(pos);
+                        let (x, y) = (tile.x.floor(), tile.y.floor());
+                        if x >= 0.
+                            && y >= 0.
+                            && (x as usize) < map.data.xsize()
+                            && (y as usize) < map.data.ysize()
+                        {
+                            if let Some(tile_id) =
+                                self.view.flag_tile_id(&map, x as usize, y as usize)
+                            {
+                                self.edit_tileset_flag(
+                                    update_state.history,
+                                    &update_state.modified,
+                                    &mut tilesets,
+                                    map.tileset_id,
+                                    tile_id,
+                                    tile - egui::pos2(x, y),
+                                    response.secondary_clicked(),
+                                );
+                            }
+                        }
+                    }
+                }
+
+                let tileset = &tilesets.data[map.tileset_id];
+                self.view.tile_overlay_ui(ui, &map, tileset);
+
+                let is_annotating = self.annotation_tool.is_some();
+                let annotation_preview =
+                    self.annotation_input(ui, &response, update_state, &map, is_focused);
+                {
+                    let annotations = update_state.data.annotations();
+                    self.view.annotations_ui(
+                        ui,
+                        annotations
+                            .data
+                            .get(&self.id)
+                            .into_iter()
+                            .flatten()
+                            .chain(annotation_preview.as_ref()),
+                        self.selected_annotation,
+                    );
+                }
+
                 let _layers_max = map.data.zsize();
                 let map_x = self.view.cursor_pos.x as i32;
                 let map_y = self.view.cursor_pos.y as i32;
@@ -427,9 +841,11 @@ impl luminol_core::Tab for Tab {
                         .is_some_and(|event| event.x != info.x || event.y != info.y)
                     {
                         self.push_to_history(
-                            update_state,
+                            update_state.history,
+                            &update_state.modified,
                             &mut map,
                             HistoryEntry::EventMoved {
+                                map_id: self.id,
                                 id: info.id,
                                 x: info.x,
                                 y: info.y,
@@ -456,9 +872,11 @@ impl luminol_core::Tab for Tab {
                             })
                             .collect();
                         self.push_to_history(
-                            update_state,
+                            update_state.history,
+                            &update_state.modified,
                             &mut map,
                             HistoryEntry::Tiles {
+                                map_id: self.id,
                                 layer: self.tilemap_undo_cache_layer,
                                 delta,
                             },
@@ -469,18 +887,66 @@ impl luminol_core::Tab for Tab {
                 if let luminol_components::SelectedLayer::Tiles(tile_layer) =
                     self.view.selected_layer
                 {
+                    let is_layer_editable = self.view.map.tiles.is_layer_editable(tile_layer);
+
+                    if !is_layer_editable
+                        && !is_editing_tileset_flags
+                        && !is_annotating
+                        && ui.input(|i| !i.modifiers.command)
+                        && (response.clicked()
+                            || response.drag_started_by(egui::PointerButton::Primary))
+                    {
+                        luminol_core::warn!(
+                            update_state.toasts,
+                            if self.view.map.tiles.locked_layers[tile_layer] {
+                                format!("Layer {} is locked", tile_layer + 1)
+                            } else {
+                                format!("Layer {} is hidden", tile_layer + 1)
+                            }
+                        );
+                    }
+
+                    // Shift-dragging selects an area of the map for tools like the dungeon
+                    // generator
+                    let is_selecting_area = !is_editing_tileset_flags
+                        && !is_annotating
+                        && ui.input(|i| i.modifiers.shift && !i.modifiers.command);
+                    if is_selecting_area && response.drag_started_by(egui::PointerButton::Primary) {
+                        self.area_selection_start = Some(egui::pos2(map_x as f32, map_y as f32));
+                    }
+                    if let Some(start) = self.area_selection_start {
+                        if response.dragged_by(egui::PointerButton::Primary) {
+                            let end = egui::pos2(map_x as f32, map_y as f32);
+                            self.view.selected_area = Some(egui::Rect::from_min_max(
+                                start.min(end),
+                                start.max(end) + egui::Vec2::splat(1.),
+                            ));
+                        } else {
+                            self.area_selection_start = None;
+                        }
+                    }
+                    if is_focused && ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        self.view.selected_area = None;
+                    }
+
                     // Tile drawing
-                    if response.is_pointer_button_down_on()
+                    if is_layer_editable
+                        && !is_editing_tileset_flags
+                        && !is_annotating
+                        && self.area_selection_start.is_none()
+                        && response.is_pointer_button_down_on()
                         && ui.input(|i| {
                             i.pointer.button_down(egui::PointerButton::Primary)
                                 && !i.modifiers.command
+                                && !i.modifiers.shift
                         })
                     {
                         if self.drawing_shape_pos.is_none() {
                             // Before drawing tiles, save the state of the current layer so we can
                             // undo it later if we need to
                             self.tilemap_undo_cache_layer = tile_layer;
-                            self.tilemap_undo_cache.copy_from_slice(&self.layer_cache);
+                            self.tilemap_undo_cache
+                                .copy_from_slice(map.data.layer_as_slice(tile_layer));
                         }
 
                         self.handle_brush(
@@ -491,6 +957,8 @@ impl luminol_core::Tab for Tab {
                             &mut map,
                         );
                     }
+                } else if is_annotating {
+                    // Clicks on the event layer place annotations instead of editing events
                 } else if let Some(selected_event_id) = self.view.selected_event_id {
                     if response.double_clicked()
                         || (is_focused && ui.input(|i| i.key_pressed(egui::Key::Enter)))
@@ -510,11 +978,18 @@ impl luminol_core::Tab for Tab {
                     // Press delete or backspace to delete the selected event
                     if is_delete_pressed {
                         let event = map.events.remove(selected_event_id);
-                        let sprite = self.view.map.events.try_remove(selected_event_id).ok();
+                        update_state
+                            .data
+                            .event_templates()
+                            .remove_instance(self.id, selected_event_id);
                         self.push_to_history(
-                            update_state,
+                            update_state.history,
+                            &update_state.modified,
                             &mut map,
-                            HistoryEntry::EventDeleted { event, sprite },
+                            HistoryEntry::EventDeleted {
+                                map_id: self.id,
+                                event: Box::new(event),
+                            },
                         );
                     }
 
@@ -575,16 +1050,76 @@ impl luminol_core::Tab for Tab {
                     if response.double_clicked()
                         || (is_focused && ui.input(|i| i.key_pressed(egui::Key::Enter)))
                     {
-                        if let Some(id) = self.add_event(update_state, &mut map) {
+                        let mut event_templates = update_state.data.event_templates();
+                        let template = self
+                            .selected_template
+                            .and_then(|index| event_templates.data.get(index));
+                        if let Some(id) = self.add_event(update_state, &mut map, template) {
+                            if let Some(template) = self
+                                .selected_template
+                                .and_then(|index| event_templates.data.get_mut(index))
+                            {
+                                template.instances.push((self.id, id));
+                                event_templates.modified = true;
+                            }
                             self.push_to_history(
-                                update_state,
+                                update_state.history,
+                                &update_state.modified,
                                 &mut map,
-                                HistoryEntry::EventCreated(id),
+                                HistoryEntry::EventCreated {
+                                    map_id: self.id,
+                                    id,
+                                },
                             );
                         }
                     }
                 }
 
+                // Handle copying and pasting events
+                if is_focused
+                    && matches!(
+                        self.view.selected_layer,
+                        luminol_components::SelectedLayer::Events
+                    )
+                    && ui.memory(|m| m.focused().is_none())
+                {
+                    for input_event in ui.input(|i| i.events.clone()) {
+                        match input_event {
+                            egui::Event::Copy => {
+                                if let Some(selected_event_id) = self.view.selected_event_id {
+                                    self.copy_event(
+                                        ui.ctx(),
+                                        update_state.toasts,
+                                        &map,
+                                        selected_event_id,
+                                    );
+                                }
+                            }
+                            egui::Event::Paste(text) => {
+                                let mut warnings = Vec::new();
+                                let pasted_event_id =
+                                    self.paste_event(update_state, &mut map, &text, &mut warnings);
+                                for warning in warnings {
+                                    luminol_core::warn!(update_state.toasts, warning);
+                                }
+                                if let Some(id) = pasted_event_id {
+                                    self.view.selected_event_id = Some(id);
+                                    self.push_to_history(
+                                        update_state.history,
+                                        &update_state.modified,
+                                        &mut map,
+                                        HistoryEntry::EventCreated {
+                                            map_id: self.id,
+                                            id,
+                                        },
+                                    );
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
                 // Handle undo/redo keypresses
                 let is_dragged_by_primary = response.dragged_by(egui::PointerButton::Primary);
                 let is_undo_pressed = is_focused
@@ -597,88 +1132,36 @@ impl luminol_core::Tab for Tab {
                             && (i.modifiers.shift || i.key_pressed(egui::Key::Y))
                             && (!i.modifiers.shift || i.key_pressed(egui::Key::Z))
                     });
-                if !is_dragged_by_primary && (is_undo_pressed || is_redo_pressed) {
-                    let new_entry = match if is_undo_pressed {
-                        self.history.pop_back()
-                    } else {
-                        self.redo_history.pop()
-                    } {
-                        None => None,
-
-                        Some(HistoryEntry::Tiles { layer, mut delta }) => {
-                            for d in delta.iter_mut() {
-                                let position = (d.0, d.1, layer);
-                                let new_id = d.2;
-                                *d = (d.0, d.1, map.data[position]);
-                                map.data[position] = new_id;
-                                self.view.map.set_tile(
-                                    &update_state.graphics.render_state,
-                                    new_id,
-                                    position,
-                                );
-                            }
-                            Some(HistoryEntry::Tiles { layer, delta })
-                        }
-
-                        Some(HistoryEntry::EventMoved { id, x, y }) => {
-                            let event = map.events.get_mut(id).unwrap();
-                            let new_entry = Some(HistoryEntry::EventMoved {
-                                id,
-                                x: event.x,
-                                y: event.y,
-                            });
-                            event.x = x;
-                            event.y = y;
-                            new_entry
-                        }
-
-                        Some(HistoryEntry::EventCreated(id)) => {
-                            let event = map.events.remove(id);
-                            let sprite = self.view.map.events.try_remove(id).ok();
-                            Some(HistoryEntry::EventDeleted { event, sprite })
-                        }
-
-                        Some(HistoryEntry::EventDeleted { event, sprite }) => {
-                            let id = event.id;
-                            map.events.insert(id, event);
-                            if let Some(sprite) = sprite {
-                                self.view.map.events.insert(id, sprite);
-                            }
-                            Some(HistoryEntry::EventCreated(id))
-                        }
-                    };
-
-                    if let Some(new_entry) = new_entry {
-                        update_state.modified.set(true);
-                        map.modified = true;
-                        if is_undo_pressed {
-                            self.redo_history.push(new_entry);
-                        } else {
-                            self.history.push_back(new_entry);
-                        }
-                    }
+                if !is_dragged_by_primary {
+                    is_undo_requested = is_undo_pressed;
+                    is_redo_requested = is_redo_pressed;
                 }
 
                 for (_, event) in map.events.iter_mut() {
                     event.extra_data.is_editor_open = false;
                 }
 
-                if let luminol_components::SelectedLayer::Tiles(tile_layer) =
-                    self.view.selected_layer
+                // Write the buffered tile changes to the tilemap
+                let mut is_collision_changed = false;
+                for (index, (&new_tile_id, cached_tile_id)) in map
+                    .data
+                    .as_slice()
+                    .iter()
+                    .zip(self.tile_cache.iter_mut())
+                    .enumerate()
                 {
-                    // Write the buffered tile changes to the tilemap
-                    for y in 0..map.data.ysize() {
-                        for x in 0..map.data.xsize() {
-                            let position = (x, y, tile_layer);
-                            let new_tile_id = map.data[position];
-                            if new_tile_id != self.layer_cache[x + y * map.data.xsize()] {
-                                self.view.map.set_tile(
-                                    &update_state.graphics.render_state,
-                                    new_tile_id,
-                                    position,
-                                );
-                            }
-                        }
+                    if new_tile_id != *cached_tile_id {
+                        *cached_tile_id = new_tile_id;
+                        let x = index % map.data.xsize();
+                        let y = (index / map.data.xsize()) % map.data.ysize();
+                        let z = index / (map.data.xsize() * map.data.ysize());
+                        self.view.map.set_tile(
+                            &update_state.graphics.render_state,
+                            new_tile_id,
+                            (x, y, z),
+                        );
+                        self.minimap.set_tile(&map, (x, y, z));
+                        is_collision_changed = true;
                     }
                 }
 
@@ -703,12 +1186,47 @@ impl luminol_core::Tab for Tab {
                                 (x, y),
                             );
                             self.passages[(x, y)] = passage;
+                            is_collision_changed = true;
                         }
                     },
                 );
+                if is_collision_changed {
+                    self.view.invalidate_route_passages();
+                }
             })
         });
 
+        if is_undo_requested {
+            update_state.undo(Some(undo_scope));
+        } else if is_redo_requested {
+            update_state.redo(Some(undo_scope));
+        }
+
+        let selected_area = self.view.selected_area.map(|area| {
+            (
+                area.min.x as usize,
+                area.min.y as usize,
+                area.width() as usize,
+                area.height() as usize,
+            )
+        });
+        ui.data_mut(|d| match selected_area {
+            Some(area) => d.insert_temp(selected_area_id(self.id), area),
+            None => d.remove::<(usize, usize, usize, usize)>(selected_area_id(self.id)),
+        });
+
+        self.show_template_update_window(ui.ctx(), update_state);
+        self.show_annotation_window(ui.ctx(), update_state);
+
+        let stale_event_ids = history::take_stale_event_editors(self.id);
+        if !stale_event_ids.is_empty() {
+            let map_id = self.id;
+            self.event_windows.clean_windows(|window| {
+                !stale_event_ids
+                    .iter()
+                    .any(|&id| window.id() == event_edit::Window::window_id(map_id, id))
+            });
+        }
         self.event_windows.display(ui.ctx(), update_state);
 
         if let Some(p) = self.save_as_image_promise.take() {
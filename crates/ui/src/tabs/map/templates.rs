@@ -0,0 +1,256 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+/// The events placed from a template that can be updated after the template has changed.
+pub(super) struct TemplateUpdate {
+    template_index: usize,
+    instances: Vec<TemplateInstance>,
+}
+
+struct TemplateInstance {
+    map_id: usize,
+    event_id: usize,
+    name: String,
+    selected: bool,
+}
+
+impl super::Tab {
+    /// Displays the event template palette.
+    pub(super) fn event_templates_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let mut event_templates = update_state.data.event_templates();
+
+        ui.selectable_value(
+            &mut self.selected_template,
+            None,
+            egui::RichText::new("Blank event").italics(),
+        )
+        .on_hover_text("Double-click on the map to place a new blank event");
+
+        let mut deleted_template = None;
+        for (index, template) in event_templates.data.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("🗑")
+                    .on_hover_text("Delete this template")
+                    .clicked()
+                {
+                    deleted_template = Some(index);
+                }
+                ui.selectable_value(&mut self.selected_template, Some(index), &template.name)
+                    .on_hover_text(format!(
+                        "Double-click on the map to place a copy of this template ({} linked events)",
+                        template.instances.len()
+                    ));
+            });
+        }
+
+        if let Some(index) = deleted_template {
+            event_templates.data.remove(index);
+            event_templates.modified = true;
+            update_state.modified.set(true);
+            self.selected_template = match self.selected_template {
+                Some(i) if i == index => None,
+                Some(i) if i > index => Some(i - 1),
+                i => i,
+            };
+            self.template_update = None;
+        }
+
+        ui.separator();
+
+        let map = update_state.data.get_map(self.id);
+        let selected_event = self
+            .view
+            .selected_event_id
+            .and_then(|id| map.events.get(id))
+            .cloned();
+        drop(map);
+
+        ui.add_enabled_ui(selected_event.is_some(), |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_template_name)
+                        .hint_text("Template name")
+                        .desired_width(ui.available_width() - 64.),
+                );
+                if ui
+                    .add_enabled(
+                        !self.new_template_name.is_empty(),
+                        egui::Button::new("Save"),
+                    )
+                    .on_hover_text("Save the selected event as a new template")
+                    .clicked()
+                {
+                    if let Some(event) = &selected_event {
+                        let mut template = luminol_data::EventTemplate::new(
+                            std::mem::take(&mut self.new_template_name),
+                            event,
+                        );
+                        template.instances.push((self.id, event.id));
+                        event_templates.data.push(template);
+                        event_templates.modified = true;
+                        update_state.modified.set(true);
+                        self.selected_template = Some(event_templates.data.len() - 1);
+                    }
+                }
+            });
+
+            if ui
+                .add_enabled(
+                    self.selected_template.is_some(),
+                    egui::Button::new("Update template from selected event"),
+                )
+                .on_hover_text(
+                    "Replace the selected template with the selected event and update the other \
+                    events placed from it",
+                )
+                .clicked()
+            {
+                if let (Some(index), Some(event)) = (self.selected_template, &selected_event) {
+                    let template = &mut event_templates.data[index];
+                    template.event = luminol_data::EventTemplate::new(String::new(), event).event;
+                    if !template.instances.contains(&(self.id, event.id)) {
+                        template.instances.push((self.id, event.id));
+                    }
+                    event_templates.modified = true;
+                    update_state.modified.set(true);
+
+                    // Forget about placed events that have since been deleted
+                    let mut instances = Vec::new();
+                    template.instances.retain(|&(map_id, event_id)| {
+                        let map = update_state.data.get_or_load_map(
+                            map_id,
+                            update_state.filesystem,
+                            update_state.project_config.as_ref().unwrap(),
+                        );
+                        let Some(instance) = map.events.get(event_id) else {
+                            return false;
+                        };
+                        if (map_id, event_id) != (self.id, event.id) {
+                            instances.push(TemplateInstance {
+                                map_id,
+                                event_id,
+                                name: instance.name.clone(),
+                                selected: false,
+                            });
+                        }
+                        true
+                    });
+
+                    self.template_update = (!instances.is_empty()).then_some(TemplateUpdate {
+                        template_index: index,
+                        instances,
+                    });
+                }
+            }
+        });
+    }
+
+    /// Displays the window asking which of the events placed from a template should be updated.
+    pub(super) fn show_template_update_window(
+        &mut self,
+        ctx: &egui::Context,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let Some(template_update) = &mut self.template_update else {
+            return;
+        };
+
+        let mut open = true;
+        let mut should_apply = false;
+        let mut should_close = false;
+
+        let event_templates = update_state.data.event_templates();
+        let Some(template) = event_templates.data.get(template_update.template_index) else {
+            drop(event_templates);
+            self.template_update = None;
+            return;
+        };
+
+        egui::Window::new("Update linked events")
+            .id(egui::Id::new("luminol_template_update").with(self.id))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The template '{}' was updated. Select the events placed from it to update:",
+                    template.name
+                ));
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.)
+                    .show(ui, |ui| {
+                        for instance in template_update.instances.iter_mut() {
+                            ui.checkbox(
+                                &mut instance.selected,
+                                format!(
+                                    "Map {}: event {} ({})",
+                                    instance.map_id, instance.event_id, instance.name
+                                ),
+                            );
+                        }
+                    });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    should_apply = ui.button("Update").clicked();
+                    should_close = ui.button("Skip").clicked();
+                });
+            });
+
+        if should_apply {
+            for instance in template_update.instances.iter().filter(|i| i.selected) {
+                let mut map = update_state.data.get_or_load_map(
+                    instance.map_id,
+                    update_state.filesystem,
+                    update_state.project_config.as_ref().unwrap(),
+                );
+                let Some(event) = map.events.get_mut(instance.event_id) else {
+                    continue;
+                };
+                let old_event = event.clone();
+                template.apply_to(event);
+                map.modified = true;
+                update_state.history.push(
+                    super::history_scope(instance.map_id),
+                    super::HistoryEntry::EventEdited {
+                        map_id: instance.map_id,
+                        event: Box::new(old_event),
+                    },
+                );
+            }
+            update_state.modified.set(true);
+        }
+
+        drop(event_templates);
+        if !open || should_apply || should_close {
+            self.template_update = None;
+        }
+    }
+}
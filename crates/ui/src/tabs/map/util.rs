@@ -22,149 +22,204 @@
 // terms of the Steamworks API by Valve Corporation, the licensors of this
 // Program grant you additional permission to convey the resulting work.
 
-impl super::Tab {
-    pub(super) fn recompute_autotile(
-        &self,
-        map: &luminol_data::rpg::Map,
-        position: (usize, usize, usize),
-    ) -> i16 {
-        if map.data[position] >= 384 {
-            return map.data[position];
-        }
+use itertools::Itertools;
+use std::collections::HashMap;
 
-        let autotile = map.data[position] / 48;
-        if autotile == 0 {
-            return 0;
-        }
+/// Returns the autotile variant the tile at a position should use depending on its neighbors, or
+/// the tile itself if it isn't an autotile.
+pub(crate) fn recompute_autotile(
+    map: &luminol_data::rpg::Map,
+    position: (usize, usize, usize),
+) -> i16 {
+    if map.data[position] >= 384 {
+        return map.data[position];
+    }
 
-        let x_array: [i8; 8] = [-1, 0, 1, 1, 1, 0, -1, -1];
-        let y_array: [i8; 8] = [-1, -1, -1, 0, 1, 1, 1, 0];
-
-        /*
-         * 765
-         * 0 4
-         * 123
-         */
-        let mut bitfield = 0u8;
-
-        // Loop through the 8 neighbors of this position
-        for (x, y) in x_array.into_iter().zip(y_array.into_iter()) {
-            bitfield <<= 1;
-            // Out-of-bounds tiles always count as valid neighbors
-            let is_out_of_bounds = ((x == -1 && position.0 == 0)
-                || (x == 1 && position.0 + 1 == map.data.xsize()))
-                || ((y == -1 && position.1 == 0) || (y == 1 && position.1 + 1 == map.data.ysize()));
-            // Otherwise, we only consider neighbors that are autotiles of the same type
-            let is_same_autotile = !is_out_of_bounds
-                && map.data[(
-                    if x == -1 {
-                        position.0 - 1
-                    } else {
-                        position.0 + x as usize
-                    },
-                    if y == -1 {
-                        position.1 - 1
-                    } else {
-                        position.1 + y as usize
-                    },
-                    position.2,
-                )] / 48
-                    == autotile;
+    let autotile = map.data[position] / 48;
+    if autotile == 0 {
+        return 0;
+    }
 
-            if is_out_of_bounds || is_same_autotile {
-                bitfield |= 1
-            }
+    let x_array: [i8; 8] = [-1, 0, 1, 1, 1, 0, -1, -1];
+    let y_array: [i8; 8] = [-1, -1, -1, 0, 1, 1, 1, 0];
+
+    /*
+     * 765
+     * 0 4
+     * 123
+     */
+    let mut bitfield = 0u8;
+
+    // Loop through the 8 neighbors of this position
+    for (x, y) in x_array.into_iter().zip(y_array.into_iter()) {
+        bitfield <<= 1;
+        // Out-of-bounds tiles always count as valid neighbors
+        let is_out_of_bounds = ((x == -1 && position.0 == 0)
+            || (x == 1 && position.0 + 1 == map.data.xsize()))
+            || ((y == -1 && position.1 == 0) || (y == 1 && position.1 + 1 == map.data.ysize()));
+        // Otherwise, we only consider neighbors that are autotiles of the same type
+        let is_same_autotile = !is_out_of_bounds
+            && map.data[(
+                if x == -1 {
+                    position.0 - 1
+                } else {
+                    position.0 + x as usize
+                },
+                if y == -1 {
+                    position.1 - 1
+                } else {
+                    position.1 + y as usize
+                },
+                position.2,
+            )] / 48
+                == autotile;
+
+        if is_out_of_bounds || is_same_autotile {
+            bitfield |= 1
         }
+    }
 
-        // Check how many edges have valid neighbors
-        autotile * 48
-            + match (bitfield & 0b01010101).count_ones() {
-                4 => {
-                    // If the autotile is surrounded on all 4 edges,
-                    // then the autotile variant is one of the first 16,
-                    // depending on which corners are surrounded
-                    let tl = (bitfield & 0b10000000 == 0) as u8;
-                    let tr = (bitfield & 0b00100000 == 0) as u8;
-                    let br = (bitfield & 0b00001000 == 0) as u8;
-                    let bl = (bitfield & 0b00000010 == 0) as u8;
-                    tl | (tr << 1) | (br << 2) | (bl << 3)
-                }
+    // Check how many edges have valid neighbors
+    autotile * 48
+        + match (bitfield & 0b01010101).count_ones() {
+            4 => {
+                // If the autotile is surrounded on all 4 edges,
+                // then the autotile variant is one of the first 16,
+                // depending on which corners are surrounded
+                let tl = (bitfield & 0b10000000 == 0) as u8;
+                let tr = (bitfield & 0b00100000 == 0) as u8;
+                let br = (bitfield & 0b00001000 == 0) as u8;
+                let bl = (bitfield & 0b00000010 == 0) as u8;
+                tl | (tr << 1) | (br << 2) | (bl << 3)
+            }
 
-                3 => {
-                    // Rotate the bitfield 90 degrees counterclockwise until
-                    // the one edge that is not surrounded is at the left
-                    let mut bitfield = bitfield;
-                    let mut i = 16u8;
-                    while bitfield & 0b00000001 != 0 {
-                        bitfield = bitfield.rotate_left(2);
-                        i += 4;
-                    }
-                    // Now, the variant is one of the next 16
-                    let tr = (bitfield & 0b00100000 == 0) as u8;
-                    let br = (bitfield & 0b00001000 == 0) as u8;
-                    i + (tr | (br << 1))
+            3 => {
+                // Rotate the bitfield 90 degrees counterclockwise until
+                // the one edge that is not surrounded is at the left
+                let mut bitfield = bitfield;
+                let mut i = 16u8;
+                while bitfield & 0b00000001 != 0 {
+                    bitfield = bitfield.rotate_left(2);
+                    i += 4;
                 }
+                // Now, the variant is one of the next 16
+                let tr = (bitfield & 0b00100000 == 0) as u8;
+                let br = (bitfield & 0b00001000 == 0) as u8;
+                i + (tr | (br << 1))
+            }
 
-                // Top and bottom edges
-                2 if bitfield & 0b01000100 == 0b01000100 => 32,
+            // Top and bottom edges
+            2 if bitfield & 0b01000100 == 0b01000100 => 32,
 
-                // Left and right edges
-                2 if bitfield & 0b00010001 == 0b00010001 => 33,
+            // Left and right edges
+            2 if bitfield & 0b00010001 == 0b00010001 => 33,
 
-                2 => {
-                    // Rotate the bitfield 90 degrees counterclockwise until
-                    // the two edges that are surrounded are at the right and bottom
-                    let mut bitfield = bitfield;
-                    let mut i = 34u8;
-                    while bitfield & 0b00010100 != 0b00010100 {
-                        bitfield = bitfield.rotate_left(2);
-                        i += 2;
-                    }
-                    let br = (bitfield & 0b00001000 == 0) as u8;
-                    i + br
+            2 => {
+                // Rotate the bitfield 90 degrees counterclockwise until
+                // the two edges that are surrounded are at the right and bottom
+                let mut bitfield = bitfield;
+                let mut i = 34u8;
+                while bitfield & 0b00010100 != 0b00010100 {
+                    bitfield = bitfield.rotate_left(2);
+                    i += 2;
                 }
+                let br = (bitfield & 0b00001000 == 0) as u8;
+                i + br
+            }
 
-                1 => {
-                    // Rotate the bitfield 90 degrees clockwise until
-                    // the edge is at the bottom
-                    let mut bitfield = bitfield;
-                    let mut i = 42u8;
-                    while bitfield & 0b00000100 == 0 {
-                        bitfield = bitfield.rotate_right(2);
-                        i += 1;
-                    }
-                    i
+            1 => {
+                // Rotate the bitfield 90 degrees clockwise until
+                // the edge is at the bottom
+                let mut bitfield = bitfield;
+                let mut i = 42u8;
+                while bitfield & 0b00000100 == 0 {
+                    bitfield = bitfield.rotate_right(2);
+                    i += 1;
                 }
+                i
+            }
+
+            0 => 46,
 
-                0 => 46,
+            _ => unreachable!(),
+        } as i16
+}
+
+/// Returns every autotile of a map whose variant doesn't match its neighbors, e.g. because the map
+/// was edited by a program that doesn't know the autotile rules, as (x, y, layer, new tile ID).
+pub(crate) fn stale_autotiles(map: &luminol_data::rpg::Map) -> Vec<(usize, usize, usize, i16)> {
+    let (xsize, ysize, zsize) = (map.data.xsize(), map.data.ysize(), map.data.zsize());
+    (0..zsize)
+        .flat_map(|z| (0..ysize).flat_map(move |y| (0..xsize).map(move |x| (x, y, z))))
+        .filter(|&position| (48..384).contains(&map.data[position]))
+        .filter_map(|position| {
+            let tile_id = recompute_autotile(map, position);
+            (tile_id != map.data[position]).then_some((position.0, position.1, position.2, tile_id))
+        })
+        .collect()
+}
+
+/// Returns the ID a new event on a map should get, or `None` if there are no event IDs left.
+pub(crate) fn next_event_id(map: &luminol_data::rpg::Map) -> Option<usize> {
+    let mut first_vacant_id = 1;
+    let mut max_event_id = 0;
+
+    for (_, event) in map.events.iter() {
+        if event.id == first_vacant_id {
+            first_vacant_id += 1;
+        }
+        max_event_id = event.id;
+    }
 
-                _ => unreachable!(),
-            } as i16
+    // Try first to allocate the event number directly after the current highest one.
+    // However, valid event number range in RPG Maker XP and VX is 1-999.
+    if max_event_id < 999 {
+        Some(max_event_id + 1)
     }
+    // Otherwise, we'll try to use a non-allocated event ID that isn't zero.
+    else if first_vacant_id <= 999 {
+        Some(first_vacant_id)
+    } else {
+        None
+    }
+}
 
+impl super::Tab {
     pub(super) fn set_tile(
         &self,
         map: &mut luminol_data::rpg::Map,
         tile: luminol_components::SelectedTile,
         position: (usize, usize, usize),
     ) {
-        if self.brush_density != 1. {
-            if self.brush_density == 0. {
+        self.set_tile_with_density(map, tile, position, self.brush_density);
+    }
+
+    /// Like `set_tile`, but uses the given brush density instead of the one from the toolbar.
+    pub(super) fn set_tile_with_density(
+        &self,
+        map: &mut luminol_data::rpg::Map,
+        tile: luminol_components::SelectedTile,
+        position: (usize, usize, usize),
+        density: f32,
+    ) {
+        if density < 1. {
+            if density <= 0. {
                 return;
             }
 
             // Pick a pseudorandom normal f32 uniformly in the interval [0, 1)
-            let mut preimage = [0u8; 40];
+            let mut preimage = [0u8; 48];
             preimage[0..16].copy_from_slice(&self.brush_seed);
             preimage[16..24].copy_from_slice(&(position.0 as u64).to_le_bytes());
             preimage[24..32].copy_from_slice(&(position.1 as u64).to_le_bytes());
             preimage[32..40].copy_from_slice(&(position.2 as u64).to_le_bytes());
+            preimage[40..48].copy_from_slice(&self.brush_user_seed.to_le_bytes());
             let image = (murmur3::murmur3_32(&mut std::io::Cursor::new(preimage), 1729).unwrap()
                 & 16777215) as f32
                 / 16777216f32;
 
             // Set the tile only if that's less than the brush density
-            if image >= self.brush_density {
+            if image >= density {
                 return;
             }
         }
@@ -193,74 +248,254 @@ impl super::Tab {
                     },
                     position.2,
                 );
-                let tile_id = self.recompute_autotile(map, position);
+                let tile_id = recompute_autotile(map, position);
                 map.data[position] = tile_id;
             }
         }
     }
 
+    /// Adds an event at the cursor position, either a blank one or a copy of `template`. Returns
+    /// the ID of the new event, or `None` if there is already an event there or there are no event
+    /// IDs left.
     pub(super) fn add_event(
         &mut self,
         update_state: &luminol_core::UpdateState<'_>,
         map: &mut luminol_data::rpg::Map,
+        template: Option<&luminol_data::EventTemplate>,
     ) -> Option<usize> {
-        let mut first_vacant_id = 1;
-        let mut max_event_id = 0;
+        if map.events.iter().any(|(_, event)| {
+            event.x == self.view.cursor_pos.x as i32 && event.y == self.view.cursor_pos.y as i32
+        }) {
+            return None;
+        }
+        let new_event_id = next_event_id(map)?;
 
-        for (_, event) in map.events.iter() {
-            if event.id == first_vacant_id {
-                first_vacant_id += 1;
-            }
-            max_event_id = event.id;
+        let x = self.view.cursor_pos.x as i32;
+        let y = self.view.cursor_pos.y as i32;
+        let event = if let Some(template) = template {
+            let event = template.instantiate(new_event_id, x, y);
+            event.extra_data.graphic_modified.set(true);
+            event
+        } else {
+            let event = luminol_data::rpg::Event::new(x, y, new_event_id);
+
+            // Only blank events need to be filled in right away
+            self.event_windows
+                .add_window(crate::windows::event_edit::Window::new(
+                    update_state,
+                    &event,
+                    self.id,
+                    map.tileset_id,
+                ));
+            event
+        };
+
+        map.events.insert(new_event_id, event);
+        Some(new_event_id)
+    }
+
+    /// Changes the ID of an event, updating the references to it in the map's command lists.
+    /// Returns `false` if the new ID is already in use.
+    pub(super) fn change_event_id(
+        &mut self,
+        history: &mut luminol_core::History,
+        modified: &luminol_core::ModifiedState,
+        event_templates: &mut luminol_data::EventTemplates,
+        map: &mut luminol_data::rpg::Map,
+        old_id: usize,
+        new_id: usize,
+    ) -> bool {
+        if new_id == 0 || map.events.contains(new_id) || !map.events.contains(old_id) {
+            return false;
+        }
+        self.remap_event_ids(
+            history,
+            modified,
+            event_templates,
+            map,
+            &HashMap::from([(old_id, new_id)]),
+        );
+        true
+    }
 
-            if event.x == self.view.cursor_pos.x as i32 && event.y == self.view.cursor_pos.y as i32
-            {
-                return None;
+    /// Renumbers the events in the map so that their IDs are consecutive starting from 1, keeping
+    /// their order.
+    pub(super) fn compact_event_ids(
+        &mut self,
+        history: &mut luminol_core::History,
+        modified: &luminol_core::ModifiedState,
+        event_templates: &mut luminol_data::EventTemplates,
+        map: &mut luminol_data::rpg::Map,
+    ) {
+        let mapping: HashMap<_, _> = map
+            .events
+            .iter()
+            .map(|(id, _)| id)
+            .enumerate()
+            .filter_map(|(index, id)| (index + 1 != id).then_some((id, index + 1)))
+            .collect();
+        if !mapping.is_empty() {
+            self.remap_event_ids(history, modified, event_templates, map, &mapping);
+        }
+    }
+
+    /// Changes event IDs according to `mapping`, which maps old IDs to new IDs, and updates every
+    /// reference to a changed ID in the map's command lists and the event templates.
+    fn remap_event_ids(
+        &mut self,
+        history: &mut luminol_core::History,
+        modified: &luminol_core::ModifiedState,
+        event_templates: &mut luminol_data::EventTemplates,
+        map: &mut luminol_data::rpg::Map,
+        mapping: &HashMap<usize, usize>,
+    ) {
+        let old_events = map.events.clone();
+
+        let ids = map.events.iter().map(|(id, _)| id).collect_vec();
+        let events = ids
+            .into_iter()
+            .filter_map(|id| map.events.option_remove(id))
+            .collect_vec();
+        map.events = luminol_data::OptionVec::new();
+        for mut event in events {
+            if let Some(&new_id) = mapping.get(&event.id) {
+                event.id = new_id;
             }
+            for command in event.pages.iter_mut().flat_map(|page| page.list.iter_mut()) {
+                command.for_each_event_id_mut(|id| {
+                    if let Some(&new_id) = mapping.get(&(*id as usize)) {
+                        *id = new_id as i32;
+                    }
+                });
+            }
+            event.extra_data.graphic_modified.set(true);
+            map.events.insert(event.id, event);
         }
 
-        // Try first to allocate the event number directly after the current highest one.
-        // However, valid event number range in RPG Maker XP and VX is 1-999.
-        let new_event_id = if max_event_id < 999 {
-            max_event_id + 1
+        if let Some(selected_event_id) = self.view.selected_event_id {
+            if let Some(&new_id) = mapping.get(&selected_event_id) {
+                self.view.selected_event_id = Some(new_id);
+            }
         }
-        // Otherwise, we'll try to use a non-allocated event ID that isn't zero.
-        else if first_vacant_id <= 999 {
-            first_vacant_id
+
+        // Event editors save to the event with the ID they were opened with, so the ones for
+        // renumbered events have to be closed before they overwrite another event
+        let map_id = self.id;
+        self.event_windows.clean_windows(|window| {
+            !mapping
+                .keys()
+                .any(|&id| window.id() == crate::windows::event_edit::Window::window_id(map_id, id))
+        });
+
+        event_templates.remap_instances(self.id, mapping);
+
+        self.push_to_history(
+            history,
+            modified,
+            map,
+            super::HistoryEntry::EventIdsChanged {
+                map_id: self.id,
+                events: Box::new(old_events),
+                mapping: mapping.clone(),
+            },
+        );
+    }
+
+    /// Edits the tileset flag shown by the tile overlay for a tile ID. `offset` is the position
+    /// that was clicked within the tile, from (0, 0) to (1, 1), and `decrement` is true if the
+    /// tile was right-clicked.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn edit_tileset_flag(
+        &mut self,
+        history: &mut luminol_core::History,
+        modified: &luminol_core::ModifiedState,
+        tilesets: &mut luminol_data::rpg::Tilesets,
+        tileset_id: usize,
+        tile_id: usize,
+        offset: egui::Vec2,
+        decrement: bool,
+    ) {
+        let flags = self.view.tile_overlay;
+        let Some(table) = tilesets
+            .data
+            .get_mut(tileset_id)
+            .and_then(|tileset| super::history::tileset_flags_mut(tileset, flags))
+        else {
+            return;
+        };
+
+        // Every tile ID of an autotile shares the same flags
+        let first_tile_id = if tile_id < 384 {
+            tile_id / 48 * 48
         } else {
-            return None;
+            tile_id
         };
+        let tile_count = if tile_id < 384 { 48 } else { 1 };
+        if first_tile_id + tile_count > table.len() {
+            return;
+        }
 
-        let event = luminol_data::rpg::Event::new(
-            self.view.cursor_pos.x as i32,
-            self.view.cursor_pos.y as i32,
-            new_event_id,
-        );
+        let old_value = table[tile_id];
+        let new_value = match flags {
+            luminol_components::TileOverlay::Passability => {
+                // Clicking near an edge of the tile toggles the passage in that direction and
+                // clicking near the center toggles every direction
+                let offset = offset - egui::Vec2::splat(0.5);
+                let direction = if offset.abs().max_elem() < 1. / 6. {
+                    0x0F
+                } else if offset.x.abs() > offset.y.abs() {
+                    if offset.x < 0. {
+                        0x02
+                    } else {
+                        0x04
+                    }
+                } else if offset.y < 0. {
+                    0x08
+                } else {
+                    0x01
+                };
+                if direction == 0x0F && old_value & 0x0F != 0x0F {
+                    old_value | 0x0F
+                } else {
+                    old_value ^ direction
+                }
+            }
+            luminol_components::TileOverlay::Priority => {
+                (old_value + if decrement { -1 } else { 1 }).rem_euclid(6)
+            }
+            luminol_components::TileOverlay::TerrainTag => {
+                (old_value + if decrement { -1 } else { 1 }).rem_euclid(8)
+            }
+            luminol_components::TileOverlay::None => return,
+        };
 
-        self.event_windows
-            .add_window(crate::windows::event_edit::Window::new(
-                update_state,
-                &event,
-                self.id,
-                map.tileset_id,
-            ));
+        let range = first_tile_id..first_tile_id + tile_count;
+        let values = table.as_slice()[range.clone()].to_vec();
+        table.as_mut_slice()[range].fill(new_value);
 
-        map.events.insert(new_event_id, event);
-        Some(new_event_id)
+        tilesets.modified = true;
+        modified.set(true);
+        self.view.invalidate_route_passages();
+        history.push(
+            super::history::tileset_history_scope(tileset_id),
+            super::history::TilesetFlagsEntry {
+                tileset_id,
+                flags,
+                tile_id: first_tile_id,
+                values,
+            },
+        );
     }
 
     pub(super) fn push_to_history(
         &mut self,
-        update_state: &luminol_core::UpdateState<'_>,
+        history: &mut luminol_core::History,
+        modified: &luminol_core::ModifiedState,
         map: &mut luminol_data::rpg::Map,
         entry: super::HistoryEntry,
     ) {
-        update_state.modified.set(true);
+        modified.set(true);
         map.modified = true;
-        self.redo_history.clear();
-        if self.history.len() == super::HISTORY_SIZE {
-            self.history.pop_front();
-        }
-        self.history.push_back(entry);
+        history.push(super::history_scope(self.id), entry);
     }
 }
@@ -308,7 +308,8 @@ pub fn show_frame_edit(
                                         state.frame_index - 1,
                                         state.frame_index,
                                     );
-                                    state.history.push(
+                                    super::push_history(
+                                        update_state,
                                         animation.id,
                                         state.frame_index,
                                         super::util::history_entries_from_two_tables(
@@ -376,7 +377,8 @@ pub fn show_frame_edit(
                 modals.copy_frames.dst_frame + i,
                 modals.copy_frames.src_frame + i,
             );
-            state.history.push(
+            super::push_history(
+                update_state,
                 animation.id,
                 modals.copy_frames.dst_frame + i,
                 super::util::history_entries_from_two_tables(dst_frame, src_frame),
@@ -394,7 +396,8 @@ pub fn show_frame_edit(
         .show_window(ui.ctx(), state.frame_index, animation.frames.len())
     {
         for i in modals.clear_frames.start_frame..=modals.clear_frames.end_frame {
-            state.history.push(
+            super::push_history(
+                update_state,
                 animation.id,
                 i,
                 super::util::history_entries_from_two_tables(
@@ -448,7 +451,7 @@ pub fn show_frame_edit(
                 }
 
                 entries.push(HistoryEntry::new_cell(&animation.frames[j].cell_data, i));
-                state.history.push(animation.id, j, entries);
+                super::push_history(update_state, animation.id, j, entries);
 
                 if modals.tween.tween_pattern {
                     let (val, orientation) = lerp(&animation.frames, 0);
@@ -502,9 +505,12 @@ pub fn show_frame_edit(
                 if data[(j, 0)] < 0 {
                     continue;
                 }
-                state
-                    .history
-                    .push(animation.id, i, vec![HistoryEntry::new_cell(data, j)]);
+                super::push_history(
+                    update_state,
+                    animation.id,
+                    i,
+                    vec![HistoryEntry::new_cell(data, j)],
+                );
                 match modals.batch_edit.mode {
                     luminol_modals::animations::batch_edit_tool::Mode::Set => {
                         if modals.batch_edit.set_pattern_enabled {
@@ -605,7 +611,9 @@ pub fn show_frame_edit(
     {
         modals.close_all_except_frame_count();
         for i in modals.change_frame_count.new_frames_len..animation.frames.len() {
-            state.history.remove_frame(animation.id, i);
+            update_state
+                .history
+                .remove_scope(super::history_scope(animation.id, i));
         }
         animation
             .frames
@@ -641,7 +649,8 @@ pub fn show_frame_edit(
                 && frame.cell_data[(max_cell, 0)] >= 0
                 && frame.cell_data[(min_cell, 0)] < 0
             {
-                state.history.push(
+                super::push_history(
+                    update_state,
                     animation.id,
                     i,
                     vec![
@@ -691,7 +700,7 @@ pub fn show_frame_edit(
                 );
             }
 
-            state.history.push(animation.id, i, entries);
+            super::push_history(update_state, animation.id, i, entries);
         }
 
         frame_view
@@ -769,7 +778,8 @@ pub fn show_frame_edit(
         let y = frame.cell_data[(drag_state.cell_index, 2)];
         frame.cell_data[(drag_state.cell_index, 1)] = drag_state.original_x;
         frame.cell_data[(drag_state.cell_index, 2)] = drag_state.original_y;
-        state.history.push(
+        super::push_history(
+            update_state,
             animation.id,
             state.frame_index,
             vec![HistoryEntry::new_cell(
@@ -974,7 +984,7 @@ pub fn show_frame_edit(
                 frame.cell_data[(next_cell_index, 6)] = 255;
                 frame.cell_data[(next_cell_index, 7)] = 1;
 
-                state.history.push(animation.id, state.frame_index, entries);
+                super::push_history(update_state, animation.id, state.frame_index, entries);
 
                 frame_view.frame.update_cell(
                     &update_state.graphics,
@@ -1018,7 +1028,7 @@ pub fn show_frame_edit(
                     );
                 }
 
-                state.history.push(animation.id, state.frame_index, entries);
+                super::push_history(update_state, animation.id, state.frame_index, entries);
 
                 frame_view.frame.update_cell(
                     &update_state.graphics,
@@ -1060,14 +1070,11 @@ pub fn show_frame_edit(
                     state.frame_needs_update = true;
                 }
 
-                let frame = &mut animation.frames[state.frame_index];
-
                 // Ctrl+Z for undo
                 if ui.input(|i| {
                     i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::Z)
                 }) {
-                    state.history.undo(animation.id, state.frame_index, frame);
-                    state.frame_needs_update = true;
+                    state.undo_requested = true;
                 }
 
                 // Ctrl+Y or Ctrl+Shift+Z for redo
@@ -1076,8 +1083,7 @@ pub fn show_frame_edit(
                         && (i.key_pressed(egui::Key::Y)
                             || (i.modifiers.shift && i.key_pressed(egui::Key::Z)))
                 }) {
-                    state.history.redo(animation.id, state.frame_index, frame);
-                    state.frame_needs_update = true;
+                    state.redo_requested = true;
                 }
             }
 
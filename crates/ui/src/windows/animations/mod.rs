@@ -27,8 +27,6 @@ mod timing;
 mod util;
 mod window;
 
-const HISTORY_SIZE: usize = 50;
-
 /// Database - Animations management window.
 pub struct Window {
     selected_animation_name: Option<String>,
@@ -55,7 +53,12 @@ struct FrameEditState {
     saved_frame_index: Option<usize>,
     saved_selected_cell_index: Option<usize>,
     frame_needs_update: bool,
-    history: History,
+    /// Whether or not undo or redo was requested for the current frame this frame. These are
+    /// handled after the data cache is restored at the end of the frame.
+    undo_requested: bool,
+    redo_requested: bool,
+    /// The revision of the undo history as of the last time the frame was updated
+    history_revision: usize,
     drag_state: Option<DragState>,
 }
 
@@ -79,79 +82,62 @@ struct TimingEditState {
     se_picker: luminol_modals::sound_picker::Modal,
 }
 
-#[derive(Debug, Default)]
-struct History(luminol_data::OptionVec<luminol_data::OptionVec<HistoryInner>>);
-
-#[derive(Debug, Default)]
-struct HistoryInner {
-    undo: std::collections::VecDeque<Vec<HistoryEntry>>,
-    redo: Vec<Vec<HistoryEntry>>,
+/// The scope of the history entries of one frame of the animation with the given ID.
+fn history_scope(animation_id: usize, frame_index: usize) -> egui::Id {
+    egui::Id::new("luminol_animation_frame")
+        .with(animation_id)
+        .with(frame_index)
 }
 
-impl History {
-    fn inner(&mut self, animation_index: usize, frame_index: usize) -> &mut HistoryInner {
-        if !self.0.contains(animation_index) {
-            self.0.insert(animation_index, Default::default());
-        }
-        let map = self.0.get_mut(animation_index).unwrap();
-        if !map.contains(frame_index) {
-            map.insert(frame_index, Default::default());
-        }
-        map.get_mut(frame_index).unwrap()
-    }
-
-    fn remove_animation(&mut self, animation_index: usize) {
-        let _ = self.0.try_remove(animation_index);
-    }
-
-    fn remove_frame(&mut self, animation_index: usize, frame_index: usize) {
-        if let Some(map) = self.0.get_mut(animation_index) {
-            let _ = map.try_remove(frame_index);
-        }
-    }
+/// Adds a group of history entries for one frame, which are undone and redone together, to the
+/// undo history.
+fn push_history(
+    update_state: &mut luminol_core::UpdateState<'_>,
+    animation_id: usize,
+    frame_index: usize,
+    mut entries: Vec<HistoryEntry>,
+) {
+    entries.shrink_to_fit();
+    update_state.push_history(
+        history_scope(animation_id, frame_index),
+        FrameStep {
+            animation_id,
+            frame_index,
+            entries,
+        },
+    );
+}
 
-    fn push(&mut self, animation_index: usize, frame_index: usize, mut entries: Vec<HistoryEntry>) {
-        entries.shrink_to_fit();
-        let inner = self.inner(animation_index, frame_index);
-        inner.redo.clear();
-        while inner.undo.len() >= HISTORY_SIZE {
-            inner.undo.pop_front();
-        }
-        inner.undo.push_back(entries);
-    }
+#[derive(Debug)]
+struct FrameStep {
+    animation_id: usize,
+    frame_index: usize,
+    entries: Vec<HistoryEntry>,
+}
 
-    fn undo(
-        &mut self,
-        animation_index: usize,
-        frame_index: usize,
-        frame: &mut luminol_data::rpg::animation::Frame,
-    ) {
-        let inner = self.inner(animation_index, frame_index);
-        let Some(mut vec) = inner.undo.pop_back() else {
-            return;
-        };
-        vec.reverse();
-        for entry in vec.iter_mut() {
-            entry.apply(frame);
-        }
-        inner.redo.push(vec);
+impl luminol_core::history::Step for FrameStep {
+    fn description(&self) -> String {
+        format!(
+            "Animation {}: edit frame {}",
+            self.animation_id + 1,
+            self.frame_index + 1
+        )
     }
 
-    fn redo(
-        &mut self,
-        animation_index: usize,
-        frame_index: usize,
-        frame: &mut luminol_data::rpg::animation::Frame,
-    ) {
-        let inner = self.inner(animation_index, frame_index);
-        let Some(mut vec) = inner.redo.pop() else {
+    fn revert(&mut self, data: &luminol_core::Data) {
+        let mut animations = data.animations();
+        let Some(frame) = animations
+            .data
+            .get_mut(self.animation_id)
+            .and_then(|animation| animation.frames.get_mut(self.frame_index))
+        else {
             return;
         };
-        vec.reverse();
-        for entry in vec.iter_mut() {
+        self.entries.reverse();
+        for entry in self.entries.iter_mut() {
             entry.apply(frame);
         }
-        inner.undo.push_back(vec);
+        animations.modified = true;
     }
 }
 
@@ -233,7 +219,9 @@ impl Default for Window {
                 saved_selected_cell_index: None,
                 frame_needs_update: false,
                 drag_state: None,
-                history: Default::default(),
+                undo_requested: false,
+                redo_requested: false,
+                history_revision: 0,
             },
             timing_edit_state: TimingEditState {
                 previous_frame: None,
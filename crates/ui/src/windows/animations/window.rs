@@ -47,6 +47,11 @@ impl luminol_core::Window for super::Window {
         let data = std::mem::take(update_state.data); // take data to avoid borrow checker issues
         let mut animations = data.animations();
         let animations_len = animations.data.len();
+        let frame_counts = animations
+            .data
+            .iter()
+            .map(|animation| animation.frames.len())
+            .collect::<Vec<_>>();
         let system = data.system();
 
         let mut modified = false;
@@ -72,7 +77,11 @@ impl luminol_core::Window for super::Window {
                     |animation| format!("{:0>4}: {}", animation.id + 1, animation.name),
                     |ui, animations, id, update_state| {
                         for i in animations.len()..animations_len {
-                            self.frame_edit_state.history.remove_animation(i);
+                            for j in 0..frame_counts[i] {
+                                update_state
+                                    .history
+                                    .remove_scope(super::history_scope(i, j));
+                            }
                         }
 
                         let animation = &mut animations[id];
@@ -469,5 +478,23 @@ impl luminol_core::Window for super::Window {
         drop(system);
 
         *update_state.data = data; // restore data
+
+        // Undo and redo need to access the data cache, so they can only be done after it's restored
+        let undo_requested = std::mem::take(&mut self.frame_edit_state.undo_requested);
+        let redo_requested = std::mem::take(&mut self.frame_edit_state.redo_requested);
+        if let Some(animation_id) = self.previous_animation {
+            let scope = super::history_scope(animation_id, self.frame_edit_state.frame_index);
+            if undo_requested {
+                update_state.undo(Some(scope));
+            } else if redo_requested {
+                update_state.redo(Some(scope));
+            }
+        }
+
+        // Update the frame if the history has changed since the last frame
+        if self.frame_edit_state.history_revision != update_state.history.revision() {
+            self.frame_edit_state.history_revision = update_state.history.revision();
+            self.frame_edit_state.frame_needs_update = true;
+        }
     }
 }
@@ -0,0 +1,148 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+/// The annotation list.
+/// Lists the annotations placed on every map in the project so that they can be searched.
+#[derive(Default)]
+pub struct Window {
+    search: String,
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_annotations")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let mut clicked_annotation = None;
+
+        egui::Window::new("Annotations")
+            .id(self.id())
+            .open(open)
+            .default_width(360.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search");
+                    ui.text_edit_singleline(&mut self.search);
+                });
+                let search = self.search.to_lowercase();
+
+                ui.separator();
+
+                let annotations = update_state.data.annotations();
+                let map_infos = update_state.data.map_infos();
+
+                let mut is_empty = true;
+                egui::ScrollArea::vertical()
+                    .max_height(400.)
+                    .show(ui, |ui| {
+                        for (&map_id, map_annotations) in annotations.data.iter() {
+                            let map_name = map_infos
+                                .data
+                                .get(&map_id)
+                                .map_or("", |info| info.name.as_str());
+                            let map_matches = map_name.to_lowercase().contains(&search);
+
+                            let matching = map_annotations
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, annotation)| {
+                                    search.is_empty()
+                                        || map_matches
+                                        || annotation.text.to_lowercase().contains(&search)
+                                })
+                                .collect::<Vec<_>>();
+                            if matching.is_empty() {
+                                continue;
+                            }
+                            is_empty = false;
+
+                            ui.label(
+                                egui::RichText::new(format!("Map {map_id:0>3}: {map_name}"))
+                                    .strong(),
+                            );
+                            for (index, annotation) in matching {
+                                let [r, g, b] = annotation.color;
+                                let (x, y) = annotation.shape.anchor();
+                                let kind = match annotation.shape {
+                                    luminol_data::AnnotationShape::Note { .. } => "Note",
+                                    luminol_data::AnnotationShape::Rectangle { .. } => "Rectangle",
+                                    luminol_data::AnnotationShape::Arrow { .. } => "Arrow",
+                                };
+                                let text = annotation.text.lines().next().unwrap_or_default();
+
+                                ui.horizontal(|ui| {
+                                    ui.colored_label(egui::Color32::from_rgb(r, g, b), "⏺");
+                                    if ui
+                                        .selectable_label(
+                                            false,
+                                            format!("{kind} at ({x}, {y}): {text}"),
+                                        )
+                                        .on_hover_text(if annotation.text.is_empty() {
+                                            "No text"
+                                        } else {
+                                            annotation.text.as_str()
+                                        })
+                                        .clicked()
+                                    {
+                                        clicked_annotation = Some((map_id, index));
+                                    }
+                                });
+                            }
+                            ui.add_space(4.);
+                        }
+                    });
+
+                if is_empty {
+                    ui.weak(if search.is_empty() {
+                        "No annotations yet. Place them from the Annotations menu of a map."
+                    } else {
+                        "No annotations match the search."
+                    });
+                }
+            });
+
+        if let Some((map_id, index)) = clicked_annotation {
+            match crate::tabs::map::Tab::new(map_id, update_state) {
+                Ok(tab) => {
+                    update_state.edit_tabs.add_tab(tab);
+                    crate::tabs::map::focus_annotation(update_state.ctx, map_id, index);
+                }
+                Err(e) => luminol_core::error!(
+                    update_state.toasts,
+                    e.wrap_err(format!("Error opening map {map_id}"))
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use crate::tabs::map::{history_scope, stale_autotiles, HistoryEntry};
+
+/// The autotile rebuilder.
+/// Recomputes the autotile variants of every map using a tileset, for when the autotiles of the
+/// tileset changed after the maps were drawn.
+#[derive(Default)]
+pub struct Window {
+    tileset_id: Option<usize>,
+    /// The number of stale autotiles in each map using the tileset, as of the last scan.
+    scan: Option<Vec<(usize, usize)>>,
+}
+
+impl Window {
+    pub fn new(tileset_id: usize) -> Self {
+        Self {
+            tileset_id: Some(tileset_id),
+            scan: None,
+        }
+    }
+
+    fn map_ids(update_state: &luminol_core::UpdateState<'_>) -> Vec<usize> {
+        let mut map_ids = update_state
+            .data
+            .map_infos()
+            .data
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        map_ids.sort_unstable();
+        map_ids
+    }
+
+    /// Loads every map using the tileset and counts their stale autotiles.
+    fn scan(
+        update_state: &luminol_core::UpdateState<'_>,
+        tileset_id: usize,
+    ) -> Vec<(usize, usize)> {
+        Self::map_ids(update_state)
+            .into_iter()
+            .filter_map(|map_id| {
+                let map = update_state.data.get_or_load_map(
+                    map_id,
+                    update_state.filesystem,
+                    update_state
+                        .project_config
+                        .as_ref()
+                        .expect("project not loaded"),
+                );
+                (map.tileset_id == tileset_id).then(|| (map_id, stale_autotiles(&map).len()))
+            })
+            .collect()
+    }
+
+    /// Fixes the stale autotiles of the given maps. The changes to each map can be undone with one
+    /// undo from the map's tab.
+    fn apply(update_state: &mut luminol_core::UpdateState<'_>, map_ids: &[usize]) -> usize {
+        let mut total = 0;
+        for &map_id in map_ids {
+            let mut map = update_state.data.get_map(map_id);
+            let changes = stale_autotiles(&map);
+            if changes.is_empty() {
+                continue;
+            }
+            total += changes.len();
+
+            let mut deltas = vec![Vec::new(); map.data.zsize()];
+            for (x, y, layer, tile_id) in changes {
+                deltas[layer].push((x, y, map.data[(x, y, layer)]));
+                map.data[(x, y, layer)] = tile_id;
+            }
+            update_state.history.push(
+                history_scope(map_id),
+                HistoryEntry::TileLayers {
+                    map_id,
+                    description: "rebuild autotiles",
+                    deltas,
+                },
+            );
+            map.modified = true;
+        }
+        if total > 0 {
+            update_state.modified.set(true);
+        }
+        total
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_autotile_rebuild")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let mut should_scan = false;
+        let mut should_apply = false;
+
+        egui::Window::new("Rebuild Autotiles")
+            .id(self.id())
+            .open(open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let tilesets = update_state.data.tilesets();
+                let tileset_name = |id: usize| {
+                    format!(
+                        "{:0>4}: {}",
+                        id + 1,
+                        tilesets.data.get(id).map_or("", |t| t.name.as_str())
+                    )
+                };
+
+                let previous_tileset_id = self.tileset_id;
+                egui::ComboBox::from_label("Tileset")
+                    .selected_text(self.tileset_id.map(tileset_name).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for id in 0..tilesets.data.len() {
+                            ui.selectable_value(&mut self.tileset_id, Some(id), tileset_name(id));
+                        }
+                    });
+                if self.tileset_id != previous_tileset_id {
+                    self.scan = None;
+                }
+
+                ui.label(
+                    "Recomputes the autotile variants of every map using the tileset so that they \
+                     match their neighbors again. Use this after changing the autotiles of a \
+                     tileset. The changes can be undone from the tab of each map.",
+                );
+
+                if let Some(scan) = &self.scan {
+                    let map_infos = update_state.data.map_infos();
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .max_height(300.)
+                        .show(ui, |ui| {
+                            egui::Grid::new("luminol_autotile_rebuild_scan")
+                                .striped(true)
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    for &(map_id, count) in scan {
+                                        ui.label(format!(
+                                            "{map_id:0>3}: {}",
+                                            map_infos
+                                                .data
+                                                .get(&map_id)
+                                                .map_or("", |info| info.name.as_str())
+                                        ));
+                                        if count == 0 {
+                                            ui.weak("Up to date");
+                                        } else {
+                                            ui.label(format!("{count} tiles"));
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    if scan.is_empty() {
+                        ui.weak("No maps use this tileset.");
+                    }
+                    ui.separator();
+                }
+
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(self.tileset_id.is_some(), |ui| {
+                        should_scan = ui.button("Scan Maps").clicked();
+                    });
+                    let has_changes = self
+                        .scan
+                        .as_ref()
+                        .is_some_and(|scan| scan.iter().any(|&(_, count)| count > 0));
+                    ui.add_enabled_ui(has_changes, |ui| {
+                        should_apply = ui.button("Apply").clicked();
+                    });
+                });
+            });
+
+        let Some(tileset_id) = self.tileset_id else {
+            return;
+        };
+        if should_scan {
+            self.scan = Some(Self::scan(update_state, tileset_id));
+        }
+        if should_apply {
+            let map_ids = self
+                .scan
+                .iter()
+                .flatten()
+                .filter(|&&(_, count)| count > 0)
+                .map(|&(map_id, _)| map_id)
+                .collect::<Vec<_>>();
+            let total = Self::apply(update_state, &map_ids);
+            luminol_core::info!(
+                update_state.toasts,
+                format!("Rebuilt {total} autotiles in {} maps", map_ids.len())
+            );
+            self.scan = Some(Self::scan(update_state, tileset_id));
+        }
+    }
+}
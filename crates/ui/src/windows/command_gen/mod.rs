@@ -14,10 +14,12 @@
 //
 // You should have received a copy of the GNU General Public License
 // along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
-use super::{CommandDescription, CommandKind, Index, Parameter};
+use luminol_data::commands::{CommandDescription, CommandKind, Index, Parameter};
+use strum::IntoEnumIterator;
 
 use ui_example::UiExample;
 
+pub mod packs;
 pub mod parameter_ui;
 pub mod ui_example;
 
@@ -27,6 +29,19 @@ pub struct CommandGeneratorWindow {
 }
 
 impl CommandGeneratorWindow {
+    pub fn new(update_state: &luminol_core::UpdateState<'_>) -> Self {
+        Self {
+            commands: update_state
+                .project_config
+                .as_ref()
+                .expect("project not loaded")
+                .command_db
+                .user
+                .clone(),
+            ui_examples: Vec::new(),
+        }
+    }
+
     /// Updates all of the parameter indexes, if they are assumed
     fn recalculate_parameter_index(parameter: &mut Parameter, passed_index: &mut u8) {
         match parameter {
@@ -69,17 +84,22 @@ impl CommandGeneratorWindow {
     }
 }
 
-impl luminol_core::window::Window for CommandGeneratorWindow {
-    fn name(&self) -> String {
-        String::from("Luminol Command Maker")
-    }
-
+impl luminol_core::Window for CommandGeneratorWindow {
     fn id(&self) -> egui::Id {
         egui::Id::new("Luminol Command Maker")
     }
 
-    fn show(&mut self, ctx: &egui::Context, open: &mut bool) {
-        egui::Window::new(self.name()).open(open).show(ctx, |ui| {
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        egui::Window::new("Luminol Command Maker").id(self.id()).open(open).show(ctx, |ui| {
             egui::ScrollArea::both().show(ui, |ui| {
                 let mut del_index = None;
                 for (idx, command) in self.commands.iter_mut().enumerate() {
@@ -208,7 +228,14 @@ impl luminol_core::window::Window for CommandGeneratorWindow {
                     }
 
                     if ui.button("Save").clicked() {
-                        command_db!().user = self.commands.clone();
+                        update_state
+                            .project_config
+                            .as_mut()
+                            .expect("project not loaded")
+                            .command_db
+                            .user
+                            .clone_from(&self.commands);
+                        update_state.modified.set(true);
                     }
                 });
             });
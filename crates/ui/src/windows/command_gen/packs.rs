@@ -0,0 +1,442 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use std::io::Write;
+
+use color_eyre::eyre::WrapErr;
+use itertools::Itertools;
+use luminol_config::command_db::{CommandDB, CommandPack, Conflict, InstalledPack};
+
+/// The command pack manager.
+/// Keeps a library of command packs in the global config, so that they can be installed, enabled,
+/// disabled and updated in every project.
+#[derive(Default)]
+pub struct Window {
+    /// The name of the pack made from the project's own commands
+    new_pack_name: String,
+    import_promise: Option<poll_promise::Promise<color_eyre::Result<CommandPack>>>,
+    export_promise: Option<poll_promise::Promise<color_eyre::Result<()>>>,
+}
+
+enum Action {
+    Install(String),
+    Update(String),
+    Uninstall(String),
+    SetEnabled(String, bool),
+    AddToLibrary(String),
+    RemoveFromLibrary(String),
+    Export(String),
+    UpdateAll,
+    PackProjectCommands,
+}
+
+/// Returns true if the error is from the user closing a file picker.
+fn is_cancelled(error: &color_eyre::Report) -> bool {
+    matches!(
+        error.root_cause().downcast_ref(),
+        Some(luminol_filesystem::Error::CancelledLoading)
+    )
+}
+
+/// Returns true if the library has a newer version of an installed pack.
+fn has_update(library: Option<&CommandPack>, installed: Option<&InstalledPack>) -> bool {
+    matches!((library, installed), (Some(library), Some(installed)) if library.version > installed.pack.version)
+}
+
+/// Tells the user that a pack was installed disabled because of its conflicts, if it has any.
+fn warn_conflicts(toasts: &mut luminol_core::Toasts, name: &str, conflicts: &[Conflict]) {
+    if !conflicts.is_empty() {
+        luminol_core::warn!(
+            *toasts,
+            format!(
+                "The command pack {name:?} was disabled because it conflicts with other \
+                 commands:\n{}",
+                conflicts.iter().join("\n")
+            )
+        );
+    }
+}
+
+impl Window {
+    fn pack_ui(
+        ui: &mut egui::Ui,
+        db: &CommandDB,
+        library: Option<&CommandPack>,
+        installed: Option<&InstalledPack>,
+        actions: &mut Vec<Action>,
+    ) {
+        let Some(pack) = installed.map(|p| &p.pack).or(library) else {
+            return;
+        };
+        let name = &pack.name;
+        let conflicts = db.conflicts(pack);
+
+        ui.group(|ui| {
+            ui.set_width(ui.available_width());
+
+            ui.horizontal(|ui| {
+                ui.strong(name);
+                ui.weak(format!("v{}", pack.version));
+
+                let status = match installed {
+                    Some(installed) if installed.enabled => "Enabled",
+                    Some(_) => "Disabled",
+                    None => "Not installed",
+                };
+                ui.label(status);
+                if has_update(library, installed) {
+                    ui.label(
+                        egui::RichText::new("Update available").color(egui::Color32::LIGHT_BLUE),
+                    );
+                }
+            });
+
+            if !pack.description.is_empty() {
+                ui.label(&pack.description);
+            }
+
+            egui::CollapsingHeader::new(format!("{} commands", pack.commands.len()))
+                .id_source(("command_pack", name))
+                .show(ui, |ui| {
+                    for command in &pack.commands {
+                        let response = ui.label(format!("{}: {}", command.code, command.name));
+                        let hover_text = [command.description.as_str(), &command.lumi_text]
+                            .into_iter()
+                            .filter(|text| !text.is_empty())
+                            .join("\n\n");
+                        if !hover_text.is_empty() {
+                            response.on_hover_text(hover_text);
+                        }
+                    }
+                });
+
+            for conflict in &conflicts {
+                ui.colored_label(ui.visuals().error_fg_color, conflict.to_string());
+            }
+
+            ui.horizontal(|ui| {
+                if let Some(installed) = installed {
+                    let mut enabled = installed.enabled;
+                    let response = ui
+                        .add_enabled(
+                            enabled || conflicts.is_empty(),
+                            egui::Checkbox::new(&mut enabled, "Enabled"),
+                        )
+                        .on_disabled_hover_text("The pack conflicts with other commands");
+                    if response.changed() {
+                        actions.push(Action::SetEnabled(name.clone(), enabled));
+                    }
+
+                    if let Some(library) =
+                        library.filter(|library| library.version > installed.pack.version)
+                    {
+                        if ui
+                            .button(format!("Update to v{}", library.version))
+                            .clicked()
+                        {
+                            actions.push(Action::Update(name.clone()));
+                        }
+                    }
+                    if ui.button("Uninstall").clicked() {
+                        actions.push(Action::Uninstall(name.clone()));
+                    }
+                } else if ui
+                    .button("Install")
+                    .on_hover_text("Packs that conflict with other commands are installed disabled")
+                    .clicked()
+                {
+                    actions.push(Action::Install(name.clone()));
+                }
+
+                if library.is_some() {
+                    if ui.button("Export…").clicked() {
+                        actions.push(Action::Export(name.clone()));
+                    }
+                    if ui.button("Remove from Library").clicked() {
+                        actions.push(Action::RemoveFromLibrary(name.clone()));
+                    }
+                } else if ui.button("Add to Library").clicked() {
+                    actions.push(Action::AddToLibrary(name.clone()));
+                }
+            });
+        });
+    }
+
+    fn apply(&mut self, update_state: &mut luminol_core::UpdateState<'_>, action: Action) {
+        let library = &mut update_state.global_config.command_packs;
+        let db = &mut update_state
+            .project_config
+            .as_mut()
+            .expect("project not loaded")
+            .command_db;
+        let library_pack =
+            |library: &[CommandPack], name: &str| library.iter().find(|p| p.name == name).cloned();
+
+        let mut project_modified = true;
+        match action {
+            Action::Install(name) | Action::Update(name) => {
+                if let Some(pack) = library_pack(library, &name) {
+                    let conflicts = db.install(pack);
+                    warn_conflicts(update_state.toasts, &name, &conflicts);
+                }
+            }
+            Action::Uninstall(name) => db.uninstall(&name),
+            Action::SetEnabled(name, enabled) => {
+                if let Some(installed) = db.packs.iter_mut().find(|p| p.pack.name == name) {
+                    installed.enabled = enabled;
+                }
+            }
+            Action::AddToLibrary(name) => {
+                if let Some(installed) = db.installed_pack(&name) {
+                    library.push(installed.pack.clone());
+                }
+                project_modified = false;
+            }
+            Action::RemoveFromLibrary(name) => {
+                library.retain(|p| p.name != name);
+                project_modified = false;
+            }
+            Action::Export(name) => {
+                if let Some(pack) = library_pack(library, &name) {
+                    self.export(pack);
+                }
+                project_modified = false;
+            }
+            Action::UpdateAll => {
+                let updates = library
+                    .iter()
+                    .filter(|p| has_update(Some(p), db.installed_pack(&p.name)))
+                    .cloned()
+                    .collect_vec();
+                for pack in updates {
+                    let name = pack.name.clone();
+                    let conflicts = db.install(pack);
+                    warn_conflicts(update_state.toasts, &name, &conflicts);
+                }
+            }
+            Action::PackProjectCommands => {
+                let name = std::mem::take(&mut self.new_pack_name);
+                let version = library
+                    .iter()
+                    .find(|p| p.name == name)
+                    .map_or(1, |p| p.version + 1);
+                library.retain(|p| p.name != name);
+                library.push(CommandPack {
+                    name,
+                    description: String::new(),
+                    version,
+                    commands: db.user.clone(),
+                });
+                project_modified = false;
+            }
+        }
+
+        if project_modified {
+            update_state.modified.set(true);
+        }
+    }
+
+    fn export(&mut self, pack: CommandPack) {
+        let c = "While exporting the command pack";
+        self.export_promise = Some(luminol_core::spawn_future(async move {
+            let text = pack.to_ron().wrap_err(c)?;
+            let mut file = luminol_filesystem::host::File::new().wrap_err(c)?;
+            file.write_all(text.as_bytes()).wrap_err(c)?;
+            file.flush().wrap_err(c)?;
+            file.save(&format!("{}.ron", pack.name), "Command packs")
+                .await
+                .wrap_err(c)
+        }));
+    }
+
+    fn import(update_state: &mut luminol_core::UpdateState<'_>, pack: CommandPack) {
+        let library = &mut update_state.global_config.command_packs;
+        if let Some(existing) = library.iter_mut().find(|p| p.name == pack.name) {
+            if existing.version > pack.version {
+                luminol_core::warn!(
+                    update_state.toasts,
+                    format!(
+                        "The library already has a newer version of the command pack '{}'",
+                        pack.name
+                    )
+                );
+                return;
+            }
+            luminol_core::info!(
+                update_state.toasts,
+                format!("Replaced the command pack '{}' in the library", pack.name)
+            );
+            *existing = pack;
+        } else {
+            luminol_core::info!(
+                update_state.toasts,
+                format!("Added the command pack '{}' to the library", pack.name)
+            );
+            library.push(pack);
+        }
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("Command Packs")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let mut actions = Vec::new();
+        let mut should_import = false;
+
+        egui::Window::new("Command Packs")
+            .id(self.id())
+            .open(open)
+            .default_width(400.)
+            .show(ctx, |ui| {
+                let library = &update_state.global_config.command_packs;
+                let db = &update_state
+                    .project_config
+                    .as_ref()
+                    .expect("project not loaded")
+                    .command_db;
+
+                ui.label(
+                    "Command packs are sets of custom commands that are shared between projects. \
+                     Packs in the library can be installed in any project, and installed packs \
+                     can be updated whenever the library has a newer version of them.",
+                );
+
+                ui.horizontal(|ui| {
+                    let is_busy = self.import_promise.is_some() || self.export_promise.is_some();
+                    ui.add_enabled_ui(!is_busy, |ui| {
+                        should_import = ui.button("Import…").clicked();
+                    });
+
+                    let has_updates = library
+                        .iter()
+                        .any(|p| has_update(Some(p), db.installed_pack(&p.name)));
+                    if ui
+                        .add_enabled(has_updates, egui::Button::new("Update All"))
+                        .clicked()
+                    {
+                        actions.push(Action::UpdateAll);
+                    }
+
+                    if is_busy {
+                        ui.spinner();
+                    }
+                });
+
+                ui.collapsing("Project commands", |ui| {
+                    ui.label(format!(
+                        "This project has {} commands of its own, made in the command maker. \
+                         They can be added to the library as a pack.",
+                        db.user.len()
+                    ));
+                    ui.horizontal(|ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut self.new_pack_name);
+
+                        let text = if library.iter().any(|p| p.name == self.new_pack_name) {
+                            "Update Pack"
+                        } else {
+                            "Create Pack"
+                        };
+                        let enabled = !self.new_pack_name.is_empty() && !db.user.is_empty();
+                        if ui.add_enabled(enabled, egui::Button::new(text)).clicked() {
+                            actions.push(Action::PackProjectCommands);
+                        }
+                    });
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        let names = library
+                            .iter()
+                            .map(|p| &p.name)
+                            .chain(db.packs.iter().map(|p| &p.pack.name))
+                            .unique()
+                            .sorted()
+                            .collect_vec();
+                        if names.is_empty() {
+                            ui.weak("The library is empty. Import a pack to get started.");
+                        }
+
+                        for name in names {
+                            let library = library.iter().find(|p| &p.name == name);
+                            let installed = db.installed_pack(name);
+                            Self::pack_ui(ui, db, library, installed, &mut actions);
+                        }
+                    });
+            });
+
+        for action in actions {
+            self.apply(update_state, action);
+        }
+
+        if should_import {
+            self.import_promise = Some(luminol_core::spawn_future(async move {
+                let c = "While importing the command pack";
+                let (file, _) =
+                    luminol_filesystem::host::File::from_file_picker("Command packs", &["ron"])
+                        .await
+                        .wrap_err(c)?;
+                let text = std::io::read_to_string(file).wrap_err(c)?;
+                CommandPack::from_ron(&text).wrap_err(c)
+            }));
+        }
+
+        if let Some(p) = self.import_promise.take() {
+            match p.try_take() {
+                Ok(Ok(pack)) => Self::import(update_state, pack),
+                Ok(Err(error)) if !is_cancelled(&error) => {
+                    luminol_core::error!(update_state.toasts, error);
+                }
+                Ok(Err(_)) => {}
+                Err(p) => self.import_promise = Some(p),
+            }
+        }
+
+        if let Some(p) = self.export_promise.take() {
+            match p.try_take() {
+                Ok(Ok(())) => luminol_core::info!(update_state.toasts, "Exported the command pack"),
+                Ok(Err(error)) if !is_cancelled(&error) => {
+                    luminol_core::error!(update_state.toasts, error);
+                }
+                Ok(Err(_)) => {}
+                Err(p) => self.export_promise = Some(p),
+            }
+        }
+    }
+}
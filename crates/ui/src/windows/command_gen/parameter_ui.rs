@@ -22,7 +22,7 @@
 // terms of the Steamworks API by Valve Corporation, the licensors of this
 // Program grant you additional permission to convey the resulting work.
 
-use command_lib::{Index, Parameter, ParameterKind};
+use luminol_data::commands::{Index, Parameter, ParameterKind};
 
 use strum::IntoEnumIterator;
 
@@ -22,7 +22,7 @@
 // terms of the Steamworks API by Valve Corporation, the licensors of this
 // Program grant you additional permission to convey the resulting work.
 
-use command_lib::{CommandDescription, CommandKind, Parameter, ParameterKind};
+use luminol_data::commands::{CommandDescription, CommandKind, Parameter, ParameterKind};
 
 pub struct UiExample {
     command: CommandDescription,
@@ -114,9 +114,43 @@ impl UiExample {
                     ParameterKind::Int => {
                         ui.add(egui::DragValue::new(&mut 0i16));
                     }
-                    ParameterKind::IntBool => {
+                    ParameterKind::IntBool | ParameterKind::Bool => {
                         ui.checkbox(&mut false, "");
                     }
+                    ParameterKind::Actor
+                    | ParameterKind::ActorOrParty
+                    | ParameterKind::Class
+                    | ParameterKind::Skill
+                    | ParameterKind::Item
+                    | ParameterKind::Weapon
+                    | ParameterKind::Armor
+                    | ParameterKind::Enemy
+                    | ParameterKind::Troop
+                    | ParameterKind::State
+                    | ParameterKind::Animation
+                    | ParameterKind::CommonEvent
+                    | ParameterKind::Map => {
+                        ui.button(format!("{}: [000: EXAMPLE]", <&str>::from(&*kind)))
+                            .clicked();
+                    }
+                    ParameterKind::Character => {
+                        ui.menu_button("Player ⏷", |_| {});
+                    }
+                    ParameterKind::AudioFile => {
+                        ui.button("Audio: EXAMPLE, 100, 100").clicked();
+                    }
+                    ParameterKind::Color | ParameterKind::Tone => {
+                        for _ in 0..4 {
+                            ui.add(egui::DragValue::new(&mut 0.0));
+                        }
+                    }
+                    ParameterKind::MoveRoute => {
+                        ui.button("Move Route: 0 move commands").clicked();
+                    }
+                    ParameterKind::StringArray => {
+                        ui.text_edit_singleline(&mut "".to_string());
+                        ui.button("+").clicked();
+                    }
                     ParameterKind::Enum { ref variants } => {
                         let (first_name, mut first_id) = variants.first().unwrap();
                         ui.menu_button(format!("{first_name} ⏷"), |ui| {
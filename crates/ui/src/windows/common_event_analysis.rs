@@ -0,0 +1,670 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+const TRIGGERS: [&str; 3] = ["None", "Autorun", "Parallel"];
+
+/// The name of a common event trigger, which may be out of range in modded or broken data.
+fn trigger_name(trigger: usize) -> &'static str {
+    TRIGGERS.get(trigger).copied().unwrap_or("Unknown")
+}
+
+/// The common event analysis.
+/// Shows which common events call each other and what else calls them, which switches start
+/// autorun and parallel common events, and finds cycles and common events that are never run.
+#[derive(Default)]
+pub struct Window {
+    analysis: Option<Analysis>,
+    /// The index of the selected common event
+    selected: Option<usize>,
+    search: String,
+}
+
+/// Where a common event is called from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CallerKind {
+    /// Another common event, by index
+    CommonEvent(usize),
+    MapEvent {
+        map_id: usize,
+        event_id: usize,
+    },
+    Troop,
+    Item,
+    Skill,
+}
+
+#[derive(Clone, Debug)]
+struct Caller {
+    kind: CallerKind,
+    label: String,
+}
+
+/// The calls between the common events of the project, by the index of the common events.
+struct Analysis {
+    names: Vec<String>,
+    triggers: Vec<usize>,
+    /// The switch ID of autorun and parallel common events
+    switches: Vec<usize>,
+    /// Whether the common event has no commands
+    is_empty: Vec<bool>,
+    /// The common events each common event calls, without duplicates
+    calls: Vec<Vec<usize>>,
+    callers: Vec<Vec<Caller>>,
+    /// Autorun and parallel common events, by the switch that starts them
+    switch_triggers: BTreeMap<usize, Vec<usize>>,
+    switch_names: Vec<String>,
+    /// Each cycle as the path of calls around it, starting and ending at the same common event
+    cycles: Vec<Vec<usize>>,
+    /// Common events that aren't autorun or parallel, and can't be reached by any call from a
+    /// map, troop, item or skill
+    unreachable: Vec<usize>,
+    /// Calls to common events that don't exist, with the ID they call
+    missing: Vec<(Caller, usize)>,
+}
+
+impl Analysis {
+    /// Scans the common events, every map, troop, item and skill for calls to common events.
+    fn new(update_state: &luminol_core::UpdateState<'_>) -> Self {
+        let common_events = update_state.data.common_events();
+        let len = common_events.data.len();
+
+        let mut calls = vec![Vec::new(); len];
+        let mut callers = vec![Vec::new(); len];
+        let mut missing = Vec::new();
+        let mut add_call = |caller: Caller, id: usize| match id.checked_sub(1) {
+            Some(index) if index < len => {
+                if let CallerKind::CommonEvent(from) = caller.kind {
+                    let calls: &mut Vec<usize> = &mut calls[from];
+                    if !calls.contains(&index) {
+                        calls.push(index);
+                    }
+                }
+                callers[index].push(caller);
+            }
+            _ => missing.push((caller, id)),
+        };
+
+        for (index, event) in common_events.data.iter().enumerate() {
+            let label = format!("{:0>4}: {}", index + 1, event.name);
+            for id in event.list.iter().filter_map(|c| c.called_common_event()) {
+                add_call(
+                    Caller {
+                        kind: CallerKind::CommonEvent(index),
+                        label: label.clone(),
+                    },
+                    id,
+                );
+            }
+        }
+
+        let map_infos = update_state.data.map_infos();
+        let config = update_state
+            .project_config
+            .as_ref()
+            .expect("project not loaded");
+        let mut map_ids = map_infos.data.keys().copied().collect::<Vec<_>>();
+        map_ids.sort_unstable();
+        for map_id in map_ids {
+            let map = update_state
+                .data
+                .get_or_load_map(map_id, update_state.filesystem, config);
+            for (event_id, event) in map.events.iter() {
+                for (page, event_page) in event.pages.iter().enumerate() {
+                    for id in event_page
+                        .list
+                        .iter()
+                        .filter_map(|c| c.called_common_event())
+                    {
+                        add_call(
+                            Caller {
+                                kind: CallerKind::MapEvent { map_id, event_id },
+                                label: format!(
+                                    "Map {map_id:0>3}: {}, EV{event_id:0>3} {} page {}",
+                                    map_infos.data[&map_id].name,
+                                    event.name,
+                                    page + 1
+                                ),
+                            },
+                            id,
+                        );
+                    }
+                }
+            }
+        }
+        drop(map_infos);
+
+        let troops = update_state.data.troops();
+        for troop in &troops.data {
+            for (page, troop_page) in troop.pages.iter().enumerate() {
+                for id in troop_page
+                    .list
+                    .iter()
+                    .filter_map(|c| c.called_common_event())
+                {
+                    add_call(
+                        Caller {
+                            kind: CallerKind::Troop,
+                            label: format!(
+                                "Troop {:0>4}: {} page {}",
+                                troop.id + 1,
+                                troop.name,
+                                page + 1
+                            ),
+                        },
+                        id,
+                    );
+                }
+            }
+        }
+        drop(troops);
+
+        // Items and skills store the index of the common event they call
+        let items = update_state.data.items();
+        for item in &items.data {
+            if let Some(index) = item.common_event_id {
+                add_call(
+                    Caller {
+                        kind: CallerKind::Item,
+                        label: format!("Item {:0>4}: {}", item.id + 1, item.name),
+                    },
+                    index + 1,
+                );
+            }
+        }
+        drop(items);
+        let skills = update_state.data.skills();
+        for skill in &skills.data {
+            if let Some(index) = skill.common_event_id {
+                add_call(
+                    Caller {
+                        kind: CallerKind::Skill,
+                        label: format!("Skill {:0>4}: {}", skill.id + 1, skill.name),
+                    },
+                    index + 1,
+                );
+            }
+        }
+        drop(skills);
+
+        for calls in &mut calls {
+            calls.sort_unstable();
+        }
+
+        let mut switch_triggers = BTreeMap::<usize, Vec<usize>>::new();
+        for (index, event) in common_events.data.iter().enumerate() {
+            if event.trigger != 0 {
+                switch_triggers
+                    .entry(event.switch_id)
+                    .or_default()
+                    .push(index);
+            }
+        }
+
+        let is_empty = common_events
+            .data
+            .iter()
+            .map(|event| event.list.iter().all(|command| command.code == 0))
+            .collect::<Vec<_>>();
+
+        // Autorun and parallel common events run on their own, everything else has to be called
+        // from outside the common events to run
+        let mut reachable = vec![false; len];
+        let mut queue = (0..len)
+            .filter(|&index| {
+                common_events.data[index].trigger != 0
+                    || callers[index]
+                        .iter()
+                        .any(|caller| !matches!(caller.kind, CallerKind::CommonEvent(_)))
+            })
+            .collect::<VecDeque<_>>();
+        for &index in &queue {
+            reachable[index] = true;
+        }
+        while let Some(index) = queue.pop_front() {
+            for &callee in &calls[index] {
+                if !std::mem::replace(&mut reachable[callee], true) {
+                    queue.push_back(callee);
+                }
+            }
+        }
+        let unreachable = (0..len)
+            .filter(|&index| !reachable[index] && !is_empty[index])
+            .collect();
+
+        let cycles = strongly_connected_components(&calls)
+            .into_iter()
+            .filter(|component| component.len() > 1 || calls[component[0]].contains(&component[0]))
+            .map(|component| shortest_cycle(&calls, &component))
+            .collect();
+
+        Self {
+            names: common_events
+                .data
+                .iter()
+                .map(|event| event.name.clone())
+                .collect(),
+            triggers: common_events
+                .data
+                .iter()
+                .map(|event| event.trigger)
+                .collect(),
+            switches: common_events
+                .data
+                .iter()
+                .map(|event| event.switch_id)
+                .collect(),
+            is_empty,
+            calls,
+            callers,
+            switch_triggers,
+            switch_names: update_state.data.system().switches.clone(),
+            cycles,
+            unreachable,
+            missing,
+        }
+    }
+
+    fn name(&self, index: usize) -> String {
+        format!(
+            "{:0>4}: {}",
+            index + 1,
+            self.names.get(index).map_or("", String::as_str)
+        )
+    }
+
+    /// The name of a switch by its ID. Common events store the ID as it is in RMXP, starting at 1.
+    fn switch_name(&self, id: usize) -> String {
+        format!(
+            "{id:0>4}: {}",
+            id.checked_sub(1)
+                .and_then(|index| self.switch_names.get(index))
+                .map_or("", String::as_str)
+        )
+    }
+
+    fn in_cycle(&self, index: usize) -> bool {
+        self.cycles.iter().any(|cycle| cycle.contains(&index))
+    }
+}
+
+/// Finds the strongly connected components of the call graph with Tarjan's algorithm. Each
+/// component is sorted.
+fn strongly_connected_components(calls: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        calls: &'a [Vec<usize>],
+        next_index: usize,
+        indices: Vec<Option<usize>>,
+        low_links: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(state: &mut State<'_>, node: usize) {
+        state.indices[node] = Some(state.next_index);
+        state.low_links[node] = state.next_index;
+        state.next_index += 1;
+        state.stack.push(node);
+        state.on_stack[node] = true;
+
+        for &next in &state.calls[node] {
+            match state.indices[next] {
+                None => {
+                    visit(state, next);
+                    state.low_links[node] = state.low_links[node].min(state.low_links[next]);
+                }
+                Some(index) if state.on_stack[next] => {
+                    state.low_links[node] = state.low_links[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(state.low_links[node]) == state.indices[node] {
+            let mut component = Vec::new();
+            while let Some(member) = state.stack.pop() {
+                state.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            component.sort_unstable();
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        calls,
+        next_index: 0,
+        indices: vec![None; calls.len()],
+        low_links: vec![0; calls.len()],
+        stack: Vec::new(),
+        on_stack: vec![false; calls.len()],
+        components: Vec::new(),
+    };
+    for node in 0..calls.len() {
+        if state.indices[node].is_none() {
+            visit(&mut state, node);
+        }
+    }
+    state.components.sort_unstable();
+    state.components
+}
+
+/// Finds the shortest cycle through the first common event of a strongly connected component,
+/// staying inside the component.
+fn shortest_cycle(calls: &[Vec<usize>], component: &[usize]) -> Vec<usize> {
+    let start = component[0];
+    let members = component.iter().copied().collect::<HashSet<_>>();
+    let mut previous = vec![None; calls.len()];
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for &next in &calls[node] {
+            if next == start {
+                let mut path = vec![node];
+                let mut current = node;
+                while let Some(before) = previous[current] {
+                    path.push(before);
+                    current = before;
+                }
+                path.reverse();
+                return path;
+            }
+            if members.contains(&next) && previous[next].is_none() {
+                previous[next] = Some(node);
+                queue.push_back(next);
+            }
+        }
+    }
+    component.to_vec()
+}
+
+impl Window {
+    fn list_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(analysis) = &self.analysis else {
+            return;
+        };
+
+        ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("Search 🔎"));
+        let search = self.search.to_lowercase();
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for index in 0..analysis.names.len() {
+                    let name = analysis.name(index);
+                    if !search.is_empty() && !name.to_lowercase().contains(&search) {
+                        continue;
+                    }
+
+                    let mut text = name;
+                    if analysis.triggers[index] != 0 {
+                        text.push_str(&format!(" [{}]", trigger_name(analysis.triggers[index])));
+                    }
+                    let text = if analysis.unreachable.contains(&index) {
+                        egui::RichText::new(text).color(egui::Color32::from_rgb(255, 160, 0))
+                    } else if analysis.in_cycle(index) {
+                        egui::RichText::new(text).color(ui.visuals().error_fg_color)
+                    } else if analysis.is_empty[index] {
+                        egui::RichText::new(text).weak()
+                    } else {
+                        egui::RichText::new(text)
+                    };
+                    if ui
+                        .selectable_label(self.selected == Some(index), text)
+                        .clicked()
+                    {
+                        self.selected = Some(index);
+                    }
+                }
+            });
+    }
+
+    fn details_ui(&mut self, ui: &mut egui::Ui, open_event: &mut Option<(usize, usize)>) {
+        let Some(analysis) = &self.analysis else {
+            return;
+        };
+
+        let mut selected = self.selected;
+        let mut follow = None;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                if let Some(index) = self.selected.filter(|&i| i < analysis.names.len()) {
+                    ui.heading(analysis.name(index));
+
+                    match analysis.triggers[index] {
+                        0 => ui.label("Runs only when called"),
+                        trigger => ui.label(format!(
+                            "{} while switch {} is on",
+                            trigger_name(trigger),
+                            analysis.switch_name(analysis.switches[index])
+                        )),
+                    };
+                    if analysis.unreachable.contains(&index) {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 160, 0),
+                            "Unreachable: nothing outside the common events leads to a call",
+                        );
+                    }
+                    for cycle in analysis.cycles.iter().filter(|c| c.contains(&index)) {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("In a cycle: {}", cycle_label(cycle)),
+                        );
+                    }
+
+                    ui.add_space(4.);
+                    ui.label(egui::RichText::new("Calls").strong());
+                    if analysis.calls[index].is_empty() {
+                        ui.weak("No other common events");
+                    }
+                    for &callee in &analysis.calls[index] {
+                        if ui.link(analysis.name(callee)).clicked() {
+                            selected = Some(callee);
+                        }
+                    }
+
+                    ui.add_space(4.);
+                    ui.label(egui::RichText::new("Called by").strong());
+                    if analysis.callers[index].is_empty() {
+                        ui.weak("Nothing");
+                    }
+                    // A caller calling more than once is only listed once
+                    let mut seen = HashSet::new();
+                    for caller in &analysis.callers[index] {
+                        if seen.insert(&caller.label) && caller_ui(ui, caller) {
+                            follow = Some(caller.kind.clone());
+                        }
+                    }
+                } else {
+                    ui.weak("Click on a common event to list its calls");
+                }
+
+                ui.separator();
+
+                ui.collapsing(
+                    format!("Switch triggers ({})", analysis.switch_triggers.len()),
+                    |ui| {
+                        for (&switch, events) in &analysis.switch_triggers {
+                            ui.label(format!("Switch {}", analysis.switch_name(switch)));
+                            ui.indent("events", |ui| {
+                                for &index in events {
+                                    let text = format!(
+                                        "{} [{}]",
+                                        analysis.name(index),
+                                        trigger_name(analysis.triggers[index])
+                                    );
+                                    if ui.link(text).clicked() {
+                                        selected = Some(index);
+                                    }
+                                }
+                            });
+                        }
+                    },
+                );
+
+                ui.collapsing(format!("Cycles ({})", analysis.cycles.len()), |ui| {
+                    for cycle in &analysis.cycles {
+                        if ui.link(cycle_label(cycle)).clicked() {
+                            selected = Some(cycle[0]);
+                        }
+                    }
+                })
+                .header_response
+                .on_hover_text("Common events that can end up calling themselves");
+
+                ui.collapsing(
+                    format!("Unreachable ({})", analysis.unreachable.len()),
+                    |ui| {
+                        for &index in &analysis.unreachable {
+                            if ui.link(analysis.name(index)).clicked() {
+                                selected = Some(index);
+                            }
+                        }
+                    },
+                )
+                .header_response
+                .on_hover_text(
+                    "Common events that aren't autorun or parallel, and aren't called by any map, \
+                     troop, item or skill, even through other common events. Calls from scripts \
+                     aren't detected, and empty common events aren't listed.",
+                );
+
+                if !analysis.missing.is_empty() {
+                    ui.collapsing(
+                        format!(
+                            "Calls to missing common events ({})",
+                            analysis.missing.len()
+                        ),
+                        |ui| {
+                            for (caller, id) in &analysis.missing {
+                                ui.horizontal(|ui| {
+                                    if caller_ui(ui, caller) {
+                                        follow = Some(caller.kind.clone());
+                                    }
+                                    ui.label(format!("calls {id:0>4}"));
+                                });
+                            }
+                        },
+                    );
+                }
+            });
+
+        match follow {
+            Some(CallerKind::CommonEvent(index)) => selected = Some(index),
+            Some(CallerKind::MapEvent { map_id, event_id }) => {
+                *open_event = Some((map_id, event_id));
+            }
+            _ => {}
+        }
+        self.selected = selected;
+    }
+}
+
+/// Shows where a common event is called from, as a link if it can be opened. Returns `true` if
+/// the link was clicked.
+fn caller_ui(ui: &mut egui::Ui, caller: &Caller) -> bool {
+    match caller.kind {
+        CallerKind::CommonEvent(_) => ui.link(&caller.label).clicked(),
+        CallerKind::MapEvent { .. } => ui
+            .link(&caller.label)
+            .on_hover_text("Open the event in its map")
+            .clicked(),
+        CallerKind::Troop | CallerKind::Item | CallerKind::Skill => {
+            ui.label(&caller.label);
+            false
+        }
+    }
+}
+
+fn cycle_label(cycle: &[usize]) -> String {
+    cycle
+        .iter()
+        .chain(cycle.first())
+        .map(|index| format!("{:0>4}", index + 1))
+        .collect::<Vec<_>>()
+        .join(" → ")
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_common_event_analysis")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        if self.analysis.is_none() {
+            self.analysis = Some(Analysis::new(update_state));
+        }
+
+        let mut open_event = None;
+
+        egui::Window::new("Common Event Analysis")
+            .id(self.id())
+            .open(open)
+            .default_size([700., 500.])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Refresh")
+                        .on_hover_text("Scan the project for calls again")
+                        .clicked()
+                    {
+                        self.analysis = Some(Analysis::new(update_state));
+                    }
+                    if let Some(analysis) = &self.analysis {
+                        ui.weak(format!(
+                            "{} cycles, {} unreachable. Red: in a cycle, orange: unreachable",
+                            analysis.cycles.len(),
+                            analysis.unreachable.len()
+                        ));
+                    }
+                });
+
+                egui::SidePanel::left("luminol_common_event_analysis_list")
+                    .default_width(250.)
+                    .show_inside(ui, |ui| self.list_ui(ui));
+
+                egui::CentralPanel::default()
+                    .show_inside(ui, |ui| self.details_ui(ui, &mut open_event));
+            });
+
+        if let Some((map_id, event_id)) = open_event {
+            super::world_graph::Window::open_event(update_state, map_id, event_id);
+        }
+    }
+}
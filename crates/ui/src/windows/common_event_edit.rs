@@ -28,14 +28,14 @@ use luminol_modals::database_modal;
 /// The common event editor.
 pub struct Window {
     tabs: luminol_core::Tabs,
-    _selected_id: usize,
+    selected_id: usize,
 }
 
 impl Default for Window {
     fn default() -> Self {
         Self {
             tabs: luminol_core::Tabs::new("common_event_tabs", false),
-            _selected_id: 0,
+            selected_id: 0,
         }
     }
 }
@@ -49,7 +49,7 @@ impl luminol_core::Window for Window {
         &mut self,
         ctx: &egui::Context,
         open: &mut bool,
-        _update_state: &mut luminol_core::UpdateState<'_>,
+        update_state: &mut luminol_core::UpdateState<'_>,
     ) {
         let name = self
             .tabs
@@ -61,8 +61,37 @@ impl luminol_core::Window for Window {
             .default_width(500.)
             .id(egui::Id::new("common_events_edit"))
             .open(open)
-            .show(ctx, |_| {
-                // TODO
+            .show(ctx, |ui| {
+                egui::SidePanel::left("common_event_edit_list_panel").show_inside(ui, |ui| {
+                    egui::ScrollArea::both()
+                        .id_source(
+                            update_state
+                                .project_config
+                                .as_ref()
+                                .expect("project not loaded")
+                                .project
+                                .persistence_id,
+                        )
+                        .auto_shrink([false; 2])
+                        .show(ui, |ui| {
+                            let common_events = update_state.data.common_events();
+
+                            for event in &common_events.data {
+                                let response = ui.selectable_label(
+                                    self.selected_id == event.id,
+                                    format!("{:0>4}: {}", event.id, event.name),
+                                );
+                                if response.clicked() {
+                                    self.selected_id = event.id;
+                                }
+                                if response.double_clicked() {
+                                    self.tabs.add_tab(CommonEventTab::new(event.clone()));
+                                }
+                            }
+                        });
+                });
+
+                self.tabs.ui(ui, update_state);
             });
     }
 
@@ -78,6 +107,18 @@ pub struct CommonEventTab {
     command_view: luminol_components::CommandView,
 }
 
+impl CommonEventTab {
+    pub fn new(event: luminol_data::rpg::CommonEvent) -> Self {
+        let id = egui::Id::new("luminol_common_event").with(event.id);
+        Self {
+            event,
+            force_close: false,
+            switch_modal: database_modal::SwitchModal::new(id.with("switch_modal")),
+            command_view: luminol_components::CommandView::new(id.with("commands")),
+        }
+    }
+}
+
 impl luminol_core::Tab for CommonEventTab {
     fn name(&self, _update_state: &luminol_core::UpdateState<'_>) -> String {
         format!("{}: {}", self.event.name, self.event.id)
@@ -124,8 +165,26 @@ impl luminol_core::Tab for CommonEventTab {
                 save_event = true;
             }
 
+            if ui
+                .button("Debug")
+                .on_hover_text("Run this common event in the event debugger")
+                .clicked()
+            {
+                let debugger = crate::windows::event_debugger::Window::new(
+                    update_state,
+                    egui::Id::new("luminol_common_event_debugger").with(self.event.id),
+                    format!("Common Event {:0>4}: {}", self.event.id, self.event.name),
+                    self.event.list.clone(),
+                    luminol_components::interpreter::Context::default(),
+                );
+                update_state.edit_windows.add_window(debugger);
+            }
+
             if save_event {
+                update_state.modified.set(true);
+
                 let mut common_events = update_state.data.common_events();
+                common_events.modified = true;
 
                 common_events.data[self.event.id - 1] = self.event.clone();
             }
@@ -139,11 +198,7 @@ impl luminol_core::Tab for CommonEventTab {
         egui::ScrollArea::both()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                self.command_view.ui(
-                    ui,
-                    &update_state.project_config.as_ref().unwrap().command_db,
-                    &mut self.event.list,
-                );
+                self.command_view.ui(ui, update_state, &mut self.event.list);
             });
     }
 
@@ -0,0 +1,481 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use luminol_components::dungeon::{Algorithm, Cell, DungeonTiles, GeneratorOptions, Layout};
+use strum::IntoEnumIterator;
+
+use crate::tabs::map::{history_scope, recompute_autotile, HistoryEntry};
+
+/// The dungeon generator.
+/// Generates rooms, caves and mazes from a seed and paints them with tiles from the tileset of a
+/// map, either into a new map or into an area of the map.
+pub struct Window {
+    map_id: Option<usize>,
+    tilepicker: Option<(usize, luminol_components::Tilepicker)>,
+    tiles: DungeonTiles,
+    options: GeneratorOptions,
+    target: Target,
+
+    new_map_name: String,
+    new_map_size: (usize, usize),
+    /// (x, y, width, height) of the area selected in the map's tab, which is generated into.
+    area: Option<(usize, usize, usize, usize)>,
+
+    preview: Option<(PreviewKey, egui::TextureHandle)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Target {
+    NewMap,
+    Area,
+}
+
+type PreviewKey = (GeneratorOptions, usize, usize);
+
+impl Default for Window {
+    fn default() -> Self {
+        Self {
+            map_id: None,
+            tilepicker: None,
+            tiles: DungeonTiles::default(),
+            options: GeneratorOptions::default(),
+            target: Target::NewMap,
+            new_map_name: "Dungeon".to_string(),
+            new_map_size: (40, 30),
+            area: None,
+            preview: None,
+        }
+    }
+}
+
+/// Paints a layout onto a map with its top left corner at (x, y), clearing the upper layers, and
+/// then recomputes the autotiles in and around the painted area. Returns the old tiles of every
+/// changed position for each layer.
+fn paint_layout(
+    map: &mut luminol_data::rpg::Map,
+    layout: &Layout,
+    tiles: &DungeonTiles,
+    (x, y): (usize, usize),
+) -> Vec<Vec<(usize, usize, i16)>> {
+    let (xsize, ysize, zsize) = (map.data.xsize(), map.data.ysize(), map.data.zsize());
+    let min_x = x.saturating_sub(1);
+    let min_y = y.saturating_sub(1);
+    let max_x = (x + layout.width + 1).min(xsize);
+    let max_y = (y + layout.height + 1).min(ysize);
+
+    let old_tiles = (0..zsize)
+        .map(|z| {
+            (min_y..max_y)
+                .flat_map(|y| (min_x..max_x).map(move |x| (x, y)))
+                .map(|(x, y)| map.data[(x, y, z)])
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for layout_y in 0..layout.height.min(ysize.saturating_sub(y)) {
+        for layout_x in 0..layout.width.min(xsize.saturating_sub(x)) {
+            let position = (x + layout_x, y + layout_y);
+            map.data[(position.0, position.1, 0)] = layout.tile_id(layout_x, layout_y, tiles);
+            for z in 1..zsize {
+                map.data[(position.0, position.1, z)] = 0;
+            }
+        }
+    }
+
+    // Autotiles on the border of the area may need a different variant now too
+    for z in 0..zsize {
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                map.data[(x, y, z)] = recompute_autotile(map, (x, y, z));
+            }
+        }
+    }
+
+    old_tiles
+        .into_iter()
+        .enumerate()
+        .map(|(z, old_tiles)| {
+            (min_y..max_y)
+                .flat_map(|y| (min_x..max_x).map(move |x| (x, y)))
+                .zip(old_tiles)
+                .filter(|&((x, y), old_id)| map.data[(x, y, z)] != old_id)
+                .map(|((x, y), old_id)| (x, y, old_id))
+                .collect()
+        })
+        .collect()
+}
+
+/// Describes a tile for the tile slots.
+fn tile_name(id: i16) -> String {
+    match id {
+        0..=47 => "(None)".to_string(),
+        48..=383 => format!("Autotile {}", id / 48),
+        _ => format!("Tile {}", id - 384),
+    }
+}
+
+impl Window {
+    fn size(&self) -> (usize, usize) {
+        match self.target {
+            Target::NewMap => self.new_map_size,
+            Target::Area => self
+                .area
+                .map_or((0, 0), |(_, _, width, height)| (width, height)),
+        }
+    }
+
+    fn generate_into_new_map(
+        &self,
+        update_state: &mut luminol_core::UpdateState<'_>,
+        tileset_id: usize,
+        layout: &Layout,
+    ) -> usize {
+        let mut map_infos = update_state.data.map_infos();
+        let id = map_infos.data.keys().max().map_or(1, |id| id + 1);
+        let order = map_infos
+            .data
+            .values()
+            .map(|info| info.order)
+            .max()
+            .map_or(1, |order| order + 1);
+        drop(map_infos);
+
+        let (width, height) = self.new_map_size;
+        let mut map = luminol_data::rpg::Map {
+            tileset_id,
+            width,
+            height,
+            encounter_step: 30,
+            data: luminol_data::Table3::new(width, height, 3),
+            ..Default::default()
+        };
+        paint_layout(&mut map, layout, &self.tiles, (0, 0));
+
+        let info = luminol_data::rpg::MapInfo {
+            name: self.new_map_name.clone(),
+            order,
+            ..Default::default()
+        };
+        update_state.data.add_map(id, info, map);
+        update_state.modified.set(true);
+        id
+    }
+
+    fn generate_into_area(
+        &self,
+        update_state: &mut luminol_core::UpdateState<'_>,
+        map_id: usize,
+        layout: &Layout,
+    ) {
+        let Some((x, y, ..)) = self.area else {
+            return;
+        };
+        let mut map = update_state.data.get_map(map_id);
+        let deltas = paint_layout(&mut map, layout, &self.tiles, (x, y));
+        update_state.history.push(
+            history_scope(map_id),
+            HistoryEntry::TileLayers {
+                map_id,
+                description: "generate dungeon",
+                deltas,
+            },
+        );
+        map.modified = true;
+        update_state.modified.set(true);
+    }
+
+    fn preview_texture(&mut self, ctx: &egui::Context) -> Option<&egui::TextureHandle> {
+        let (width, height) = self.size();
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let key = (self.options.clone(), width, height);
+        if self.preview.as_ref().map_or(true, |(k, _)| *k != key) {
+            let layout = Layout::generate(&self.options, width, height);
+            let pixels = layout
+                .cells
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Floor => egui::Color32::from_gray(200),
+                    Cell::Wall => egui::Color32::from_rgb(120, 90, 60),
+                    Cell::Ceiling => egui::Color32::from_gray(30),
+                })
+                .collect();
+            let image = egui::ColorImage {
+                size: [width, height],
+                pixels,
+            };
+            let texture = ctx.load_texture(
+                "luminol_dungeon_preview",
+                image,
+                egui::TextureOptions::NEAREST,
+            );
+            self.preview = Some((key, texture));
+        }
+        self.preview.as_ref().map(|(_, texture)| texture)
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_dungeon_generator")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let mut should_generate = false;
+        let mut tileset_id = None;
+
+        egui::Window::new("Dungeon Generator")
+            .id(self.id())
+            .open(open)
+            .default_width(560.)
+            .show(ctx, |ui| {
+                {
+                    let map_infos = update_state.data.map_infos();
+                    let mut map_ids = map_infos.data.keys().copied().collect::<Vec<_>>();
+                    map_ids.sort_unstable();
+                    let map_name = |id: usize| {
+                        format!(
+                            "{id:0>3}: {}",
+                            map_infos
+                                .data
+                                .get(&id)
+                                .map_or("", |info| info.name.as_str())
+                        )
+                    };
+
+                    egui::ComboBox::from_label("Map")
+                        .selected_text(self.map_id.map(map_name).unwrap_or_default())
+                        .show_ui(ui, |ui| {
+                            for &id in map_ids.iter() {
+                                ui.selectable_value(&mut self.map_id, Some(id), map_name(id));
+                            }
+                        });
+                }
+
+                let Some(map_id) = self.map_id else {
+                    ui.label("Choose a map. Its tileset is used to paint the generated tiles.");
+                    return;
+                };
+
+                if self.tilepicker.as_ref().map(|(id, _)| *id) != Some(map_id) {
+                    self.tilepicker = Some((
+                        map_id,
+                        luminol_components::Tilepicker::new(update_state, map_id),
+                    ));
+                }
+                tileset_id = Some(update_state.data.get_map(map_id).tileset_id);
+
+                ui.columns(2, |columns| {
+                    let (tilepicker_id, tilepicker) =
+                        self.tilepicker.as_mut().expect("tilepicker not loaded");
+                    egui::ScrollArea::both()
+                        .id_source(*tilepicker_id)
+                        .max_height(400.)
+                        .show_viewport(&mut columns[0], |ui, rect| {
+                            tilepicker.ui(update_state, ui, rect);
+                        });
+
+                    let ui = &mut columns[1];
+                    let selected_tile = tilepicker
+                        .selected_tiles()
+                        .next()
+                        .map_or(0, |tile| tile.to_id());
+
+                    egui::Grid::new("luminol_dungeon_generator_tiles")
+                        .num_columns(3)
+                        .show(ui, |ui| {
+                            for (label, tile) in [
+                                ("Floor", &mut self.tiles.floor),
+                                ("Wall", &mut self.tiles.wall),
+                                ("Ceiling", &mut self.tiles.ceiling),
+                            ] {
+                                ui.label(label);
+                                ui.label(tile_name(*tile));
+                                if ui
+                                    .button("Use selected")
+                                    .on_hover_text("Use the tile selected in the tilepicker")
+                                    .clicked()
+                                {
+                                    *tile = selected_tile;
+                                }
+                                ui.end_row();
+                            }
+                        });
+
+                    ui.separator();
+
+                    egui::ComboBox::from_label("Algorithm")
+                        .selected_text(self.options.algorithm.to_string())
+                        .show_ui(ui, |ui| {
+                            for algorithm in Algorithm::iter() {
+                                ui.selectable_value(
+                                    &mut self.options.algorithm,
+                                    algorithm,
+                                    algorithm.to_string(),
+                                );
+                            }
+                        });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Seed");
+                        ui.add(egui::DragValue::new(&mut self.options.seed));
+                        if ui.button("🎲").on_hover_text("Random seed").clicked() {
+                            self.options.seed = rand::random();
+                        }
+                    });
+
+                    match self.options.algorithm {
+                        Algorithm::BspRooms => {
+                            ui.add(
+                                egui::Slider::new(&mut self.options.min_room_size, 2..=16)
+                                    .text("Minimum room size"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut self.options.corridor_width, 1..=4)
+                                    .text("Corridor width"),
+                            );
+                        }
+                        Algorithm::Caves => {
+                            ui.add(
+                                egui::Slider::new(&mut self.options.cave_fill, 0.3..=0.6)
+                                    .text("Fill"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut self.options.cave_iterations, 0..=10)
+                                    .text("Smoothing steps"),
+                            );
+                        }
+                        Algorithm::Maze => {
+                            ui.add(
+                                egui::Slider::new(&mut self.options.corridor_width, 1..=4)
+                                    .text("Corridor width"),
+                            );
+                        }
+                    }
+                    ui.add(
+                        egui::Slider::new(&mut self.options.wall_height, 0..=4).text("Wall height"),
+                    );
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.target, Target::NewMap, "New map");
+                        ui.radio_value(&mut self.target, Target::Area, "Area of this map");
+                    });
+                    match self.target {
+                        Target::NewMap => {
+                            ui.horizontal(|ui| {
+                                ui.label("Name");
+                                ui.text_edit_singleline(&mut self.new_map_name);
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Size");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.new_map_size.0).range(20..=500),
+                                );
+                                ui.label("×");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.new_map_size.1).range(15..=500),
+                                );
+                            });
+                        }
+                        Target::Area => {
+                            self.area = crate::tabs::map::selected_area(ui.ctx(), map_id);
+                            match self.area {
+                                Some((x, y, width, height)) => {
+                                    ui.label(format!("{width}×{height} tiles at ({x}, {y})"))
+                                }
+                                None => ui.weak(
+                                    "Open the map and shift-drag on one of its tile layers to \
+                                     select an area",
+                                ),
+                            };
+                        }
+                    }
+
+                    ui.separator();
+
+                    if let Some(texture) = self.preview_texture(ui.ctx()) {
+                        let size = texture.size_vec2();
+                        let scale = (240. / size.x).min(180. / size.y);
+                        ui.image((texture.id(), size * scale));
+                    }
+
+                    let has_tiles =
+                        self.tiles.floor >= 48 && self.tiles.wall >= 48 && self.tiles.ceiling >= 48;
+                    let has_area = self.target == Target::NewMap || self.area.is_some();
+                    ui.add_enabled_ui(has_tiles && has_area, |ui| {
+                        should_generate = ui
+                            .button("Generate")
+                            .on_disabled_hover_text(if has_tiles {
+                                "Select an area of the map"
+                            } else {
+                                "Choose the floor, wall and ceiling tiles"
+                            })
+                            .clicked();
+                    });
+                });
+            });
+
+        let (Some(map_id), Some(tileset_id)) = (self.map_id, tileset_id) else {
+            return;
+        };
+        if !should_generate {
+            return;
+        }
+
+        let (width, height) = self.size();
+        let layout = Layout::generate(&self.options, width, height);
+        match self.target {
+            Target::NewMap => {
+                let new_map_id = self.generate_into_new_map(update_state, tileset_id, &layout);
+                match crate::tabs::map::Tab::new(new_map_id, update_state) {
+                    Ok(tab) => update_state.edit_tabs.add_tab(tab),
+                    Err(e) => luminol_core::error!(
+                        update_state.toasts,
+                        e.wrap_err(format!("Error opening map {new_map_id}"))
+                    ),
+                }
+            }
+            Target::Area => {
+                self.generate_into_area(update_state, map_id, &layout);
+                luminol_core::info!(
+                    update_state.toasts,
+                    format!("Generated a {width}×{height} area into map {map_id}")
+                );
+            }
+        }
+    }
+}
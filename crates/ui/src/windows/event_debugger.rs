@@ -0,0 +1,465 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use luminol_components::interpreter::{
+    Answer, BattleResult, Context, GameState, Input, Interpreter, Status, TraceKind,
+};
+use luminol_data::rpg::EventCommand;
+use strum::IntoEnumIterator;
+
+/// How many lines Run executes before giving up, so that an endless loop can't freeze the editor.
+const MAX_STEPS: usize = 10_000;
+
+/// The buttons Button Input Processing can be answered with, and their codes.
+const BUTTONS: [(&str, i32); 9] = [
+    ("None", 0),
+    ("Down", 2),
+    ("Left", 4),
+    ("Right", 6),
+    ("Up", 8),
+    ("A", 11),
+    ("B", 12),
+    ("C", 13),
+    ("X", 14),
+];
+
+/// The event debugger.
+/// Runs a command list in the headless interpreter against a simulated game state, one line at a
+/// time or up to a breakpoint, and shows which branches were taken.
+pub struct Window {
+    id: egui::Id,
+    title: String,
+    list: Vec<EventCommand>,
+    interpreter: Interpreter,
+    status: Status,
+    /// The state the list starts running from
+    initial_state: GameState,
+    state: GameState,
+    /// The answer being entered for Input Number
+    number: i32,
+}
+
+impl Window {
+    /// Creates a debugger for a command list. The list starts running from the page simulation's
+    /// switches, variables and self switches if the simulation is turned on.
+    pub fn new(
+        update_state: &luminol_core::UpdateState<'_>,
+        id: egui::Id,
+        title: impl Into<String>,
+        list: Vec<EventCommand>,
+        context: Context,
+    ) -> Self {
+        let initial_state = update_state
+            .toolbar
+            .page_simulation
+            .as_ref()
+            .map(simulated_state)
+            .unwrap_or_default();
+        Self {
+            id,
+            title: title.into(),
+            interpreter: Interpreter::new(list.clone(), context),
+            list,
+            status: Status::Running,
+            state: initial_state.clone(),
+            initial_state,
+            number: 0,
+        }
+    }
+
+    /// Starts the list over from the initial state, keeping the breakpoints.
+    fn reset(&mut self) {
+        let breakpoints = std::mem::take(&mut self.interpreter.breakpoints);
+        self.interpreter = Interpreter::new(self.list.clone(), self.interpreter.context());
+        self.interpreter.breakpoints = breakpoints;
+        self.state = self.initial_state.clone();
+        self.status = Status::Running;
+    }
+
+    fn status_text(&self) -> String {
+        match &self.status {
+            Status::Running if self.interpreter.steps() == 0 => "Ready".to_string(),
+            Status::Running => "Paused".to_string(),
+            Status::Breakpoint => "Stopped at a breakpoint".to_string(),
+            Status::Waiting(_) => "Waiting for input".to_string(),
+            Status::StepLimit => {
+                format!("Stopped after {MAX_STEPS} lines, the list may loop forever")
+            }
+            Status::Finished => "Finished".to_string(),
+        }
+    }
+
+    fn input_ui(&mut self, ui: &mut egui::Ui, input: &Input) {
+        let mut answer = None;
+        ui.horizontal_wrapped(|ui| match input {
+            Input::Choice {
+                choices,
+                can_cancel,
+            } => {
+                ui.label("Choose:");
+                for (index, choice) in choices.iter().enumerate() {
+                    if ui.button(choice).clicked() {
+                        answer = Some(Answer::Choice(index));
+                    }
+                }
+                if ui
+                    .add_enabled(*can_cancel, egui::Button::new("Cancel"))
+                    .clicked()
+                {
+                    answer = Some(Answer::Cancel);
+                }
+            }
+            Input::Number { digits } => {
+                ui.label("Enter a number:");
+                let max = 10i32.saturating_pow(*digits as u32).saturating_sub(1);
+                ui.add(egui::DragValue::new(&mut self.number).range(0..=max));
+                if ui.button("Ok").clicked() {
+                    answer = Some(Answer::Number(self.number));
+                }
+            }
+            Input::Button => {
+                ui.label("Press a button:");
+                for (name, code) in BUTTONS {
+                    if ui.button(name).clicked() {
+                        answer = Some(Answer::Button(code));
+                    }
+                }
+            }
+            Input::Battle {
+                can_escape,
+                can_lose,
+            } => {
+                ui.label("Battle result:");
+                for result in BattleResult::iter() {
+                    let enabled = result != BattleResult::Escape || *can_escape;
+                    let response = ui.add_enabled(enabled, egui::Button::new(result.to_string()));
+                    let response = if result == BattleResult::Lose && !can_lose {
+                        response.on_hover_text("Losing this battle is a game over")
+                    } else {
+                        response
+                    };
+                    if response.clicked() {
+                        answer = Some(Answer::Battle(result));
+                    }
+                }
+            }
+        });
+
+        if let Some(answer) = answer {
+            self.status = self.interpreter.answer(&mut self.state, answer);
+        }
+    }
+
+    fn list_ui(&mut self, ui: &mut egui::Ui, update_state: &luminol_core::UpdateState<'_>) {
+        let Some((source, list, current)) = self.interpreter.position() else {
+            ui.weak("The list has finished running.");
+            return;
+        };
+
+        let call_stack = self
+            .interpreter
+            .call_stack()
+            .map(|(source, index)| format!("{source} line {index}"))
+            .collect::<Vec<_>>();
+        if call_stack.len() > 1 {
+            ui.label(format!("Call stack: {}", call_stack.join(" → ")));
+        }
+
+        let lines = luminol_components::Listing::new(update_state).lines(list);
+        let mut toggled = None;
+        egui::ScrollArea::both()
+            .id_source(self.id.with("list"))
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for (index, line) in lines.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let breakpoint = self.interpreter.breakpoints.contains(&(source, index));
+                        let marker = egui::RichText::new(if breakpoint { "●" } else { "○" })
+                            .monospace()
+                            .color(if breakpoint {
+                                ui.visuals().error_fg_color
+                            } else {
+                                ui.visuals().weak_text_color()
+                            });
+                        if ui
+                            .add(egui::Label::new(marker).sense(egui::Sense::click()))
+                            .on_hover_text("Toggle breakpoint")
+                            .clicked()
+                        {
+                            toggled = Some(index);
+                        }
+
+                        let arrow = if index == current { "▶" } else { " " };
+                        ui.monospace(arrow);
+                        let response = ui.label(line.layout_job(ui.style()));
+                        if index == current {
+                            response.scroll_to_me(None);
+                        }
+                    });
+                }
+            });
+
+        if let Some(index) = toggled {
+            if !self.interpreter.breakpoints.remove(&(source, index)) {
+                self.interpreter.breakpoints.insert((source, index));
+            }
+        }
+    }
+
+    fn trace_ui(&self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .id_source(self.id.with("trace"))
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for entry in self.interpreter.trace() {
+                    let color = match entry.kind {
+                        TraceKind::Command => ui.visuals().text_color(),
+                        TraceKind::Taken => egui::Color32::LIGHT_GREEN,
+                        TraceKind::Skipped => ui.visuals().weak_text_color(),
+                        TraceKind::Output => ui.visuals().strong_text_color(),
+                        TraceKind::NotSimulated => ui.visuals().warn_fg_color,
+                    };
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{}{} line {}: {}",
+                            "  ".repeat(entry.depth),
+                            entry.source,
+                            entry.index + 1,
+                            entry.message
+                        ))
+                        .monospace()
+                        .color(color),
+                    );
+                }
+            });
+    }
+
+    fn state_ui(&mut self, ui: &mut egui::Ui, update_state: &luminol_core::UpdateState<'_>) {
+        let state = &mut self.state;
+
+        ui.horizontal(|ui| {
+            ui.label("Gold");
+            ui.add(egui::DragValue::new(&mut state.gold).range(0..=9_999_999));
+        });
+        if let Some(timer) = &mut state.timer {
+            ui.horizontal(|ui| {
+                ui.label("Timer");
+                ui.add(egui::DragValue::new(timer).suffix(" s"));
+            });
+        }
+
+        let system = update_state.data.system();
+        let actors = update_state.data.actors();
+        let party = state
+            .party
+            .iter()
+            .map(|&id| {
+                actors
+                    .data
+                    .get(id.wrapping_sub(1))
+                    .map_or_else(|| format!("{id:0>3}"), |actor| actor.name.clone())
+            })
+            .collect::<Vec<_>>();
+        drop(actors);
+        ui.label(format!("Party: {}", party.join(", ")));
+
+        ui.collapsing(format!("Switches ({} on)", state.switches.len()), |ui| {
+            let mut ids = state.switches.iter().copied().collect::<Vec<_>>();
+            ids.sort_unstable();
+            for id in ids {
+                let mut value = true;
+                let name = id
+                    .checked_sub(1)
+                    .and_then(|index| system.switches.get(index))
+                    .map_or("", String::as_str);
+                if ui
+                    .checkbox(&mut value, format!("{id:0>4}: {name}"))
+                    .changed()
+                {
+                    state.set_switch(id, value);
+                }
+            }
+        });
+
+        ui.collapsing(format!("Variables ({} set)", state.variables.len()), |ui| {
+            let mut ids = state.variables.keys().copied().collect::<Vec<_>>();
+            ids.sort_unstable();
+            for id in ids {
+                ui.horizontal(|ui| {
+                    let mut value = state.variable(id);
+                    if ui.add(egui::DragValue::new(&mut value)).changed() {
+                        state.set_variable(id, value);
+                    }
+                    let name = id
+                        .checked_sub(1)
+                        .and_then(|index| system.variables.get(index))
+                        .map_or("", String::as_str);
+                    ui.label(format!("{id:0>4}: {name}"));
+                });
+            }
+        });
+        drop(system);
+
+        ui.collapsing(
+            format!("Self switches ({} on)", state.self_switches.len()),
+            |ui| {
+                let mut self_switches = state.self_switches.iter().copied().collect::<Vec<_>>();
+                self_switches.sort_unstable_by_key(|&(map, event, ch)| (map, event, ch as u8));
+                for (map_id, event_id, ch) in self_switches {
+                    ui.label(format!("Map {map_id:0>3}, event {event_id:0>3}: {ch}"));
+                }
+            },
+        );
+
+        for (label, counts) in [
+            ("Items", &state.items),
+            ("Weapons", &state.weapons),
+            ("Armor", &state.armors),
+        ] {
+            ui.collapsing(format!("{label} ({})", counts.len()), |ui| {
+                let mut counts = counts.iter().collect::<Vec<_>>();
+                counts.sort_unstable();
+                for (id, count) in counts {
+                    ui.label(format!("{id:0>3}: ×{count}"));
+                }
+            });
+        }
+
+        ui.separator();
+
+        if ui
+            .button("Use as starting state")
+            .on_hover_text("Start from the current state when the list is reset")
+            .clicked()
+        {
+            self.initial_state = self.state.clone();
+        }
+    }
+}
+
+/// Converts the page simulation, which uses the 0-based switch and variable indices of the system
+/// data, into a game state using the IDs shown in game.
+fn simulated_state(simulation: &luminol_core::PageSimulation) -> GameState {
+    GameState {
+        switches: simulation.switches.iter().map(|id| id + 1).collect(),
+        variables: simulation
+            .variables
+            .iter()
+            .map(|(id, value)| (id + 1, *value))
+            .collect(),
+        self_switches: simulation.self_switches.clone(),
+        ..Default::default()
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        self.id
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        egui::Window::new(format!("Debugging {}", self.title))
+            .id(self.id)
+            .open(open)
+            .default_size([720., 480.])
+            .show(ctx, |ui| {
+                egui::TopBottomPanel::top(self.id.with("controls")).show_inside(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let can_run = matches!(
+                            self.status,
+                            Status::Running | Status::Breakpoint | Status::StepLimit
+                        );
+                        let common_events = update_state.data.common_events();
+                        if ui
+                            .add_enabled(can_run, egui::Button::new("Step"))
+                            .on_hover_text("Run the next line")
+                            .clicked()
+                        {
+                            self.status =
+                                self.interpreter.step(&mut self.state, &common_events.data);
+                        }
+                        if ui
+                            .add_enabled(can_run, egui::Button::new("Run"))
+                            .on_hover_text(
+                                "Run until a breakpoint, a command waiting for the player or the \
+                                 end of the list",
+                            )
+                            .clicked()
+                        {
+                            self.status = self.interpreter.run(
+                                &mut self.state,
+                                &common_events.data,
+                                MAX_STEPS,
+                            );
+                        }
+                        drop(common_events);
+
+                        if ui
+                            .button("Reset")
+                            .on_hover_text("Start over from the starting state")
+                            .clicked()
+                        {
+                            self.reset();
+                        }
+
+                        ui.separator();
+                        ui.label(self.status_text());
+                        ui.weak(format!("{} lines run", self.interpreter.steps()));
+                    });
+
+                    if let Status::Waiting(input) = self.status.clone() {
+                        ui.separator();
+                        self.input_ui(ui, &input);
+                    }
+                });
+
+                egui::SidePanel::right(self.id.with("state"))
+                    .default_width(220.)
+                    .show_inside(ui, |ui| {
+                        egui::ScrollArea::vertical()
+                            .id_source(self.id.with("state_scroll"))
+                            .show(ui, |ui| self.state_ui(ui, update_state));
+                    });
+
+                egui::TopBottomPanel::bottom(self.id.with("trace"))
+                    .resizable(true)
+                    .default_height(140.)
+                    .show_inside(ui, |ui| self.trace_ui(ui));
+
+                egui::CentralPanel::default().show_inside(ui, |ui| {
+                    self.list_ui(ui, update_state);
+                });
+            });
+    }
+}
@@ -35,10 +35,14 @@ pub struct Window {
     event_id: usize,
     selected_page: usize,
 
+    /// The state of the event before the edits that haven't been added to the history yet
+    pending_history: Option<rpg::Event>,
+
     switch_1_modal: SwitchModal,
     switch_2_modal: SwitchModal,
     variable_modal: VariableModal,
     graphic_modal: GraphicPicker,
+    command_view: luminol_components::CommandView,
 }
 
 impl Window {
@@ -63,19 +67,27 @@ impl Window {
             event_id: event.id,
             selected_page: 0,
 
+            pending_history: None,
+
             switch_1_modal: SwitchModal::new(id_source.with("switch_1_modal")),
             switch_2_modal: SwitchModal::new(id_source.with("switch_2_modal")),
             variable_modal: VariableModal::new(id_source.with("variable_modal")),
             graphic_modal,
+            command_view: luminol_components::CommandView::new(id_source.with("commands")),
         }
     }
+
+    /// The ID of the editor window for the event with the given ID on the given map.
+    pub fn window_id(map_id: usize, event_id: usize) -> egui::Id {
+        egui::Id::new("luminol_event_edit")
+            .with(map_id)
+            .with(event_id)
+    }
 }
 
 impl luminol_core::Window for Window {
     fn id(&self) -> egui::Id {
-        egui::Id::new("luminol_event_edit")
-            .with(self.map_id)
-            .with(self.event_id)
+        Self::window_id(self.map_id, self.event_id)
     }
 
     fn show(
@@ -93,200 +105,255 @@ impl luminol_core::Window for Window {
         };
         drop(map);
 
+        let snapshot = self.pending_history.is_none().then(|| event.clone());
         let mut modified = false;
         let mut graphic_modified = false;
 
-        egui::Window::new(format!("Event '{}' ID {}", event.name, self.event_id))
-            .open(open)
-            .id(self.id())
-            .show(ctx, |ui| {
-                let id_source = self.id();
-                let previous_page = self.selected_page;
-
-                egui::TopBottomPanel::top(id_source.with("top_panel")).show_inside(ui, |ui| {
-                    ui.add_space(1.0); // pad the top of the window
-                    ui.horizontal(|ui| {
-                        ui.label("Name: ");
-                        ui.text_edit_singleline(&mut event.name);
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Page: ");
-                        for i in 0..event.pages.len() {
-                            ui.selectable_value(&mut self.selected_page, i, format!("{}", i + 1));
-                        }
-
-                        if ui
-                            .button(egui::RichText::new("Add").color(egui::Color32::LIGHT_GREEN))
-                            .clicked()
-                        {
-                            modified |= true;
-                            event.pages.push(rpg::EventPage::default());
-                            self.selected_page = event.pages.len() - 1;
-                        }
-
-                        let button = egui::Button::new(
-                            egui::RichText::new("Delete").color(egui::Color32::LIGHT_RED),
-                        );
-                        if ui.add_enabled(event.pages.len() > 1, button).clicked() {
-                            modified |= true;
-                            event.pages.remove(self.selected_page);
-                            self.selected_page = self.selected_page.saturating_sub(1);
-                        }
-                        if ui.button(egui::RichText::new("Clear")).clicked() {
-                            modified |= true;
-                            event.pages[self.selected_page] = rpg::EventPage::default();
-                        }
-                    });
-                    ui.add_space(1.0); // pad the bottom of the window
-                });
-
-                let page = &mut event.pages[self.selected_page];
-                if self.selected_page != previous_page {
-                    // reset the modal if we've changed pages
-                    self.graphic_modal.reset(update_state, &mut page.graphic);
-                }
+        let window_response =
+            egui::Window::new(format!("Event '{}' ID {}", event.name, self.event_id))
+                .open(open)
+                .id(self.id())
+                .show(ctx, |ui| {
+                    let id_source = self.id();
+                    let previous_page = self.selected_page;
 
-                egui::SidePanel::left(id_source.with("side_panel")).show_inside(ui, |ui| {
-                    ui.label("Conditions");
-                    ui.group(|ui| {
+                    egui::TopBottomPanel::top(id_source.with("top_panel")).show_inside(ui, |ui| {
+                        ui.add_space(1.0); // pad the top of the window
                         ui.horizontal(|ui| {
-                            ui.checkbox(&mut page.condition.switch1_valid, "Switch");
-                            let res = ui.add_enabled(
-                                page.condition.switch1_valid,
-                                self.switch_1_modal
-                                    .button(&mut page.condition.switch1_id, update_state),
-                            );
-                            modified |= res.changed();
-                            ui.label("is ON");
-                        });
-                        ui.horizontal(|ui| {
-                            ui.checkbox(&mut page.condition.switch2_valid, "Switch");
-                            let res = ui.add_enabled(
-                                page.condition.switch2_valid,
-                                self.switch_2_modal
-                                    .button(&mut page.condition.switch2_id, update_state),
-                            );
-                            modified |= res.changed();
-                            ui.label("is ON");
-                        });
-                        ui.horizontal(|ui| {
-                            ui.checkbox(&mut page.condition.variable_valid, "Variable");
-                            let res = ui.add_enabled(
-                                page.condition.variable_valid,
-                                self.variable_modal
-                                    .button(&mut page.condition.variable_id, update_state),
-                            );
-                            modified |= res.changed();
-                            ui.label("is");
-                            let res = ui.add_enabled(
-                                page.condition.variable_valid,
-                                egui::DragValue::new(&mut page.condition.variable_value),
-                            );
-                            modified |= res.changed();
-                            ui.label("or above");
+                            ui.label("Name: ");
+                            modified |= ui.text_edit_singleline(&mut event.name).changed();
                         });
                         ui.horizontal(|ui| {
-                            ui.checkbox(&mut page.condition.self_switch_valid, "Self Switch");
-                            // TODO add self switch text box (config option)
-                            let res = ui.add_enabled(
-                                // FIXME ensure shrink
-                                page.condition.self_switch_valid,
-                                luminol_components::EnumMenuButton::new(
-                                    &mut page.condition.self_switch_ch,
-                                    id_source.with("self_switch_ch"),
-                                ),
+                            ui.label("Page: ");
+                            for i in 0..event.pages.len() {
+                                ui.selectable_value(
+                                    &mut self.selected_page,
+                                    i,
+                                    format!("{}", i + 1),
+                                );
+                            }
+
+                            if ui
+                                .button(
+                                    egui::RichText::new("Add").color(egui::Color32::LIGHT_GREEN),
+                                )
+                                .clicked()
+                            {
+                                modified |= true;
+                                event.pages.push(rpg::EventPage::default());
+                                self.selected_page = event.pages.len() - 1;
+                            }
+
+                            let button = egui::Button::new(
+                                egui::RichText::new("Delete").color(egui::Color32::LIGHT_RED),
                             );
-                            modified |= res.changed();
-                            ui.label("is ON");
-                            // ensure we expand to fit the side panel
-                            ui.add_space(ui.available_width()); // cross justify doesn't seem to be able to replace this?
+                            if ui.add_enabled(event.pages.len() > 1, button).clicked() {
+                                modified |= true;
+                                event.pages.remove(self.selected_page);
+                                self.selected_page = self.selected_page.saturating_sub(1);
+                            }
+                            if ui.button(egui::RichText::new("Clear")).clicked() {
+                                modified |= true;
+                                event.pages[self.selected_page] = rpg::EventPage::default();
+                            }
+                            if ui
+                                .button("Debug")
+                                .on_hover_text("Run this page in the event debugger")
+                                .clicked()
+                            {
+                                let debugger = crate::windows::event_debugger::Window::new(
+                                    update_state,
+                                    id_source.with("debugger").with(self.selected_page),
+                                    format!(
+                                        "Event '{}' ID {} Page {}",
+                                        event.name,
+                                        self.event_id,
+                                        self.selected_page + 1
+                                    ),
+                                    event.pages[self.selected_page].list.clone(),
+                                    luminol_components::interpreter::Context {
+                                        map_id: self.map_id,
+                                        event_id: self.event_id,
+                                        ..Default::default()
+                                    },
+                                );
+                                update_state.edit_windows.add_window(debugger);
+                            }
                         });
+                        ui.add_space(1.0); // pad the bottom of the window
                     });
 
-                    ui.horizontal(|ui| {
-                        ui.vertical(|ui| {
-                            ui.label("Graphic");
+                    let page = &mut event.pages[self.selected_page];
+                    if self.selected_page != previous_page {
+                        // reset the modal and the command list if we've changed pages
+                        self.graphic_modal.reset(update_state, &mut page.graphic);
+                        self.command_view = luminol_components::CommandView::new(
+                            id_source.with("commands").with(self.selected_page),
+                        );
+                    }
 
-                            graphic_modified = self
-                                .graphic_modal
-                                .button(&mut page.graphic, update_state)
-                                .ui(ui)
-                                .changed();
-                        });
-                        ui.vertical(|ui| {
-                            ui.label("Autonomous Movement");
-                            ui.group(|ui| {
-                                // FIXME these expand to fit, which is kinda annoying
-                                ui.horizontal(|ui| {
-                                    ui.label("Move Type");
-                                    modified |= luminol_components::EnumComboBox::new(
-                                        id_source.with("move_type"),
-                                        &mut page.move_type,
-                                    )
-                                    .ui(ui)
+                    egui::SidePanel::left(id_source.with("side_panel")).show_inside(ui, |ui| {
+                        ui.label("Conditions");
+                        ui.group(|ui| {
+                            ui.horizontal(|ui| {
+                                modified |= ui
+                                    .checkbox(&mut page.condition.switch1_valid, "Switch")
                                     .changed();
-                                });
-                                ui.add_enabled(
-                                    page.move_type == luminol_data::rpg::MoveType::Custom,
-                                    egui::Button::new("Move Route..."),
-                                ); // TODO
-                                ui.horizontal(|ui| {
-                                    ui.label("Move Speed");
-                                    modified |= luminol_components::EnumComboBox::new(
-                                        id_source.with("move_speed"),
-                                        &mut page.move_speed,
-                                    )
-                                    .ui(ui)
+                                let res = ui.add_enabled(
+                                    page.condition.switch1_valid,
+                                    self.switch_1_modal
+                                        .button(&mut page.condition.switch1_id, update_state),
+                                );
+                                modified |= res.changed();
+                                ui.label("is ON");
+                            });
+                            ui.horizontal(|ui| {
+                                modified |= ui
+                                    .checkbox(&mut page.condition.switch2_valid, "Switch")
                                     .changed();
-                                });
-                                ui.horizontal(|ui| {
-                                    ui.label("Move Frequency");
-                                    modified |= luminol_components::EnumComboBox::new(
-                                        id_source.with("move_frequency"),
-                                        &mut page.move_frequency,
-                                    )
+                                let res = ui.add_enabled(
+                                    page.condition.switch2_valid,
+                                    self.switch_2_modal
+                                        .button(&mut page.condition.switch2_id, update_state),
+                                );
+                                modified |= res.changed();
+                                ui.label("is ON");
+                            });
+                            ui.horizontal(|ui| {
+                                modified |= ui
+                                    .checkbox(&mut page.condition.variable_valid, "Variable")
+                                    .changed();
+                                let res = ui.add_enabled(
+                                    page.condition.variable_valid,
+                                    self.variable_modal
+                                        .button(&mut page.condition.variable_id, update_state),
+                                );
+                                modified |= res.changed();
+                                ui.label("is");
+                                let res = ui.add_enabled(
+                                    page.condition.variable_valid,
+                                    egui::DragValue::new(&mut page.condition.variable_value),
+                                );
+                                modified |= res.changed();
+                                ui.label("or above");
+                            });
+                            ui.horizontal(|ui| {
+                                modified |= ui
+                                    .checkbox(&mut page.condition.self_switch_valid, "Self Switch")
+                                    .changed();
+                                // TODO add self switch text box (config option)
+                                let res = ui.add_enabled(
+                                    // FIXME ensure shrink
+                                    page.condition.self_switch_valid,
+                                    luminol_components::EnumMenuButton::new(
+                                        &mut page.condition.self_switch_ch,
+                                        id_source.with("self_switch_ch"),
+                                    ),
+                                );
+                                modified |= res.changed();
+                                ui.label("is ON");
+                                // ensure we expand to fit the side panel
+                                ui.add_space(ui.available_width()); // cross justify doesn't seem to be able to replace this?
+                            });
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.vertical(|ui| {
+                                ui.label("Graphic");
+
+                                graphic_modified = self
+                                    .graphic_modal
+                                    .button(&mut page.graphic, update_state)
                                     .ui(ui)
                                     .changed();
+                            });
+                            ui.vertical(|ui| {
+                                ui.label("Autonomous Movement");
+                                ui.group(|ui| {
+                                    // FIXME these expand to fit, which is kinda annoying
+                                    ui.horizontal(|ui| {
+                                        ui.label("Move Type");
+                                        modified |= luminol_components::EnumComboBox::new(
+                                            id_source.with("move_type"),
+                                            &mut page.move_type,
+                                        )
+                                        .ui(ui)
+                                        .changed();
+                                    });
+                                    ui.add_enabled(
+                                        page.move_type == luminol_data::rpg::MoveType::Custom,
+                                        egui::Button::new("Move Route..."),
+                                    ); // TODO
+                                    ui.horizontal(|ui| {
+                                        ui.label("Move Speed");
+                                        modified |= luminol_components::EnumComboBox::new(
+                                            id_source.with("move_speed"),
+                                            &mut page.move_speed,
+                                        )
+                                        .ui(ui)
+                                        .changed();
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.label("Move Frequency");
+                                        modified |= luminol_components::EnumComboBox::new(
+                                            id_source.with("move_frequency"),
+                                            &mut page.move_frequency,
+                                        )
+                                        .ui(ui)
+                                        .changed();
+                                    });
+                                    ui.add_space(ui.available_height());
                                 });
-                                ui.add_space(ui.available_height());
                             });
                         });
-                    });
 
-                    ui.columns(2, |columns| {
-                        let [left, right] = columns else {
-                            unreachable!()
-                        };
+                        ui.columns(2, |columns| {
+                            let [left, right] = columns else {
+                                unreachable!()
+                            };
 
-                        left.label("Options");
-                        left.group(|ui| {
-                            ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
-                            modified |= ui
-                                .checkbox(&mut page.walk_anime, "Move Animation")
-                                .changed();
-                            modified |= ui
-                                .checkbox(&mut page.step_anime, "Stop Animation")
-                                .changed();
-                            modified |= ui
-                                .checkbox(&mut page.direction_fix, "Direction Fix")
-                                .changed();
-                            modified |= ui.checkbox(&mut page.through, "Through").changed();
-                            modified |= ui
-                                .checkbox(&mut page.always_on_top, "Always on Top")
-                                .changed();
-                        });
+                            left.label("Options");
+                            left.group(|ui| {
+                                ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+                                modified |= ui
+                                    .checkbox(&mut page.walk_anime, "Move Animation")
+                                    .changed();
+                                modified |= ui
+                                    .checkbox(&mut page.step_anime, "Stop Animation")
+                                    .changed();
+                                modified |= ui
+                                    .checkbox(&mut page.direction_fix, "Direction Fix")
+                                    .changed();
+                                modified |= ui.checkbox(&mut page.through, "Through").changed();
+                                modified |= ui
+                                    .checkbox(&mut page.always_on_top, "Always on Top")
+                                    .changed();
+                            });
 
-                        right.label("Trigger");
-                        right.group(|ui| {
-                            modified |= luminol_components::EnumRadioList::new(&mut page.trigger)
-                                .ui(ui)
-                                .changed();
+                            right.label("Trigger");
+                            right.group(|ui| {
+                                modified |=
+                                    luminol_components::EnumRadioList::new(&mut page.trigger)
+                                        .ui(ui)
+                                        .changed();
+                            });
                         });
                     });
+
+                    egui::CentralPanel::default().show_inside(ui, |ui| {
+                        egui::ScrollArea::both()
+                            .auto_shrink([false; 2])
+                            .show(ui, |ui| {
+                                modified |= self
+                                    .command_view
+                                    .ui(ui, update_state, &mut page.list)
+                                    .changed();
+                            });
+                    });
                 });
-            });
 
         if graphic_modified {
+            modified = true;
             event.extra_data.graphic_modified.set(true);
         }
 
@@ -296,6 +363,32 @@ impl luminol_core::Window for Window {
 
         if modified {
             map.modified = true;
+            if let Some(snapshot) = snapshot {
+                self.pending_history = Some(snapshot);
+            }
+        }
+        drop(map);
+
+        // Wait until the user leaves the editor before adding the edits to the history, so that
+        // typing a name or dragging a value only creates one history entry and undoing from the
+        // map never has to deal with edits that are still in progress
+        let window_rect = window_response.map(|response| response.response.rect);
+        let lost_focus = ctx.input(|i| {
+            i.pointer.any_pressed()
+                && i.pointer
+                    .press_origin()
+                    .is_some_and(|pos| window_rect.map_or(true, |rect| !rect.contains(pos)))
+        });
+        if !*open || lost_focus {
+            if let Some(event) = self.pending_history.take() {
+                update_state.push_history(
+                    crate::tabs::map::history_scope(self.map_id),
+                    crate::tabs::map::HistoryEntry::EventEdited {
+                        map_id: self.map_id,
+                        event: Box::new(event),
+                    },
+                );
+            }
         }
     }
 
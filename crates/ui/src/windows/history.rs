@@ -0,0 +1,103 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+/// The undo history panel.
+/// Lists every step in the undo history and allows jumping to any of them.
+#[derive(Default)]
+pub struct Window {}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_history")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        egui::Window::new("History")
+            .id(self.id())
+            .open(open)
+            .default_width(300.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Undo").clicked() {
+                        update_state.undo(None);
+                    }
+                    if ui.button("Redo").clicked() {
+                        update_state.redo(None);
+                    }
+                });
+
+                ui.separator();
+
+                let position = update_state.history.position();
+                let mut new_position = None;
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, true])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        if ui
+                            .selectable_label(
+                                position == 0,
+                                egui::RichText::new("Initial state").italics(),
+                            )
+                            .clicked()
+                        {
+                            new_position = Some(0);
+                        }
+
+                        for (index, (description, applied)) in
+                            update_state.history.descriptions().enumerate()
+                        {
+                            // Steps that have been undone are displayed greyed out
+                            let text = if applied {
+                                egui::RichText::new(description)
+                            } else {
+                                egui::RichText::new(description).weak()
+                            };
+                            if ui.selectable_label(position == index + 1, text).clicked() {
+                                new_position = Some(index + 1);
+                            }
+                        }
+                    });
+
+                if let Some(new_position) = new_position {
+                    if update_state
+                        .history
+                        .jump_to(new_position, update_state.data)
+                    {
+                        update_state.modified.set(true);
+                    }
+                }
+            });
+    }
+}
@@ -28,12 +28,20 @@ pub mod about;
 pub mod actors;
 /// The animation editor.
 pub mod animations;
+/// The project-wide list of map annotations.
+pub mod annotations;
 /// The archive manager for creating and extracting RGSSAD archives.
 pub mod archive_manager;
 /// The armor editor.
 pub mod armor;
+/// The autotile rebuilder for maps using a changed tileset.
+pub mod autotile_rebuild;
 /// The class editor.
 pub mod classes;
+/// The command maker for custom commands, and the command pack manager.
+pub mod command_gen;
+/// The common event analysis, showing calls between common events and their triggers.
+pub mod common_event_analysis;
 /// The common event editor.
 pub mod common_event_edit;
 /// Config window
@@ -41,10 +49,16 @@ pub mod config_window;
 /// Playtest console
 #[cfg(not(target_arch = "wasm32"))]
 pub mod console;
+/// The dungeon generator for creating maps procedurally.
+pub mod dungeon_generator;
 /// The enemy editor.
 pub mod enemies;
+/// The event debugger, which runs command lists in a headless interpreter.
+pub mod event_debugger;
 /// The event editor.
 pub mod event_edit;
+/// The undo history panel.
+pub mod history;
 /// The item editor.
 pub mod items;
 /// The map picker.
@@ -53,6 +67,8 @@ pub mod map_picker;
 pub mod misc;
 /// New project window
 pub mod new_project;
+/// The page simulator for previewing active event pages.
+pub mod page_simulation;
 pub mod preferences;
 /// The crash reporter.
 pub mod reporter;
@@ -66,5 +82,15 @@ pub mod skills;
 pub mod sound_test;
 /// The state editor.
 pub mod states;
+/// The Tiled map import and export.
+pub mod tiled;
+/// The tileset editor.
+pub mod tilesets;
+/// The troop editor.
+pub mod troops;
 /// The weapon editor.
 pub mod weapons;
+/// The world export for rendering several maps into one image.
+pub mod world_export;
+/// The world graph showing the transfers between maps.
+pub mod world_graph;
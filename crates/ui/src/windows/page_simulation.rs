@@ -0,0 +1,241 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use strum::IntoEnumIterator;
+
+/// The page simulator.
+/// Sets the switches, variables and self switches used by map views to display each event with the
+/// page that would be active in game.
+#[derive(Default)]
+pub struct Window {
+    /// The simulated state while the simulation is turned off, so that it isn't lost
+    stashed: Option<luminol_core::PageSimulation>,
+    search: String,
+    /// The map whose events' self switches are listed
+    self_switch_map_id: Option<usize>,
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_page_simulation")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        egui::Window::new("Page Simulation")
+            .id(self.id())
+            .open(open)
+            .default_width(320.)
+            .show(ctx, |ui| {
+                let mut enabled = update_state.toolbar.page_simulation.is_some();
+                ui.horizontal(|ui| {
+                    if ui
+                        .checkbox(&mut enabled, "Simulate game state")
+                        .on_hover_text(
+                            "Display every event using the last page whose conditions are met, \
+                             and grey out events without such a page",
+                        )
+                        .changed()
+                    {
+                        if enabled {
+                            update_state.toolbar.page_simulation =
+                                Some(self.stashed.take().unwrap_or_default());
+                        } else {
+                            self.stashed = update_state.toolbar.page_simulation.take();
+                        }
+                    }
+
+                    if ui.button("Reset").clicked() {
+                        if let Some(simulation) = &mut update_state.toolbar.page_simulation {
+                            *simulation = Default::default();
+                        }
+                        self.stashed = None;
+                    }
+                });
+
+                let Some(simulation) = &mut update_state.toolbar.page_simulation else {
+                    return;
+                };
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Search");
+                    ui.text_edit_singleline(&mut self.search);
+                });
+                let search = self.search.to_lowercase();
+                // Matches the name or the displayed number of a switch, variable or event
+                let matches = |number: usize, name: &str| {
+                    search.is_empty()
+                        || name.to_lowercase().contains(&search)
+                        || number.to_string().contains(&search)
+                };
+
+                let system = update_state.data.system();
+                let row_height = ui.spacing().interact_size.y;
+
+                ui.collapsing(
+                    format!("Switches ({} on)", simulation.switches.len()),
+                    |ui| {
+                        let ids = (0..system.switches.len())
+                            .filter(|&id| matches(id + 1, &system.switches[id]))
+                            .collect::<Vec<_>>();
+                        egui::ScrollArea::vertical()
+                            .id_source("switches")
+                            .max_height(250.)
+                            .show_rows(ui, row_height, ids.len(), |ui, range| {
+                                for &id in &ids[range] {
+                                    let mut value = simulation.switches.contains(&id);
+                                    if ui
+                                        .checkbox(
+                                            &mut value,
+                                            format!("{:0>4}: {}", id + 1, system.switches[id]),
+                                        )
+                                        .changed()
+                                    {
+                                        if value {
+                                            simulation.switches.insert(id);
+                                        } else {
+                                            simulation.switches.remove(&id);
+                                        }
+                                    }
+                                }
+                            });
+                    },
+                );
+
+                ui.collapsing(
+                    format!("Variables ({} set)", simulation.variables.len()),
+                    |ui| {
+                        let ids = (0..system.variables.len())
+                            .filter(|&id| matches(id + 1, &system.variables[id]))
+                            .collect::<Vec<_>>();
+                        egui::ScrollArea::vertical()
+                            .id_source("variables")
+                            .max_height(250.)
+                            .show_rows(ui, row_height, ids.len(), |ui, range| {
+                                for &id in &ids[range] {
+                                    ui.horizontal(|ui| {
+                                        let mut value =
+                                            simulation.variables.get(&id).copied().unwrap_or(0);
+                                        if ui.add(egui::DragValue::new(&mut value)).changed() {
+                                            if value == 0 {
+                                                simulation.variables.remove(&id);
+                                            } else {
+                                                simulation.variables.insert(id, value);
+                                            }
+                                        }
+                                        ui.label(format!(
+                                            "{:0>4}: {}",
+                                            id + 1,
+                                            system.variables[id]
+                                        ));
+                                    });
+                                }
+                            });
+                    },
+                );
+
+                drop(system);
+
+                ui.collapsing(
+                    format!("Self switches ({} on)", simulation.self_switches.len()),
+                    |ui| {
+                        let map_infos = update_state.data.map_infos();
+                        let mut map_ids = map_infos.data.keys().copied().collect::<Vec<_>>();
+                        map_ids.sort_unstable();
+
+                        let map_name = |id: usize| {
+                            map_infos
+                                .data
+                                .get(&id)
+                                .map_or_else(String::new, |info| format!("{id:0>3}: {}", info.name))
+                        };
+                        egui::ComboBox::from_label("Map")
+                            .selected_text(
+                                self.self_switch_map_id.map(map_name).unwrap_or_default(),
+                            )
+                            .show_ui(ui, |ui| {
+                                for id in map_ids {
+                                    ui.selectable_value(
+                                        &mut self.self_switch_map_id,
+                                        Some(id),
+                                        map_name(id),
+                                    );
+                                }
+                            });
+                        drop(map_infos);
+
+                        let Some(map_id) = self.self_switch_map_id else {
+                            return;
+                        };
+                        let map = update_state.data.get_or_load_map(
+                            map_id,
+                            update_state.filesystem,
+                            update_state
+                                .project_config
+                                .as_ref()
+                                .expect("project not loaded"),
+                        );
+
+                        egui::ScrollArea::vertical()
+                            .id_source("self_switches")
+                            .max_height(250.)
+                            .show(ui, |ui| {
+                                for (event_id, event) in map.events.iter() {
+                                    if !matches(event_id, &event.name) {
+                                        continue;
+                                    }
+                                    ui.horizontal(|ui| {
+                                        for self_switch in luminol_data::rpg::SelfSwitch::iter() {
+                                            let key = (map_id, event_id, self_switch);
+                                            let mut value = simulation.self_switches.contains(&key);
+                                            if ui
+                                                .checkbox(&mut value, self_switch.to_string())
+                                                .changed()
+                                            {
+                                                if value {
+                                                    simulation.self_switches.insert(key);
+                                                } else {
+                                                    simulation.self_switches.remove(&key);
+                                                }
+                                            }
+                                        }
+                                        ui.label(format!("{event_id:0>3}: {}", event.name));
+                                    });
+                                }
+                            });
+                    },
+                );
+            });
+    }
+}
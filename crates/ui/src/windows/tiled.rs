@@ -0,0 +1,382 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use color_eyre::eyre::WrapErr;
+use luminol_components::tiled::TiledMap;
+use luminol_filesystem::FileSystem;
+
+use crate::tabs::map::{history_scope, next_event_id, stale_autotiles, HistoryEntry};
+
+/// The Tiled import and export.
+/// Exports maps as Tiled TMX maps with their tileset as a TSX, and imports TMX maps back into
+/// existing maps.
+#[derive(Default)]
+pub struct Window {
+    map_id: Option<usize>,
+    export_promise: Option<poll_promise::Promise<color_eyre::Result<()>>>,
+    import_promise: Option<poll_promise::Promise<color_eyre::Result<TiledMap>>>,
+}
+
+/// Returns true if the error is from the user closing a file picker.
+fn is_cancelled(error: &color_eyre::Report) -> bool {
+    matches!(
+        error.root_cause().downcast_ref(),
+        Some(luminol_filesystem::Error::CancelledLoading)
+    )
+}
+
+impl Window {
+    fn export(&mut self, update_state: &mut luminol_core::UpdateState<'_>, map_id: usize) {
+        let c = "While exporting the map to Tiled";
+
+        let map = update_state.data.get_or_load_map(
+            map_id,
+            update_state.filesystem,
+            update_state
+                .project_config
+                .as_ref()
+                .expect("project not loaded"),
+        );
+        let tilesets = update_state.data.tilesets();
+        let Some(tileset) = tilesets.data.get(map.tileset_id) else {
+            luminol_core::error!(
+                update_state.toasts,
+                color_eyre::eyre::eyre!("Map {map_id} uses a tileset that doesn't exist")
+            );
+            return;
+        };
+
+        let tileset_name = format!("Tileset{:0>3}", map.tileset_id);
+        let image = luminol_components::tiled::tileset_image(update_state.filesystem, tileset);
+        let mut png = Vec::new();
+        let result =
+            luminol_components::tiled::write_tsx(tileset, &format!("{tileset_name}.png"), &image)
+                .and_then(|tsx| {
+                    let tmx =
+                        luminol_components::tiled::write_tmx(&map, &format!("{tileset_name}.tsx"))?;
+                    image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+                    Ok((tsx, tmx))
+                })
+                .wrap_err(c);
+        let (tsx, tmx) = match result {
+            Ok(files) => files,
+            Err(error) => {
+                luminol_core::error!(update_state.toasts, error);
+                return;
+            }
+        };
+
+        self.export_promise = Some(luminol_core::spawn_future(async move {
+            let destination = luminol_filesystem::host::FileSystem::from_folder_picker()
+                .await
+                .wrap_err(c)?;
+            destination
+                .write(format!("Map{map_id:0>3}.tmx"), tmx)
+                .wrap_err(c)?;
+            destination
+                .write(format!("{tileset_name}.tsx"), tsx)
+                .wrap_err(c)?;
+            destination
+                .write(format!("{tileset_name}.png"), png)
+                .wrap_err(c)?;
+            Ok(())
+        }));
+    }
+
+    /// Replaces the tiles of a map with the tiles of a TMX map and moves, creates or deletes its
+    /// events according to the objects of the TMX map. The tiles and the events can each be
+    /// restored with one undo from the map's tab.
+    fn apply_import(
+        update_state: &mut luminol_core::UpdateState<'_>,
+        map_id: usize,
+        tiled_map: TiledMap,
+    ) -> color_eyre::Result<()> {
+        let mut map = update_state.data.get_or_load_map(
+            map_id,
+            update_state.filesystem,
+            update_state
+                .project_config
+                .as_ref()
+                .expect("project not loaded"),
+        );
+        if (tiled_map.width, tiled_map.height) != (map.data.xsize(), map.data.ysize()) {
+            color_eyre::eyre::bail!(
+                "The TMX map is {}x{} tiles but map {map_id} is {}x{} tiles. Resize one of them so \
+                 that their sizes match.",
+                tiled_map.width,
+                tiled_map.height,
+                map.data.xsize(),
+                map.data.ysize()
+            );
+        }
+
+        let width = tiled_map.width;
+        let old_tiles = map.data.clone();
+        for (layer, tiles) in tiled_map.layers.iter().take(map.data.zsize()).enumerate() {
+            for (index, &tile_id) in tiles.iter().enumerate() {
+                map.data[(index % width, index / width, layer)] = tile_id;
+            }
+        }
+        // Tiled doesn't know the autotile rules, so autotiles may have been drawn with any variant
+        for (x, y, z, tile_id) in stale_autotiles(&map) {
+            map.data[(x, y, z)] = tile_id;
+        }
+        let deltas = (0..map.data.zsize())
+            .map(|layer| {
+                let mut delta = Vec::new();
+                for y in 0..map.data.ysize() {
+                    for x in 0..map.data.xsize() {
+                        let old_id = old_tiles[(x, y, layer)];
+                        if old_id != map.data[(x, y, layer)] {
+                            delta.push((x, y, old_id));
+                        }
+                    }
+                }
+                delta
+            })
+            .collect::<Vec<_>>();
+        if deltas.iter().any(|delta| !delta.is_empty()) {
+            update_state.history.push(
+                history_scope(map_id),
+                HistoryEntry::TileLayers {
+                    map_id,
+                    description: "import tiles from Tiled",
+                    deltas,
+                },
+            );
+        }
+
+        let old_events = map.events.clone();
+        let mut created_events = 0;
+        let mut moved_events = 0;
+        let mut deleted_events = 0;
+        if tiled_map.has_event_layer {
+            let kept_ids = tiled_map
+                .objects
+                .iter()
+                .filter_map(|object| object.event_id)
+                .collect::<std::collections::HashSet<_>>();
+            let deleted_ids = map
+                .events
+                .iter()
+                .map(|(id, _)| id)
+                .filter(|id| !kept_ids.contains(id))
+                .collect::<Vec<_>>();
+            for id in deleted_ids {
+                map.events.remove(id);
+                update_state
+                    .data
+                    .event_templates()
+                    .remove_instance(map_id, id);
+                deleted_events += 1;
+            }
+        }
+
+        // Objects duplicated in Tiled keep the event ID of the original, so only the first object
+        // with an ID moves its event and the others become new events
+        let mut used_ids = std::collections::HashSet::new();
+        for object in tiled_map.objects {
+            let x = object.x.clamp(0, width as i32 - 1);
+            let y = object.y.clamp(0, tiled_map.height as i32 - 1);
+            match object
+                .event_id
+                .filter(|&id| map.events.contains(id) && used_ids.insert(id))
+            {
+                Some(id) => {
+                    let event = &mut map.events[id];
+                    if (event.x, event.y) != (x, y) {
+                        event.x = x;
+                        event.y = y;
+                        moved_events += 1;
+                    }
+                }
+                None => {
+                    let Some(id) = next_event_id(&map) else {
+                        continue;
+                    };
+                    let mut event = luminol_data::rpg::Event::new(x, y, id);
+                    if !object.name.is_empty() {
+                        event.name = object.name;
+                    }
+                    map.events.insert(id, event);
+                    created_events += 1;
+                }
+            }
+        }
+        if created_events + moved_events + deleted_events > 0 {
+            update_state.history.push(
+                history_scope(map_id),
+                HistoryEntry::EventsChanged {
+                    map_id,
+                    description: "import events from Tiled",
+                    events: Box::new(old_events),
+                },
+            );
+        }
+
+        map.modified = true;
+        update_state.modified.set(true);
+
+        if tiled_map.layers.len() > map.data.zsize() {
+            luminol_core::warn!(
+                update_state.toasts,
+                format!(
+                    "Only the first {} tile layers of the TMX map were imported",
+                    map.data.zsize()
+                )
+            );
+        }
+        if tiled_map.invalid_tiles > 0 {
+            luminol_core::warn!(
+                update_state.toasts,
+                format!(
+                    "{} tiles from other tilesets or empty autotile slots were left empty",
+                    tiled_map.invalid_tiles
+                )
+            );
+        }
+        luminol_core::info!(
+            update_state.toasts,
+            format!(
+                "Imported the TMX map into map {map_id} ({created_events} new events, \
+                 {deleted_events} deleted events)"
+            )
+        );
+        Ok(())
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_tiled")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let mut should_export = false;
+        let mut should_import = false;
+
+        egui::Window::new("Tiled Import/Export")
+            .id(self.id())
+            .open(open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let map_infos = update_state.data.map_infos();
+                let mut map_ids = map_infos.data.keys().copied().collect::<Vec<_>>();
+                map_ids.sort_unstable();
+                let map_name = |id: usize| {
+                    format!(
+                        "{id:0>3}: {}",
+                        map_infos
+                            .data
+                            .get(&id)
+                            .map_or("", |info| info.name.as_str())
+                    )
+                };
+
+                egui::ComboBox::from_label("Map")
+                    .selected_text(self.map_id.map(map_name).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for &id in map_ids.iter() {
+                            ui.selectable_value(&mut self.map_id, Some(id), map_name(id));
+                        }
+                    });
+
+                ui.label(
+                    "Exports the map as a TMX map, with its tileset and autotiles as a TSX \
+                     tileset. Importing a TMX map replaces the tiles of the map and moves or \
+                     creates its events from the objects of the TMX map. The TMX map must use \
+                     the exported tileset and have the same size as the map.",
+                );
+
+                ui.horizontal(|ui| {
+                    let is_busy = self.export_promise.is_some() || self.import_promise.is_some();
+                    ui.add_enabled_ui(self.map_id.is_some() && !is_busy, |ui| {
+                        should_export = ui.button("Export…").clicked();
+                        should_import = ui.button("Import…").clicked();
+                    });
+                    if is_busy {
+                        ui.spinner();
+                    }
+                });
+            });
+
+        if let Some(map_id) = self.map_id {
+            if should_export {
+                self.export(update_state, map_id);
+            }
+            if should_import {
+                self.import_promise = Some(luminol_core::spawn_future(async move {
+                    let (file, _) =
+                        luminol_filesystem::host::File::from_file_picker("Tiled maps", &["tmx"])
+                            .await?;
+                    let tmx =
+                        std::io::read_to_string(file).wrap_err("While reading the TMX file")?;
+                    TiledMap::parse(&tmx)
+                }));
+            }
+        }
+
+        if let Some(p) = self.export_promise.take() {
+            match p.try_take() {
+                Ok(Ok(())) => {
+                    luminol_core::info!(update_state.toasts, "Exported the map to Tiled")
+                }
+                Ok(Err(error)) if !is_cancelled(&error) => {
+                    luminol_core::error!(update_state.toasts, error);
+                }
+                Ok(Err(_)) => {}
+                Err(p) => self.export_promise = Some(p),
+            }
+        }
+
+        if let Some(p) = self.import_promise.take() {
+            match p.try_take() {
+                Ok(Ok(tiled_map)) => {
+                    if let Some(map_id) = self.map_id {
+                        if let Err(error) = Self::apply_import(update_state, map_id, tiled_map) {
+                            luminol_core::error!(
+                                update_state.toasts,
+                                error.wrap_err("While importing the TMX map")
+                            );
+                        }
+                    }
+                }
+                Ok(Err(error)) if !is_cancelled(&error) => {
+                    luminol_core::error!(update_state.toasts, error);
+                }
+                Ok(Err(_)) => {}
+                Err(p) => self.import_promise = Some(p),
+            }
+        }
+    }
+}
@@ -0,0 +1,416 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use luminol_components::UiExt;
+use luminol_core::Modal;
+use strum::IntoEnumIterator;
+
+use luminol_modals::graphic_picker::basic::Modal as GraphicPicker;
+use luminol_modals::graphic_picker::hue::Modal as HueGraphicPicker;
+
+/// Database - Tilesets management window.
+pub struct Window {
+    selected_tileset_name: Option<String>,
+    previous_tileset: Option<usize>,
+
+    tileset_picker: GraphicPicker,
+    autotile_pickers: Vec<GraphicPicker>,
+    panorama_picker: HueGraphicPicker,
+    fog_picker: HueGraphicPicker,
+    battleback_picker: GraphicPicker,
+
+    flag_view: Option<luminol_components::TilesetFlagView>,
+    flag: luminol_components::TilesetFlag,
+    /// The tileset whose fog values are being dragged or typed, if any
+    fog_edit: Option<usize>,
+
+    view: luminol_components::DatabaseView,
+}
+
+/// Converts an autotile name to the path used by the graphic picker.
+fn autotile_path(name: &str) -> Option<camino::Utf8PathBuf> {
+    (!name.is_empty()).then(|| name.into())
+}
+
+impl Window {
+    pub fn new(update_state: &luminol_core::UpdateState<'_>) -> Self {
+        let tilesets = update_state.data.tilesets();
+        let tileset = &tilesets.data[0];
+        Self {
+            selected_tileset_name: None,
+            previous_tileset: None,
+
+            tileset_picker: GraphicPicker::new(
+                update_state,
+                "Graphics/Tilesets".into(),
+                tileset.tileset_name.as_deref(),
+                egui::vec2(64., 64.),
+                "tileset_graphic_picker",
+            ),
+            autotile_pickers: (0..7)
+                .map(|i| {
+                    GraphicPicker::new(
+                        update_state,
+                        "Graphics/Autotiles".into(),
+                        tileset
+                            .autotile_names
+                            .get(i)
+                            .and_then(|name| autotile_path(name))
+                            .as_deref(),
+                        egui::vec2(64., 64.),
+                        ("tileset_autotile_picker", i),
+                    )
+                })
+                .collect(),
+            panorama_picker: HueGraphicPicker::new(
+                update_state,
+                "Graphics/Panoramas".into(),
+                tileset.panorama_name.as_deref(),
+                tileset.panorama_hue,
+                egui::vec2(64., 64.),
+                "tileset_panorama_picker",
+            ),
+            fog_picker: HueGraphicPicker::new(
+                update_state,
+                "Graphics/Fogs".into(),
+                tileset.fog_name.as_deref(),
+                tileset.fog_hue,
+                egui::vec2(64., 64.),
+                "tileset_fog_picker",
+            ),
+            battleback_picker: GraphicPicker::new(
+                update_state,
+                "Graphics/Battlebacks".into(),
+                tileset.battleback_name.as_deref(),
+                egui::vec2(64., 64.),
+                "tileset_battleback_picker",
+            ),
+
+            flag_view: None,
+            flag: luminol_components::TilesetFlag::default(),
+            fog_edit: None,
+
+            view: luminol_components::DatabaseView::new(),
+        }
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("tileset_editor")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        let data = std::mem::take(update_state.data); // take data to avoid borrow checker issues
+        let mut tilesets = data.tilesets();
+
+        let mut modified = false;
+
+        self.selected_tileset_name = None;
+
+        let name = if let Some(name) = &self.selected_tileset_name {
+            format!("Editing tileset {:?}", name)
+        } else {
+            "Tileset Editor".into()
+        };
+
+        let response = egui::Window::new(name)
+            .id(self.id())
+            .default_width(500.)
+            .open(open)
+            .show(ctx, |ui| {
+                self.view.show(
+                    ui,
+                    update_state,
+                    "Tilesets",
+                    &mut tilesets.data,
+                    |tileset| format!("{:0>4}: {}", tileset.id + 1, tileset.name),
+                    |ui, tilesets, id, update_state| {
+                        let tileset = &mut tilesets[id];
+                        self.selected_tileset_name = Some(tileset.name.clone());
+
+                        // Changes to the tileset and autotile graphics reload the atlas so that
+                        // the open maps using this tileset are updated. The panorama and fog are
+                        // recreated separately, and only once a fog value is no longer being
+                        // dragged or typed since that changes it every frame.
+                        let mut graphics_changed = false;
+                        let mut planes_changed = false;
+                        let mut fog_changed = false;
+
+                        if self.previous_tileset != Some(tileset.id) {
+                            self.flag_view = None;
+                        }
+                        if tileset.autotile_names.len() < 7 {
+                            tileset.autotile_names.resize(7, String::new());
+                            modified = true;
+                        }
+
+                        ui.with_padded_stripe(false, |ui| {
+                            modified |= ui
+                                .add(luminol_components::Field::new(
+                                    "Name",
+                                    egui::TextEdit::singleline(&mut tileset.name)
+                                        .desired_width(f32::INFINITY),
+                                ))
+                                .changed();
+                        });
+
+                        ui.with_padded_stripe(true, |ui| {
+                            ui.columns(2, |columns| {
+                                let changed = columns[0]
+                                    .add(luminol_components::Field::new(
+                                        "Tileset Graphic",
+                                        self.tileset_picker
+                                            .button(&mut tileset.tileset_name, update_state),
+                                    ))
+                                    .changed();
+                                if self.previous_tileset != Some(tileset.id) {
+                                    // avoid desyncs by resetting the modal if the tileset has changed
+                                    self.tileset_picker
+                                        .reset(update_state, &mut tileset.tileset_name);
+                                }
+                                graphics_changed |= changed;
+
+                                modified |= columns[1]
+                                    .add(luminol_components::Field::new(
+                                        "Battleback",
+                                        self.battleback_picker
+                                            .button(&mut tileset.battleback_name, update_state),
+                                    ))
+                                    .changed();
+                                if self.previous_tileset != Some(tileset.id) {
+                                    self.battleback_picker
+                                        .reset(update_state, &mut tileset.battleback_name);
+                                }
+                            });
+                        });
+
+                        ui.with_padded_stripe(false, |ui| {
+                            ui.columns(7, |columns| {
+                                for (i, (picker, name)) in self
+                                    .autotile_pickers
+                                    .iter_mut()
+                                    .zip(tileset.autotile_names.iter_mut())
+                                    .enumerate()
+                                {
+                                    let mut path = autotile_path(name);
+                                    let changed = columns[i]
+                                        .add(luminol_components::Field::new(
+                                            format!("Autotile {}", i + 1),
+                                            picker.button(&mut path, update_state),
+                                        ))
+                                        .changed();
+                                    if self.previous_tileset != Some(tileset.id) {
+                                        picker.reset(update_state, &mut path);
+                                    }
+                                    if changed {
+                                        *name = path.map(String::from).unwrap_or_default();
+                                        graphics_changed = true;
+                                    }
+                                }
+                            });
+
+                            if ui
+                                .button("Rebuild Autotiles…")
+                                .on_hover_text(
+                                    "Recompute the autotile variants of the maps using this \
+                                     tileset",
+                                )
+                                .clicked()
+                            {
+                                update_state.edit_windows.add_window(
+                                    crate::windows::autotile_rebuild::Window::new(tileset.id),
+                                );
+                            }
+                        });
+
+                        ui.with_padded_stripe(true, |ui| {
+                            ui.columns(2, |columns| {
+                                planes_changed |= columns[0]
+                                    .add(luminol_components::Field::new(
+                                        "Panorama",
+                                        self.panorama_picker.button(
+                                            (&mut tileset.panorama_name, &mut tileset.panorama_hue),
+                                            update_state,
+                                        ),
+                                    ))
+                                    .changed();
+                                if self.previous_tileset != Some(tileset.id) {
+                                    self.panorama_picker.reset(
+                                        update_state,
+                                        (&mut tileset.panorama_name, &mut tileset.panorama_hue),
+                                    );
+                                }
+
+                                planes_changed |= columns[1]
+                                    .add(luminol_components::Field::new(
+                                        "Fog",
+                                        self.fog_picker.button(
+                                            (&mut tileset.fog_name, &mut tileset.fog_hue),
+                                            update_state,
+                                        ),
+                                    ))
+                                    .changed();
+                                if self.previous_tileset != Some(tileset.id) {
+                                    self.fog_picker.reset(
+                                        update_state,
+                                        (&mut tileset.fog_name, &mut tileset.fog_hue),
+                                    );
+                                }
+                            });
+                        });
+
+                        ui.with_padded_stripe(false, |ui| {
+                            ui.columns(5, |columns| {
+                                fog_changed |= columns[0]
+                                    .add(luminol_components::Field::new(
+                                        "Fog Opacity",
+                                        egui::DragValue::new(&mut tileset.fog_opacity)
+                                            .range(0..=255),
+                                    ))
+                                    .changed();
+
+                                planes_changed |= columns[1]
+                                    .add(luminol_components::Field::new(
+                                        "Fog Blending",
+                                        luminol_components::EnumComboBox::new(
+                                            (tileset.id, "fog_blend_type"),
+                                            &mut tileset.fog_blend_type,
+                                        ),
+                                    ))
+                                    .changed();
+
+                                fog_changed |= columns[2]
+                                    .add(luminol_components::Field::new(
+                                        "Fog Zoom",
+                                        egui::DragValue::new(&mut tileset.fog_zoom)
+                                            .range(100..=800)
+                                            .suffix("%"),
+                                    ))
+                                    .changed();
+
+                                fog_changed |= columns[3]
+                                    .add(luminol_components::Field::new(
+                                        "Fog SX",
+                                        egui::DragValue::new(&mut tileset.fog_sx).range(-256..=256),
+                                    ))
+                                    .changed();
+
+                                fog_changed |= columns[4]
+                                    .add(luminol_components::Field::new(
+                                        "Fog SY",
+                                        egui::DragValue::new(&mut tileset.fog_sy).range(-256..=256),
+                                    ))
+                                    .changed();
+                            });
+                        });
+
+                        if graphics_changed {
+                            modified = true;
+                            update_state.graphics.atlas_loader.reload_atlas(
+                                &update_state.graphics,
+                                update_state.filesystem,
+                                tileset,
+                            );
+                            self.flag_view = None;
+                        }
+                        if fog_changed {
+                            modified = true;
+                            self.fog_edit = Some(tileset.id);
+                        }
+                        if let Some(fog_tileset_id) = self.fog_edit {
+                            let editing = ui.ctx().dragged_id().is_some()
+                                || ui.memory(|m| m.focused().is_some());
+                            if !editing || fog_tileset_id != tileset.id {
+                                self.fog_edit = None;
+                                crate::tabs::map::reload_tileset_planes(ui.ctx(), fog_tileset_id);
+                            }
+                        }
+                        if planes_changed {
+                            modified = true;
+                            crate::tabs::map::reload_tileset_planes(ui.ctx(), tileset.id);
+                        }
+
+                        let flag_view = self.flag_view.get_or_insert_with(|| {
+                            luminol_components::TilesetFlagView::new(update_state, tileset)
+                        });
+
+                        // The flag tables need an entry for every tile of the tileset graphic
+                        let tile_count = flag_view.tile_count();
+                        for table in [
+                            &mut tileset.passages,
+                            &mut tileset.priorities,
+                            &mut tileset.terrain_tags,
+                        ] {
+                            if table.len() < tile_count {
+                                table.resize(tile_count);
+                                modified = true;
+                            }
+                        }
+
+                        ui.with_padded_stripe(true, |ui| {
+                            ui.horizontal_wrapped(|ui| {
+                                for flag in luminol_components::TilesetFlag::iter() {
+                                    ui.selectable_value(&mut self.flag, flag, flag.to_string());
+                                }
+                            });
+                            flag_view.flag = self.flag;
+
+                            egui::ScrollArea::vertical()
+                                .id_source((tileset.id, "tileset_flags"))
+                                .max_height(480.)
+                                .show_viewport(ui, |ui, rect| {
+                                    modified |=
+                                        flag_view.ui(update_state, ui, rect, tileset).changed();
+                                });
+                        });
+
+                        self.previous_tileset = Some(tileset.id);
+                    },
+                )
+            });
+
+        if response.is_some_and(|ir| ir.inner.is_some_and(|ir| ir.inner.modified)) {
+            modified = true;
+        }
+
+        if modified {
+            update_state.modified.set(true);
+            tilesets.modified = true;
+        }
+
+        drop(tilesets);
+
+        *update_state.data = data; // restore data
+    }
+}
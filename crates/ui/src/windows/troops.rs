@@ -0,0 +1,797 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use luminol_components::UiExt;
+use luminol_data::rpg::troop;
+
+/// The area of the screen enemies stand in during battle.
+const BATTLE_AREA: egui::Vec2 = egui::vec2(640., 320.);
+/// The y coordinate new and auto-arranged members stand on.
+const BASELINE: i32 = 304;
+/// The most members a troop can have in the default battle system.
+const MAX_MEMBERS: usize = 8;
+/// The size drawn for enemies whose battler couldn't be loaded.
+const PLACEHOLDER_SIZE: egui::Vec2 = egui::vec2(64., 64.);
+const SPANS: [&str; 3] = ["Battle", "Turn", "Moment"];
+
+pub struct Window {
+    selected_troop_name: Option<String>,
+    editor: Editor,
+    view: luminol_components::DatabaseView,
+}
+
+/// The state of the editor for the selected troop.
+struct Editor {
+    previous_troop: Option<usize>,
+    selected_page: usize,
+    previous_page: Option<usize>,
+
+    formation: Formation,
+    command_view: luminol_components::CommandView,
+}
+
+/// The battle formation of a troop, drawn over a battleback.
+struct Formation {
+    battleback_name: Option<camino::Utf8PathBuf>,
+    /// Loaded battlers and battlebacks, or `None` if they couldn't be loaded
+    textures: HashMap<camino::Utf8PathBuf, Option<Arc<luminol_graphics::Texture>>>,
+    selected_member: Option<usize>,
+    /// Movement of the dragged member that hasn't added up to a whole pixel yet
+    drag_remainder: egui::Vec2,
+}
+
+impl Formation {
+    fn texture(
+        &mut self,
+        update_state: &luminol_core::UpdateState<'_>,
+        directory: &str,
+        name: &camino::Utf8Path,
+    ) -> Option<Arc<luminol_graphics::Texture>> {
+        self.textures
+            .entry(camino::Utf8Path::new(directory).join(name))
+            .or_insert_with_key(|path| {
+                update_state
+                    .graphics
+                    .texture_loader
+                    .load_now(update_state.filesystem, path)
+                    .ok()
+            })
+            .clone()
+    }
+
+    /// The battler and name of the enemy of every member of a troop.
+    fn battlers(
+        &mut self,
+        update_state: &luminol_core::UpdateState<'_>,
+        data: &luminol_core::Data,
+        troop: &luminol_data::rpg::Troop,
+    ) -> Vec<(Option<Arc<luminol_graphics::Texture>>, String)> {
+        let enemies = data.enemies();
+        troop
+            .members
+            .iter()
+            .map(|member| {
+                let Some(enemy) = enemies.data.get(member.enemy_id) else {
+                    return (None, String::new());
+                };
+                let texture = enemy
+                    .battler_name
+                    .as_deref()
+                    .and_then(|name| self.texture(update_state, "Graphics/Battlers", name));
+                (texture, enemy.name.clone())
+            })
+            .collect()
+    }
+
+    /// Spreads the members evenly across the battle area, in order, standing on the same line.
+    fn arrange(
+        &mut self,
+        update_state: &luminol_core::UpdateState<'_>,
+        data: &luminol_core::Data,
+        troop: &mut luminol_data::rpg::Troop,
+    ) {
+        let widths = self
+            .battlers(update_state, data, troop)
+            .iter()
+            .map(|(texture, _)| {
+                texture
+                    .as_ref()
+                    .map_or(PLACEHOLDER_SIZE.x, |t| t.size_vec2().x)
+            })
+            .collect::<Vec<_>>();
+        let count = troop.members.len() as f32;
+        let total_width = widths.iter().sum::<f32>();
+
+        let mut left = 0.;
+        for (index, (member, width)) in troop.members.iter_mut().zip(widths).enumerate() {
+            let center = if total_width <= BATTLE_AREA.x {
+                // Leave the same space between every battler and the edges
+                let gap = (BATTLE_AREA.x - total_width) / (count + 1.);
+                left += gap;
+                let center = left + width / 2.;
+                left += width;
+                center
+            } else {
+                // The battlers don't fit side by side, so they'll overlap anyway
+                BATTLE_AREA.x * (index as f32 + 0.5) / count
+            };
+            member.x = center.round() as i32;
+            member.y = BASELINE;
+        }
+    }
+
+    /// Shows the formation and lets members be selected and dragged around. Returns `true` if a
+    /// member was moved.
+    fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        update_state: &luminol_core::UpdateState<'_>,
+        data: &luminol_core::Data,
+        troop: &mut luminol_data::rpg::Troop,
+    ) -> bool {
+        let battlers = self.battlers(update_state, data, troop);
+
+        let scale = (ui.available_width() / BATTLE_AREA.x).min(1.);
+        let (response, painter) =
+            ui.allocate_painter(BATTLE_AREA * scale, egui::Sense::click_and_drag());
+        let area = response.rect;
+        let full_uv = egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.));
+
+        // Battlers are drawn from their bottom center, like enemy sprites in battle
+        let member_rect = |member: &troop::Member, index: usize| {
+            let size = battlers[index]
+                .0
+                .as_ref()
+                .map_or(PLACEHOLDER_SIZE, |texture| texture.size_vec2());
+            egui::Rect::from_min_size(
+                area.min
+                    + egui::vec2(member.x as f32 - size.x / 2., member.y as f32 - size.y) * scale,
+                size * scale,
+            )
+        };
+        // Members lower on the screen are drawn in front, like in battle
+        let order = (0..troop.members.len())
+            .sorted_by_key(|&index| troop.members[index].y)
+            .collect::<Vec<_>>();
+
+        let pick = |pos: egui::Pos2| {
+            order
+                .iter()
+                .rev()
+                .copied()
+                .find(|&index| member_rect(&troop.members[index], index).contains(pos))
+        };
+        if response.drag_started() {
+            self.selected_member = ui.input(|i| i.pointer.press_origin()).and_then(pick);
+            self.drag_remainder = egui::Vec2::ZERO;
+        } else if response.clicked() {
+            self.selected_member = response.interact_pointer_pos().and_then(pick);
+        }
+
+        let mut modified = false;
+        if response.dragged() {
+            if let Some(member) = self
+                .selected_member
+                .and_then(|index| troop.members.get_mut(index))
+            {
+                self.drag_remainder += response.drag_delta() / scale;
+                let (dx, dy) = (self.drag_remainder.x.trunc(), self.drag_remainder.y.trunc());
+                self.drag_remainder -= egui::vec2(dx, dy);
+                if dx != 0. || dy != 0. {
+                    member.x = (member.x + dx as i32).clamp(0, BATTLE_AREA.x as i32);
+                    member.y = (member.y + dy as i32).clamp(0, BATTLE_AREA.y as i32);
+                    modified = true;
+                }
+            }
+        }
+
+        let battleback = self
+            .battleback_name
+            .clone()
+            .and_then(|name| self.texture(update_state, "Graphics/Battlebacks", &name));
+        match battleback {
+            Some(texture) => {
+                painter.image(texture.texture_id, area, full_uv, egui::Color32::WHITE);
+            }
+            None => {
+                painter.rect_filled(area, 0., ui.visuals().extreme_bg_color);
+            }
+        }
+
+        for &index in &order {
+            let member = &troop.members[index];
+            let rect = member_rect(member, index);
+            let (texture, name) = &battlers[index];
+
+            // Hidden members only appear during battle when an event makes them appear
+            let tint = if member.hidden {
+                egui::Color32::from_white_alpha(96)
+            } else {
+                egui::Color32::WHITE
+            };
+            match texture {
+                Some(texture) => {
+                    painter.image(texture.texture_id, rect, full_uv, tint);
+                }
+                None => {
+                    painter.rect_stroke(rect, 2., egui::Stroke::new(1., tint));
+                    painter.text(
+                        rect.center(),
+                        egui::Align2::CENTER_CENTER,
+                        name,
+                        egui::FontId::proportional(12.),
+                        tint,
+                    );
+                }
+            }
+            if self.selected_member == Some(index) {
+                painter.rect_stroke(rect, 2., ui.visuals().selection.stroke);
+            }
+
+            let label = if member.immortal {
+                format!("{} ∞", index + 1)
+            } else {
+                (index + 1).to_string()
+            };
+            let galley =
+                painter.layout_no_wrap(label, egui::FontId::monospace(12.), egui::Color32::WHITE);
+            let label_rect = egui::Rect::from_min_size(rect.min, galley.size()).expand(1.);
+            painter.rect_filled(label_rect, 2., egui::Color32::from_black_alpha(160));
+            painter.galley(rect.min, galley, egui::Color32::WHITE);
+        }
+
+        if let Some(index) = self.selected_member {
+            if let Some((_, name)) = battlers.get(index) {
+                response.on_hover_text(format!("{}: {name}", index + 1));
+            }
+        }
+
+        modified
+    }
+}
+
+/// Names a troop after its members, like `Ghost*2, Basilisk`.
+fn auto_name(troop: &luminol_data::rpg::Troop, enemies: &luminol_data::rpg::Enemies) -> String {
+    troop
+        .members
+        .iter()
+        .filter_map(|member| enemies.data.get(member.enemy_id))
+        .map(|enemy| enemy.name.as_str())
+        .dedup_with_count()
+        .map(|(count, name)| match count {
+            1 => name.to_string(),
+            count => format!("{name}*{count}"),
+        })
+        .join(", ")
+}
+
+/// Removes a member from a troop. Page conditions and commands that refer to the members after it
+/// are moved down with them. The conditions on the removed member are turned off, and the commands
+/// that refer to it are pointed past the last member, where they have no effect.
+fn remove_member(troop: &mut luminol_data::rpg::Troop, index: usize) {
+    troop.members.remove(index);
+
+    for page in &mut troop.pages {
+        let condition = &mut page.condition;
+        match condition.enemy_index.cmp(&index) {
+            std::cmp::Ordering::Less => {}
+            std::cmp::Ordering::Equal => {
+                condition.enemy_valid = false;
+                condition.enemy_index = 0;
+            }
+            std::cmp::Ordering::Greater => condition.enemy_index -= 1,
+        }
+
+        for command in &mut page.list {
+            command.for_each_enemy_index_mut(|member| match (*member as usize).cmp(&index) {
+                std::cmp::Ordering::Less => {}
+                std::cmp::Ordering::Equal => *member = MAX_MEMBERS as i32,
+                std::cmp::Ordering::Greater => *member -= 1,
+            });
+        }
+    }
+}
+
+impl Window {
+    pub fn new(update_state: &luminol_core::UpdateState<'_>) -> Self {
+        Self {
+            selected_troop_name: None,
+            editor: Editor {
+                previous_troop: None,
+                selected_page: 0,
+                previous_page: None,
+
+                formation: Formation {
+                    battleback_name: update_state.data.system().battleback_name.clone(),
+                    textures: HashMap::new(),
+                    selected_member: None,
+                    drag_remainder: egui::Vec2::ZERO,
+                },
+                command_view: luminol_components::CommandView::new("troop_commands"),
+            },
+            view: luminol_components::DatabaseView::new(),
+        }
+    }
+}
+
+impl Editor {
+    fn members_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        update_state: &luminol_core::UpdateState<'_>,
+        data: &luminol_core::Data,
+        troop: &mut luminol_data::rpg::Troop,
+    ) -> bool {
+        let mut modified = false;
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    troop.members.len() < MAX_MEMBERS,
+                    egui::Button::new("Add Member"),
+                )
+                .clicked()
+            {
+                // Add another of the selected enemy, which is the quickest way to fill a troop
+                let enemy_id = self
+                    .formation
+                    .selected_member
+                    .and_then(|index| troop.members.get(index))
+                    .map_or(0, |member| member.enemy_id);
+                troop.members.push(troop::Member {
+                    enemy_id,
+                    x: BATTLE_AREA.x as i32 / 2,
+                    y: BASELINE,
+                    ..Default::default()
+                });
+                self.formation.selected_member = Some(troop.members.len() - 1);
+                modified = true;
+            }
+
+            if ui
+                .button("Auto-arrange")
+                .on_hover_text("Spread the members evenly across the screen")
+                .clicked()
+            {
+                self.formation.arrange(update_state, data, troop);
+                modified = true;
+            }
+
+            if ui
+                .button("Auto-name")
+                .on_hover_text("Name the troop after its members")
+                .clicked()
+            {
+                troop.name = auto_name(troop, &data.enemies());
+                modified = true;
+            }
+
+            let battlebacks = std::iter::once(data.system().battleback_name.clone())
+                .chain(
+                    data.tilesets()
+                        .data
+                        .iter()
+                        .map(|tileset| tileset.battleback_name.clone()),
+                )
+                .flatten()
+                .unique()
+                .collect::<Vec<_>>();
+            egui::ComboBox::from_id_source("troop_battleback")
+                .selected_text(
+                    self.formation
+                        .battleback_name
+                        .as_ref()
+                        .map_or("(None)", |name| name.as_str()),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.formation.battleback_name, None, "(None)");
+                    for name in battlebacks {
+                        let text = name.to_string();
+                        ui.selectable_value(&mut self.formation.battleback_name, Some(name), text);
+                    }
+                })
+                .response
+                .on_hover_text("The battleback to preview the formation over");
+        });
+
+        modified |= self.formation.ui(ui, update_state, data, troop);
+
+        let enemies = data.enemies();
+        let troop_id = troop.id;
+        let mut removed = None;
+        for (index, member) in troop.members.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .selectable_label(
+                        self.formation.selected_member == Some(index),
+                        format!("{}.", index + 1),
+                    )
+                    .clicked()
+                {
+                    self.formation.selected_member = Some(index);
+                }
+                modified |= ui
+                    .add(
+                        egui::DragValue::new(&mut member.x)
+                            .range(0..=BATTLE_AREA.x as i32)
+                            .prefix("x: "),
+                    )
+                    .changed();
+                modified |= ui
+                    .add(
+                        egui::DragValue::new(&mut member.y)
+                            .range(0..=BATTLE_AREA.y as i32)
+                            .prefix("y: "),
+                    )
+                    .changed();
+                modified |= ui
+                    .checkbox(&mut member.hidden, "Hidden")
+                    .on_hover_text("Only appears when an event makes it appear")
+                    .changed();
+                modified |= ui
+                    .checkbox(&mut member.immortal, "Immortal")
+                    .on_hover_text("Can't be killed until an event makes it mortal")
+                    .changed();
+                if ui.button("✖").on_hover_text("Remove").clicked() {
+                    removed = Some(index);
+                }
+                modified |= ui
+                    .add(luminol_components::OptionalIdComboBox::new(
+                        update_state,
+                        (troop_id, index, "enemy_id"),
+                        &mut member.enemy_id,
+                        0..enemies.data.len(),
+                        |id| {
+                            enemies.data.get(id).map_or_else(
+                                || "".into(),
+                                |e| format!("{:0>4}: {}", id + 1, e.name),
+                            )
+                        },
+                    ))
+                    .changed();
+            });
+        }
+
+        if let Some(index) = removed {
+            remove_member(troop, index);
+            self.formation.selected_member = None;
+            modified = true;
+        }
+
+        modified
+    }
+
+    fn condition_ui(
+        ui: &mut egui::Ui,
+        update_state: &luminol_core::UpdateState<'_>,
+        data: &luminol_core::Data,
+        members: &[troop::Member],
+        troop_id: usize,
+        condition: &mut troop::Condition,
+    ) -> bool {
+        let mut modified = false;
+        let enemies = data.enemies();
+        let actors = data.actors();
+        let system = data.system();
+
+        ui.horizontal(|ui| {
+            modified |= ui.checkbox(&mut condition.turn_valid, "Turn").changed();
+            ui.add_enabled_ui(condition.turn_valid, |ui| {
+                modified |= ui
+                    .add(egui::DragValue::new(&mut condition.turn_a).range(0..=i32::MAX))
+                    .changed();
+                ui.label("+");
+                modified |= ui
+                    .add(egui::DragValue::new(&mut condition.turn_b).range(0..=i32::MAX))
+                    .changed();
+                ui.label("× X");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            modified |= ui.checkbox(&mut condition.enemy_valid, "Enemy").changed();
+            ui.add_enabled_ui(condition.enemy_valid, |ui| {
+                let member_name = |index: usize| {
+                    let name = members
+                        .get(index)
+                        .and_then(|member| enemies.data.get(member.enemy_id))
+                        .map_or("", |enemy| enemy.name.as_str());
+                    format!("{}. {name}", index + 1)
+                };
+                egui::ComboBox::from_id_source((troop_id, "enemy_index"))
+                    .selected_text(member_name(condition.enemy_index))
+                    .show_ui(ui, |ui| {
+                        for index in 0..members.len() {
+                            modified |= ui
+                                .selectable_value(
+                                    &mut condition.enemy_index,
+                                    index,
+                                    member_name(index),
+                                )
+                                .changed();
+                        }
+                    });
+                ui.label("HP ≤");
+                modified |= ui
+                    .add(
+                        egui::DragValue::new(&mut condition.enemy_hp)
+                            .range(0..=100)
+                            .suffix("%"),
+                    )
+                    .changed();
+            });
+        });
+
+        ui.horizontal(|ui| {
+            modified |= ui.checkbox(&mut condition.actor_valid, "Actor").changed();
+            ui.add_enabled_ui(condition.actor_valid, |ui| {
+                ui.label("HP ≤");
+                modified |= ui
+                    .add(
+                        egui::DragValue::new(&mut condition.actor_hp)
+                            .range(0..=100)
+                            .suffix("%"),
+                    )
+                    .changed();
+                modified |= ui
+                    .add(luminol_components::OptionalIdComboBox::new(
+                        update_state,
+                        (troop_id, "actor_id"),
+                        &mut condition.actor_id,
+                        0..actors.data.len(),
+                        |id| {
+                            actors.data.get(id).map_or_else(
+                                || "".into(),
+                                |a| format!("{:0>4}: {}", id + 1, a.name),
+                            )
+                        },
+                    ))
+                    .changed();
+            });
+        });
+
+        ui.horizontal(|ui| {
+            modified |= ui.checkbox(&mut condition.switch_valid, "Switch").changed();
+            ui.add_enabled_ui(condition.switch_valid, |ui| {
+                ui.label("is ON");
+                modified |= ui
+                    .add(luminol_components::OptionalIdComboBox::new(
+                        update_state,
+                        (troop_id, "switch_id"),
+                        &mut condition.switch_id,
+                        0..system.switches.len(),
+                        |id| {
+                            system
+                                .switches
+                                .get(id)
+                                .map_or_else(|| "".into(), |s| format!("{:0>4}: {}", id + 1, s))
+                        },
+                    ))
+                    .changed();
+            });
+        });
+
+        modified
+    }
+
+    fn pages_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        update_state: &mut luminol_core::UpdateState<'_>,
+        data: &mut luminol_core::Data,
+        troop: &mut luminol_data::rpg::Troop,
+    ) -> bool {
+        let mut modified = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Page:");
+            for index in 0..troop.pages.len() {
+                ui.selectable_value(&mut self.selected_page, index, format!("{}", index + 1));
+            }
+
+            if ui
+                .button(egui::RichText::new("Add").color(egui::Color32::LIGHT_GREEN))
+                .clicked()
+            {
+                troop.pages.push(troop::Page::default());
+                self.selected_page = troop.pages.len() - 1;
+                modified = true;
+            }
+
+            let button =
+                egui::Button::new(egui::RichText::new("Delete").color(egui::Color32::LIGHT_RED));
+            if ui.add_enabled(troop.pages.len() > 1, button).clicked() {
+                troop.pages.remove(self.selected_page);
+                self.selected_page = self.selected_page.saturating_sub(1);
+                self.previous_page = None;
+                modified = true;
+            }
+
+            if ui.button("Clear").clicked() {
+                troop.pages[self.selected_page] = troop::Page::default();
+                self.previous_page = None;
+                modified = true;
+            }
+        });
+
+        if self.previous_page != Some(self.selected_page) {
+            // Start with a fresh selection for the new page's command list
+            self.command_view = luminol_components::CommandView::new(
+                egui::Id::new("troop_commands")
+                    .with(troop.id)
+                    .with(self.selected_page),
+            );
+            self.previous_page = Some(self.selected_page);
+        }
+
+        let page = &mut troop.pages[self.selected_page];
+
+        ui.horizontal(|ui| {
+            ui.label("Conditions");
+            ui.separator();
+            ui.label("Span");
+            let span = usize::try_from(page.span).unwrap_or_default().min(2);
+            egui::ComboBox::from_id_source((troop.id, "span"))
+                .selected_text(SPANS[span])
+                .show_ui(ui, |ui| {
+                    for (value, name) in SPANS.into_iter().enumerate() {
+                        modified |= ui
+                            .selectable_value(&mut page.span, value as i32, name)
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Battle: runs once per battle. Turn: runs once per turn. Moment: runs \
+                     whenever the conditions are met.",
+                );
+        });
+        ui.group(|ui| {
+            modified |= Self::condition_ui(
+                ui,
+                update_state,
+                data,
+                &troop.members,
+                troop.id,
+                &mut page.condition,
+            );
+        });
+
+        egui::ScrollArea::both()
+            .id_source((troop.id, "commands"))
+            .max_height(300.)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                // Command lists look up the names of everything else in the data, so it's put back
+                // while the list is shown
+                std::mem::swap(update_state.data, data);
+                modified |= self
+                    .command_view
+                    .ui(ui, update_state, &mut page.list)
+                    .changed();
+                std::mem::swap(update_state.data, data);
+            });
+
+        modified
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("troop_editor")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        // Take data to avoid borrow checker issues
+        let mut data = std::mem::take(update_state.data);
+        // The troops are taken out of the data as well, so it can be lent to the command lists
+        let mut troops = std::mem::take(&mut data.troops().data);
+
+        let mut modified = false;
+
+        self.selected_troop_name = None;
+
+        let name = if let Some(name) = &self.selected_troop_name {
+            format!("Editing troop {:?}", name)
+        } else {
+            "Troop Editor".into()
+        };
+
+        let response = egui::Window::new(name)
+            .id(self.id())
+            .default_width(700.)
+            .open(open)
+            .show(ctx, |ui| {
+                self.view.show(
+                    ui,
+                    update_state,
+                    "Troops",
+                    &mut troops,
+                    |troop| format!("{:0>4}: {}", troop.id + 1, troop.name),
+                    |ui, troops, id, update_state| {
+                        let troop = &mut troops[id];
+                        self.selected_troop_name = Some(troop.name.clone());
+
+                        let editor = &mut self.editor;
+                        if editor.previous_troop != Some(troop.id) {
+                            editor.formation.selected_member = None;
+                            editor.selected_page = 0;
+                            editor.previous_page = None;
+                        }
+                        if troop.pages.is_empty() {
+                            troop.pages.push(troop::Page::default());
+                        }
+                        editor.selected_page = editor.selected_page.min(troop.pages.len() - 1);
+
+                        ui.with_padded_stripe(false, |ui| {
+                            modified |= ui
+                                .add(luminol_components::Field::new(
+                                    "Name",
+                                    egui::TextEdit::singleline(&mut troop.name)
+                                        .desired_width(f32::INFINITY),
+                                ))
+                                .changed();
+                        });
+
+                        ui.with_padded_stripe(true, |ui| {
+                            modified |= editor.members_ui(ui, update_state, &data, troop);
+                        });
+
+                        ui.with_padded_stripe(false, |ui| {
+                            modified |= editor.pages_ui(ui, update_state, &mut data, troop);
+                        });
+
+                        editor.previous_troop = Some(troop.id);
+                    },
+                )
+            });
+
+        if response.is_some_and(|ir| ir.inner.is_some_and(|ir| ir.inner.modified)) {
+            modified = true;
+        }
+
+        let mut data_troops = data.troops();
+        data_troops.data = troops;
+        if modified {
+            update_state.modified.set(true);
+            data_troops.modified = true;
+        }
+        drop(data_troops);
+
+        *update_state.data = data; // restore data
+    }
+}
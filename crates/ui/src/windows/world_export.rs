@@ -0,0 +1,590 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use color_eyre::eyre::WrapErr;
+use std::collections::{HashSet, VecDeque};
+use std::io::Write;
+
+use super::world_graph::Graph;
+
+/// Number of empty tiles left between maps that aren't connected
+const LAYOUT_GAP: i32 = 4;
+
+/// The world export.
+/// Lays out several maps on one canvas, either automatically from the transfers between them or
+/// by dragging them around, and saves them as a single image.
+#[derive(Default)]
+pub struct Window {
+    graph: Option<Graph>,
+    /// Layout of every map, in the same order as the nodes of the graph
+    placements: Vec<Placement>,
+
+    pan: egui::Vec2,
+    /// Size of a tile on the canvas in points
+    zoom: f32,
+    /// The map being dragged and the part of the drag distance that is smaller than a tile
+    dragged: Option<(usize, egui::Vec2)>,
+
+    /// The exported image is scaled down by this factor
+    downscale: u32,
+    include_events: bool,
+    include_fog: bool,
+
+    export: Option<Export>,
+    save_promise: Option<poll_promise::Promise<color_eyre::Result<()>>>,
+}
+
+#[derive(Clone, Copy)]
+struct Placement {
+    included: bool,
+    /// Position of the top left corner of the map in tiles
+    position: (i32, i32),
+}
+
+/// A world export in progress. Maps are rendered one at a time, to avoid keeping the rendered
+/// images of every map on the GPU at once.
+struct Export {
+    image: image::RgbaImage,
+    /// Position of the top left corner of the image in tiles
+    origin: (i32, i32),
+    downscale: u32,
+    /// Indices of the maps that still need to be rendered
+    pending: Vec<usize>,
+    current: Option<(
+        usize,
+        poll_promise::Promise<color_eyre::Result<image::RgbaImage>>,
+    )>,
+    total: usize,
+}
+
+/// Returns the direction from the inside of a map towards the nearest edge of the map from a
+/// position.
+fn edge_direction(position: (i32, i32), size: [usize; 2]) -> (i32, i32) {
+    let (x, y) = position;
+    let (width, height) = (size[0] as i32, size[1] as i32);
+    [
+        (x, (-1, 0)),
+        (width - 1 - x, (1, 0)),
+        (y, (0, -1)),
+        (height - 1 - y, (0, 1)),
+    ]
+    .into_iter()
+    .min_by_key(|(distance, _)| *distance)
+    .map(|(_, direction)| direction)
+    .unwrap()
+}
+
+/// Returns the area a map covers on the canvas as (min x, min y, max x, max y) in tiles.
+fn map_rect(graph: &Graph, placements: &[Placement], index: usize) -> (i32, i32, i32, i32) {
+    let (x, y) = placements[index].position;
+    let [width, height] = graph.nodes[index].thumbnail.size();
+    (x, y, x + width as i32, y + height as i32)
+}
+
+/// Places the included maps so that maps connected by a transfer near the edge of a map are next
+/// to each other. Maps that would overlap an already placed map, and maps that aren't connected to
+/// any placed map, are placed to the right of the maps placed so far.
+fn auto_layout(graph: &Graph, placements: &mut [Placement], start_map_id: usize) {
+    let mut roots = (0..graph.nodes.len())
+        .filter(|&index| placements[index].included)
+        .collect::<Vec<_>>();
+    // Start from the player's starting map if it's included
+    roots.sort_by_key(|&index| graph.nodes[index].map_id != start_map_id);
+
+    let mut placed = HashSet::new();
+    for root in roots {
+        if placed.contains(&root) {
+            continue;
+        }
+
+        let bounds = placed
+            .iter()
+            .map(|&index| map_rect(graph, placements, index))
+            .reduce(
+                |(a_min_x, a_min_y, a_max_x, a_max_y), (b_min_x, b_min_y, b_max_x, b_max_y)| {
+                    (
+                        a_min_x.min(b_min_x),
+                        a_min_y.min(b_min_y),
+                        a_max_x.max(b_max_x),
+                        a_max_y.max(b_max_y),
+                    )
+                },
+            );
+        placements[root].position =
+            bounds.map_or((0, 0), |(_, min_y, max_x, _)| (max_x + LAYOUT_GAP, min_y));
+        placed.insert(root);
+
+        let mut queue = VecDeque::from([root]);
+        while let Some(index) = queue.pop_front() {
+            let map_id = graph.nodes[index].map_id;
+            let (x, y) = placements[index].position;
+
+            for edge in graph.edges.iter() {
+                let (Some(&source), Some(&target)) = (
+                    graph.node_indices.get(&edge.source_map),
+                    graph.node_indices.get(&edge.target_map),
+                ) else {
+                    continue;
+                };
+
+                // Work out where the other map goes if the tiles on both sides of the transfer
+                // were next to each other
+                let (dx, dy) =
+                    edge_direction(edge.event_position, graph.nodes[source].thumbnail.size());
+                let (other, position) = if edge.source_map == map_id {
+                    (
+                        target,
+                        (
+                            x + edge.event_position.0 + dx - edge.target_position.0,
+                            y + edge.event_position.1 + dy - edge.target_position.1,
+                        ),
+                    )
+                } else if edge.target_map == map_id {
+                    (
+                        source,
+                        (
+                            x + edge.target_position.0 - edge.event_position.0 - dx,
+                            y + edge.target_position.1 - edge.event_position.1 - dy,
+                        ),
+                    )
+                } else {
+                    continue;
+                };
+
+                if !placements[other].included || placed.contains(&other) {
+                    continue;
+                }
+
+                let previous_position = placements[other].position;
+                placements[other].position = position;
+                let (min_x, min_y, max_x, max_y) = map_rect(graph, placements, other);
+                let overlaps = placed.iter().any(|&placed_index| {
+                    let (other_min_x, other_min_y, other_max_x, other_max_y) =
+                        map_rect(graph, placements, placed_index);
+                    min_x < other_max_x
+                        && other_min_x < max_x
+                        && min_y < other_max_y
+                        && other_min_y < max_y
+                });
+                if overlaps {
+                    placements[other].position = previous_position;
+                    continue;
+                }
+
+                placed.insert(other);
+                queue.push_back(other);
+            }
+        }
+    }
+}
+
+impl Window {
+    /// Starts rendering the map at the given index.
+    fn render_map(
+        &self,
+        index: usize,
+        update_state: &luminol_core::UpdateState<'_>,
+    ) -> color_eyre::Result<poll_promise::Promise<color_eyre::Result<image::RgbaImage>>> {
+        let graph = self.graph.as_ref().expect("world graph not loaded");
+        let map_id = graph.nodes[index].map_id;
+
+        let map = update_state.data.get_or_load_map(
+            map_id,
+            update_state.filesystem,
+            update_state
+                .project_config
+                .as_ref()
+                .expect("project not loaded"),
+        );
+        let tilesets = update_state.data.tilesets();
+        let tileset = tilesets.data.get(map.tileset_id).ok_or_else(|| {
+            color_eyre::eyre::eyre!(
+                "Map {map_id} uses tileset {} which doesn't exist",
+                map.tileset_id
+            )
+        })?;
+
+        let passages = luminol_data::Table2::new(map.data.xsize(), map.data.ysize());
+        let mut renderer = luminol_graphics::Map::new(
+            &update_state.graphics,
+            update_state.filesystem,
+            &map,
+            tileset,
+            &passages,
+        )
+        .wrap_err_with(|| format!("While rendering map {map_id}"))?;
+        renderer.grid_enabled = false;
+        renderer.coll_enabled = false;
+        renderer.event_enabled = self.include_events;
+        renderer.fog_enabled = self.include_fog;
+
+        Ok(luminol_core::spawn_future(
+            luminol_components::render_map_image(&update_state.graphics, &mut renderer, &map),
+        ))
+    }
+
+    fn start_export(&mut self) {
+        let Some(graph) = &self.graph else {
+            return;
+        };
+
+        let pending = (0..graph.nodes.len())
+            .rev()
+            .filter(|&index| self.placements[index].included)
+            .collect::<Vec<_>>();
+        let Some(bounds) = pending
+            .iter()
+            .map(|&index| map_rect(graph, &self.placements, index))
+            .reduce(
+                |(a_min_x, a_min_y, a_max_x, a_max_y), (b_min_x, b_min_y, b_max_x, b_max_y)| {
+                    (
+                        a_min_x.min(b_min_x),
+                        a_min_y.min(b_min_y),
+                        a_max_x.max(b_max_x),
+                        a_max_y.max(b_max_y),
+                    )
+                },
+            )
+        else {
+            return;
+        };
+
+        let (min_x, min_y, max_x, max_y) = bounds;
+        let downscale = self.downscale.max(1);
+        self.export = Some(Export {
+            image: image::RgbaImage::new(
+                (max_x - min_x) as u32 * 32 / downscale,
+                (max_y - min_y) as u32 * 32 / downscale,
+            ),
+            origin: (min_x, min_y),
+            downscale,
+            total: pending.len(),
+            pending,
+            current: None,
+        });
+    }
+
+    /// Advances the export in progress, if any. Called once per frame.
+    fn poll_export(&mut self, update_state: &mut luminol_core::UpdateState<'_>) {
+        let Some(export) = &mut self.export else {
+            return;
+        };
+        update_state.ctx.request_repaint();
+
+        if let Some((index, promise)) = export.current.take() {
+            match promise.try_take() {
+                Ok(Ok(map_image)) => {
+                    let map_image = if export.downscale > 1 {
+                        image::imageops::resize(
+                            &map_image,
+                            map_image.width() / export.downscale,
+                            map_image.height() / export.downscale,
+                            image::imageops::FilterType::Triangle,
+                        )
+                    } else {
+                        map_image
+                    };
+                    let (x, y) = self.placements[index].position;
+                    image::imageops::overlay(
+                        &mut export.image,
+                        &map_image,
+                        ((x - export.origin.0) * 32 / export.downscale as i32) as i64,
+                        ((y - export.origin.1) * 32 / export.downscale as i32) as i64,
+                    );
+                }
+                Ok(Err(error)) => {
+                    luminol_core::error!(update_state.toasts, error);
+                    self.export = None;
+                    return;
+                }
+                Err(promise) => {
+                    export.current = Some((index, promise));
+                    return;
+                }
+            }
+        }
+
+        if let Some(index) = export.pending.pop() {
+            match self.render_map(index, update_state) {
+                Ok(promise) => {
+                    if let Some(export) = &mut self.export {
+                        export.current = Some((index, promise));
+                    }
+                }
+                Err(error) => {
+                    luminol_core::error!(update_state.toasts, error);
+                    self.export = None;
+                }
+            }
+            return;
+        }
+
+        let Some(export) = self.export.take() else {
+            return;
+        };
+        let c = "While saving the world image";
+        self.save_promise = Some(luminol_core::spawn_future(async move {
+            let mut file = luminol_filesystem::host::File::new().wrap_err(c)?;
+            export
+                .image
+                .write_to(
+                    &mut std::io::BufWriter::new(&mut file),
+                    image::ImageFormat::Png,
+                )
+                .wrap_err(c)?;
+            file.flush().wrap_err(c)?;
+            file.save("world.png", "Portable Network Graphics")
+                .await
+                .wrap_err(c)
+        }));
+    }
+
+    fn canvas_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(graph) = &mut self.graph else {
+            return;
+        };
+
+        let (response, painter) =
+            ui.allocate_painter(ui.available_size(), egui::Sense::click_and_drag());
+        let rect = response.rect;
+        let painter = painter.with_clip_rect(rect);
+
+        if response.hovered() {
+            let zoom_delta = ui.input(|i| i.zoom_delta());
+            self.zoom = (self.zoom * zoom_delta).clamp(0.25, 16.);
+        }
+
+        let zoom = self.zoom;
+        let pan = self.pan;
+        let placements = &mut self.placements;
+        let screen_rect = |graph: &Graph, placements: &[Placement], index: usize| {
+            let (min_x, min_y, max_x, max_y) = map_rect(graph, placements, index);
+            egui::Rect::from_min_max(
+                rect.min + pan + egui::vec2(min_x as f32, min_y as f32) * zoom,
+                rect.min + pan + egui::vec2(max_x as f32, max_y as f32) * zoom,
+            )
+        };
+
+        // Maps are dragged a whole tile at a time
+        if response.drag_started() {
+            self.dragged = response.interact_pointer_pos().and_then(|pos| {
+                (0..placements.len())
+                    .rev()
+                    .filter(|&index| placements[index].included)
+                    .find(|&index| screen_rect(graph, placements, index).contains(pos))
+                    .map(|index| (index, egui::Vec2::ZERO))
+            });
+        }
+        if response.dragged() {
+            match &mut self.dragged {
+                Some((index, remainder)) => {
+                    *remainder += response.drag_delta() / zoom;
+                    let tiles = remainder.trunc();
+                    *remainder -= tiles;
+                    let (x, y) = &mut placements[*index].position;
+                    *x += tiles.x as i32;
+                    *y += tiles.y as i32;
+                }
+                None => self.pan += response.drag_delta(),
+            }
+        } else {
+            self.dragged = None;
+        }
+
+        painter.rect_filled(rect, 0., ui.visuals().extreme_bg_color);
+
+        let font = egui::FontId::proportional(12.);
+        for index in 0..placements.len() {
+            if !placements[index].included {
+                continue;
+            }
+            let map_rect = screen_rect(graph, placements, index);
+            if !rect.intersects(map_rect) {
+                continue;
+            }
+
+            let node = &mut graph.nodes[index];
+            painter.image(
+                node.thumbnail.texture_id(ui.ctx()),
+                map_rect,
+                egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.)),
+                egui::Color32::WHITE,
+            );
+            let stroke = if self.dragged.is_some_and(|(dragged, _)| dragged == index) {
+                egui::Stroke::new(2., ui.visuals().selection.stroke.color)
+            } else {
+                egui::Stroke::new(1., ui.visuals().weak_text_color())
+            };
+            painter.rect_stroke(map_rect, 0., stroke);
+            painter.text(
+                map_rect.left_top() + egui::vec2(2., 2.),
+                egui::Align2::LEFT_TOP,
+                format!("{:0>3}: {}", node.map_id, node.name),
+                font.clone(),
+                ui.visuals().strong_text_color(),
+            );
+        }
+    }
+
+    fn side_panel_ui(&mut self, ui: &mut egui::Ui, update_state: &luminol_core::UpdateState<'_>) {
+        let Some(graph) = &self.graph else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            if ui.button("All").clicked() {
+                self.placements
+                    .iter_mut()
+                    .for_each(|placement| placement.included = true);
+            }
+            if ui.button("None").clicked() {
+                self.placements
+                    .iter_mut()
+                    .for_each(|placement| placement.included = false);
+            }
+            if ui
+                .button("Auto layout")
+                .on_hover_text(
+                    "Place maps connected by transfers near their edges next to each other",
+                )
+                .clicked()
+            {
+                let start_map_id = update_state.data.system().start_map_id;
+                auto_layout(graph, &mut self.placements, start_map_id);
+            }
+        });
+
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 120.)
+            .show(ui, |ui| {
+                for (node, placement) in graph.nodes.iter().zip(self.placements.iter_mut()) {
+                    ui.checkbox(
+                        &mut placement.included,
+                        format!("{:0>3}: {}", node.map_id, node.name),
+                    );
+                }
+            });
+
+        ui.separator();
+
+        egui::ComboBox::from_label("Scale")
+            .selected_text(format!("{}%", 100 / self.downscale.max(1)))
+            .show_ui(ui, |ui| {
+                for downscale in [1, 2, 4, 8] {
+                    ui.selectable_value(
+                        &mut self.downscale,
+                        downscale,
+                        format!("{}%", 100 / downscale),
+                    );
+                }
+            });
+        ui.checkbox(&mut self.include_events, "Events");
+        ui.checkbox(&mut self.include_fog, "Fog");
+
+        if let Some(export) = &self.export {
+            let done = export.total - export.pending.len() - export.current.is_some() as usize;
+            ui.add(
+                egui::ProgressBar::new(done as f32 / export.total.max(1) as f32).text(format!(
+                    "Rendering map {} of {}",
+                    done + 1,
+                    export.total
+                )),
+            );
+        } else if ui
+            .add_enabled(
+                self.save_promise.is_none()
+                    && self.placements.iter().any(|placement| placement.included),
+                egui::Button::new("Export PNG"),
+            )
+            .clicked()
+        {
+            self.start_export();
+        }
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_world_export")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        if self.graph.is_none() {
+            let graph = Graph::new(update_state);
+            self.placements = vec![
+                Placement {
+                    included: false,
+                    position: (0, 0),
+                };
+                graph.nodes.len()
+            ];
+            self.graph = Some(graph);
+            self.zoom = 2.;
+            self.downscale = 1;
+            self.include_events = true;
+            self.include_fog = true;
+        }
+
+        egui::Window::new("World Export")
+            .id(self.id())
+            .open(open)
+            .default_size([800., 600.])
+            .show(ctx, |ui| {
+                egui::SidePanel::left("luminol_world_export_maps")
+                    .default_width(220.)
+                    .show_inside(ui, |ui| self.side_panel_ui(ui, update_state));
+
+                egui::CentralPanel::default().show_inside(ui, |ui| self.canvas_ui(ui));
+            });
+
+        self.poll_export(update_state);
+
+        if let Some(p) = self.save_promise.take() {
+            match p.try_take() {
+                Ok(Ok(())) => {}
+                Ok(Err(error))
+                    if !matches!(
+                        error.root_cause().downcast_ref(),
+                        Some(luminol_filesystem::Error::CancelledLoading)
+                    ) =>
+                {
+                    luminol_core::error!(update_state.toasts, error);
+                }
+                Ok(Err(_)) => {}
+                Err(p) => self.save_promise = Some(p),
+            }
+        }
+    }
+}
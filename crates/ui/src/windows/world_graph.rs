@@ -0,0 +1,571 @@
+// Copyright (C) 2024 Melody Madeline Lyons
+//
+// This file is part of Luminol.
+//
+// Luminol is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Luminol is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Luminol.  If not, see <http://www.gnu.org/licenses/>.
+//
+//     Additional permission under GNU GPL version 3 section 7
+//
+// If you modify this Program, or any covered work, by linking or combining
+// it with Steamworks API by Valve Corporation, containing parts covered by
+// terms of the Steamworks API by Valve Corporation, the licensors of this
+// Program grant you additional permission to convey the resulting work.
+
+use std::collections::{HashMap, HashSet};
+
+/// Ideal distance between two connected maps in the layout
+const LAYOUT_DISTANCE: f32 = 200.;
+/// Size of the longest side of map thumbnails
+const THUMBNAIL_SIZE: f32 = 64.;
+
+/// The world graph.
+/// Shows which maps are connected by Transfer Player commands so that orphaned maps and one-way
+/// transfers can be found.
+#[derive(Default)]
+pub struct Window {
+    graph: Option<Graph>,
+    pan: egui::Vec2,
+    zoom: f32,
+    selected_map: Option<usize>,
+    dragged_node: Option<usize>,
+}
+
+/// The maps of the project and the transfers between them.
+pub(super) struct Graph {
+    pub(super) nodes: Vec<Node>,
+    pub(super) edges: Vec<Edge>,
+    /// Maps a map ID to its index in `nodes`
+    pub(super) node_indices: HashMap<usize, usize>,
+    /// How far nodes are allowed to move in one step of the layout, decreases over time
+    temperature: f32,
+}
+
+pub(super) struct Node {
+    pub(super) map_id: usize,
+    pub(super) name: String,
+    position: egui::Vec2,
+    pub(super) thumbnail: luminol_components::Minimap,
+}
+
+pub(super) struct Edge {
+    pub(super) source_map: usize,
+    event_id: usize,
+    event_name: String,
+    pub(super) event_position: (i32, i32),
+    /// Whether the event page does nothing but move the player to another place
+    door: bool,
+    pub(super) target_map: usize,
+    pub(super) target_position: (i32, i32),
+}
+
+/// Whether an event page is a door: it runs an unconditional Transfer Player command and
+/// otherwise only sets up the transition (sound effects, fades, move routes and waits), as opposed
+/// to a cutscene that transfers the player somewhere as part of a longer script.
+fn is_door(list: &[luminol_data::rpg::EventCommand]) -> bool {
+    list.iter()
+        .any(|command| command.code == 201 && command.indent == 0)
+        && list.iter().all(|command| {
+            matches!(
+                command.code,
+                0 | 106 | 108 | 201 | 209 | 210 | 221 | 222 | 223 | 249 | 250 | 408 | 509
+            )
+        })
+}
+
+impl Edge {
+    fn label(&self) -> String {
+        format!(
+            "EV{:0>3} {} ({}, {}) → ({}, {})",
+            self.event_id,
+            self.event_name,
+            self.event_position.0,
+            self.event_position.1,
+            self.target_position.0,
+            self.target_position.1
+        )
+    }
+}
+
+impl Graph {
+    /// Scans every map for Transfer Player commands.
+    pub(super) fn new(update_state: &mut luminol_core::UpdateState<'_>) -> Self {
+        let map_infos = update_state.data.map_infos();
+        let tilesets = update_state.data.tilesets();
+        let config = update_state
+            .project_config
+            .as_ref()
+            .expect("project not loaded");
+
+        let mut map_ids = map_infos.data.keys().copied().collect::<Vec<_>>();
+        map_ids.sort_unstable();
+
+        let mut nodes = Vec::with_capacity(map_ids.len());
+        let mut edges = Vec::new();
+        let mut seen_edges = HashSet::new();
+
+        for (index, &map_id) in map_ids.iter().enumerate() {
+            let result =
+                update_state
+                    .data
+                    .with_map(map_id, update_state.filesystem, config, |map| {
+                        let tileset = tilesets.data.get(map.tileset_id).ok_or_else(|| {
+                            color_eyre::eyre::eyre!(
+                                "Map {map_id} uses tileset {} which doesn't exist",
+                                map.tileset_id
+                            )
+                        })?;
+                        let atlas = update_state.graphics.atlas_loader.load_atlas(
+                            &update_state.graphics,
+                            update_state.filesystem,
+                            tileset,
+                        );
+
+                        // Start with the maps on a circle so that the layout doesn't have to
+                        // untangle them
+                        let angle = index as f32 / map_ids.len() as f32 * std::f32::consts::TAU;
+                        nodes.push(Node {
+                            map_id,
+                            name: map_infos.data[&map_id].name.clone(),
+                            position: egui::Vec2::angled(angle)
+                                * LAYOUT_DISTANCE
+                                * map_ids.len() as f32
+                                / std::f32::consts::TAU,
+                            thumbnail: luminol_components::Minimap::new(atlas, map),
+                        });
+
+                        for (event_id, event) in map.events.iter() {
+                            for page in event.pages.iter() {
+                                let door = is_door(&page.list);
+                                for (target_map, x, y) in page
+                                    .list
+                                    .iter()
+                                    .filter_map(|command| command.transfer_destination())
+                                {
+                                    if seen_edges.insert((map_id, event_id, target_map, x, y)) {
+                                        edges.push(Edge {
+                                            source_map: map_id,
+                                            event_id,
+                                            event_name: event.name.clone(),
+                                            event_position: (event.x, event.y),
+                                            door,
+                                            target_map,
+                                            target_position: (x, y),
+                                        });
+                                    }
+                                }
+                            }
+                        }
+                        Ok(())
+                    });
+            // Maps that can't be loaded or drawn are left out of the graph
+            if let Err(e) = result.and_then(|result| result) {
+                luminol_core::error!(
+                    update_state.toasts,
+                    e.wrap_err(format!("Error loading map {map_id}"))
+                );
+            }
+        }
+
+        let node_indices = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.map_id, index))
+            .collect();
+
+        Self {
+            nodes,
+            edges,
+            node_indices,
+            temperature: LAYOUT_DISTANCE,
+        }
+    }
+
+    /// Runs one step of a Fruchterman-Reingold force-directed layout. Returns `true` if the nodes
+    /// are still moving.
+    fn step_layout(&mut self, pinned_node: Option<usize>) -> bool {
+        if self.temperature < 0.5 {
+            return false;
+        }
+
+        let mut forces = vec![egui::Vec2::ZERO; self.nodes.len()];
+
+        // Every node repels every other node
+        for i in 0..self.nodes.len() {
+            for j in i + 1..self.nodes.len() {
+                let delta = self.nodes[i].position - self.nodes[j].position;
+                let distance = delta.length().max(1.);
+                let force = delta / distance * (LAYOUT_DISTANCE * LAYOUT_DISTANCE / distance);
+                forces[i] += force;
+                forces[j] -= force;
+            }
+        }
+
+        // Connected nodes attract each other
+        for edge in self.edges.iter() {
+            let (Some(&i), Some(&j)) = (
+                self.node_indices.get(&edge.source_map),
+                self.node_indices.get(&edge.target_map),
+            ) else {
+                continue;
+            };
+            if i == j {
+                continue;
+            }
+            let delta = self.nodes[j].position - self.nodes[i].position;
+            let distance = delta.length().max(1.);
+            let force = delta / distance * (distance * distance / LAYOUT_DISTANCE);
+            forces[i] += force;
+            forces[j] -= force;
+        }
+
+        for (index, (node, force)) in self.nodes.iter_mut().zip(forces).enumerate() {
+            if pinned_node == Some(index) {
+                continue;
+            }
+            // Pull everything slightly towards the center so disconnected maps don't drift away
+            let force = force - node.position * 0.05;
+            let length = force.length();
+            if length > 0. {
+                node.position += force / length * length.min(self.temperature);
+            }
+        }
+
+        self.temperature *= 0.97;
+        true
+    }
+
+    /// Maps that are neither the source nor the target of any transfer.
+    fn orphaned_maps(&self) -> impl Iterator<Item = &Node> {
+        let connected = self
+            .edges
+            .iter()
+            .filter(|edge| edge.source_map != edge.target_map)
+            .flat_map(|edge| [edge.source_map, edge.target_map])
+            .collect::<HashSet<_>>();
+        self.nodes
+            .iter()
+            .filter(move |node| !connected.contains(&node.map_id))
+    }
+
+    /// Whether there is no transfer leading back from the target map of an edge to its source map.
+    fn is_one_way(&self, edge: &Edge) -> bool {
+        edge.source_map != edge.target_map
+            && !self.edges.iter().any(|other| {
+                other.source_map == edge.target_map && other.target_map == edge.source_map
+            })
+    }
+}
+
+impl Window {
+    /// Opens an event in its map tab.
+    pub(super) fn open_event(
+        update_state: &mut luminol_core::UpdateState<'_>,
+        map_id: usize,
+        event_id: usize,
+    ) {
+        let tab_id = crate::tabs::map::history_scope(map_id);
+        if !update_state.edit_tabs.is_open(tab_id) {
+            match crate::tabs::map::Tab::new(map_id, update_state) {
+                Ok(tab) => update_state.edit_tabs.add_tab(tab),
+                Err(e) => {
+                    luminol_core::error!(
+                        update_state.toasts,
+                        e.wrap_err(format!("Error opening map {map_id}"))
+                    );
+                    return;
+                }
+            }
+        }
+        update_state.edit_tabs.focus_tab_by_id(tab_id);
+        crate::tabs::map::focus_event(update_state.ctx, map_id, event_id);
+    }
+
+    fn graph_ui(&mut self, ui: &mut egui::Ui, open_event: &mut Option<(usize, usize)>) {
+        let Some(graph) = &mut self.graph else {
+            return;
+        };
+
+        let (response, painter) =
+            ui.allocate_painter(ui.available_size(), egui::Sense::click_and_drag());
+        let rect = response.rect;
+
+        if response.hovered() {
+            let zoom_delta = ui.input(|i| i.zoom_delta());
+            self.zoom = (self.zoom * zoom_delta).clamp(0.1, 4.);
+        }
+
+        let zoom = self.zoom;
+        let pan = self.pan;
+        let to_screen = |position: egui::Vec2| rect.center() + pan + position * zoom;
+        let node_rect = |node: &Node| {
+            let [width, height] = node.thumbnail.size();
+            let size = egui::vec2(width as f32, height as f32);
+            let size = size / size.max_elem().max(1.) * THUMBNAIL_SIZE * zoom;
+            egui::Rect::from_center_size(to_screen(node.position), size)
+        };
+
+        let pointer_pos = response.interact_pointer_pos();
+        if response.drag_started() {
+            self.dragged_node = pointer_pos.and_then(|pos| {
+                graph
+                    .nodes
+                    .iter()
+                    .rposition(|node| node_rect(node).contains(pos))
+            });
+        }
+        if response.dragged() {
+            match self.dragged_node {
+                Some(index) => graph.nodes[index].position += response.drag_delta() / zoom,
+                None => self.pan += response.drag_delta(),
+            }
+        } else {
+            self.dragged_node = None;
+        }
+
+        if graph.step_layout(self.dragged_node) {
+            ui.ctx().request_repaint();
+        }
+
+        let painter = painter.with_clip_rect(rect);
+        painter.rect_filled(rect, 0., ui.visuals().extreme_bg_color);
+
+        let text_color = ui.visuals().text_color();
+        let font = egui::FontId::proportional(11. * zoom.max(0.5));
+        let mut clicked_label = None;
+
+        for (index, edge) in graph.edges.iter().enumerate() {
+            let (Some(&source), Some(&target)) = (
+                graph.node_indices.get(&edge.source_map),
+                graph.node_indices.get(&edge.target_map),
+            ) else {
+                continue;
+            };
+            if source == target {
+                continue;
+            }
+            let source_rect = node_rect(&graph.nodes[source]);
+            let target_rect = node_rect(&graph.nodes[target]);
+
+            let color = if graph.is_one_way(edge) {
+                egui::Color32::from_rgb(255, 160, 0)
+            } else {
+                ui.visuals().weak_text_color()
+            };
+            let stroke = egui::Stroke::new(1.5, color);
+
+            // Offset the edge a little so that transfers in both directions don't overlap
+            let direction = (target_rect.center() - source_rect.center()).normalized();
+            let offset = direction.rot90() * 4.;
+            let start = source_rect.center() + offset;
+            let end =
+                target_rect.center() + offset - direction * target_rect.size().min_elem() / 2.;
+
+            if edge.door {
+                painter.line_segment([start, end], stroke);
+            } else {
+                painter.extend(egui::Shape::dashed_line(&[start, end], stroke, 6., 4.));
+            }
+            painter.arrow(end - direction * 8., direction * 8., stroke);
+
+            let selected = self
+                .selected_map
+                .is_some_and(|id| id == edge.source_map || id == edge.target_map);
+            if selected || zoom >= 1. {
+                let label_rect = painter.text(
+                    start + (end - start) / 2.,
+                    egui::Align2::CENTER_CENTER,
+                    edge.label(),
+                    font.clone(),
+                    text_color,
+                );
+                if response.clicked() && pointer_pos.is_some_and(|pos| label_rect.contains(pos)) {
+                    clicked_label = Some(index);
+                }
+            }
+        }
+
+        let orphaned_maps = graph
+            .orphaned_maps()
+            .map(|node| node.map_id)
+            .collect::<HashSet<_>>();
+        let mut clicked_node = None;
+
+        for node in graph.nodes.iter_mut() {
+            let node_rect = node_rect(node);
+            if !rect.intersects(node_rect.expand(100.)) {
+                continue;
+            }
+
+            painter.image(
+                node.thumbnail.texture_id(ui.ctx()),
+                node_rect,
+                egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.)),
+                egui::Color32::WHITE,
+            );
+            let stroke = if self.selected_map == Some(node.map_id) {
+                egui::Stroke::new(2., ui.visuals().selection.stroke.color)
+            } else if orphaned_maps.contains(&node.map_id) {
+                egui::Stroke::new(2., egui::Color32::RED)
+            } else {
+                egui::Stroke::new(1., ui.visuals().weak_text_color())
+            };
+            painter.rect_stroke(node_rect, 0., stroke);
+            painter.text(
+                node_rect.center_bottom() + egui::vec2(0., 2.),
+                egui::Align2::CENTER_TOP,
+                format!("{:0>3}: {}", node.map_id, node.name),
+                font.clone(),
+                text_color,
+            );
+
+            if response.clicked() && pointer_pos.is_some_and(|pos| node_rect.contains(pos)) {
+                clicked_node = Some(node.map_id);
+            }
+        }
+
+        if let Some(index) = clicked_label {
+            let edge = &graph.edges[index];
+            *open_event = Some((edge.source_map, edge.event_id));
+        } else if response.clicked() {
+            self.selected_map = clicked_node;
+        }
+    }
+
+    fn side_panel_ui(&mut self, ui: &mut egui::Ui, open_event: &mut Option<(usize, usize)>) {
+        let Some(graph) = &self.graph else {
+            return;
+        };
+
+        let mut edge_button = |ui: &mut egui::Ui, edge: &Edge, target: usize| {
+            let name = graph
+                .node_indices
+                .get(&target)
+                .map_or("(missing map)", |&index| graph.nodes[index].name.as_str());
+            let text = format!("{:0>3}: {name}\n{}", target, edge.label());
+            let text = if graph.is_one_way(edge) {
+                egui::RichText::new(text).color(egui::Color32::from_rgb(255, 160, 0))
+            } else {
+                egui::RichText::new(text)
+            };
+            if ui
+                .button(text)
+                .on_hover_text(if edge.door {
+                    "Door"
+                } else {
+                    "Transfer as part of an event script"
+                })
+                .clicked()
+            {
+                *open_event = Some((edge.source_map, edge.event_id));
+            }
+        };
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if let Some(map_id) = self.selected_map {
+                ui.heading(format!("Map {map_id:0>3}"));
+
+                ui.label(egui::RichText::new("Exits").strong());
+                for edge in graph.edges.iter().filter(|edge| edge.source_map == map_id) {
+                    edge_button(ui, edge, edge.target_map);
+                }
+
+                ui.label(egui::RichText::new("Entrances").strong());
+                for edge in graph.edges.iter().filter(|edge| edge.target_map == map_id) {
+                    edge_button(ui, edge, edge.source_map);
+                }
+            } else {
+                ui.weak("Click on a map to list its transfers");
+            }
+
+            ui.separator();
+
+            ui.collapsing("Orphaned maps", |ui| {
+                for node in graph.orphaned_maps() {
+                    if ui
+                        .selectable_label(
+                            self.selected_map == Some(node.map_id),
+                            format!("{:0>3}: {}", node.map_id, node.name),
+                        )
+                        .clicked()
+                    {
+                        self.selected_map = Some(node.map_id);
+                    }
+                }
+            });
+
+            ui.collapsing("One-way transfers", |ui| {
+                for edge in graph.edges.iter().filter(|edge| graph.is_one_way(edge)) {
+                    edge_button(ui, edge, edge.target_map);
+                }
+            });
+        });
+    }
+}
+
+impl luminol_core::Window for Window {
+    fn id(&self) -> egui::Id {
+        egui::Id::new("luminol_world_graph")
+    }
+
+    fn requires_filesystem(&self) -> bool {
+        true
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        update_state: &mut luminol_core::UpdateState<'_>,
+    ) {
+        if self.graph.is_none() {
+            self.graph = Some(Graph::new(update_state));
+            self.zoom = 1.;
+        }
+
+        let mut open_event = None;
+
+        egui::Window::new("World Graph")
+            .id(self.id())
+            .open(open)
+            .default_size([800., 600.])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Refresh")
+                        .on_hover_text("Scan the maps for transfers again")
+                        .clicked()
+                    {
+                        self.graph = Some(Graph::new(update_state));
+                    }
+                    if ui.button("Relayout").clicked() {
+                        if let Some(graph) = &mut self.graph {
+                            graph.temperature = LAYOUT_DISTANCE;
+                        }
+                    }
+                    ui.weak("Orange: one-way, dashed: not a door, red border: orphaned");
+                });
+
+                egui::SidePanel::right("luminol_world_graph_transfers")
+                    .default_width(250.)
+                    .show_inside(ui, |ui| self.side_panel_ui(ui, &mut open_event));
+
+                egui::CentralPanel::default()
+                    .show_inside(ui, |ui| self.graph_ui(ui, &mut open_event));
+            });
+
+        if let Some((map_id, event_id)) = open_event {
+            Self::open_event(update_state, map_id, event_id);
+        }
+    }
+}
@@ -55,6 +55,7 @@ pub struct App {
     project_config: Option<luminol_config::project::Config>,
 
     toolbar: luminol_core::ToolbarState,
+    history: luminol_core::History,
 
     modified: luminol_core::ModifiedState,
     modified_during_prev_frame: bool,
@@ -280,6 +281,7 @@ impl App {
             global_config,
             project_config,
             toolbar: luminol_core::ToolbarState::default(),
+            history: luminol_core::History::default(),
 
             modified,
             modified_during_prev_frame: false,
@@ -336,11 +338,12 @@ impl luminol_eframe::App for App {
             data: &mut self.data,
             bytes_loader: self.bytes_loader.clone(),
             edit_windows: &mut luminol_core::EditWindows::default(),
-            edit_tabs: &mut luminol_core::EditTabs::default(),
+            edit_tabs: &mut self.tabs.edit_tabs(),
             toasts: &mut self.toasts,
             project_config: &mut self.project_config,
             global_config: &mut self.global_config,
             toolbar: &mut self.toolbar,
+            history: &mut self.history,
             modified: self.modified.clone(),
             modified_during_prev_frame: &mut self.modified_during_prev_frame,
             project_manager: &mut self.project_manager,
@@ -377,6 +380,7 @@ impl luminol_eframe::App for App {
                 // This means closing the project, for example, won't close tabs until the frame after.
                 self.tabs
                     .process_edit_tabs(std::mem::take(update_state.edit_tabs));
+                *update_state.edit_tabs = self.tabs.edit_tabs();
                 self.windows
                     .process_edit_windows(std::mem::take(update_state.edit_windows));
             });
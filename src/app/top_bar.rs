@@ -133,6 +133,22 @@ impl TopBar {
             }
 
             ui.add_enabled_ui(update_state.filesystem.project_loaded(), |ui| {
+                if ui.button("Undo").clicked() {
+                    update_state.undo(None);
+                }
+
+                if ui.button("Redo").clicked() {
+                    update_state.redo(None);
+                }
+
+                if ui.button("History").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::history::Window::default());
+                }
+
+                ui.separator();
+
                 if ui.button("Project Config").clicked() {
                     let config = update_state.project_config.as_ref().unwrap();
                     update_state
@@ -141,9 +157,15 @@ impl TopBar {
                 }
 
                 if ui.button("Event Commands").clicked() {
-                    // update_state.windows.add_window(
-                    //     luminol_ui::windows::command_gen::CommandGeneratorWindow::default(),
-                    // );
+                    let window =
+                        luminol_ui::windows::command_gen::CommandGeneratorWindow::new(update_state);
+                    update_state.edit_windows.add_window(window);
+                }
+
+                if ui.button("Command Packs").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::command_gen::packs::Window::default());
                 }
             });
         });
@@ -167,11 +189,11 @@ impl TopBar {
                         .add_window(luminol_ui::windows::map_picker::Window::default());
                 }
 
-                ui.add_enabled_ui(false, |ui| {
-                    if ui.button("Tilesets [TODO]").clicked() {
-                        todo!();
-                    }
-                });
+                if ui.button("Tilesets").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::tilesets::Window::new(update_state));
+                }
 
                 if ui.button("Animations").clicked() {
                     update_state
@@ -255,11 +277,11 @@ impl TopBar {
                         .add_window(luminol_ui::windows::enemies::Window::new(update_state));
                 }
 
-                ui.add_enabled_ui(false, |ui| {
-                    if ui.button("Troops [TODO]").clicked() {
-                        todo!();
-                    }
-                });
+                if ui.button("Troops").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::troops::Window::new(update_state));
+                }
             });
         });
 
@@ -286,6 +308,56 @@ impl TopBar {
                     .edit_windows
                     .add_window(luminol_ui::windows::script_manager::Window::default());
             }
+
+            ui.add_enabled_ui(update_state.filesystem.project_loaded(), |ui| {
+                if ui.button("Annotations").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::annotations::Window::default());
+                }
+
+                if ui.button("Common Event Analysis").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::common_event_analysis::Window::default());
+                }
+
+                if ui.button("Dungeon Generator").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::dungeon_generator::Window::default());
+                }
+
+                if ui.button("Page Simulation").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::page_simulation::Window::default());
+                }
+
+                if ui.button("Rebuild Autotiles").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::autotile_rebuild::Window::default());
+                }
+
+                if ui.button("Tiled Import/Export").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::tiled::Window::default());
+                }
+
+                if ui.button("World Graph").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::world_graph::Window::default());
+                }
+
+                if ui.button("World Export").clicked() {
+                    update_state
+                        .edit_windows
+                        .add_window(luminol_ui::windows::world_export::Window::default());
+                }
+            });
         });
 
         ui.separator();
@@ -439,14 +511,41 @@ impl TopBar {
         ))
         .on_hover_text("The proportion of tiles the brush is able to draw on");
 
+        if update_state.toolbar.pencil == luminol_core::Pencil::Scatter {
+            ui.add(
+                egui::DragValue::new(&mut update_state.toolbar.brush_scatter_radius)
+                    .range(0..=16)
+                    .prefix("Radius: "),
+            )
+            .on_hover_text("The radius of the scatter brush in tiles");
+
+            ui.add(
+                egui::Slider::new(&mut update_state.toolbar.brush_falloff, 0.0..=1.0)
+                    .text("Falloff"),
+            )
+            .on_hover_text(
+                "How much the brush density drops off towards the edge of the scatter brush",
+            );
+        }
+
         let alt_down = ui.input(|i| i.modifiers.alt);
         let mut brush_random = update_state.toolbar.brush_random != alt_down;
         ui.add(egui::Checkbox::new(
-            &mut brush_random, "Randomize ID",
+            &mut brush_random, "Random variation",
         ))
-        .on_hover_text("If enabled, the brush will randomly place tiles out of the selected tiles in the tilepicker instead of placing them in a pattern");
+        .on_hover_text("If enabled, the pen, fill, rectangle and circle brushes will randomly place tiles out of the selected tiles in the tilepicker according to their weights instead of placing them in a pattern");
         update_state.toolbar.brush_random = brush_random != alt_down;
 
+        ui.add(egui::DragValue::new(&mut update_state.toolbar.brush_seed).prefix("Seed: "))
+            .on_hover_text("Seed used for the random brush patterns; the same seed always produces the same tiles");
+        if ui
+            .button("🎲")
+            .on_hover_text("Pick a new random brush seed")
+            .clicked()
+        {
+            update_state.toolbar.reroll_brush_seed();
+        }
+
         if open_project {
             update_state.project_manager.open_project_picker();
         }